@@ -0,0 +1,143 @@
+//! `lingcode dict` 子命令：词库工具
+//!
+//! ```text
+//! lingcode dict import [--format <格式>] <文件> [--output <词库.dict.yaml>] [--user] [--store <目录>]
+//! lingcode dict lint [--json] <词库.dict.yaml>...
+//! ```
+//!
+//! 导入格式为 scel、sogou、qq、baidu 或 mspy，省略时按文件内容识别。
+//! `--output` 写出自定义词库文件，`--user` 把词条加入用户词库；都省略时只检查文件。
+//!
+//! `lint` 逐行输出诊断，`--json` 时每行一个 JSON 对象；有错误时以非零状态退出
+
+use crate::userdict;
+use anyhow::{bail, Context, Result};
+use lingcode_dict::format::write_dict_yaml;
+use lingcode_dict::{import_bytes, lint_file, ImportFormat, ImportReport, Severity};
+use std::path::{Path, PathBuf};
+
+const USAGE: &str = "用法: lingcode dict <import [--format <scel|sogou|qq|baidu|mspy>] <文件> [--output <词库.dict.yaml>] [--user] [--store <目录>] | lint [--json] <词库.dict.yaml>...>";
+
+/// 最多显示的无效行数
+const MAX_INVALID_SHOWN: usize = 20;
+
+/// 执行 `dict` 子命令，`args` 为子命令之后的参数
+pub fn run(args: &[String]) -> Result<()> {
+    match args.first().map(String::as_str) {
+        Some("import") => import(&args[1..]),
+        Some("lint") => lint(&args[1..]),
+        _ => bail!(USAGE),
+    }
+}
+
+/// 导入其他输入法的词库
+fn import(args: &[String]) -> Result<()> {
+    let mut format = None;
+    let mut output = None;
+    let mut to_user = false;
+    let mut store_dir = userdict::default_store_dir();
+    let mut input = None;
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--format" => {
+                let id = iter.next().context("--format 缺少格式参数")?;
+                format = Some(ImportFormat::from_id(id).with_context(|| format!("未知的词库格式: {}", id))?);
+            }
+            "--output" => output = Some(PathBuf::from(iter.next().context("--output 缺少文件参数")?)),
+            "--store" => store_dir = PathBuf::from(iter.next().context("--store 缺少目录参数")?),
+            "--user" => to_user = true,
+            _ if input.is_none() => input = Some(PathBuf::from(arg)),
+            _ => bail!(USAGE),
+        }
+    }
+    let input = input.context(USAGE)?;
+
+    let data = std::fs::read(&input).with_context(|| format!("无法读取: {}", input.display()))?;
+    let format = match format {
+        Some(format) => format,
+        None => ImportFormat::detect(&data).context("无法识别词库格式，请用 --format 指定")?,
+    };
+    let report = import_bytes(&data, format).with_context(|| format!("无法导入: {}", input.display()))?;
+    print_report(&input, &report);
+
+    if let Some(output) = output {
+        let name = report.name.clone().unwrap_or_else(|| dict_name(&output));
+        write_dict_yaml(&output, &name, &report.entries)?;
+        println!("已写出自定义词库: {}", output.display());
+    }
+
+    if to_user {
+        let (mut store, mut dict) = userdict::open_store(&store_dir)?;
+        let now = dict.now();
+        let mut added = 0;
+        for entry in &report.entries {
+            if dict.get_record(&entry.text, &entry.pinyin).is_none() {
+                store.add_word(&mut dict, &entry.text, &entry.pinyin, now)?;
+                added += 1;
+            }
+        }
+        store.compact(&dict)?;
+        println!("已加入用户词库 {} 条，已存在 {} 条", added, report.entries.len() - added);
+    }
+    Ok(())
+}
+
+/// 检查词库文件
+fn lint(args: &[String]) -> Result<()> {
+    let json = args.iter().any(|arg| arg == "--json");
+    let files: Vec<&String> = args.iter().filter(|arg| *arg != "--json").collect();
+    if files.is_empty() {
+        bail!(USAGE);
+    }
+
+    let (mut errors, mut warnings) = (0, 0);
+    for file in files {
+        let diagnostics = lint_file(Path::new(file)).with_context(|| format!("无法读取: {}", file))?;
+        for diagnostic in &diagnostics {
+            if json {
+                println!("{}", diagnostic.to_json());
+            } else {
+                println!("{}", diagnostic);
+            }
+            match diagnostic.severity {
+                Severity::Error => errors += 1,
+                Severity::Warning => warnings += 1,
+            }
+        }
+    }
+    if !json {
+        println!("{} 个错误，{} 个警告", errors, warnings);
+    }
+    if errors > 0 {
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
+/// 输出导入统计与无效行
+fn print_report(input: &Path, report: &ImportReport) {
+    println!("{} ({})", input.display(), report.format);
+    if let Some(ref name) = report.name {
+        println!("词库名称: {}", name);
+    }
+    for invalid in report.invalid.iter().take(MAX_INVALID_SHOWN) {
+        println!("  第 {} 行: {}", invalid.line, invalid.reason);
+    }
+    if report.invalid.len() > MAX_INVALID_SHOWN {
+        println!("  …… 另有 {} 行", report.invalid.len() - MAX_INVALID_SHOWN);
+    }
+    println!(
+        "导入 {} 条，跳过 {} 行（空行与注释），无效 {} 行，重复 {} 条",
+        report.entries.len(),
+        report.skipped,
+        report.invalid.len(),
+        report.duplicates
+    );
+}
+
+/// 由输出文件名得到词库名称（去掉 `.dict.yaml`）
+fn dict_name(output: &Path) -> String {
+    let file = output.file_name().and_then(|name| name.to_str()).unwrap_or("imported");
+    file.strip_suffix(".dict.yaml").unwrap_or(file).to_string()
+}
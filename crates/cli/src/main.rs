@@ -5,11 +5,27 @@
 use anyhow::Result;
 use lingcode_engine::Engine;
 use lingcode_core::types::KeyEvent;
+use lingcode_dict::LoadProgress;
 use lingcode_pinyin::SimplifiedPinyinEngine;
 use std::io::{self, Write};
 use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+mod dict;
+mod userdict;
 
 fn main() -> Result<()> {
+    // 子命令：lingcode userdict ... / lingcode dict ...
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    match args.first().map(String::as_str) {
+        Some("userdict") => return userdict::run(&args[1..]),
+        Some("dict") => return dict::run(&args[1..]),
+        _ => {}
+    }
+
+    // --explain：显示每个候选词的得分明细
+    let explain = args.iter().any(|arg| arg == "--explain");
+
     println!("╔══════════════════════════════════════════╗");
     println!("║       📝 灵码输入法 CLI Demo v0.3        ║");
     println!("╠══════════════════════════════════════════╣");
@@ -25,20 +41,24 @@ fn main() -> Result<()> {
     ).join("Library/Rime/cn_dicts");
 
     let pinyin_engine = if rime_dict_dir.exists() {
-        println!("📚 正在加载雾凇拼音词库...");
-
-        // 直接使用新的多词库加载方法
-        let engine = SimplifiedPinyinEngine::with_rime_dicts(
-            rime_dict_dir.to_str().unwrap());
-
-        if engine.has_external_dict() {
-            println!("✅ 词库加载完成！");
-            // 显示统计信息
-            if let Some(stats) = engine.dict_stats() {
-                println!("   总计: {} 条词条", stats.total_entries);
-                println!("   唯一拼音: {}", stats.unique_pinyin);
+        println!("📚 正在后台加载雾凇拼音词库，基础词库就绪后即可输入...");
+
+        // 小词库先加载，每个文件完成时报告进度
+        let files_reported = AtomicUsize::new(0);
+        let on_progress = move |progress: &LoadProgress| {
+            if progress.files_done > files_reported.swap(progress.files_done, Ordering::Relaxed) {
+                let status = if progress.finished { "✅ 词库加载完成" } else { "📚 词库加载中" };
+                eprintln!(
+                    "\n{}: {}/{} 个文件，{} 条词条",
+                    status, progress.files_done, progress.files_total, progress.entries_loaded
+                );
             }
-        } else {
+        };
+        let engine = SimplifiedPinyinEngine::with_rime_dicts_in_background(
+            rime_dict_dir.to_str().unwrap(),
+            Some(Box::new(on_progress)),
+        );
+        if engine.dict_load_progress().is_none_or(|progress| progress.files_total == 0) {
             println!("⚠️  未找到雾凇拼音词库文件，使用内置基础词典");
         }
         engine
@@ -51,6 +71,7 @@ fn main() -> Result<()> {
     println!();
 
     let mut engine = Engine::with_pinyin_engine(pinyin_engine);
+    engine.set_explain_mode(explain);
     let mut committed_text = String::new();
 
     loop {
@@ -80,6 +101,11 @@ fn main() -> Result<()> {
                     }
                 }
             }
+
+            // 得分明细
+            for (i, breakdown) in engine.candidate_explanations().iter().take(5).enumerate() {
+                print!("\n   {}. {}  {}", i + 1, candidates[i].text, breakdown);
+            }
         }
         
         io::stdout().flush()?;
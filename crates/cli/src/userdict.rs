@@ -0,0 +1,156 @@
+//! `lingcode userdict` 子命令：查看与编辑用户词库
+//!
+//! ```text
+//! lingcode userdict [--store <目录>] list [编码]
+//! lingcode userdict [--store <目录>] add <词> <编码>
+//! lingcode userdict [--store <目录>] delete <词> <编码>
+//! lingcode userdict [--store <目录>] pin <词> <编码> <位置>
+//! lingcode userdict [--store <目录>] unpin <词> <编码>
+//! lingcode userdict [--store <目录>] purge <起始时间> <结束时间>
+//! lingcode userdict [--store <目录>] import-rime <快照.userdb.txt>
+//! lingcode userdict [--store <目录>] export-rime <快照.userdb.txt> [词典名]
+//! ```
+//!
+//! 设置环境变量 `LINGCODE_USERDICT_PASSPHRASE` 时按口令打开加密的用户词库
+
+use anyhow::{bail, Context, Result};
+use lingcode_dict::{DictCipher, UserDict, UserDictStore};
+use std::path::{Path, PathBuf};
+
+/// 加密用户词库口令的环境变量
+const PASSPHRASE_ENV: &str = "LINGCODE_USERDICT_PASSPHRASE";
+
+const USAGE: &str = "用法: lingcode userdict [--store <目录>] <list [编码] | add <词> <编码> | delete <词> <编码> | pin <词> <编码> <位置> | unpin <词> <编码> | purge <起始时间> <结束时间> | import-rime <文件> | export-rime <文件> [词典名]>";
+
+/// 默认的用户词库目录
+pub fn default_store_dir() -> PathBuf {
+    PathBuf::from(std::env::var("HOME").unwrap_or_default()).join(".lingcode/userdict")
+}
+
+/// 执行 `userdict` 子命令，`args` 为子命令之后的参数
+pub fn run(args: &[String]) -> Result<()> {
+    let mut dir = default_store_dir();
+    let mut rest = Vec::new();
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        if arg == "--store" {
+            dir = PathBuf::from(iter.next().context("--store 缺少目录参数")?);
+        } else {
+            rest.push(arg.as_str());
+        }
+    }
+
+    let (mut store, mut dict) = open_store(&dir)?;
+    let now = dict.now();
+
+    match rest.as_slice() {
+        ["list"] => list(&dict, None),
+        ["list", code] => list(&dict, Some(code)),
+        ["add", text, code] => {
+            if dict.get_record(text, code).is_some() {
+                println!("已存在: {} ({})", text, code);
+            } else {
+                store.add_word(&mut dict, text, code, now)?;
+                println!("已添加: {} ({})", text, code);
+            }
+        }
+        ["delete", text, code] => {
+            let existed = dict.get_record(text, code).is_some();
+            store.delete(&mut dict, text, code, now)?;
+            if existed {
+                println!("已删除: {} ({})", text, code);
+            } else {
+                println!("已屏蔽: {} ({})", text, code);
+            }
+        }
+        ["pin", text, code, position] => {
+            let position: usize = position.parse().with_context(|| format!("无效的位置: {}", position))?;
+            if position == 0 {
+                bail!("位置从 1 开始");
+            }
+            store.pin(&mut dict, text, code, Some(position - 1), now)?;
+            println!("已固定: {} ({}) 到第 {} 位", text, code, position);
+        }
+        ["unpin", text, code] => {
+            store.pin(&mut dict, text, code, None, now)?;
+            println!("已取消固定: {} ({})", text, code);
+        }
+        ["purge", start, end] => {
+            let start: u64 = start.parse().with_context(|| format!("无效的时间戳: {}", start))?;
+            let end: u64 = end.parse().with_context(|| format!("无效的时间戳: {}", end))?;
+            let purged = store.purge(&mut dict, start, end, now)?;
+            println!("已清除 {} 条词条", purged);
+        }
+        ["import-rime", file] => {
+            let summary = dict.import_rime_userdb(Path::new(file))?;
+            for invalid in &summary.invalid {
+                println!("  第 {} 行: {}", invalid.line, invalid.reason);
+            }
+            println!(
+                "已合并 {} 条词条、{} 条删除记录，无效 {} 行",
+                summary.imported,
+                summary.deleted,
+                summary.invalid.len()
+            );
+        }
+        ["export-rime", file] => export_rime(&dict, file, "luna_pinyin")?,
+        ["export-rime", file, db_name] => export_rime(&dict, file, db_name)?,
+        _ => bail!(USAGE),
+    }
+
+    store.compact(&dict)?;
+    Ok(())
+}
+
+/// 导出为 Rime 用户词典快照
+fn export_rime(dict: &UserDict, file: &str, db_name: &str) -> Result<()> {
+    let count = dict.export_rime_userdb(Path::new(file), db_name)?;
+    println!("已导出 {} 条到 {}", count, file);
+    Ok(())
+}
+
+/// 打开用户词库存储，设置了口令环境变量时按加密存储打开
+pub fn open_store(dir: &Path) -> Result<(UserDictStore, UserDict)> {
+    let opened = match std::env::var(PASSPHRASE_ENV) {
+        Ok(passphrase) => UserDictStore::open_encrypted(dir, DictCipher::from_passphrase(&passphrase)?),
+        Err(_) => UserDictStore::open(dir),
+    };
+    opened.with_context(|| format!("无法打开用户词库: {}", dir.display()))
+}
+
+/// 列出词条、固定与删除记录，`code` 非空时只列出该编码
+fn list(dict: &UserDict, code: Option<&str>) {
+    let matches = |pinyin: &str| code.is_none_or(|code| code == pinyin);
+
+    let mut records: Vec<_> = dict.records().filter(|r| matches(&r.pinyin)).collect();
+    records.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.pinyin.cmp(&b.pinyin)));
+    for record in &records {
+        println!("{}\t{}\t{}", record.text, record.pinyin, record.count);
+    }
+
+    let pins: Vec<_> = dict
+        .pins()
+        .iter()
+        .filter(|pin| matches(&pin.pinyin))
+        .filter_map(|pin| pin.position.map(|position| (pin, position)))
+        .collect();
+    for (pin, position) in &pins {
+        println!("📌 {}\t{}\t第 {} 位", pin.text, pin.pinyin, position + 1);
+    }
+
+    let suppressed: Vec<_> = dict
+        .tombstones()
+        .iter()
+        .filter(|t| matches(&t.pinyin) && dict.is_suppressed(&t.text, &t.pinyin))
+        .collect();
+    for tombstone in &suppressed {
+        println!("🚫 {}\t{}", tombstone.text, tombstone.pinyin);
+    }
+
+    println!(
+        "共 {} 条词条，{} 条固定，{} 条已删除",
+        records.len(),
+        pins.len(),
+        suppressed.len()
+    );
+}
@@ -2,6 +2,8 @@
 //! 
 //! 提供简体中文和繁体中文之间的转换功能
 
+use std::path::Path;
+
 /// 转换模式
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ConversionMode {
@@ -46,13 +48,22 @@ pub struct ChineseConverter {
 impl ChineseConverter {
     /// 创建新的转换器
     pub fn new() -> Self {
+        Self::with_configs(|mode| mode.as_config().to_string())
+    }
+
+    /// 使用指定目录中的 OpenCC 配置（如输入法资源目录中的 `opencc`）创建转换器
+    pub fn from_dir(dir: &Path) -> Self {
+        Self::with_configs(|mode| dir.join(mode.as_config()).to_string_lossy().into_owned())
+    }
+
+    fn with_configs(config: impl Fn(ConversionMode) -> String) -> Self {
         Self {
-            s2t: opencc::OpenCC::new(ConversionMode::S2T.as_config()),
-            s2hk: opencc::OpenCC::new(ConversionMode::S2HK.as_config()),
-            s2tw: opencc::OpenCC::new(ConversionMode::S2TW.as_config()),
-            t2s: opencc::OpenCC::new(ConversionMode::T2S.as_config()),
-            tw2s: opencc::OpenCC::new(ConversionMode::TW2S.as_config()),
-            hk2s: opencc::OpenCC::new(ConversionMode::HK2S.as_config()),
+            s2t: opencc::OpenCC::new(&config(ConversionMode::S2T)),
+            s2hk: opencc::OpenCC::new(&config(ConversionMode::S2HK)),
+            s2tw: opencc::OpenCC::new(&config(ConversionMode::S2TW)),
+            t2s: opencc::OpenCC::new(&config(ConversionMode::T2S)),
+            tw2s: opencc::OpenCC::new(&config(ConversionMode::TW2S)),
+            hk2s: opencc::OpenCC::new(&config(ConversionMode::HK2S)),
         }
     }
 
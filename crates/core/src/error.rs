@@ -20,6 +20,9 @@ pub enum LingCodeError {
     #[error("Invalid parameter: {0}")]
     InvalidParameter(String),
 
+    #[error("Decryption error: {0}")]
+    DecryptionError(String),
+
     #[error("IO error: {0}")]
     IoError(#[from] std::io::Error),
 
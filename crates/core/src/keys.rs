@@ -0,0 +1,223 @@
+//! 按键名称解析
+//!
+//! 兼容 Rime 的按键写法（如 `Control+p`、`Shift+Tab`、`Page_Down`），
+//! 功能键使用 X11 keysym 作为按键码
+
+use crate::types::{KeyEvent, KeyModifiers};
+
+/// 常用功能键的 X11 keysym
+pub mod keysym {
+    pub const BACKSPACE: u32 = 0xff08;
+    pub const TAB: u32 = 0xff09;
+    pub const RETURN: u32 = 0xff0d;
+    pub const ESCAPE: u32 = 0xff1b;
+    pub const HOME: u32 = 0xff50;
+    pub const LEFT: u32 = 0xff51;
+    pub const UP: u32 = 0xff52;
+    pub const RIGHT: u32 = 0xff53;
+    pub const DOWN: u32 = 0xff54;
+    pub const PAGE_UP: u32 = 0xff55;
+    pub const PAGE_DOWN: u32 = 0xff56;
+    pub const END: u32 = 0xff57;
+    pub const ISO_LEFT_TAB: u32 = 0xfe20;
+    pub const SHIFT_L: u32 = 0xffe1;
+    pub const SHIFT_R: u32 = 0xffe2;
+    pub const CONTROL_L: u32 = 0xffe3;
+    pub const CONTROL_R: u32 = 0xffe4;
+    pub const CAPS_LOCK: u32 = 0xffe5;
+    pub const ALT_L: u32 = 0xffe9;
+    pub const ALT_R: u32 = 0xffea;
+    pub const DELETE: u32 = 0xffff;
+}
+
+/// 按键名称 -> (keysym, 对应字符)，无对应字符的功能键为 '\0'
+const NAMED_KEYS: &[(&str, u32, char)] = &[
+    ("BackSpace", keysym::BACKSPACE, '\u{8}'),
+    ("Tab", keysym::TAB, '\t'),
+    ("ISO_Left_Tab", keysym::ISO_LEFT_TAB, '\0'),
+    ("Return", keysym::RETURN, '\r'),
+    ("Escape", keysym::ESCAPE, '\u{1b}'),
+    ("Home", keysym::HOME, '\0'),
+    ("Left", keysym::LEFT, '\0'),
+    ("Up", keysym::UP, '\0'),
+    ("Right", keysym::RIGHT, '\0'),
+    ("Down", keysym::DOWN, '\0'),
+    ("Page_Up", keysym::PAGE_UP, '\0'),
+    ("Prior", keysym::PAGE_UP, '\0'),
+    ("Page_Down", keysym::PAGE_DOWN, '\0'),
+    ("Next", keysym::PAGE_DOWN, '\0'),
+    ("End", keysym::END, '\0'),
+    ("Shift_L", keysym::SHIFT_L, '\0'),
+    ("Shift_R", keysym::SHIFT_R, '\0'),
+    ("Control_L", keysym::CONTROL_L, '\0'),
+    ("Control_R", keysym::CONTROL_R, '\0'),
+    ("Caps_Lock", keysym::CAPS_LOCK, '\0'),
+    ("Alt_L", keysym::ALT_L, '\0'),
+    ("Alt_R", keysym::ALT_R, '\0'),
+    ("Delete", keysym::DELETE, '\0'),
+    ("space", ' ' as u32, ' '),
+    ("exclam", '!' as u32, '!'),
+    ("quotedbl", '"' as u32, '"'),
+    ("numbersign", '#' as u32, '#'),
+    ("dollar", '$' as u32, '$'),
+    ("percent", '%' as u32, '%'),
+    ("ampersand", '&' as u32, '&'),
+    ("apostrophe", '\'' as u32, '\''),
+    ("parenleft", '(' as u32, '('),
+    ("parenright", ')' as u32, ')'),
+    ("asterisk", '*' as u32, '*'),
+    ("plus", '+' as u32, '+'),
+    ("comma", ',' as u32, ','),
+    ("minus", '-' as u32, '-'),
+    ("period", '.' as u32, '.'),
+    ("slash", '/' as u32, '/'),
+    ("colon", ':' as u32, ':'),
+    ("semicolon", ';' as u32, ';'),
+    ("less", '<' as u32, '<'),
+    ("equal", '=' as u32, '='),
+    ("greater", '>' as u32, '>'),
+    ("question", '?' as u32, '?'),
+    ("at", '@' as u32, '@'),
+    ("bracketleft", '[' as u32, '['),
+    ("backslash", '\\' as u32, '\\'),
+    ("bracketright", ']' as u32, ']'),
+    ("asciicircum", '^' as u32, '^'),
+    ("underscore", '_' as u32, '_'),
+    ("grave", '`' as u32, '`'),
+    ("braceleft", '{' as u32, '{'),
+    ("bar", '|' as u32, '|'),
+    ("braceright", '}' as u32, '}'),
+    ("asciitilde", '~' as u32, '~'),
+];
+
+/// 根据按键名称查找 keysym 与对应字符
+pub fn keysym_from_name(name: &str) -> Option<(u32, char)> {
+    if let Some((_, code, ch)) = NAMED_KEYS.iter().find(|(n, _, _)| *n == name) {
+        return Some((*code, *ch));
+    }
+
+    // 单个可打印 ASCII 字符直接使用其编码
+    let mut chars = name.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) if c.is_ascii_graphic() => Some((c as u32, c)),
+        _ => None,
+    }
+}
+
+/// 根据 keysym 查找按键名称
+pub fn keysym_name(keycode: u32) -> Option<&'static str> {
+    NAMED_KEYS
+        .iter()
+        .find(|(_, code, _)| *code == keycode)
+        .map(|(name, _, _)| *name)
+}
+
+impl KeyEvent {
+    /// 创建按键事件
+    pub fn new(keycode: u32, key: char, modifiers: KeyModifiers) -> Self {
+        Self {
+            keycode,
+            key,
+            modifiers,
+        }
+    }
+
+    /// 由字符创建按键事件（无修饰键）
+    pub fn from_char(key: char) -> Self {
+        Self::new(key as u32, key, KeyModifiers::new())
+    }
+
+    /// 由 keysym 创建功能键事件（无修饰键）
+    pub fn from_keysym(keycode: u32) -> Self {
+        let key = NAMED_KEYS
+            .iter()
+            .find(|(_, code, _)| *code == keycode)
+            .map(|(_, _, ch)| *ch)
+            .unwrap_or('\0');
+        Self::new(keycode, key, KeyModifiers::new())
+    }
+
+    /// 解析 Rime 风格的按键描述，如 `Control+p`、`Shift+Tab`、`Page_Down`
+    pub fn parse(spec: &str) -> Option<Self> {
+        let spec = spec.trim();
+        if spec.is_empty() {
+            return None;
+        }
+
+        let mut modifiers = KeyModifiers::new();
+        let mut rest = spec;
+        // 按键本身可能是 '+'，因此只在 '+' 后仍有内容时才拆分修饰键
+        while let Some(pos) = rest.find('+') {
+            if pos + 1 >= rest.len() {
+                break;
+            }
+            match &rest[..pos] {
+                "Shift" => modifiers.shift = true,
+                "Control" => modifiers.ctrl = true,
+                "Alt" => modifiers.alt = true,
+                "Super" | "Meta" => modifiers.meta = true,
+                "Release" => modifiers.release = true,
+                _ => return None,
+            }
+            rest = &rest[pos + 1..];
+        }
+
+        let (keycode, key) = keysym_from_name(rest)?;
+        Some(Self::new(keycode, key, modifiers))
+    }
+
+    /// 是否是按键释放事件
+    pub fn is_release(&self) -> bool {
+        self.modifiers.release
+    }
+
+    /// 是否是没有对应字符的功能键
+    pub fn is_function_key(&self) -> bool {
+        self.key == '\0'
+    }
+
+    /// 判断两个按键事件是否表示同一个按键（忽略按键码的来源差异）
+    pub fn same_key(&self, other: &KeyEvent) -> bool {
+        if self.modifiers != other.modifiers {
+            return false;
+        }
+        if self.is_function_key() || other.is_function_key() {
+            self.keycode == other.keycode
+        } else {
+            self.key == other.key
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_named_keys() {
+        let key = KeyEvent::parse("Page_Down").unwrap();
+        assert_eq!(key.keycode, keysym::PAGE_DOWN);
+        assert!(key.is_function_key());
+
+        let key = KeyEvent::parse("Control+p").unwrap();
+        assert!(key.modifiers.ctrl);
+        assert_eq!(key.key, 'p');
+
+        let key = KeyEvent::parse("Shift+Tab").unwrap();
+        assert!(key.modifiers.shift);
+        assert_eq!(key.keycode, keysym::TAB);
+
+        assert_eq!(KeyEvent::parse("minus").unwrap().key, '-');
+        assert_eq!(KeyEvent::parse("Control++").unwrap().key, '+');
+        assert!(KeyEvent::parse("Hyper+x").is_none());
+        assert!(KeyEvent::parse("NoSuchKey").is_none());
+    }
+
+    #[test]
+    fn test_same_key() {
+        let return_key = KeyEvent::parse("Return").unwrap();
+        let from_terminal = KeyEvent::new(13, '\r', KeyModifiers::new());
+        assert!(return_key.same_key(&from_terminal));
+        assert!(!return_key.same_key(&KeyEvent::from_char('a')));
+    }
+}
@@ -4,20 +4,24 @@
 //! 支持多种输入方案（拼音、双拼等）和简繁转换
 
 pub mod types;
+pub mod keys;
 pub mod candidate;
 pub mod segment;
 pub mod config;
 pub mod error;
 pub mod resource_loader;
 pub mod schema_parser;
+pub mod syllable;
 
 pub use types::*;
+pub use keys::*;
 pub use candidate::*;
 pub use segment::*;
 pub use config::*;
 pub use error::*;
 pub use resource_loader::*;
 pub use schema_parser::*;
+pub use syllable::*;
 
 /// 库版本
 pub const VERSION: &str = env!("CARGO_PKG_VERSION");
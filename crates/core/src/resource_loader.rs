@@ -1,8 +1,10 @@
 //! Resource loader module for loading schemas, dictionaries and other resources
 //! This module handles loading resources from Rime-compatible formats
 
+use std::collections::BTreeMap;
 use std::path::{Path, PathBuf};
 use std::fs;
+use std::time::SystemTime;
 
 /// Resource loader for managing input method resources
 pub struct ResourceLoader {
@@ -17,6 +19,11 @@ impl ResourceLoader {
         }
     }
 
+    /// Get the resource root directory
+    pub fn resource_dir(&self) -> &Path {
+        &self.resource_dir
+    }
+
     /// Get the path to the schemas directory
     pub fn schemas_dir(&self) -> PathBuf {
         self.resource_dir.join("schemas")
@@ -75,6 +82,131 @@ impl ResourceLoader {
         }
         Ok(dicts)
     }
+
+    /// Classify a file under the resource roots, `None` for files that are not resources
+    pub fn kind_of(&self, path: &Path) -> Option<ResourceKind> {
+        let kind = if path.starts_with(self.schemas_dir()) {
+            ResourceKind::Schema
+        } else if path.starts_with(self.dicts_dir()) {
+            ResourceKind::Dict
+        } else if path.starts_with(self.opencc_dir()) {
+            ResourceKind::OpenCC
+        } else if path.parent() == Some(self.resource_dir.as_path()) {
+            ResourceKind::Config
+        } else {
+            return None;
+        };
+        let name = path.file_name()?.to_str()?;
+        let relevant = match kind {
+            ResourceKind::Schema | ResourceKind::Config => name.ends_with(".yaml"),
+            ResourceKind::Dict => name.ends_with(".yaml") || name.ends_with(".txt"),
+            ResourceKind::OpenCC => !name.starts_with('.'),
+        };
+        relevant.then_some(kind)
+    }
+
+    /// Record the size and modification time of every resource file
+    ///
+    /// The root directory is scanned for config files only; the schema, dictionary and
+    /// OpenCC directories are scanned recursively. Missing directories are skipped.
+    pub fn snapshot(&self) -> ResourceSnapshot {
+        let mut files = BTreeMap::new();
+        for dir in [self.schemas_dir(), self.dicts_dir(), self.opencc_dir()] {
+            self.scan(&dir, true, &mut files);
+        }
+        self.scan(&self.resource_dir, false, &mut files);
+        ResourceSnapshot { files }
+    }
+
+    fn scan(&self, dir: &Path, recursive: bool, files: &mut BTreeMap<PathBuf, FileStamp>) {
+        let Ok(entries) = fs::read_dir(dir) else {
+            return;
+        };
+        for entry in entries.flatten() {
+            let path = entry.path();
+            let Ok(metadata) = entry.metadata() else {
+                continue;
+            };
+            if metadata.is_dir() {
+                if recursive {
+                    self.scan(&path, true, files);
+                }
+            } else if self.kind_of(&path).is_some() {
+                let stamp = FileStamp {
+                    modified: metadata.modified().ok(),
+                    len: metadata.len(),
+                };
+                files.insert(path, stamp);
+            }
+        }
+    }
+}
+
+/// Kind of resource a file belongs to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ResourceKind {
+    /// `*.schema.yaml` under the schemas directory
+    Schema,
+    /// Dictionary tables under the dictionaries directory
+    Dict,
+    /// OpenCC configuration and data
+    OpenCC,
+    /// YAML files directly under the resource root (e.g. `default.yaml`)
+    Config,
+}
+
+/// A resource file that was added, modified or removed between two snapshots
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResourceChange {
+    /// Path of the file
+    pub path: PathBuf,
+    /// Kind of resource
+    pub kind: ResourceKind,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct FileStamp {
+    modified: Option<SystemTime>,
+    len: u64,
+}
+
+/// Sizes and modification times of the resource files, see [`ResourceLoader::snapshot`]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ResourceSnapshot {
+    files: BTreeMap<PathBuf, FileStamp>,
+}
+
+impl ResourceSnapshot {
+    /// Number of files in the snapshot
+    pub fn len(&self) -> usize {
+        self.files.len()
+    }
+
+    /// Whether the snapshot contains no files
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty()
+    }
+
+    /// Files that differ between this snapshot and a newer one, in path order
+    pub fn changes(&self, newer: &ResourceSnapshot, loader: &ResourceLoader) -> Vec<ResourceChange> {
+        let modified = newer
+            .files
+            .iter()
+            .filter(|(path, stamp)| self.files.get(*path) != Some(stamp))
+            .map(|(path, _)| path);
+        let removed = self.files.keys().filter(|path| !newer.files.contains_key(*path));
+        let mut paths: Vec<&PathBuf> = modified.chain(removed).collect();
+        paths.sort();
+        paths
+            .into_iter()
+            .filter_map(|path| {
+                loader.kind_of(path).map(|kind| ResourceChange {
+                    path: path.clone(),
+                    kind,
+                })
+            })
+            .collect()
+    }
 }
 
 #[cfg(test)]
@@ -88,4 +220,30 @@ mod tests {
         assert_eq!(loader.dicts_dir(), PathBuf::from("/path/to/resources/dicts"));
         assert_eq!(loader.opencc_dir(), PathBuf::from("/path/to/resources/opencc"));
     }
+
+    #[test]
+    fn test_snapshot_changes() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let root = temp_dir.path();
+        let loader = ResourceLoader::new(root);
+        fs::create_dir_all(loader.dicts_dir().join("cn_dicts")).unwrap();
+        fs::create_dir_all(loader.schemas_dir()).unwrap();
+        fs::write(loader.schemas_dir().join("test.schema.yaml"), "schema: {}\n").unwrap();
+        fs::write(loader.dicts_dir().join("cn_dicts/base.dict.yaml"), "---\n...\n").unwrap();
+        fs::write(root.join("README.md"), "not a resource").unwrap();
+
+        let before = loader.snapshot();
+        assert_eq!(before.len(), 2);
+        assert!(before.changes(&loader.snapshot(), &loader).is_empty());
+
+        fs::write(loader.dicts_dir().join("cn_dicts/base.dict.yaml"), "---\n...\n中\tzhong\n").unwrap();
+        fs::write(root.join("default.yaml"), "config_version: 1\n").unwrap();
+        fs::remove_file(loader.schemas_dir().join("test.schema.yaml")).unwrap();
+        let kinds: Vec<ResourceKind> = before
+            .changes(&loader.snapshot(), &loader)
+            .into_iter()
+            .map(|change| change.kind)
+            .collect();
+        assert_eq!(kinds, vec![ResourceKind::Config, ResourceKind::Dict, ResourceKind::Schema]);
+    }
 }
@@ -26,6 +26,26 @@ pub struct Schema {
     #[serde(default)]
     pub translator: Option<Translator>,
     
+    /// Key binder configuration
+    #[serde(default)]
+    pub key_binder: Option<KeyBinder>,
+    
+    /// Punctuator configuration
+    #[serde(default)]
+    pub punctuator: Option<Punctuator>,
+    
+    /// ASCII composer configuration
+    #[serde(default)]
+    pub ascii_composer: Option<AsciiComposer>,
+    
+    /// Candidate ranking configuration
+    #[serde(default)]
+    pub ranking: Option<Ranking>,
+
+    /// Privacy configuration for learning
+    #[serde(default)]
+    pub privacy: Option<Privacy>,
+    
     /// Additional custom fields
     #[serde(flatten)]
     pub extra: HashMap<String, serde_yaml::Value>,
@@ -109,15 +129,216 @@ pub struct Speller {
 }
 
 /// Translator configuration
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct Translator {
-    /// Dictionary name
+    /// Dictionary name; its `import_tables` are loaded in order
     #[serde(default)]
     pub dictionary: Option<String>,
     
     /// Prism name
     #[serde(default)]
     pub prism: Option<String>,
+
+    /// Additional dictionaries loaded after `dictionary`, in order
+    #[serde(default)]
+    pub dictionaries: Vec<DictionaryConfig>,
+}
+
+/// An additional dictionary, e.g. `{name: team_jargon, source: jargon, priority: 120}`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DictionaryConfig {
+    /// File name relative to the dictionary directory, without `.dict.yaml`
+    pub name: String,
+
+    /// Source identifier reported in `Candidate.source` and used by
+    /// `ranking/boosts`; defaults to the file name
+    #[serde(default)]
+    pub source: Option<String>,
+
+    /// Priority among dictionaries, higher first; defaults to 40
+    #[serde(default)]
+    pub priority: Option<u32>,
+}
+
+/// Key binder configuration (`key_binder` section)
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct KeyBinder {
+    /// Preset to import bindings from
+    #[serde(default)]
+    pub import_preset: Option<String>,
+    
+    /// Key bindings
+    #[serde(default)]
+    pub bindings: Vec<KeyBinding>,
+}
+
+/// A single key binding, e.g. `{when: composing, accept: Tab, send: Page_Down}`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct KeyBinding {
+    /// Condition under which the binding applies
+    pub when: BindingCondition,
+    
+    /// Key that triggers the binding, in Rime key notation
+    pub accept: String,
+    
+    /// Action performed when the binding triggers
+    #[serde(flatten)]
+    pub action: BindingAction,
+}
+
+/// Condition of a key binding
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BindingCondition {
+    /// Input is being composed
+    Composing,
+    /// The candidate menu is shown
+    HasMenu,
+    /// The candidate menu has been paged
+    Paging,
+    /// Always applies
+    Always,
+}
+
+/// Action of a key binding
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BindingAction {
+    /// Send another key instead
+    Send(String),
+    /// Send a sequence of keys, e.g. `{Left}{Left}`
+    SendSequence(String),
+    /// Toggle a switch
+    Toggle(String),
+    /// Turn a switch on
+    SetOption(String),
+    /// Turn a switch off
+    UnsetOption(String),
+    /// Commit the composition in the given form
+    Commit(CommitStyle),
+    /// Undo the last commit and restore its composition, e.g. `undo: ~`
+    Undo,
+}
+
+/// Form in which the composition is committed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CommitStyle {
+    /// The raw input as typed, e.g. `zhongwen`
+    RawInput,
+    /// Pinyin with spaces between syllables, e.g. `zhong wen`
+    SpacedPinyin,
+    /// Pinyin with tone marks, e.g. `zhōngwén`
+    ToneMarks,
+    /// The highlighted candidate converted to traditional Chinese
+    Traditional,
+}
+
+/// Punctuator configuration (`punctuator` section)
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Punctuator {
+    /// Preset to import punctuation tables from
+    #[serde(default)]
+    pub import_preset: Option<String>,
+    
+    /// Rules used when the `full_shape` switch is on
+    #[serde(default)]
+    pub full_shape: HashMap<String, PunctRule>,
+    
+    /// Rules used in half-width mode
+    #[serde(default)]
+    pub half_shape: HashMap<String, PunctRule>,
+}
+
+/// Mapping rule for a punctuation key
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum PunctRule {
+    /// Commit the text immediately, e.g. `{commit: 。}`
+    Commit { commit: String },
+    /// Alternate between an opening and a closing mark, e.g. `{pair: [“, ”]}`
+    Pair { pair: Vec<String> },
+    /// Offer alternatives as candidates, e.g. `[、, /]`
+    Alternatives(Vec<String>),
+    /// A single text, e.g. `，`
+    Text(String),
+}
+
+/// ASCII composer configuration (`ascii_composer` section)
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AsciiComposer {
+    /// Keep the usual Caps Lock behaviour (uppercase letters) in ASCII mode
+    #[serde(default)]
+    pub good_old_caps_lock: bool,
+    
+    /// What happens to the pending input when a key switches ASCII mode,
+    /// keyed by key name (`Shift_L`, `Shift_R`, `Caps_Lock`, ...)
+    #[serde(default)]
+    pub switch_key: HashMap<String, SwitchStyle>,
+}
+
+/// Candidate ranking configuration (`ranking` section)
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Ranking {
+    /// Probability multiplier per candidate source (`user`, `builtin`,
+    /// `sentence` or a dictionary source such as `base`, `ext`, `tencent`,
+    /// `custom`); dictionaries without one are boosted by priority / 100
+    #[serde(default)]
+    pub boosts: HashMap<String, f64>,
+    
+    /// Probability multiplier for candidates whose pinyin only starts with the input
+    #[serde(default)]
+    pub prefix_penalty: Option<f64>,
+
+    /// How learned user words are scored over time
+    #[serde(default)]
+    pub user_frequency: Option<UserFrequency>,
+}
+
+/// User frequency scoring policy (`ranking/user_frequency`)
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "policy", rename_all = "snake_case")]
+pub enum UserFrequency {
+    /// Use count times `e^(-days / decay_days)` times an activity factor (the default)
+    Decay {
+        #[serde(default = "default_decay_days")]
+        decay_days: f64,
+    },
+    /// Like `decay`, but the score halves every `half_life_days` days without use
+    HalfLife { half_life_days: f64 },
+    /// Plain use count, never decays
+    Count,
+    /// Uses within the last `window_days` days
+    SlidingWindow { window_days: f64 },
+}
+
+fn default_decay_days() -> f64 {
+    30.0
+}
+
+/// Privacy configuration (`privacy` section)
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Privacy {
+    /// Regular expressions; commits whose text or input code matches any of
+    /// them are never learned or kept in the commit history
+    #[serde(default)]
+    pub blocklist: Vec<String>,
+}
+
+/// Behaviour of an ASCII mode switch key
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SwitchStyle {
+    /// Continue composing with an inline ASCII segment
+    InlineAscii,
+    /// Commit the pending input as raw text
+    CommitCode,
+    /// Commit the highlighted candidate
+    CommitText,
+    /// Discard the pending input
+    Clear,
+    /// Do not switch
+    Noop,
 }
 
 impl Schema {
@@ -152,4 +373,162 @@ schema:
         assert_eq!(schema.schema.schema_id, "luna_pinyin");
         assert_eq!(schema.schema.name, "朙月拼音");
     }
+
+    #[test]
+    fn test_parse_key_binder() {
+        let yaml = r#"
+schema:
+  schema_id: test
+  name: Test
+key_binder:
+  bindings:
+    - {when: composing, accept: Tab, send: Page_Down}
+    - {when: has_menu, accept: Control+p, send_sequence: "{Up}{Up}"}
+    - {when: always, accept: Control+period, toggle: ascii_punct}
+    - {when: composing, accept: Shift+Return, commit: tone_marks}
+    - {when: always, accept: Control+z, undo: ~}
+"#;
+        let schema = Schema::from_yaml(yaml).unwrap();
+        let bindings = schema.key_binder.unwrap().bindings;
+        assert_eq!(bindings.len(), 5);
+        assert_eq!(bindings[0].when, BindingCondition::Composing);
+        assert_eq!(bindings[0].action, BindingAction::Send("Page_Down".to_string()));
+        assert_eq!(bindings[1].action, BindingAction::SendSequence("{Up}{Up}".to_string()));
+        assert_eq!(bindings[2].action, BindingAction::Toggle("ascii_punct".to_string()));
+        assert_eq!(bindings[3].action, BindingAction::Commit(CommitStyle::ToneMarks));
+        assert_eq!(bindings[4].action, BindingAction::Undo);
+        assert!(!schema.extra.contains_key("key_binder"));
+    }
+
+    #[test]
+    fn test_parse_ranking() {
+        let yaml = r#"
+schema:
+  schema_id: test
+  name: Test
+ranking:
+  boosts:
+    user: 50
+    tencent: 0.2
+  prefix_penalty: 0.3
+"#;
+        let schema = Schema::from_yaml(yaml).unwrap();
+        let ranking = schema.ranking.unwrap();
+        assert_eq!(ranking.boosts.get("user"), Some(&50.0));
+        assert_eq!(ranking.boosts.get("tencent"), Some(&0.2));
+        assert_eq!(ranking.prefix_penalty, Some(0.3));
+        assert_eq!(ranking.user_frequency, None);
+    }
+
+    #[test]
+    fn test_parse_user_frequency() {
+        let yaml = r#"
+schema:
+  schema_id: test
+  name: Test
+ranking:
+  user_frequency:
+    policy: half_life
+    half_life_days: 180
+"#;
+        let schema = Schema::from_yaml(yaml).unwrap();
+        assert_eq!(
+            schema.ranking.unwrap().user_frequency,
+            Some(UserFrequency::HalfLife { half_life_days: 180.0 })
+        );
+    }
+
+    #[test]
+    fn test_parse_dictionaries() {
+        let yaml = r#"
+schema:
+  schema_id: test
+  name: Test
+translator:
+  dictionary: rime_ice
+  dictionaries:
+    - name: team/jargon
+      source: jargon
+      priority: 120
+    - name: names
+"#;
+        let schema = Schema::from_yaml(yaml).unwrap();
+        let translator = schema.translator.unwrap();
+        assert_eq!(translator.dictionary.as_deref(), Some("rime_ice"));
+        assert_eq!(
+            translator.dictionaries,
+            vec![
+                DictionaryConfig {
+                    name: "team/jargon".to_string(),
+                    source: Some("jargon".to_string()),
+                    priority: Some(120),
+                },
+                DictionaryConfig {
+                    name: "names".to_string(),
+                    source: None,
+                    priority: None,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_privacy() {
+        let yaml = r#"
+schema:
+  schema_id: test
+  name: Test
+privacy:
+  blocklist:
+    - '^\d{6,}$'
+    - 机密
+"#;
+        let schema = Schema::from_yaml(yaml).unwrap();
+        let privacy = schema.privacy.unwrap();
+        assert_eq!(privacy.blocklist, vec![r"^\d{6,}$".to_string(), "机密".to_string()]);
+        assert!(!schema.extra.contains_key("privacy"));
+    }
+
+    #[test]
+    fn test_parse_ascii_composer() {
+        let yaml = r#"
+schema:
+  schema_id: test
+  name: Test
+ascii_composer:
+  good_old_caps_lock: true
+  switch_key:
+    Shift_L: commit_code
+    Shift_R: commit_text
+    Caps_Lock: clear
+    Control_L: noop
+"#;
+        let schema = Schema::from_yaml(yaml).unwrap();
+        let composer = schema.ascii_composer.unwrap();
+        assert!(composer.good_old_caps_lock);
+        assert_eq!(composer.switch_key["Shift_L"], SwitchStyle::CommitCode);
+        assert_eq!(composer.switch_key["Control_L"], SwitchStyle::Noop);
+    }
+
+    #[test]
+    fn test_parse_punctuator() {
+        let yaml = r#"
+schema:
+  schema_id: test
+  name: Test
+punctuator:
+  import_preset: default
+  half_shape:
+    ',': {commit: '，'}
+    '"': {pair: ['“', '”']}
+    '/': ['、', '/']
+    '~': '～'
+"#;
+        let schema = Schema::from_yaml(yaml).unwrap();
+        let half_shape = schema.punctuator.unwrap().half_shape;
+        assert_eq!(half_shape[","], PunctRule::Commit { commit: "，".to_string() });
+        assert_eq!(half_shape["\""], PunctRule::Pair { pair: vec!["“".to_string(), "”".to_string()] });
+        assert_eq!(half_shape["/"], PunctRule::Alternatives(vec!["、".to_string(), "/".to_string()]));
+        assert_eq!(half_shape["~"], PunctRule::Text("～".to_string()));
+    }
 }
@@ -0,0 +1,258 @@
+//! 拼音音节
+//!
+//! 提供普通话音节表、拼音串切分以及声调符号标注
+
+/// 普通话全部音节（按字母序排列，ü 写作 v）
+pub const SYLLABLES: &[&str] = &[
+    "a", "ai", "an", "ang", "ao",
+    "ba", "bai", "ban", "bang", "bao", "bei", "ben", "beng", "bi", "bian", "biao", "bie", "bin", "bing", "bo", "bu",
+    "ca", "cai", "can", "cang", "cao", "ce", "cen", "ceng", "cha", "chai", "chan", "chang", "chao", "che", "chen",
+    "cheng", "chi", "chong", "chou", "chu", "chua", "chuai", "chuan", "chuang", "chui", "chun", "chuo", "ci", "cong",
+    "cou", "cu", "cuan", "cui", "cun", "cuo",
+    "da", "dai", "dan", "dang", "dao", "de", "dei", "den", "deng", "di", "dia", "dian", "diao", "die", "ding", "diu",
+    "dong", "dou", "du", "duan", "dui", "dun", "duo",
+    "e", "ei", "en", "eng", "er",
+    "fa", "fan", "fang", "fei", "fen", "feng", "fo", "fou", "fu",
+    "ga", "gai", "gan", "gang", "gao", "ge", "gei", "gen", "geng", "gong", "gou", "gu", "gua", "guai", "guan", "guang",
+    "gui", "gun", "guo",
+    "ha", "hai", "han", "hang", "hao", "he", "hei", "hen", "heng", "hong", "hou", "hu", "hua", "huai", "huan", "huang",
+    "hui", "hun", "huo",
+    "ji", "jia", "jian", "jiang", "jiao", "jie", "jin", "jing", "jiong", "jiu", "ju", "juan", "jue", "jun",
+    "ka", "kai", "kan", "kang", "kao", "ke", "kei", "ken", "keng", "kong", "kou", "ku", "kua", "kuai", "kuan", "kuang",
+    "kui", "kun", "kuo",
+    "la", "lai", "lan", "lang", "lao", "le", "lei", "leng", "li", "lia", "lian", "liang", "liao", "lie", "lin", "ling",
+    "liu", "lo", "long", "lou", "lu", "luan", "lun", "luo", "lv", "lve",
+    "ma", "mai", "man", "mang", "mao", "me", "mei", "men", "meng", "mi", "mian", "miao", "mie", "min", "ming", "miu",
+    "mo", "mou", "mu",
+    "na", "nai", "nan", "nang", "nao", "ne", "nei", "nen", "neng", "ni", "nian", "niang", "niao", "nie", "nin", "ning",
+    "niu", "nong", "nou", "nu", "nuan", "nun", "nuo", "nv", "nve",
+    "o", "ou",
+    "pa", "pai", "pan", "pang", "pao", "pei", "pen", "peng", "pi", "pian", "piao", "pie", "pin", "ping", "po", "pou",
+    "pu",
+    "qi", "qia", "qian", "qiang", "qiao", "qie", "qin", "qing", "qiong", "qiu", "qu", "quan", "que", "qun",
+    "ran", "rang", "rao", "re", "ren", "reng", "ri", "rong", "rou", "ru", "rua", "ruan", "rui", "run", "ruo",
+    "sa", "sai", "san", "sang", "sao", "se", "sen", "seng", "sha", "shai", "shan", "shang", "shao", "she", "shei",
+    "shen", "sheng", "shi", "shou", "shu", "shua", "shuai", "shuan", "shuang", "shui", "shun", "shuo", "si", "song",
+    "sou", "su", "suan", "sui", "sun", "suo",
+    "ta", "tai", "tan", "tang", "tao", "te", "teng", "ti", "tian", "tiao", "tie", "ting", "tong", "tou", "tu", "tuan",
+    "tui", "tun", "tuo",
+    "wa", "wai", "wan", "wang", "wei", "wen", "weng", "wo", "wu",
+    "xi", "xia", "xian", "xiang", "xiao", "xie", "xin", "xing", "xiong", "xiu", "xu", "xuan", "xue", "xun",
+    "ya", "yan", "yang", "yao", "ye", "yi", "yin", "ying", "yo", "yong", "you", "yu", "yuan", "yue", "yun",
+    "za", "zai", "zan", "zang", "zao", "ze", "zei", "zen", "zeng", "zha", "zhai", "zhan", "zhang", "zhao", "zhe",
+    "zhei", "zhen", "zheng", "zhi", "zhong", "zhou", "zhu", "zhua", "zhuai", "zhuan", "zhuang", "zhui", "zhun", "zhuo",
+    "zi", "zong", "zou", "zu", "zuan", "zui", "zun", "zuo",
+];
+
+/// 最长音节的字母数
+const MAX_SYLLABLE_LEN: usize = 6;
+
+/// 是否为完整的音节
+pub fn is_syllable(s: &str) -> bool {
+    SYLLABLES.binary_search(&s).is_ok()
+}
+
+/// 将拼音串切分为音节，撇号作为显式分隔符
+///
+/// 优先匹配较长的音节，较长的音节使余下部分无法切分时改用较短的（如 `xian` 切为 `xian`，
+/// `xianshi` 切为 `xian shi`）；无法完整切分时返回 None。
+/// 先自后向前求出每个位置之后能否完整切分，再自前向后选取，耗时与拼音串长度成正比
+pub fn split_syllables(input: &str) -> Option<Vec<&str>> {
+    if !input.is_ascii() {
+        return None;
+    }
+    let completable = completable(input);
+    split_forward(input, |end, _| completable[end])
+}
+
+/// 将拼音串切分为恰好 `count` 个音节（如按汉字数切分 `xian`：一个字为 `xian`，两个字为 `xi an`），
+/// 有多种切分时优先匹配较长的音节；无法切分为 `count` 个音节时返回 None
+pub fn split_syllables_into(input: &str, count: usize) -> Option<Vec<&str>> {
+    let counts = suffix_counts(input)?;
+    if !counts[0].get(count).copied().unwrap_or(false) {
+        return None;
+    }
+    split_forward(input, |end, taken| {
+        count
+            .checked_sub(taken + 1)
+            .is_some_and(|rest| counts[end].get(rest).copied().unwrap_or(false))
+    })
+}
+
+/// 拼音串能切分出的音节数：第 n 项表示能否恰好切分为 n 个音节
+///
+/// 按音节边界动态规划，每个位置只向后尝试最长音节长度以内的切分，耗时与拼音串长度的平方成正比
+pub fn syllable_counts(input: &str) -> Vec<bool> {
+    match suffix_counts(input) {
+        Some(mut counts) => counts.swap_remove(0),
+        None => vec![false; input.len() + 1],
+    }
+}
+
+/// 自前向后切分，`accept(end, taken)` 判断在已取 `taken` 个音节时能否取到 `end` 为止的音节
+fn split_forward(input: &str, accept: impl Fn(usize, usize) -> bool) -> Option<Vec<&str>> {
+    let mut result = Vec::new();
+    let mut start = 0;
+    while start < input.len() {
+        if input.as_bytes()[start] == b'\'' {
+            start += 1;
+            continue;
+        }
+        let end = (start + 1..=input.len().min(start + MAX_SYLLABLE_LEN))
+            .rev()
+            .find(|&end| is_syllable(&input[start..end]) && accept(end, result.len()))?;
+        result.push(&input[start..end]);
+        start = end;
+    }
+    if result.is_empty() {
+        None
+    } else {
+        Some(result)
+    }
+}
+
+/// 各位置（字节）之后的部分能否完整切分，末尾为 true
+fn completable(input: &str) -> Vec<bool> {
+    let len = input.len();
+    let mut completable = vec![false; len + 1];
+    completable[len] = true;
+    for start in (0..len).rev() {
+        completable[start] = if input.as_bytes()[start] == b'\'' {
+            completable[start + 1]
+        } else {
+            (start + 1..=len.min(start + MAX_SYLLABLE_LEN)).any(|end| completable[end] && is_syllable(&input[start..end]))
+        };
+    }
+    completable
+}
+
+/// 各位置之后的部分能切分出的音节数：`counts[i][n]` 表示第 i 个字节起能否恰好切分为 n 个音节
+fn suffix_counts(input: &str) -> Option<Vec<Vec<bool>>> {
+    if !input.is_ascii() {
+        return None;
+    }
+    let len = input.len();
+    let mut counts = vec![vec![false; len + 1]; len + 1];
+    counts[len][0] = true;
+    for start in (0..len).rev() {
+        if input.as_bytes()[start] == b'\'' {
+            counts[start] = counts[start + 1].clone();
+            continue;
+        }
+        for end in start + 1..=len.min(start + MAX_SYLLABLE_LEN) {
+            if is_syllable(&input[start..end]) {
+                for n in 0..len - end + 1 {
+                    if counts[end][n] {
+                        counts[start][n + 1] = true;
+                    }
+                }
+            }
+        }
+    }
+    Some(counts)
+}
+
+/// 拆分带数字声调的音节，如 `zhong1` -> (`zhong`, 1)；无数字时声调为 0（轻声）
+pub fn parse_tone(s: &str) -> (&str, u8) {
+    match s.as_bytes().last() {
+        Some(&d @ b'0'..=b'5') => (&s[..s.len() - 1], d - b'0'),
+        _ => (s, 0),
+    }
+}
+
+/// 为音节标注声调符号，`tone` 为 1-4，其他值表示轻声（不标注）
+///
+/// 标调规则：有 a 或 e 时标在其上，`ou` 标在 o 上，否则标在最后一个元音上；
+/// `v` 输出为 `ü`
+pub fn add_tone_mark(syllable: &str, tone: u8) -> String {
+    let chars: Vec<char> = syllable.chars().map(|c| if c == 'v' { 'ü' } else { c }).collect();
+    if !(1..=4).contains(&tone) {
+        return chars.into_iter().collect();
+    }
+
+    let position = chars
+        .iter()
+        .position(|&c| c == 'a' || c == 'e')
+        .or_else(|| syllable.find("ou"))
+        .or_else(|| chars.iter().rposition(|&c| "iouü".contains(c)))
+        // 无元音的音节（如 m、ng）标在第一个字母上
+        .unwrap_or(0);
+
+    chars
+        .iter()
+        .enumerate()
+        .map(|(i, &c)| if i == position { tone_vowel(c, tone) } else { c })
+        .collect()
+}
+
+fn tone_vowel(vowel: char, tone: u8) -> char {
+    let marks: [char; 4] = match vowel {
+        'a' => ['ā', 'á', 'ǎ', 'à'],
+        'e' => ['ē', 'é', 'ě', 'è'],
+        'i' => ['ī', 'í', 'ǐ', 'ì'],
+        'o' => ['ō', 'ó', 'ǒ', 'ò'],
+        'u' => ['ū', 'ú', 'ǔ', 'ù'],
+        'ü' => ['ǖ', 'ǘ', 'ǚ', 'ǜ'],
+        _ => return vowel,
+    };
+    marks[tone as usize - 1]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_syllable_table_sorted() {
+        assert!(SYLLABLES.windows(2).all(|w| w[0] < w[1]));
+        assert!(SYLLABLES.iter().all(|s| s.len() <= MAX_SYLLABLE_LEN));
+    }
+
+    #[test]
+    fn test_split_syllables() {
+        assert_eq!(split_syllables("zhongwen"), Some(vec!["zhong", "wen"]));
+        assert_eq!(split_syllables("xian"), Some(vec!["xian"]));
+        assert_eq!(split_syllables("xi'an"), Some(vec!["xi", "an"]));
+        assert_eq!(split_syllables("fangan"), Some(vec!["fang", "an"]));
+        assert_eq!(split_syllables("zhongv"), None);
+        assert_eq!(split_syllables("hello"), None);
+        assert_eq!(split_syllables("'"), None);
+        assert_eq!(split_syllables("xian'"), Some(vec!["xian"]));
+    }
+
+    #[test]
+    fn test_split_long_unsplittable() {
+        // 每个 xian 都有两种切分，以无法切分的字母结尾时回溯会尝试所有组合
+        let input = format!("{}q", "xian".repeat(200));
+        let start = std::time::Instant::now();
+        assert_eq!(split_syllables(&input), None);
+        assert_eq!(split_syllables_into(&input, 200), None);
+        assert!(!syllable_counts(&input).contains(&true));
+        assert!(start.elapsed() < std::time::Duration::from_secs(1));
+        assert_eq!(split_syllables(&"xian".repeat(200)).map(|s| s.len()), Some(200));
+    }
+
+    #[test]
+    fn test_split_syllables_into() {
+        assert_eq!(split_syllables_into("xian", 1), Some(vec!["xian"]));
+        assert_eq!(split_syllables_into("xian", 2), Some(vec!["xi", "an"]));
+        assert_eq!(split_syllables_into("xian", 3), None);
+        assert_eq!(split_syllables_into("xi'an", 1), None);
+        assert_eq!(split_syllables_into("fangan", 2), Some(vec!["fang", "an"]));
+        let counts = syllable_counts("xian");
+        assert_eq!(counts, vec![false, true, true, false, false]);
+    }
+
+    #[test]
+    fn test_add_tone_mark() {
+        assert_eq!(add_tone_mark("zhong", 1), "zhōng");
+        assert_eq!(add_tone_mark("hao", 3), "hǎo");
+        assert_eq!(add_tone_mark("gou", 3), "gǒu");
+        assert_eq!(add_tone_mark("gui", 4), "guì");
+        assert_eq!(add_tone_mark("liu", 2), "liú");
+        assert_eq!(add_tone_mark("lv", 4), "lǜ");
+        assert_eq!(add_tone_mark("de", 0), "de");
+        assert_eq!(parse_tone("wen2"), ("wen", 2));
+        assert_eq!(parse_tone("de"), ("de", 0));
+    }
+}
@@ -64,6 +64,8 @@ pub enum InputState {
     Composing,
     /// 选择候选词
     Selecting,
+    /// 上屏后显示联想候选
+    Predicting,
 }
 
 /// 按键事件
@@ -84,6 +86,8 @@ pub struct KeyModifiers {
     pub ctrl: bool,
     pub alt: bool,
     pub meta: bool,
+    /// 按键释放事件（与 Rime 一致，作为修饰标志传递）
+    pub release: bool,
 }
 
 impl KeyModifiers {
@@ -92,7 +96,7 @@ impl KeyModifiers {
     }
 
     pub fn is_empty(&self) -> bool {
-        !self.shift && !self.ctrl && !self.alt && !self.meta
+        !self.shift && !self.ctrl && !self.alt && !self.meta && !self.release
     }
 
     pub fn with_shift(mut self) -> Self {
@@ -104,6 +108,11 @@ impl KeyModifiers {
         self.ctrl = true;
         self
     }
+
+    pub fn with_release(mut self) -> Self {
+        self.release = true;
+        self
+    }
 }
 
 /// 配置选项
@@ -143,5 +152,8 @@ mod tests {
     fn test_key_modifiers() {
         let mods = KeyModifiers::new().with_ctrl().with_shift();
         assert!(mods.ctrl && mods.shift);
+
+        let release = KeyModifiers::new().with_release();
+        assert!(!release.is_empty());
     }
 }
@@ -0,0 +1,210 @@
+//! 后台分批加载词库
+//!
+//! 大词库（如 `tencent.dict.yaml`）加载需要数秒。[`BackgroundLoad`] 在后台线程中按文件从小到大逐个加载，
+//! 每个文件加载完成后作为一个批次交给调用方合并（见 [`RimeDictLoader::merge`]），
+//! 小的基础词库先就绪，大词库就绪后再并入。进度通过回调与 [`BackgroundLoad::progress`] 报告
+
+use crate::paged::MemoryBudget;
+use crate::rime_loader::{DictSource, DictSpec, RimeDictLoader};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+
+/// 进度回调（在加载线程中调用）
+pub type ProgressCallback = Box<dyn Fn(&LoadProgress) + Send>;
+
+/// 加载进度
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LoadProgress {
+    /// 正在加载或刚加载完成的文件
+    pub file: Option<String>,
+    /// 已加载完成的文件数
+    pub files_done: usize,
+    /// 要加载的文件数（不含不存在的文件）
+    pub files_total: usize,
+    /// 已加载的词条数
+    pub entries_loaded: usize,
+    /// 是否全部加载完成
+    pub finished: bool,
+}
+
+/// 一个加载完成的词库文件
+pub struct LoadedDict {
+    /// 文件名（相对词库目录）
+    pub name: String,
+    /// 来源
+    pub source: DictSource,
+    /// 加载结果，失败时为错误说明
+    pub result: Result<RimeDictLoader, String>,
+}
+
+/// 后台词库加载任务
+///
+/// 任务销毁时不等待加载线程，线程在当前文件加载完成后结束
+pub struct BackgroundLoad {
+    receiver: Receiver<LoadedDict>,
+    progress: Arc<Mutex<LoadProgress>>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl BackgroundLoad {
+    /// 在后台加载词库组合，文件按大小从小到大加载
+    pub fn spawn(dir: &Path, specs: &[DictSpec], on_progress: Option<ProgressCallback>) -> std::io::Result<Self> {
+        Self::spawn_with_budget(dir, specs, MemoryBudget::default(), on_progress)
+    }
+
+    /// 在后台按内存预算加载词库组合（见 [`RimeDictLoader::with_memory_budget`]）
+    pub fn spawn_with_budget(
+        dir: &Path,
+        specs: &[DictSpec],
+        budget: MemoryBudget,
+        on_progress: Option<ProgressCallback>,
+    ) -> std::io::Result<Self> {
+        let mut files: Vec<(u64, String, PathBuf, DictSource)> = specs
+            .iter()
+            .filter_map(|spec| {
+                let path = spec.path(dir);
+                let size = fs::metadata(&path).ok()?.len();
+                let name = path.strip_prefix(dir).unwrap_or(&path).display().to_string();
+                Some((size, name, path, spec.source.clone()))
+            })
+            .collect();
+        files.sort_by_key(|(size, ..)| *size);
+
+        let progress = Arc::new(Mutex::new(LoadProgress {
+            files_total: files.len(),
+            finished: files.is_empty(),
+            ..Default::default()
+        }));
+        let (sender, receiver) = mpsc::channel();
+        let shared = Arc::clone(&progress);
+        let report = move |update: &dyn Fn(&mut LoadProgress)| {
+            let snapshot = {
+                let mut progress = shared.lock().unwrap();
+                update(&mut progress);
+                progress.clone()
+            };
+            if let Some(callback) = &on_progress {
+                callback(&snapshot);
+            }
+        };
+        let thread = std::thread::Builder::new()
+            .name("lingcode-dict-load".to_string())
+            .spawn(move || {
+                let mut loaded_before = 0;
+                for (_, name, path, source) in files {
+                    report(&|p| p.file = Some(name.clone()));
+                    let mut loader = RimeDictLoader::new().with_memory_budget(budget.clone());
+                    let result = loader
+                        .load_from_file_with_progress(&path, source.clone(), &mut |count| {
+                            report(&|p| p.entries_loaded = loaded_before + count)
+                        })
+                        .map_err(|e| e.to_string());
+                    if let Err(e) = &result {
+                        log::warn!("加载 {} 失败: {}", name, e);
+                    }
+                    let count = *result.as_ref().unwrap_or(&0);
+                    loaded_before += count;
+                    let loaded = LoadedDict {
+                        name,
+                        source,
+                        result: result.map(|_| loader),
+                    };
+                    if sender.send(loaded).is_err() {
+                        return;
+                    }
+                    report(&|p| {
+                        p.files_done += 1;
+                        p.entries_loaded = loaded_before;
+                        p.finished = p.files_done == p.files_total;
+                    });
+                }
+            })?;
+        Ok(Self {
+            receiver,
+            progress,
+            thread: Some(thread),
+        })
+    }
+
+    /// 取出已加载完成、尚未取出的文件（不阻塞）
+    pub fn try_recv(&self) -> Vec<LoadedDict> {
+        let mut loaded = Vec::new();
+        loop {
+            match self.receiver.try_recv() {
+                Ok(dict) => loaded.push(dict),
+                Err(TryRecvError::Empty) | Err(TryRecvError::Disconnected) => return loaded,
+            }
+        }
+    }
+
+    /// 等待下一个加载完成的文件，全部取出后返回 None
+    pub fn recv(&self) -> Option<LoadedDict> {
+        self.receiver.recv().ok()
+    }
+
+    /// 等待全部加载完成，返回尚未取出的文件
+    pub fn wait(&mut self) -> Vec<LoadedDict> {
+        let loaded: Vec<LoadedDict> = self.receiver.iter().collect();
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+        loaded
+    }
+
+    /// 当前进度
+    pub fn progress(&self) -> LoadProgress {
+        self.progress.lock().unwrap().clone()
+    }
+
+    /// 是否全部加载完成
+    pub fn is_finished(&self) -> bool {
+        self.progress.lock().unwrap().finished
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_background_load() {
+        let dir = TempDir::new().unwrap();
+        let mut tencent = String::from("---\n...\n");
+        for i in 0..100 {
+            tencent.push_str(&format!("词{}\tci\t{}\n", i, i));
+        }
+        fs::write(dir.path().join("tencent.dict.yaml"), tencent).unwrap();
+        fs::write(dir.path().join("8105.dict.yaml"), "---\n...\n中\tzhong\t100\n").unwrap();
+
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let recorded = Arc::clone(&events);
+        let mut load = BackgroundLoad::spawn(
+            dir.path(),
+            &DictSpec::rime_ice_defaults(),
+            Some(Box::new(move |p: &LoadProgress| recorded.lock().unwrap().push(p.clone()))),
+        )
+        .unwrap();
+
+        // 小词库先加载
+        let loaded = load.wait();
+        let names: Vec<&str> = loaded.iter().map(|d| d.name.as_str()).collect();
+        assert_eq!(names, vec!["8105.dict.yaml", "tencent.dict.yaml"]);
+
+        let mut merged = RimeDictLoader::new();
+        for dict in loaded {
+            merged.merge(dict.result.unwrap());
+        }
+        assert_eq!(merged.len(), 101);
+        assert_eq!(merged.loaded_sources().len(), 2);
+
+        let events = events.lock().unwrap();
+        let last = events.last().unwrap();
+        assert_eq!((last.files_done, last.files_total, last.entries_loaded), (2, 2, 101));
+        assert!(last.finished);
+        assert!(events.iter().any(|p| p.files_done == 1 && !p.finished));
+    }
+}
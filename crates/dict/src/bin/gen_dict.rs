@@ -0,0 +1,115 @@
+//! 由语料生成带权重的词库
+//!
+//! ```text
+//! lingcode-gen-dict --words <词表> --readings <读音表.dict.yaml> [--readings <读音表.dict.yaml>]...
+//!                   [--reference <词库.dict.yaml>] [--name <名称>] --output <词库.dict.yaml> <语料>...
+//! ```
+//!
+//! 词表每行一个词，也可以是已有的 Rime 词库（已有拼音保留，权重重新计算）。
+//! 读音表为 Rime 词库，用于为没有拼音的词标注拼音，可以给出多个：单字读音表（如雾凇拼音的
+//! `cn_dicts/8105.dict.yaml`）之外，建议同时给出整词读音表（如 `cn_dicts/base.dict.yaml`），
+//! 含多音字的词只能按整词读音标注，没有整词读音时跳过并列出。
+//! 给出 `--reference` 时按参考词库校准权重，否则出现次数为中位数的词权重为 100
+
+use anyhow::{bail, Context, Result};
+use lingcode_dict::corpus::read_word_list;
+use lingcode_dict::format::write_dict_yaml;
+use lingcode_dict::{DictBuilder, ReadingTable, RimeDictLoader};
+use std::fs::File;
+use std::io::BufReader;
+use std::path::PathBuf;
+
+const USAGE: &str = "用法: lingcode-gen-dict --words <词表> --readings <读音表.dict.yaml> [--readings <读音表.dict.yaml>]... [--reference <词库.dict.yaml>] [--name <名称>] --output <词库.dict.yaml> <语料>...";
+
+/// 最多显示的跳过的词数
+const MAX_SKIPPED_SHOWN: usize = 20;
+
+fn main() -> Result<()> {
+    let mut words = None;
+    let mut readings = Vec::new();
+    let mut reference = None;
+    let mut name = None;
+    let mut output = None;
+    let mut corpus = Vec::new();
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let mut value = |flag: &str| args.next().with_context(|| format!("{} 缺少参数", flag));
+        match arg.as_str() {
+            "--words" => words = Some(PathBuf::from(value("--words")?)),
+            "--readings" => readings.push(PathBuf::from(value("--readings")?)),
+            "--reference" => reference = Some(PathBuf::from(value("--reference")?)),
+            "--name" => name = Some(value("--name")?),
+            "--output" => output = Some(PathBuf::from(value("--output")?)),
+            _ if arg.starts_with("--") => bail!(USAGE),
+            _ => corpus.push(PathBuf::from(arg)),
+        }
+    }
+    let (Some(words), Some(output)) = (words, output) else {
+        bail!(USAGE);
+    };
+    if readings.is_empty() || corpus.is_empty() {
+        bail!(USAGE);
+    }
+
+    let words = read_word_list(&words).with_context(|| format!("无法读取词表: {}", words.display()))?;
+    let mut builder = DictBuilder::new(words);
+    for path in &corpus {
+        let file = File::open(path).with_context(|| format!("无法读取语料: {}", path.display()))?;
+        builder
+            .add_corpus(BufReader::new(file))
+            .with_context(|| format!("无法读取语料: {}", path.display()))?;
+    }
+
+    let mut loader = RimeDictLoader::new();
+    for path in &readings {
+        loader
+            .load_from_file(path)
+            .with_context(|| format!("无法读取读音表: {}", path.display()))?;
+    }
+    let readings = ReadingTable::from_loader(&loader);
+    let reference = match reference {
+        Some(path) => {
+            let mut loader = RimeDictLoader::new();
+            loader
+                .load_from_file(&path)
+                .with_context(|| format!("无法读取参考词库: {}", path.display()))?;
+            Some(loader)
+        }
+        None => None,
+    };
+
+    let report = builder.build(&readings, reference.as_ref());
+    println!("语料 {} 个文件，切分出 {} 个词", corpus.len(), report.tokens);
+    println!(
+        "生成 {} 条词条，其中 {} 条未在语料中出现；权重系数 {:.3}",
+        report.entries.len(),
+        report.unseen,
+        report.scale
+    );
+    print_skipped("个词无法标注拼音，已跳过", &report.unannotated);
+    print_skipped(
+        "个词含多音字且读音表中没有整词读音，已跳过（可在词表中注明拼音，或用 --readings 加入整词读音表）",
+        &report.polyphonic,
+    );
+
+    let name = name.unwrap_or_else(|| {
+        let file = output.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+        file.trim_end_matches(".dict.yaml").to_string()
+    });
+    write_dict_yaml(&output, &name, &report.entries)?;
+    println!("已写出词库: {}", output.display());
+    Ok(())
+}
+
+fn print_skipped(reason: &str, words: &[String]) {
+    if words.is_empty() {
+        return;
+    }
+    println!("{} {}:", words.len(), reason);
+    for word in words.iter().take(MAX_SKIPPED_SHOWN) {
+        println!("  {}", word);
+    }
+    if words.len() > MAX_SKIPPED_SHOWN {
+        println!("  ……");
+    }
+}
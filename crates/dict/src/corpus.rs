@@ -0,0 +1,500 @@
+//! 由语料统计词频生成词库
+//!
+//! 用词表对纯文本语料做正向 / 逆向最大匹配切分（见 [`Segmenter`]），统计词表中每个词的出现次数，
+//! 按读音表标注拼音（见 [`ReadingTable`]），把词频换算为与其他词库可比的权重，
+//! 生成的词条可用 [`write_dict_yaml`](crate::format::write_dict_yaml) 写出为 Rime 词库。
+//! 含多音字的词无法由单字读音确定拼音（如“银行”“重庆”），需要读音表中有整词读音
+//! （如雾凇拼音的 `base.dict.yaml`）或在词表中注明拼音，否则跳过并在结果中列出
+
+use crate::import::DEFAULT_IMPORT_WEIGHT;
+use crate::lint::is_han;
+use crate::rime_loader::{DictEntry, RimeDictLoader};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io::{self, BufRead};
+use std::path::Path;
+
+/// 最大匹配分词
+pub struct Segmenter {
+    words: HashSet<String>,
+    /// 最长词的字数
+    max_chars: usize,
+}
+
+impl Segmenter {
+    pub fn new(words: impl IntoIterator<Item = String>) -> Self {
+        let words: HashSet<String> = words.into_iter().filter(|w| !w.is_empty()).collect();
+        let max_chars = words.iter().map(|w| w.chars().count()).max().unwrap_or(1);
+        Self { words, max_chars }
+    }
+
+    /// 是否为词表中的词
+    pub fn contains(&self, word: &str) -> bool {
+        self.words.contains(word)
+    }
+
+    /// 正向最大匹配：从左到右每次取词表中最长的词，不在词表中的字单独成词
+    pub fn forward<'a>(&self, text: &'a str) -> Vec<&'a str> {
+        let bounds = char_bounds(text);
+        let count = bounds.len() - 1;
+        let mut words = Vec::new();
+        let mut start = 0;
+        while start < count {
+            let mut end = (start + self.max_chars).min(count);
+            while end > start + 1 && !self.contains(&text[bounds[start]..bounds[end]]) {
+                end -= 1;
+            }
+            words.push(&text[bounds[start]..bounds[end]]);
+            start = end;
+        }
+        words
+    }
+
+    /// 逆向最大匹配：从右到左每次取词表中最长的词
+    pub fn backward<'a>(&self, text: &'a str) -> Vec<&'a str> {
+        let bounds = char_bounds(text);
+        let mut words = Vec::new();
+        let mut end = bounds.len() - 1;
+        while end > 0 {
+            let mut start = end.saturating_sub(self.max_chars);
+            while start + 1 < end && !self.contains(&text[bounds[start]..bounds[end]]) {
+                start += 1;
+            }
+            words.push(&text[bounds[start]..bounds[end]]);
+            end = start;
+        }
+        words.reverse();
+        words
+    }
+
+    /// 双向最大匹配：两个方向结果不同时取词数少的，词数相同取单字少的，仍相同取逆向结果
+    pub fn segment<'a>(&self, text: &'a str) -> Vec<&'a str> {
+        let forward = self.forward(text);
+        let backward = self.backward(text);
+        if forward == backward {
+            return forward;
+        }
+        let singles = |words: &[&str]| words.iter().filter(|w| w.chars().count() == 1).count();
+        let key = |words: &[&str]| (words.len(), singles(words));
+        if key(&forward) < key(&backward) {
+            forward
+        } else {
+            backward
+        }
+    }
+}
+
+/// 各字符的起始位置，末尾附加文本长度
+fn char_bounds(text: &str) -> Vec<usize> {
+    text.char_indices().map(|(i, _)| i).chain([text.len()]).collect()
+}
+
+/// 单字读音：权重最高的读音，以及是否有多个读音
+struct CharReading {
+    pinyin: String,
+    weight: u32,
+    polyphonic: bool,
+}
+
+/// 无法标注拼音的原因
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Unannotated {
+    /// 有字没有读音
+    Missing,
+    /// 读音表中没有整词读音，且词中有多音字
+    Polyphonic,
+}
+
+/// 读音表：单字与词的拼音
+#[derive(Default)]
+pub struct ReadingTable {
+    chars: HashMap<char, CharReading>,
+    words: HashMap<String, (String, u32)>,
+}
+
+impl ReadingTable {
+    /// 从 Rime 词库（如 `8105.dict.yaml`）构建，拼音去掉音节间的空格
+    pub fn from_loader(loader: &RimeDictLoader) -> Self {
+        let mut table = Self::default();
+        loader.for_each_entry(|sourced| {
+            let entry = &sourced.entry;
+            let mut chars = entry.text.chars();
+            match (chars.next(), chars.next()) {
+                (Some(c), None) => table.add_char(c, &entry.pinyin, entry.weight),
+                (Some(_), Some(_)) => table.add_word(&entry.text, &entry.pinyin, entry.weight),
+                _ => {}
+            }
+        });
+        table
+    }
+
+    /// 加入单字读音，已有不同读音时记为多音字并保留权重高的读音
+    pub fn add_char(&mut self, c: char, pinyin: &str, weight: u32) {
+        let pinyin = pinyin.replace(' ', "");
+        match self.chars.get_mut(&c) {
+            Some(current) if current.pinyin == pinyin => current.weight = current.weight.max(weight),
+            Some(current) => {
+                current.polyphonic = true;
+                if weight > current.weight {
+                    current.pinyin = pinyin;
+                    current.weight = weight;
+                }
+            }
+            None => {
+                self.chars.insert(
+                    c,
+                    CharReading {
+                        pinyin,
+                        weight,
+                        polyphonic: false,
+                    },
+                );
+            }
+        }
+    }
+
+    /// 是否为有多个读音的字
+    pub fn is_polyphonic(&self, c: char) -> bool {
+        self.chars.get(&c).is_some_and(|reading| reading.polyphonic)
+    }
+
+    /// 加入词的读音，已有读音时保留权重高的
+    pub fn add_word(&mut self, word: &str, pinyin: &str, weight: u32) {
+        let reading = (pinyin.replace(' ', ""), weight);
+        match self.words.get_mut(word) {
+            Some(current) if current.1 >= weight => {}
+            Some(current) => *current = reading,
+            None => {
+                self.words.insert(word.to_string(), reading);
+            }
+        }
+    }
+
+    /// 标注拼音：读音表中有整词时用整词读音，否则逐字拼接
+    ///
+    /// 逐字拼接时词中有多音字则无法确定读音，不按权重最高的读音猜测
+    pub fn annotate(&self, word: &str) -> Result<String, Unannotated> {
+        if let Some((pinyin, _)) = self.words.get(word) {
+            return Ok(pinyin.clone());
+        }
+        let mut pinyin = String::new();
+        let mut polyphonic = false;
+        for c in word.chars() {
+            let reading = self.chars.get(&c).ok_or(Unannotated::Missing)?;
+            polyphonic |= reading.polyphonic;
+            pinyin.push_str(&reading.pinyin);
+        }
+        if polyphonic {
+            return Err(Unannotated::Polyphonic);
+        }
+        Ok(pinyin)
+    }
+
+    /// 单字读音数
+    pub fn len(&self) -> usize {
+        self.chars.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.chars.is_empty()
+    }
+}
+
+/// 词表中的一个词，可以带拼音
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WordListEntry {
+    pub text: String,
+    pub pinyin: Option<String>,
+}
+
+/// 读取词表：每行一个词，或 `词\t拼音[\t权重]`（原有权重忽略）；
+/// `#` 开头的行为注释，以 `---` 开头的文件（Rime 词库）跳过 YAML 头部
+pub fn read_word_list(path: &Path) -> io::Result<Vec<WordListEntry>> {
+    Ok(parse_word_list(&fs::read_to_string(path)?))
+}
+
+/// 解析词表内容（见 [`read_word_list`]），同一个词只保留第一次出现
+pub fn parse_word_list(content: &str) -> Vec<WordListEntry> {
+    let mut in_header = content.trim_start().starts_with("---");
+    let mut seen = HashSet::new();
+    let mut words = Vec::new();
+    for line in content.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        if in_header {
+            in_header = line != "...";
+            continue;
+        }
+        let mut parts = line.split('\t');
+        let text = parts.next().unwrap_or_default().trim();
+        let pinyin = parts.next().map(|p| p.replace(' ', "")).filter(|p| !p.is_empty());
+        if !text.is_empty() && seen.insert(text.to_string()) {
+            words.push(WordListEntry {
+                text: text.to_string(),
+                pinyin,
+            });
+        }
+    }
+    words
+}
+
+/// 词库生成结果
+#[derive(Debug, Clone, Default)]
+pub struct DictBuildReport {
+    /// 生成的词条，按权重从高到低排列
+    pub entries: Vec<DictEntry>,
+    /// 语料切分出的词数（含不在词表中的单字）
+    pub tokens: u64,
+    /// 语料中没有出现的词数
+    pub unseen: usize,
+    /// 有字没有读音、无法标注拼音而跳过的词
+    pub unannotated: Vec<String>,
+    /// 含多音字且读音表中没有整词读音而跳过的词，可在词表中注明拼音或提供含整词读音的读音表
+    pub polyphonic: Vec<String>,
+    /// 出现次数换算为权重的系数
+    pub scale: f64,
+}
+
+/// 由语料统计词频并生成词库
+///
+/// 权重为出现次数乘以换算系数：给出参考词库时，系数取两者共有词的权重与出现次数之比的中位数，
+/// 使生成的权重与参考词库可比；否则出现次数为中位数的词权重为默认的 100。
+/// 语料中没有出现的词按出现半次计算，排在出现过的词之后
+pub struct DictBuilder {
+    words: Vec<WordListEntry>,
+    segmenter: Segmenter,
+    counts: HashMap<String, u64>,
+    tokens: u64,
+}
+
+impl DictBuilder {
+    pub fn new(words: Vec<WordListEntry>) -> Self {
+        let segmenter = Segmenter::new(words.iter().map(|w| w.text.clone()));
+        Self {
+            words,
+            segmenter,
+            counts: HashMap::new(),
+            tokens: 0,
+        }
+    }
+
+    /// 统计一段文本，非汉字字符视为分隔
+    pub fn add_text(&mut self, text: &str) {
+        for run in text.split(|c: char| !is_han(c)).filter(|run| !run.is_empty()) {
+            for word in self.segmenter.segment(run) {
+                self.tokens += 1;
+                if self.segmenter.contains(word) {
+                    *self.counts.entry(word.to_string()).or_insert(0) += 1;
+                }
+            }
+        }
+    }
+
+    /// 逐行统计语料
+    pub fn add_corpus(&mut self, reader: impl BufRead) -> io::Result<()> {
+        for line in reader.lines() {
+            self.add_text(&line?);
+        }
+        Ok(())
+    }
+
+    /// 词在语料中的出现次数
+    pub fn count(&self, word: &str) -> u64 {
+        self.counts.get(word).copied().unwrap_or(0)
+    }
+
+    /// 语料切分出的词数
+    pub fn tokens(&self) -> u64 {
+        self.tokens
+    }
+
+    /// 生成词条，`reference` 为校准权重用的参考词库
+    pub fn build(&self, readings: &ReadingTable, reference: Option<&RimeDictLoader>) -> DictBuildReport {
+        let scale = self.scale(reference);
+        let mut report = DictBuildReport {
+            tokens: self.tokens,
+            scale,
+            ..Default::default()
+        };
+        for word in &self.words {
+            let annotated = match &word.pinyin {
+                Some(pinyin) => Ok(pinyin.clone()),
+                None => readings.annotate(&word.text),
+            };
+            let pinyin = match annotated {
+                Ok(pinyin) => pinyin,
+                Err(Unannotated::Missing) => {
+                    report.unannotated.push(word.text.clone());
+                    continue;
+                }
+                Err(Unannotated::Polyphonic) => {
+                    report.polyphonic.push(word.text.clone());
+                    continue;
+                }
+            };
+            let count = match self.count(&word.text) {
+                0 => {
+                    report.unseen += 1;
+                    0.5
+                }
+                count => count as f64,
+            };
+            report.entries.push(DictEntry {
+                text: word.text.clone(),
+                pinyin,
+                weight: (count * scale).round().clamp(1.0, u32::MAX as f64) as u32,
+            });
+        }
+        report.entries.sort_by_key(|entry| std::cmp::Reverse(entry.weight));
+        report
+    }
+
+    /// 出现次数换算为权重的系数
+    fn scale(&self, reference: Option<&RimeDictLoader>) -> f64 {
+        if let Some(reference) = reference {
+            let mut ratios = Vec::new();
+            reference.for_each_entry(|sourced| {
+                let count = self.count(&sourced.entry.text);
+                if count > 0 {
+                    ratios.push(sourced.entry.weight as f64 / count as f64);
+                }
+            });
+            if let Some(ratio) = median(&mut ratios) {
+                return ratio;
+            }
+        }
+        let mut counts: Vec<f64> = self.counts.values().map(|&count| count as f64).collect();
+        median(&mut counts).map_or(1.0, |count| DEFAULT_IMPORT_WEIGHT as f64 / count)
+    }
+}
+
+fn median(values: &mut [f64]) -> Option<f64> {
+    if values.is_empty() {
+        return None;
+    }
+    values.sort_by(f64::total_cmp);
+    Some(values[values.len() / 2])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::DictSource;
+
+    fn words(list: &[&str]) -> Vec<WordListEntry> {
+        list.iter()
+            .map(|text| WordListEntry {
+                text: text.to_string(),
+                pinyin: None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_segment() {
+        let segmenter = Segmenter::new(["研究", "研究生", "生命", "起源", "的"].map(String::from));
+        let text = "研究生命的起源";
+        assert_eq!(segmenter.forward(text), vec!["研究生", "命", "的", "起源"]);
+        assert_eq!(segmenter.backward(text), vec!["研究", "生命", "的", "起源"]);
+        // 词数相同时取单字少的
+        assert_eq!(segmenter.segment(text), vec!["研究", "生命", "的", "起源"]);
+        assert_eq!(segmenter.segment("研究生"), vec!["研究生"]);
+    }
+
+    #[test]
+    fn test_build_dict() {
+        let mut builder = DictBuilder::new(words(&["中华", "中国", "钟声", "华夏", "鑫"]));
+        builder
+            .add_corpus("中国，中国人。\n中华、中国与华夏\nChina 中华中国".as_bytes())
+            .unwrap();
+        assert_eq!((builder.count("中国"), builder.count("中华"), builder.count("钟声")), (4, 2, 0));
+        assert_eq!(builder.tokens(), 9);
+
+        let mut readings = ReadingTable::default();
+        for (c, pinyin, weight) in [('中', "zhong", 100), ('华', "hua", 100), ('国', "guo", 100)] {
+            readings.add_char(c, pinyin, weight);
+        }
+        readings.add_word("钟声", "zhong sheng", 10);
+        readings.add_char('夏', "xia", 100);
+
+        // 出现次数为中位数的词（中华，2 次）权重为 100
+        let report = builder.build(&readings, None);
+        let entries: Vec<_> = report.entries.iter().map(|e| (e.text.as_str(), e.pinyin.as_str(), e.weight)).collect();
+        assert_eq!(
+            entries,
+            vec![("中国", "zhongguo", 200), ("中华", "zhonghua", 100), ("华夏", "huaxia", 50), ("钟声", "zhongsheng", 25)]
+        );
+        assert_eq!(report.unseen, 1);
+        assert_eq!(report.unannotated, vec!["鑫"]);
+
+        // 按参考词库校准
+        let mut reference = RimeDictLoader::new();
+        reference.add_entries(
+            [("中国", 40_000), ("华夏", 1000)].map(|(text, weight)| DictEntry {
+                text: text.to_string(),
+                pinyin: String::new(),
+                weight,
+            }),
+            DictSource::base(),
+        );
+        let report = builder.build(&readings, Some(&reference));
+        assert_eq!(report.scale, 10_000.0);
+        assert_eq!(report.entries[0].weight, 40_000);
+    }
+
+    #[test]
+    fn test_polyphonic_readings() {
+        let mut readings = ReadingTable::default();
+        for (c, pinyin, weight) in [
+            ('银', "yin", 100),
+            ('行', "xing", 100),
+            ('行', "hang", 60),
+            ('重', "zhong", 100),
+            ('重', "chong", 40),
+            ('庆', "qing", 100),
+            ('人', "ren", 100),
+        ] {
+            readings.add_char(c, pinyin, weight);
+        }
+        assert!(readings.is_polyphonic('行'));
+        assert!(!readings.is_polyphonic('银'));
+
+        // 不按权重最高的单字读音猜测（银行不是 yinxing，重庆不是 zhongqing）
+        assert_eq!(readings.annotate("银行"), Err(Unannotated::Polyphonic));
+        assert_eq!(readings.annotate("重庆"), Err(Unannotated::Polyphonic));
+        assert_eq!(readings.annotate("鑫"), Err(Unannotated::Missing));
+        readings.add_word("银行", "yin hang", 10);
+        assert_eq!(readings.annotate("银行").as_deref(), Ok("yinhang"));
+
+        // 有整词读音或在词表中注明拼音的词照常生成，其余列出
+        let mut list = words(&["银行", "重庆", "行人"]);
+        list[1].pinyin = Some("chongqing".to_string());
+        let mut builder = DictBuilder::new(list);
+        builder.add_text("银行在重庆");
+        let report = builder.build(&readings, None);
+        let entries: Vec<_> = report.entries.iter().map(|e| (e.text.as_str(), e.pinyin.as_str())).collect();
+        assert_eq!(entries, vec![("银行", "yinhang"), ("重庆", "chongqing")]);
+        assert_eq!(report.polyphonic, vec!["行人"]);
+        assert!(report.unannotated.is_empty());
+    }
+
+    #[test]
+    fn test_parse_word_list() {
+        let list = parse_word_list("---\nname: test\n...\n# 注释\n中华\tzhong hua\t5\n华夏\n中华\n");
+        assert_eq!(
+            list,
+            vec![
+                WordListEntry {
+                    text: "中华".to_string(),
+                    pinyin: Some("zhonghua".to_string()),
+                },
+                WordListEntry {
+                    text: "华夏".to_string(),
+                    pinyin: None,
+                },
+            ]
+        );
+    }
+}
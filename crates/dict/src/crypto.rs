@@ -0,0 +1,287 @@
+//! 用户词库加密
+//!
+//! 使用 XChaCha20-Poly1305 认证加密，密钥由前端直接提供（32 字节），
+//! 或由口令经 Argon2id 派生。密文格式：
+//!
+//! ```text
+//! 魔数 "LCENC" | 版本 (1) | 密钥类型 (0 = 密钥, 1 = 口令) | 盐 (16) | 随机数 (24) | 密文与认证标签
+//! ```
+//!
+//! 魔数到盐的部分作为附加认证数据，被篡改时同样无法解密
+
+use argon2::Argon2;
+use chacha20poly1305::aead::{Aead, KeyInit, Payload};
+use chacha20poly1305::{XChaCha20Poly1305, XNonce};
+use lingcode_core::error::LingCodeError;
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Mutex;
+use zeroize::Zeroizing;
+
+/// 密文魔数
+const MAGIC: &[u8; 5] = b"LCENC";
+/// 密文格式版本
+const FORMAT_VERSION: u8 = 1;
+/// 密钥类型：直接提供的密钥
+const KDF_NONE: u8 = 0;
+/// 密钥类型：Argon2id 口令派生
+const KDF_ARGON2ID: u8 = 1;
+
+/// 密钥长度
+pub const KEY_LEN: usize = 32;
+/// 盐长度
+const SALT_LEN: usize = 16;
+/// 随机数长度
+const NONCE_LEN: usize = 24;
+/// 附加认证数据（头部）长度
+const HEADER_LEN: usize = MAGIC.len() + 2 + SALT_LEN;
+
+type Key = Zeroizing<[u8; KEY_LEN]>;
+
+/// 加密所用的秘密
+enum Secret {
+    /// 直接提供的密钥
+    Key(Key),
+    /// 口令
+    Passphrase(Zeroizing<String>),
+}
+
+/// 用户词库加密器
+///
+/// 同一个加密器加密时使用固定的盐，口令只派生一次；
+/// 解密时按密文中的盐派生并缓存密钥，可以读取其他盐加密的数据
+pub struct DictCipher {
+    secret: Secret,
+    kdf: u8,
+    salt: [u8; SALT_LEN],
+    key: Key,
+    /// 解密时按盐缓存的派生密钥
+    derived: Mutex<HashMap<[u8; SALT_LEN], Key>>,
+}
+
+impl DictCipher {
+    /// 使用前端提供的 32 字节密钥
+    pub fn from_key(key: [u8; KEY_LEN]) -> Self {
+        let key = Zeroizing::new(key);
+        Self {
+            secret: Secret::Key(key.clone()),
+            kdf: KDF_NONE,
+            salt: [0; SALT_LEN],
+            key,
+            derived: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// 使用任意长度的密钥字节，长度不是 32 字节时返回错误
+    pub fn from_key_bytes(bytes: &[u8]) -> Result<Self, LingCodeError> {
+        let key: [u8; KEY_LEN] = bytes.try_into().map_err(|_| {
+            LingCodeError::InvalidParameter(format!("密钥长度应为 {} 字节，实际为 {} 字节", KEY_LEN, bytes.len()))
+        })?;
+        Ok(Self::from_key(key))
+    }
+
+    /// 由口令派生密钥（Argon2id，随机盐）
+    pub fn from_passphrase(passphrase: &str) -> Result<Self, LingCodeError> {
+        if passphrase.is_empty() {
+            return Err(LingCodeError::InvalidParameter("口令不能为空".to_string()));
+        }
+        let salt: [u8; SALT_LEN] = random_bytes()?;
+        let key = derive_key(passphrase, &salt)?;
+        Ok(Self {
+            secret: Secret::Passphrase(Zeroizing::new(passphrase.to_string())),
+            kdf: KDF_ARGON2ID,
+            salt,
+            key,
+            derived: Mutex::new(HashMap::new()),
+        })
+    }
+
+    /// 生成随机密钥，供前端保存到系统钥匙串
+    pub fn generate_key() -> Result<[u8; KEY_LEN], LingCodeError> {
+        random_bytes()
+    }
+
+    /// 加密
+    pub fn seal(&self, plaintext: &[u8]) -> Result<Vec<u8>, LingCodeError> {
+        let mut header = Vec::with_capacity(HEADER_LEN + NONCE_LEN + plaintext.len() + 16);
+        header.extend_from_slice(MAGIC);
+        header.push(FORMAT_VERSION);
+        header.push(self.kdf);
+        header.extend_from_slice(&self.salt);
+
+        let nonce: [u8; NONCE_LEN] = random_bytes()?;
+        let cipher = XChaCha20Poly1305::new(self.key.as_ref().into());
+        let ciphertext = cipher
+            .encrypt(XNonce::from_slice(&nonce), Payload { msg: plaintext, aad: &header })
+            .map_err(|_| LingCodeError::Unknown("加密失败".to_string()))?;
+
+        let mut sealed = header;
+        sealed.extend_from_slice(&nonce);
+        sealed.extend_from_slice(&ciphertext);
+        Ok(sealed)
+    }
+
+    /// 解密，密钥错误或数据被篡改时返回 [`LingCodeError::DecryptionError`]
+    pub fn open(&self, sealed: &[u8]) -> Result<Vec<u8>, LingCodeError> {
+        if !is_sealed(sealed) || sealed.len() < HEADER_LEN + NONCE_LEN {
+            return Err(LingCodeError::DecryptionError("不是加密的用户词库数据".to_string()));
+        }
+        let (header, rest) = sealed.split_at(HEADER_LEN);
+        let (nonce, ciphertext) = rest.split_at(NONCE_LEN);
+        if header[MAGIC.len()] != FORMAT_VERSION {
+            return Err(LingCodeError::DecryptionError(format!(
+                "不支持的加密格式版本: {}",
+                header[MAGIC.len()]
+            )));
+        }
+        let kdf = header[MAGIC.len() + 1];
+        let salt: [u8; SALT_LEN] = header[MAGIC.len() + 2..].try_into().unwrap_or_default();
+
+        let key = self.key_for(kdf, &salt)?;
+        let cipher = XChaCha20Poly1305::new(key.as_ref().into());
+        cipher
+            .decrypt(XNonce::from_slice(nonce), Payload { msg: ciphertext, aad: header })
+            .map_err(|_| LingCodeError::DecryptionError("密钥错误或数据已损坏".to_string()))
+    }
+
+    /// 解密密文中的盐与密钥类型对应的密钥
+    fn key_for(&self, kdf: u8, salt: &[u8; SALT_LEN]) -> Result<Key, LingCodeError> {
+        match (&self.secret, kdf) {
+            (Secret::Key(key), KDF_NONE) => Ok(key.clone()),
+            (Secret::Passphrase(_), KDF_ARGON2ID) if *salt == self.salt => Ok(self.key.clone()),
+            (Secret::Passphrase(passphrase), KDF_ARGON2ID) => {
+                let mut derived = self.derived.lock().unwrap_or_else(|e| e.into_inner());
+                if let Some(key) = derived.get(salt) {
+                    return Ok(key.clone());
+                }
+                let key = derive_key(passphrase, salt)?;
+                derived.insert(*salt, key.clone());
+                Ok(key)
+            }
+            (Secret::Key(_), _) => Err(LingCodeError::DecryptionError("数据使用口令加密，需要提供口令".to_string())),
+            (Secret::Passphrase(_), _) => Err(LingCodeError::DecryptionError("数据使用密钥加密，需要提供密钥".to_string())),
+        }
+    }
+}
+
+impl fmt::Debug for DictCipher {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let kind = match self.secret {
+            Secret::Key(_) => "key",
+            Secret::Passphrase(_) => "passphrase",
+        };
+        f.debug_struct("DictCipher").field("secret", &kind).finish_non_exhaustive()
+    }
+}
+
+/// 数据是否为加密格式
+pub fn is_sealed(data: &[u8]) -> bool {
+    data.starts_with(MAGIC)
+}
+
+/// 用新的加密器重新加密（轮换密钥），`new` 为 None 时解密为明文
+pub fn rotate(sealed: &[u8], old: &DictCipher, new: Option<&DictCipher>) -> Result<Vec<u8>, LingCodeError> {
+    let plaintext = Zeroizing::new(old.open(sealed)?);
+    match new {
+        Some(new) => new.seal(&plaintext),
+        None => Ok(plaintext.to_vec()),
+    }
+}
+
+/// 有加密器时加密，否则原样返回
+pub fn seal_with(data: Vec<u8>, cipher: Option<&DictCipher>) -> Result<Vec<u8>, LingCodeError> {
+    match cipher {
+        Some(cipher) => cipher.seal(&data),
+        None => Ok(data),
+    }
+}
+
+/// 解密加密格式的数据，明文原样返回；数据已加密但没有加密器时返回错误
+pub fn unseal_with(data: Vec<u8>, cipher: Option<&DictCipher>) -> Result<Vec<u8>, LingCodeError> {
+    if !is_sealed(&data) {
+        return Ok(data);
+    }
+    match cipher {
+        Some(cipher) => cipher.open(&data),
+        None => Err(LingCodeError::DecryptionError("用户词库已加密，需要提供密钥".to_string())),
+    }
+}
+
+/// 编码为十六进制（用于按行存储的日志）
+pub(crate) fn to_hex(data: &[u8]) -> String {
+    data.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// 解码十六进制，格式错误时返回 None
+pub(crate) fn from_hex(text: &str) -> Option<Vec<u8>> {
+    if !text.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..text.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(text.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+/// 由口令与盐派生密钥
+fn derive_key(passphrase: &str, salt: &[u8; SALT_LEN]) -> Result<Key, LingCodeError> {
+    let mut key = Zeroizing::new([0u8; KEY_LEN]);
+    Argon2::default()
+        .hash_password_into(passphrase.as_bytes(), salt, key.as_mut())
+        .map_err(|e| LingCodeError::Unknown(format!("密钥派生失败: {}", e)))?;
+    Ok(key)
+}
+
+/// 生成随机字节
+fn random_bytes<const N: usize>() -> Result<[u8; N], LingCodeError> {
+    let mut bytes = [0u8; N];
+    getrandom::getrandom(&mut bytes).map_err(|e| LingCodeError::Unknown(format!("无法生成随机数: {}", e)))?;
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_seal_and_open() {
+        let cipher = DictCipher::from_key([7; KEY_LEN]);
+        let sealed = cipher.seal("中国 zhongguo".as_bytes()).unwrap();
+        assert!(is_sealed(&sealed));
+        assert!(!String::from_utf8_lossy(&sealed).contains("zhongguo"));
+        assert_eq!(cipher.open(&sealed).unwrap(), "中国 zhongguo".as_bytes());
+
+        let wrong = DictCipher::from_key([8; KEY_LEN]);
+        assert!(matches!(wrong.open(&sealed), Err(LingCodeError::DecryptionError(_))));
+
+        let mut tampered = sealed.clone();
+        *tampered.last_mut().unwrap() ^= 1;
+        assert!(matches!(cipher.open(&tampered), Err(LingCodeError::DecryptionError(_))));
+    }
+
+    #[test]
+    fn test_passphrase_and_rotation() {
+        let old = DictCipher::from_passphrase("correct horse").unwrap();
+        let sealed = old.seal(b"secret").unwrap();
+
+        // 同一口令、不同盐的加密器也能解密
+        let reopened = DictCipher::from_passphrase("correct horse").unwrap();
+        assert_eq!(reopened.open(&sealed).unwrap(), b"secret");
+        let wrong = DictCipher::from_passphrase("battery staple").unwrap();
+        assert!(matches!(wrong.open(&sealed), Err(LingCodeError::DecryptionError(_))));
+
+        let new = DictCipher::from_key(DictCipher::generate_key().unwrap());
+        let rotated = rotate(&sealed, &old, Some(&new)).unwrap();
+        assert_eq!(new.open(&rotated).unwrap(), b"secret");
+        assert!(old.open(&rotated).is_err());
+        assert_eq!(rotate(&rotated, &new, None).unwrap(), b"secret");
+    }
+
+    #[test]
+    fn test_hex_round_trip() {
+        let data = [0u8, 1, 0xab, 0xff];
+        assert_eq!(from_hex(&to_hex(&data)).unwrap(), data);
+        assert!(from_hex("abc").is_none());
+        assert!(from_hex("zz").is_none());
+    }
+}
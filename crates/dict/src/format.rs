@@ -1 +1,73 @@
-// This file is intentionally left blank.
\ No newline at end of file
+//! 词库文件写出
+//!
+//! 写出的文件与 Rime 词库格式相同（YAML 头部 + `词\t拼音\t权重`），
+//! 可由 [`RimeDictLoader`](crate::RimeDictLoader) 加载
+
+use crate::rime_loader::DictEntry;
+use crate::journal::write_atomic;
+use std::path::Path;
+
+/// 生成词库文件内容
+pub fn to_dict_yaml(name: &str, entries: &[DictEntry]) -> String {
+    let mut out = format!(
+        "# 由灵码输入法生成\n---\nname: {}\nversion: \"1\"\nsort: by_weight\n...\n",
+        yaml_quote(name)
+    );
+    for entry in entries {
+        out.push_str(&format!("{}\t{}\t{}\n", entry.text, entry.pinyin, entry.weight));
+    }
+    out
+}
+
+/// 转为 YAML 双引号字符串，转义引号、反斜杠与控制字符
+fn yaml_quote(value: &str) -> String {
+    let mut quoted = String::with_capacity(value.len() + 2);
+    quoted.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\t' => quoted.push_str("\\t"),
+            c if c.is_control() => quoted.push_str(&format!("\\u{:04x}", c as u32)),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// 写出词库文件
+pub fn write_dict_yaml(path: &Path, name: &str, entries: &[DictEntry]) -> anyhow::Result<()> {
+    write_atomic(path, to_dict_yaml(name, entries).as_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{DictSource, RimeDictLoader};
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_write_and_load() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("imported.dict.yaml");
+        let entries = vec![DictEntry {
+            text: "你好".to_string(),
+            pinyin: "nihao".to_string(),
+            weight: 5,
+        }];
+        write_dict_yaml(&path, "imported", &entries).unwrap();
+
+        let mut loader = RimeDictLoader::new();
+        assert_eq!(loader.load_from_file(&path).unwrap(), 1);
+        assert_eq!(loader.lookup_sourced("nihao")[0].source, DictSource::custom());
+    }
+
+    #[test]
+    fn test_name_quoted() {
+        let yaml = to_dict_yaml("a: b\n...\n\"c\"", &[]);
+        assert!(yaml.contains("name: \"a: b\\n...\\n\\\"c\\\"\"\n"));
+        assert_eq!(yaml.lines().filter(|line| *line == "...").count(), 1);
+    }
+}
@@ -0,0 +1,570 @@
+//! 其他输入法词库导入
+//!
+//! 支持搜狗细胞词库（`.scel`）、搜狗 / QQ / 百度拼音的文本导出与微软拼音自造词导出。
+//! 导入的拼音统一规范化为小写、无分隔符、无声调、ü 写作 v 的形式（如 `lvse`），
+//! 与输入的拼音串直接对应，可加入 [`DictSource::custom`](crate::DictSource::custom) 词库或用户词库
+
+use crate::lint::is_han;
+use crate::rime_loader::DictEntry;
+use crate::user_dict::UserDict;
+use lingcode_core::error::{LingCodeError, Result};
+use lingcode_core::syllable::is_syllable;
+use std::collections::HashSet;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+/// 未给出词频时的默认权重
+pub const DEFAULT_IMPORT_WEIGHT: u32 = 100;
+
+/// 细胞词库文件头
+const SCEL_MAGIC: [&[u8]; 2] = [
+    b"\x40\x15\x00\x00\x44\x43\x53\x01\x01\x00\x00\x00",
+    b"\x40\x15\x00\x00\x45\x43\x53\x01\x01\x00\x00\x00",
+];
+/// 细胞词库名称的位置与长度
+const SCEL_NAME: (usize, usize) = (0x130, 0x208);
+/// 细胞词库拼音表的位置
+const SCEL_PINYIN_TABLE: usize = 0x1540;
+/// 细胞词库词条的位置
+const SCEL_WORDS: usize = 0x2628;
+
+/// 导入格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImportFormat {
+    /// 搜狗细胞词库（`.scel`）
+    Scel,
+    /// 搜狗拼音文本导出：`'ni'hao 你好`，拼音与词之间可带词频
+    Sogou,
+    /// QQ 拼音文本导出：`ni'hao 你好 1`
+    Qq,
+    /// 百度拼音文本导出：`你好(ni|hao) 1`
+    Baidu,
+    /// 微软拼音自造词导出：`nihao 1 你好`（编码 位置 词），编码可以是简拼
+    MsPinyin,
+}
+
+impl ImportFormat {
+    /// 所有格式
+    pub const ALL: [ImportFormat; 5] = [
+        ImportFormat::Scel,
+        ImportFormat::Sogou,
+        ImportFormat::Qq,
+        ImportFormat::Baidu,
+        ImportFormat::MsPinyin,
+    ];
+
+    /// 格式标识（用于命令行参数）
+    pub fn id(&self) -> &'static str {
+        match self {
+            ImportFormat::Scel => "scel",
+            ImportFormat::Sogou => "sogou",
+            ImportFormat::Qq => "qq",
+            ImportFormat::Baidu => "baidu",
+            ImportFormat::MsPinyin => "mspy",
+        }
+    }
+
+    /// 根据标识查找格式
+    pub fn from_id(id: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|format| format.id() == id)
+    }
+
+    /// 根据文件内容猜测格式，无法判断时返回 None
+    pub fn detect(data: &[u8]) -> Option<Self> {
+        if SCEL_MAGIC.iter().any(|magic| data.starts_with(magic)) {
+            return Some(ImportFormat::Scel);
+        }
+        let text = decode_text(data);
+        let line = text
+            .lines()
+            .map(str::trim)
+            .find(|line| !line.is_empty() && !is_comment(line))?;
+        if line.starts_with('\'') {
+            Some(ImportFormat::Sogou)
+        } else if line.ends_with(')') || (line.contains('(') && line.contains('|')) {
+            Some(ImportFormat::Baidu)
+        } else if line.split_whitespace().nth(1).is_some_and(|field| field.parse::<u32>().is_ok()) {
+            Some(ImportFormat::MsPinyin)
+        } else if line.split_whitespace().count() >= 2 {
+            Some(ImportFormat::Qq)
+        } else {
+            None
+        }
+    }
+}
+
+impl fmt::Display for ImportFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.id())
+    }
+}
+
+/// 无法导入的行
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InvalidLine {
+    /// 行号（从 1 开始；细胞词库为词条序号）
+    pub line: usize,
+    /// 原因
+    pub reason: String,
+}
+
+/// 导入结果
+#[derive(Debug, Clone)]
+pub struct ImportReport {
+    /// 导入格式
+    pub format: ImportFormat,
+    /// 词库名称（细胞词库中记录的名称）
+    pub name: Option<String>,
+    /// 导入的词条
+    pub entries: Vec<DictEntry>,
+    /// 跳过的空行与注释行
+    pub skipped: usize,
+    /// 无法解析的行
+    pub invalid: Vec<InvalidLine>,
+    /// 重复的词条（同一词与拼音只保留第一条）
+    pub duplicates: usize,
+}
+
+impl ImportReport {
+    fn new(format: ImportFormat) -> Self {
+        Self {
+            format,
+            name: None,
+            entries: Vec::new(),
+            skipped: 0,
+            invalid: Vec::new(),
+            duplicates: 0,
+        }
+    }
+
+    fn push(&mut self, seen: &mut HashSet<(String, String)>, entry: DictEntry) {
+        if seen.insert((entry.text.clone(), entry.pinyin.clone())) {
+            self.entries.push(entry);
+        } else {
+            self.duplicates += 1;
+        }
+    }
+
+    fn invalid(&mut self, line: usize, reason: impl Into<String>) {
+        self.invalid.push(InvalidLine {
+            line,
+            reason: reason.into(),
+        });
+    }
+
+    /// 把导入的词条加入用户词库，已有的词条保持不变，返回新增的条数
+    pub fn add_to_user_dict(&self, dict: &mut UserDict, timestamp: u64) -> usize {
+        let mut added = 0;
+        for entry in &self.entries {
+            if dict.get_record(&entry.text, &entry.pinyin).is_none() {
+                dict.add_word_at(&entry.text, &entry.pinyin, timestamp);
+                added += 1;
+            }
+        }
+        added
+    }
+}
+
+/// 按指定格式导入文件
+pub fn import_file(path: &Path, format: ImportFormat) -> Result<ImportReport> {
+    import_bytes(&fs::read(path)?, format)
+}
+
+/// 按指定格式导入数据
+///
+/// 文本格式逐行解析，无法解析的行记入 [`ImportReport::invalid`]；
+/// 细胞词库文件头或结构损坏时返回错误
+pub fn import_bytes(data: &[u8], format: ImportFormat) -> Result<ImportReport> {
+    if format == ImportFormat::Scel {
+        return import_scel(data);
+    }
+
+    let mut report = ImportReport::new(format);
+    let mut seen = HashSet::new();
+    for (index, line) in decode_text(data).lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || is_comment(line) {
+            report.skipped += 1;
+            continue;
+        }
+        let parsed = match format {
+            ImportFormat::Sogou => parse_sogou_line(line),
+            ImportFormat::Qq => parse_qq_line(line),
+            ImportFormat::Baidu => parse_baidu_line(line),
+            ImportFormat::MsPinyin => parse_ms_pinyin_line(line),
+            ImportFormat::Scel => unreachable!(),
+        };
+        match parsed {
+            Ok(entry) => report.push(&mut seen, entry),
+            Err(reason) => report.invalid(index + 1, reason),
+        }
+    }
+    Ok(report)
+}
+
+/// 规范化拼音：去掉分隔符与声调，转为小写，ü 写作 v
+///
+/// 返回音节列表，含有拼音以外的字符时返回 None
+pub fn normalize_pinyin(raw: &str) -> Option<Vec<String>> {
+    let syllables: Vec<String> = raw
+        .split(|c: char| c == '\'' || c == '|' || c == '-' || c.is_whitespace())
+        .filter(|s| !s.is_empty())
+        .map(normalize_syllable)
+        .collect::<Option<_>>()?;
+    (!syllables.is_empty()).then_some(syllables)
+}
+
+/// 规范化单个音节
+fn normalize_syllable(raw: &str) -> Option<String> {
+    let raw = raw.to_lowercase().replace("u:", "v");
+    let mut syllable = String::with_capacity(raw.len());
+    for c in raw.chars() {
+        match c {
+            'a'..='z' => syllable.push(c),
+            // 数字声调
+            '1'..='5' => {}
+            _ => syllable.push(strip_tone_mark(c)?),
+        }
+    }
+    (!syllable.is_empty()).then_some(syllable)
+}
+
+/// 去掉带调字母的声调
+fn strip_tone_mark(c: char) -> Option<char> {
+    let base = match c {
+        'ā' | 'á' | 'ǎ' | 'à' => 'a',
+        'ē' | 'é' | 'ě' | 'è' => 'e',
+        'ī' | 'í' | 'ǐ' | 'ì' => 'i',
+        'ō' | 'ó' | 'ǒ' | 'ò' => 'o',
+        'ū' | 'ú' | 'ǔ' | 'ù' => 'u',
+        'ü' | 'ǖ' | 'ǘ' | 'ǚ' | 'ǜ' => 'v',
+        'ń' | 'ň' | 'ǹ' => 'n',
+        'ḿ' => 'm',
+        _ => return None,
+    };
+    Some(base)
+}
+
+/// 由词与完整拼音创建词条，检查各音节是否有效，词全为汉字时检查音节数与字数是否一致
+fn full_pinyin_entry(text: &str, pinyin: &str, weight: Option<u32>) -> std::result::Result<DictEntry, String> {
+    let syllables = normalize_pinyin(pinyin).ok_or_else(|| format!("无效的拼音: {}", pinyin))?;
+    if let Some(invalid) = syllables.iter().find(|s| !is_syllable(s)) {
+        return Err(format!("无效的音节: {} ({})", invalid, pinyin));
+    }
+    if text.chars().all(is_han) && text.chars().count() != syllables.len() {
+        return Err(format!("拼音与词长度不符: {} {}", text, pinyin));
+    }
+    entry(text, syllables.concat(), weight)
+}
+
+fn entry(text: &str, pinyin: String, weight: Option<u32>) -> std::result::Result<DictEntry, String> {
+    if text.is_empty() || text.chars().any(|c| c.is_whitespace() || c.is_control()) {
+        return Err(format!("无效的词: {:?}", text));
+    }
+    Ok(DictEntry {
+        text: text.to_string(),
+        pinyin,
+        weight: weight.unwrap_or(DEFAULT_IMPORT_WEIGHT),
+    })
+}
+
+/// 搜狗：`'ni'hao 你好` 或 `'ni'hao 2 你好`
+fn parse_sogou_line(line: &str) -> std::result::Result<DictEntry, String> {
+    let fields: Vec<&str> = line.split_whitespace().collect();
+    match fields.as_slice() {
+        [pinyin, text] if pinyin.starts_with('\'') => full_pinyin_entry(text, pinyin, None),
+        [pinyin, weight, text] if pinyin.starts_with('\'') => {
+            full_pinyin_entry(text, pinyin, Some(parse_weight(weight)?))
+        }
+        _ => Err("应为 '拼音 [词频] 词".to_string()),
+    }
+}
+
+/// QQ：`ni'hao 你好 1`
+fn parse_qq_line(line: &str) -> std::result::Result<DictEntry, String> {
+    let fields: Vec<&str> = line.split_whitespace().collect();
+    match fields.as_slice() {
+        [pinyin, text] => full_pinyin_entry(text, pinyin, None),
+        [pinyin, text, weight] => full_pinyin_entry(text, pinyin, Some(parse_weight(weight)?)),
+        _ => Err("应为 拼音 词 [词频]".to_string()),
+    }
+}
+
+/// 百度：`你好(ni|hao) 1`
+fn parse_baidu_line(line: &str) -> std::result::Result<DictEntry, String> {
+    let (word, weight) = match line.rsplit_once(char::is_whitespace) {
+        Some((word, weight)) => (word.trim(), Some(parse_weight(weight)?)),
+        None => (line, None),
+    };
+    let (text, pinyin) = word
+        .strip_suffix(')')
+        .and_then(|word| word.split_once('('))
+        .ok_or_else(|| "应为 词(拼|音) [词频]".to_string())?;
+    full_pinyin_entry(text, pinyin, weight)
+}
+
+/// 微软拼音：`nihao 1 你好`，位置越靠前权重越高
+fn parse_ms_pinyin_line(line: &str) -> std::result::Result<DictEntry, String> {
+    let fields: Vec<&str> = line.split_whitespace().collect();
+    let (code, position, text) = match fields.as_slice() {
+        [code, position, text] => (*code, Some(parse_weight(position)?), *text),
+        [code, text] => (*code, None, *text),
+        _ => return Err("应为 编码 [位置] 词".to_string()),
+    };
+    let syllables = normalize_pinyin(code).ok_or_else(|| format!("无效的编码: {}", code))?;
+    let weight = position.map(|position| DEFAULT_IMPORT_WEIGHT.saturating_sub(position.saturating_sub(1)).max(1));
+    entry(text, syllables.concat(), weight)
+}
+
+fn parse_weight(field: &str) -> std::result::Result<u32, String> {
+    field.parse().map_err(|_| format!("无效的词频: {}", field))
+}
+
+/// 细胞词库
+///
+/// 拼音表为（序号, 长度, UTF-16 拼音）列表；词条按同音词分组，每组为拼音序号列表
+/// 及若干（长度, UTF-16 词, 扩展长度, 扩展数据），扩展数据的前两个字节为词频
+fn import_scel(data: &[u8]) -> Result<ImportReport> {
+    if !SCEL_MAGIC.iter().any(|magic| data.starts_with(magic)) || data.len() < SCEL_WORDS {
+        return Err(LingCodeError::DictError("不是搜狗细胞词库文件".to_string()));
+    }
+    let corrupt = || LingCodeError::DictError("细胞词库文件已损坏".to_string());
+    let mut report = ImportReport::new(ImportFormat::Scel);
+    let (name_start, name_len) = SCEL_NAME;
+    let name = decode_utf16_nul(&data[name_start..name_start + name_len]);
+    report.name = (!name.is_empty()).then_some(name);
+
+    // 拼音表
+    let mut table: Vec<String> = Vec::new();
+    let mut reader = ScelReader::new(data, SCEL_PINYIN_TABLE + 4);
+    while reader.pos < SCEL_WORDS {
+        let index = reader.u16().ok_or_else(corrupt)? as usize;
+        let len = reader.u16().ok_or_else(corrupt)? as usize;
+        // 拼音表之后以零填充
+        if len == 0 {
+            break;
+        }
+        let syllable = reader.utf16(len).ok_or_else(corrupt)?;
+        if index >= table.len() {
+            table.resize(index + 1, String::new());
+        }
+        table[index] = syllable;
+    }
+
+    // 词条
+    let mut seen = HashSet::new();
+    let mut number = 0;
+    let mut reader = ScelReader::new(data, SCEL_WORDS);
+    while reader.pos < data.len() {
+        let same = reader.u16().ok_or_else(corrupt)?;
+        let pinyin_len = reader.u16().ok_or_else(corrupt)? as usize;
+        let pinyin: Option<Vec<&str>> = (0..pinyin_len / 2)
+            .map(|_| reader.u16().map(|i| table.get(i as usize).map(String::as_str)))
+            .collect::<Option<Vec<_>>>()
+            .ok_or_else(corrupt)?
+            .into_iter()
+            .collect();
+        for _ in 0..same {
+            number += 1;
+            let len = reader.u16().ok_or_else(corrupt)? as usize;
+            let text = reader.utf16(len).ok_or_else(corrupt)?;
+            let ext_len = reader.u16().ok_or_else(corrupt)? as usize;
+            let ext = reader.bytes(ext_len).ok_or_else(corrupt)?;
+            let weight = (ext.len() >= 2).then(|| u16::from_le_bytes([ext[0], ext[1]]) as u32);
+            let parsed = match pinyin {
+                Some(ref pinyin) => full_pinyin_entry(&text, &pinyin.join("'"), weight.filter(|w| *w > 0)),
+                None => Err("拼音序号超出拼音表".to_string()),
+            };
+            match parsed {
+                Ok(entry) => report.push(&mut seen, entry),
+                Err(reason) => report.invalid(number, reason),
+            }
+        }
+    }
+    Ok(report)
+}
+
+/// 细胞词库的小端读取器
+struct ScelReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> ScelReader<'a> {
+    fn new(data: &'a [u8], pos: usize) -> Self {
+        Self { data, pos }
+    }
+
+    fn bytes(&mut self, len: usize) -> Option<&'a [u8]> {
+        let bytes = self.data.get(self.pos..self.pos.checked_add(len)?)?;
+        self.pos += len;
+        Some(bytes)
+    }
+
+    fn u16(&mut self) -> Option<u16> {
+        self.bytes(2).map(|b| u16::from_le_bytes([b[0], b[1]]))
+    }
+
+    fn utf16(&mut self, len: usize) -> Option<String> {
+        let bytes = self.bytes(len)?;
+        let units: Vec<u16> = bytes.chunks_exact(2).map(|b| u16::from_le_bytes([b[0], b[1]])).collect();
+        String::from_utf16(&units).ok()
+    }
+}
+
+/// 解码以 NUL 结尾的 UTF-16LE 字符串
+fn decode_utf16_nul(bytes: &[u8]) -> String {
+    let units: Vec<u16> = bytes
+        .chunks_exact(2)
+        .map(|b| u16::from_le_bytes([b[0], b[1]]))
+        .take_while(|unit| *unit != 0)
+        .collect();
+    String::from_utf16_lossy(&units)
+}
+
+/// 解码文本导出：按 BOM 识别 UTF-16LE / UTF-16BE / UTF-8，无 BOM 时按 UTF-8 解码
+fn decode_text(data: &[u8]) -> String {
+    let utf16 = |bytes: &[u8], from: fn([u8; 2]) -> u16| {
+        let units: Vec<u16> = bytes.chunks_exact(2).map(|b| from([b[0], b[1]])).collect();
+        String::from_utf16_lossy(&units)
+    };
+    if let Some(rest) = data.strip_prefix(b"\xff\xfe") {
+        utf16(rest, u16::from_le_bytes)
+    } else if let Some(rest) = data.strip_prefix(b"\xfe\xff") {
+        utf16(rest, u16::from_be_bytes)
+    } else {
+        String::from_utf8_lossy(data.strip_prefix(b"\xef\xbb\xbf").unwrap_or(data)).into_owned()
+    }
+}
+
+/// 注释行（`#`、`;` 或 `//` 开头）
+fn is_comment(line: &str) -> bool {
+    line.starts_with('#') || line.starts_with(';') || line.starts_with("//")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn utf16(text: &str) -> Vec<u8> {
+        text.encode_utf16().flat_map(u16::to_le_bytes).collect()
+    }
+
+    /// 构造细胞词库：拼音表 [ni, hao, lv]，词条 你好(ni hao, 词频 5) 与 绿(lv)
+    fn sample_scel() -> Vec<u8> {
+        let mut data = vec![0u8; SCEL_WORDS];
+        data[..12].copy_from_slice(SCEL_MAGIC[0]);
+        let name = utf16("测试词库");
+        data[SCEL_NAME.0..SCEL_NAME.0 + name.len()].copy_from_slice(&name);
+
+        let mut table = vec![3, 0, 0, 0];
+        for (i, syllable) in ["ni", "hao", "lv"].iter().enumerate() {
+            let bytes = utf16(syllable);
+            table.extend_from_slice(&(i as u16).to_le_bytes());
+            table.extend_from_slice(&(bytes.len() as u16).to_le_bytes());
+            table.extend_from_slice(&bytes);
+        }
+        // 剩余部分填充为长度为 0 的拼音
+        table.resize(SCEL_WORDS - SCEL_PINYIN_TABLE, 0);
+        data[SCEL_PINYIN_TABLE..].copy_from_slice(&table);
+
+        let group = |data: &mut Vec<u8>, pinyin: &[u16], words: &[(&str, u16)]| {
+            data.extend_from_slice(&(words.len() as u16).to_le_bytes());
+            data.extend_from_slice(&(pinyin.len() as u16 * 2).to_le_bytes());
+            for index in pinyin {
+                data.extend_from_slice(&index.to_le_bytes());
+            }
+            for (word, weight) in words {
+                let bytes = utf16(word);
+                data.extend_from_slice(&(bytes.len() as u16).to_le_bytes());
+                data.extend_from_slice(&bytes);
+                data.extend_from_slice(&10u16.to_le_bytes());
+                data.extend_from_slice(&weight.to_le_bytes());
+                data.extend_from_slice(&[0; 8]);
+            }
+        };
+        group(&mut data, &[0, 1], &[("你好", 5)]);
+        group(&mut data, &[2], &[("绿", 0), ("你好", 1)]);
+        data
+    }
+
+    #[test]
+    fn test_normalize_pinyin() {
+        assert_eq!(normalize_pinyin("'Zhong'guo").unwrap().concat(), "zhongguo");
+        assert_eq!(normalize_pinyin("lü se").unwrap().concat(), "lvse");
+        assert_eq!(normalize_pinyin("nu:3|ren2").unwrap().concat(), "nvren");
+        assert_eq!(normalize_pinyin("nǐ hǎo").unwrap(), vec!["ni", "hao"]);
+        assert!(normalize_pinyin("ni好").is_none());
+        assert!(normalize_pinyin("''").is_none());
+    }
+
+    #[test]
+    fn test_import_text_formats() {
+        let sogou = "# 注释\n'ni'hao 你好\n'zhong'guo 3 中国\n\n'ni'hao 你好\nnihao\n";
+        let report = import_bytes(sogou.as_bytes(), ImportFormat::Sogou).unwrap();
+        assert_eq!(report.entries.len(), 2);
+        assert_eq!((report.entries[1].pinyin.as_str(), report.entries[1].weight), ("zhongguo", 3));
+        assert_eq!((report.skipped, report.duplicates), (2, 1));
+        assert_eq!(report.invalid.len(), 1);
+        assert_eq!(report.invalid[0].line, 6);
+
+        let qq = "ni'hao 你好 1\nlv'se 绿色 2\nzhong 中国 1\nni'hoa 你好 1\n";
+        let report = import_bytes(qq.as_bytes(), ImportFormat::Qq).unwrap();
+        assert_eq!(report.entries.len(), 2);
+        assert_eq!(report.entries[1].pinyin, "lvse");
+        assert!(report.invalid[0].reason.contains("长度"));
+        assert!(report.invalid[1].reason.contains("hoa"));
+
+        let baidu = "你好(ni|hao) 2\n绿色(lü|se)\n你好 2\n";
+        let report = import_bytes(baidu.as_bytes(), ImportFormat::Baidu).unwrap();
+        assert_eq!(report.entries.len(), 2);
+        assert_eq!(report.entries[1].pinyin, "lvse");
+        assert_eq!(report.invalid.len(), 1);
+
+        // 微软拼音导出为带 BOM 的 UTF-16LE，编码可以是简拼
+        let mut mspy = b"\xff\xfe".to_vec();
+        mspy.extend(utf16(";自造词\r\nnh 1 你好\r\nzhongguo 2 中国\r\n"));
+        let report = import_bytes(&mspy, ImportFormat::MsPinyin).unwrap();
+        assert_eq!(report.entries.len(), 2);
+        assert_eq!((report.entries[0].pinyin.as_str(), report.entries[0].weight), ("nh", 100));
+        assert_eq!(report.entries[1].weight, 99);
+        assert_eq!(report.skipped, 1);
+    }
+
+    #[test]
+    fn test_import_scel() {
+        let data = sample_scel();
+        assert_eq!(ImportFormat::detect(&data), Some(ImportFormat::Scel));
+
+        let report = import_bytes(&data, ImportFormat::Scel).unwrap();
+        assert_eq!(report.name.as_deref(), Some("测试词库"));
+        assert_eq!(report.entries.len(), 2);
+        assert_eq!((report.entries[0].text.as_str(), report.entries[0].pinyin.as_str()), ("你好", "nihao"));
+        assert_eq!(report.entries[0].weight, 5);
+        assert_eq!((report.entries[1].text.as_str(), report.entries[1].weight), ("绿", DEFAULT_IMPORT_WEIGHT));
+        // 你好 挂在 lv 下，拼音与词长度不符
+        assert_eq!(report.invalid.len(), 1);
+        assert_eq!(report.invalid[0].line, 3);
+
+        let truncated = &data[..data.len() - 3];
+        assert!(import_bytes(truncated, ImportFormat::Scel).is_err());
+        assert!(import_bytes("'ni'hao 你好".as_bytes(), ImportFormat::Scel).is_err());
+    }
+
+    #[test]
+    fn test_detect_and_add_to_user_dict() {
+        assert_eq!(ImportFormat::detect("'ni'hao 你好".as_bytes()), Some(ImportFormat::Sogou));
+        assert_eq!(ImportFormat::detect("ni'hao 你好 1".as_bytes()), Some(ImportFormat::Qq));
+        assert_eq!(ImportFormat::detect("你好(ni|hao) 1".as_bytes()), Some(ImportFormat::Baidu));
+        assert_eq!(ImportFormat::detect("; x\nnh 1 你好".as_bytes()), Some(ImportFormat::MsPinyin));
+        assert_eq!(ImportFormat::from_id("mspy"), Some(ImportFormat::MsPinyin));
+
+        let report = import_bytes("'ni'hao 你好\n'zhong'guo 中国".as_bytes(), ImportFormat::Sogou).unwrap();
+        let mut dict = UserDict::new();
+        dict.record_usage_at("你好", "nihao", 1);
+        assert_eq!(report.add_to_user_dict(&mut dict, 2), 1);
+        assert_eq!(dict.get_reading_count("你好", "nihao"), 1);
+        assert_eq!(dict.get_reading_count("中国", "zhongguo"), 1);
+    }
+}
@@ -0,0 +1,793 @@
+//! 用户词库的日志式存储
+//!
+//! 用户词库保存在一个目录中：
+//! - `snapshot.json`：某一时刻的完整词库，附带已包含的最后一条日志序号
+//! - `journal.log`：快照之后的使用事件，每行一个 JSON，只追加写入
+//! - `device_id`：本机设备 ID，创建存储时生成，之后每次打开都使用同一个 ID
+//!
+//! 每次使用只追加一行日志，不再重写整个词库；日志达到一定条数后压缩为新快照。
+//! 快照先写入临时文件再原子重命名，崩溃时旧快照保持完整；
+//! 启动时加载快照并重放序号更大的日志，末尾写了一半的日志行会被丢弃，中间损坏的行被跳过。
+//! JSON 格式（`UserDict::load_from_file` / `save_to_file`）仍可用于导入导出
+//!
+//! 使用加密器打开时，快照整体加密，每行日志只保留序号明文，事件内容加密后以十六进制存储；
+//! 打开时发现的明文数据会立即压缩为加密快照
+
+use crate::crypto::{from_hex, is_sealed, seal_with, to_hex, unseal_with, DictCipher};
+use crate::user_dict::{generate_device_id, UserDict, WordRecord};
+use lingcode_core::error::LingCodeError;
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+/// 快照文件名
+pub const SNAPSHOT_FILE: &str = "snapshot.json";
+
+/// 日志文件名
+pub const JOURNAL_FILE: &str = "journal.log";
+
+/// 设备 ID 文件名
+pub const DEVICE_ID_FILE: &str = "device_id";
+
+/// 默认日志压缩阈值（条）
+pub const DEFAULT_COMPACT_THRESHOLD: usize = 1000;
+
+/// 日志中的使用事件
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum JournalEvent {
+    /// 记录一次使用
+    Record {
+        text: String,
+        pinyin: String,
+        timestamp: u64,
+    },
+    /// 撤销一次使用，`previous` 为记录前的词条状态
+    Rollback {
+        text: String,
+        pinyin: String,
+        previous: Option<WordRecord>,
+    },
+    /// 删除或屏蔽词条
+    Delete {
+        text: String,
+        pinyin: String,
+        timestamp: u64,
+    },
+    /// 手动添加词条
+    Add {
+        text: String,
+        pinyin: String,
+        timestamp: u64,
+    },
+    /// 固定或取消固定候选位置
+    Pin {
+        text: String,
+        pinyin: String,
+        position: Option<usize>,
+        timestamp: u64,
+    },
+    /// 清除时间段内的使用记录
+    Purge { start: u64, end: u64, timestamp: u64 },
+}
+
+impl JournalEvent {
+    /// 将事件应用到词库
+    pub fn apply(&self, dict: &mut UserDict) {
+        match self {
+            JournalEvent::Record { text, pinyin, timestamp } => dict.record_usage_at(text, pinyin, *timestamp),
+            JournalEvent::Rollback { text, pinyin, previous } => dict.rollback_usage(text, pinyin, previous.clone()),
+            JournalEvent::Delete { text, pinyin, timestamp } => {
+                dict.delete_at(text, pinyin, *timestamp);
+            }
+            JournalEvent::Add { text, pinyin, timestamp } => dict.add_word_at(text, pinyin, *timestamp),
+            JournalEvent::Pin { text, pinyin, position, timestamp } => dict.pin_at(text, pinyin, *position, *timestamp),
+            JournalEvent::Purge { start, end, timestamp } => {
+                dict.purge_at(*start, *end, *timestamp);
+            }
+        }
+    }
+}
+
+/// 带序号的日志行
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct JournalEntry {
+    seq: u64,
+    #[serde(flatten)]
+    event: JournalEvent,
+}
+
+/// 加密的日志行：序号明文，事件内容加密
+#[derive(Debug, Serialize, Deserialize)]
+struct SealedEntry {
+    seq: u64,
+    sealed: String,
+}
+
+/// 日志行（加密或明文）
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum JournalLine {
+    Sealed(SealedEntry),
+    Plain(JournalEntry),
+}
+
+impl JournalLine {
+    fn seq(&self) -> u64 {
+        match self {
+            JournalLine::Sealed(entry) => entry.seq,
+            JournalLine::Plain(entry) => entry.seq,
+        }
+    }
+}
+
+/// 快照文件内容
+#[derive(Debug, Serialize, Deserialize)]
+struct Snapshot {
+    /// 快照已包含的最后一条日志序号
+    last_seq: u64,
+    dict: UserDict,
+}
+
+/// 用户词库的日志式存储
+#[derive(Debug)]
+pub struct UserDictStore {
+    dir: PathBuf,
+    journal: File,
+    /// 下一条日志的序号
+    next_seq: u64,
+    /// 上次压缩后追加的日志条数
+    pending: usize,
+    /// 日志达到此条数时自动压缩
+    compact_threshold: usize,
+    /// 每次追加后同步到磁盘
+    sync_on_append: bool,
+    /// 加密器，为 None 时以明文存储
+    cipher: Option<DictCipher>,
+}
+
+impl UserDictStore {
+    /// 打开存储目录，返回存储与恢复出的词库
+    ///
+    /// 目录不存在时创建空词库。存储已加密时返回 [`LingCodeError::DecryptionError`]
+    pub fn open(dir: &Path) -> anyhow::Result<(Self, UserDict)> {
+        Self::open_with(dir, None)
+    }
+
+    /// 打开加密的存储目录，之后的快照与日志都加密写入
+    ///
+    /// 密钥错误时返回 [`LingCodeError::DecryptionError`]，不会改动磁盘上的数据；
+    /// 目录中已有的明文数据会立即加密
+    pub fn open_encrypted(dir: &Path, cipher: DictCipher) -> anyhow::Result<(Self, UserDict)> {
+        Self::open_with(dir, Some(cipher))
+    }
+
+    fn open_with(dir: &Path, cipher: Option<DictCipher>) -> anyhow::Result<(Self, UserDict)> {
+        fs::create_dir_all(dir)?;
+
+        let snapshot_path = dir.join(SNAPSHOT_FILE);
+        let mut plaintext_found = false;
+        let (mut dict, last_seq) = if snapshot_path.exists() {
+            let data = fs::read(&snapshot_path)?;
+            plaintext_found |= !is_sealed(&data);
+            let snapshot: Snapshot = serde_json::from_slice(&unseal_with(data, cipher.as_ref())?)?;
+            let mut dict = snapshot.dict;
+            dict.rebuild_index();
+            (dict, snapshot.last_seq)
+        } else {
+            (UserDict::new(), 0)
+        };
+        // 日志重放的使用次数记在本机设备下，设备 ID 必须在重放前确定
+        let device_id = read_or_create_device_id(dir, &dict)?;
+        dict.set_device_id(&device_id);
+
+        let journal_path = dir.join(JOURNAL_FILE);
+        let replay = replay_journal(&journal_path, &mut dict, last_seq, cipher.as_ref())?;
+        plaintext_found |= replay.plaintext > 0;
+        let journal = OpenOptions::new().create(true).append(true).open(&journal_path)?;
+
+        let mut store = Self {
+            dir: dir.to_path_buf(),
+            journal,
+            next_seq: last_seq.max(replay.max_seq) + 1,
+            pending: replay.replayed,
+            compact_threshold: DEFAULT_COMPACT_THRESHOLD,
+            sync_on_append: true,
+            cipher,
+        };
+        if store.cipher.is_some() && plaintext_found {
+            log::info!("加密用户词库中的明文数据: {}", dir.display());
+            store.compact(&dict)?;
+        }
+        Ok((store, dict))
+    }
+
+    /// 设置自动压缩阈值（条）
+    pub fn set_compact_threshold(&mut self, threshold: usize) {
+        self.compact_threshold = threshold.max(1);
+    }
+
+    /// 设置是否每次追加后同步到磁盘（关闭后更快，但断电时可能丢失最近的记录）
+    pub fn set_sync_on_append(&mut self, sync: bool) {
+        self.sync_on_append = sync;
+    }
+
+    /// 加密器，未加密时为 None
+    pub fn cipher(&self) -> Option<&DictCipher> {
+        self.cipher.as_ref()
+    }
+
+    /// 是否加密存储
+    pub fn is_encrypted(&self) -> bool {
+        self.cipher.is_some()
+    }
+
+    /// 轮换密钥：用新的加密器重写快照并清空日志，`cipher` 为 None 时改为明文存储
+    ///
+    /// 快照写入成功前磁盘上仍是旧密钥加密的数据，之后只能用新密钥打开；
+    /// 写入失败时继续使用旧的加密器
+    pub fn rotate_key(&mut self, dict: &UserDict, cipher: Option<DictCipher>) -> anyhow::Result<()> {
+        self.compact_with(dict, cipher.as_ref())?;
+        self.cipher = cipher;
+        Ok(())
+    }
+
+    /// 存储目录
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// 上次压缩后追加的日志条数
+    pub fn pending_events(&self) -> usize {
+        self.pending
+    }
+
+    /// 记录一次使用：写入日志并更新词库
+    pub fn record_usage(&mut self, dict: &mut UserDict, text: &str, pinyin: &str, timestamp: u64) -> anyhow::Result<()> {
+        self.apply(
+            dict,
+            JournalEvent::Record {
+                text: text.to_string(),
+                pinyin: pinyin.to_string(),
+                timestamp,
+            },
+        )
+    }
+
+    /// 撤销一次使用：写入日志并更新词库
+    pub fn rollback_usage(
+        &mut self,
+        dict: &mut UserDict,
+        text: &str,
+        pinyin: &str,
+        previous: Option<WordRecord>,
+    ) -> anyhow::Result<()> {
+        self.apply(
+            dict,
+            JournalEvent::Rollback {
+                text: text.to_string(),
+                pinyin: pinyin.to_string(),
+                previous,
+            },
+        )
+    }
+
+    /// 删除或屏蔽词条
+    pub fn delete(&mut self, dict: &mut UserDict, text: &str, pinyin: &str, timestamp: u64) -> anyhow::Result<()> {
+        self.apply(
+            dict,
+            JournalEvent::Delete {
+                text: text.to_string(),
+                pinyin: pinyin.to_string(),
+                timestamp,
+            },
+        )
+    }
+
+    /// 手动添加词条
+    pub fn add_word(&mut self, dict: &mut UserDict, text: &str, pinyin: &str, timestamp: u64) -> anyhow::Result<()> {
+        self.apply(
+            dict,
+            JournalEvent::Add {
+                text: text.to_string(),
+                pinyin: pinyin.to_string(),
+                timestamp,
+            },
+        )
+    }
+
+    /// 固定或取消固定候选位置
+    pub fn pin(
+        &mut self,
+        dict: &mut UserDict,
+        text: &str,
+        pinyin: &str,
+        position: Option<usize>,
+        timestamp: u64,
+    ) -> anyhow::Result<()> {
+        self.apply(
+            dict,
+            JournalEvent::Pin {
+                text: text.to_string(),
+                pinyin: pinyin.to_string(),
+                position,
+                timestamp,
+            },
+        )
+    }
+
+    /// 清除时间段内的使用记录，返回清除的词条数
+    ///
+    /// 清除后立即压缩，使日志中记录这些词条的旧事件一并从磁盘删除
+    pub fn purge(&mut self, dict: &mut UserDict, start: u64, end: u64, timestamp: u64) -> anyhow::Result<usize> {
+        let before = dict.stats().total_records;
+        self.apply(dict, JournalEvent::Purge { start, end, timestamp })?;
+        self.compact(dict)?;
+        Ok(before - dict.stats().total_records)
+    }
+
+    /// 追加事件并应用到词库，达到阈值时自动压缩
+    ///
+    /// 日志写入成功后才修改词库；写入失败时截掉写了一半的内容，词库保持不变，与磁盘一致
+    pub fn apply(&mut self, dict: &mut UserDict, event: JournalEvent) -> anyhow::Result<()> {
+        let entry = JournalEntry { seq: self.next_seq, event };
+        let mut line = serde_json::to_vec(&entry)?;
+        if let Some(ref cipher) = self.cipher {
+            let sealed = SealedEntry {
+                seq: entry.seq,
+                sealed: to_hex(&cipher.seal(&line)?),
+            };
+            line = serde_json::to_vec(&sealed)?;
+        }
+        line.push(b'\n');
+        let len = self.journal.metadata()?.len();
+        if let Err(e) = self.append_line(&line) {
+            let _ = self.journal.set_len(len);
+            return Err(e.into());
+        }
+        entry.event.apply(dict);
+        self.next_seq += 1;
+        self.pending += 1;
+
+        // 事件已经落盘，压缩失败只影响日志长度，下次追加时重试
+        if self.pending >= self.compact_threshold {
+            if let Err(e) = self.compact(dict) {
+                log::warn!("压缩用户词库失败: {}", e);
+            }
+        }
+        Ok(())
+    }
+
+    fn append_line(&mut self, line: &[u8]) -> std::io::Result<()> {
+        self.journal.write_all(line)?;
+        if self.sync_on_append {
+            self.journal.sync_data()?;
+        }
+        Ok(())
+    }
+
+    /// 将词库压缩为新快照并清空日志
+    ///
+    /// 快照写入临时文件后原子重命名；重命名成功后才清空日志，
+    /// 中途崩溃时按序号重放不会重复计数
+    pub fn compact(&mut self, dict: &UserDict) -> anyhow::Result<()> {
+        let cipher = self.cipher.take();
+        let result = self.compact_with(dict, cipher.as_ref());
+        self.cipher = cipher;
+        result
+    }
+
+    /// 用指定的加密器写入快照并清空日志
+    fn compact_with(&mut self, dict: &UserDict, cipher: Option<&DictCipher>) -> anyhow::Result<()> {
+        let snapshot = Snapshot {
+            last_seq: self.next_seq - 1,
+            dict: dict.clone(),
+        };
+        let data = seal_with(serde_json::to_vec(&snapshot)?, cipher)?;
+        write_atomic(&self.dir.join(SNAPSHOT_FILE), &data)?;
+
+        self.journal.set_len(0)?;
+        self.journal.sync_all()?;
+        self.pending = 0;
+        log::debug!("用户词库已压缩: {}", self.dir.display());
+        Ok(())
+    }
+
+    /// 从 JSON 文件导入词库，替换当前内容并立即压缩，导入的词频记在本机设备下
+    pub fn import_json(&mut self, dict: &mut UserDict, path: &Path) -> anyhow::Result<()> {
+        let mut imported = UserDict::load_from_file(path)?;
+        imported.set_device_id(dict.device_id());
+        *dict = imported;
+        self.compact(dict)
+    }
+
+    /// 导出为 JSON 文件（明文）
+    pub fn export_json(&self, dict: &UserDict, path: &Path) -> anyhow::Result<()> {
+        dict.save_to_file(path)
+    }
+}
+
+/// 读取存储目录中的设备 ID，没有时沿用快照中的 ID（旧版本的存储）或生成新 ID 并写入
+fn read_or_create_device_id(dir: &Path, dict: &UserDict) -> anyhow::Result<String> {
+    let path = dir.join(DEVICE_ID_FILE);
+    if path.exists() {
+        let id = fs::read_to_string(&path)?.trim().to_string();
+        if !id.is_empty() {
+            return Ok(id);
+        }
+    }
+    let id = if dict.device_id().is_empty() {
+        generate_device_id()
+    } else {
+        dict.device_id().to_string()
+    };
+    write_atomic(&path, id.as_bytes())?;
+    Ok(id)
+}
+
+/// 日志重放结果
+struct Replay {
+    /// 最大序号
+    max_seq: u64,
+    /// 重放条数
+    replayed: usize,
+    /// 其中明文的条数
+    plaintext: usize,
+}
+
+/// 重放日志中序号大于 `after` 的事件
+///
+/// 中间无法解析的行被跳过；最后一行无法解析（崩溃时写了一半）时截断到它之前。
+/// 完整的加密行无法解密时返回错误，不截断
+fn replay_journal(path: &Path, dict: &mut UserDict, after: u64, cipher: Option<&DictCipher>) -> anyhow::Result<Replay> {
+    let mut replay = Replay {
+        max_seq: after,
+        replayed: 0,
+        plaintext: 0,
+    };
+    if !path.exists() {
+        return Ok(replay);
+    }
+
+    let mut reader = BufReader::new(File::open(path)?);
+    let mut offset: u64 = 0;
+    // 最近一行无法解析的日志的起始位置，之后又读到完整的日志时说明它在中间
+    let mut corrupt: Option<u64> = None;
+    let mut line = Vec::new();
+    loop {
+        line.clear();
+        let read = reader.read_until(b'\n', &mut line)?;
+        if read == 0 {
+            break;
+        }
+        let start = offset;
+        offset += read as u64;
+        let entry = match line.last() {
+            Some(b'\n') => serde_json::from_slice::<JournalLine>(&line).ok(),
+            _ => None,
+        };
+        let entry = match entry {
+            Some(entry) => entry,
+            None => {
+                if let Some(previous) = corrupt.replace(start) {
+                    log::warn!("跳过用户词库日志中第 {} 字节处损坏的一行", previous);
+                }
+                continue;
+            }
+        };
+        if let Some(previous) = corrupt.take() {
+            log::warn!("跳过用户词库日志中第 {} 字节处损坏的一行", previous);
+        }
+        if entry.seq() <= after {
+            continue;
+        }
+        let entry = match entry {
+            JournalLine::Plain(entry) => {
+                replay.plaintext += 1;
+                entry
+            }
+            JournalLine::Sealed(sealed) => open_sealed_entry(&sealed, cipher)?,
+        };
+        entry.event.apply(dict);
+        replay.max_seq = replay.max_seq.max(entry.seq);
+        replay.replayed += 1;
+    }
+    if let Some(start) = corrupt {
+        log::warn!("用户词库日志末尾在第 {} 字节处写了一半，丢弃之后的内容", start);
+        OpenOptions::new().write(true).open(path)?.set_len(start)?;
+    }
+    Ok(replay)
+}
+
+/// 解密一行加密日志
+fn open_sealed_entry(sealed: &SealedEntry, cipher: Option<&DictCipher>) -> Result<JournalEntry, LingCodeError> {
+    let cipher = cipher.ok_or_else(|| LingCodeError::DecryptionError("用户词库日志已加密，需要提供密钥".to_string()))?;
+    let data = from_hex(&sealed.sealed)
+        .ok_or_else(|| LingCodeError::DecryptionError(format!("日志第 {} 条格式错误", sealed.seq)))?;
+    let entry: JournalEntry = serde_json::from_slice(&cipher.open(&data)?)
+        .map_err(|_| LingCodeError::DecryptionError(format!("日志第 {} 条内容损坏", sealed.seq)))?;
+    if entry.seq != sealed.seq {
+        return Err(LingCodeError::DecryptionError(format!("日志第 {} 条序号不匹配", sealed.seq)));
+    }
+    Ok(entry)
+}
+
+/// 原子写入文件：先写临时文件并同步，再重命名覆盖目标
+pub fn write_atomic(path: &Path, data: &[u8]) -> anyhow::Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let mut tmp_name = path.file_name().unwrap_or_default().to_os_string();
+    tmp_name.push(".tmp");
+    let tmp_path = path.with_file_name(tmp_name);
+
+    {
+        let mut writer = BufWriter::new(File::create(&tmp_path)?);
+        writer.write_all(data)?;
+        writer.flush()?;
+        writer.get_ref().sync_all()?;
+    }
+    fs::rename(&tmp_path, path)?;
+
+    // 同步目录，确保重命名落盘（部分平台不支持打开目录，忽略错误）
+    if let Some(parent) = path.parent() {
+        if let Ok(dir) = File::open(parent) {
+            let _ = dir.sync_all();
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_journal_replay() {
+        let dir = TempDir::new().unwrap();
+        {
+            let (mut store, mut dict) = UserDictStore::open(dir.path()).unwrap();
+            store.record_usage(&mut dict, "中国", "zhongguo", 100).unwrap();
+            store.record_usage(&mut dict, "中国", "zhongguo", 200).unwrap();
+            store.record_usage(&mut dict, "行", "hang", 300).unwrap();
+            let previous = dict.get_record("行", "hang").cloned();
+            store.record_usage(&mut dict, "行", "hang", 400).unwrap();
+            store.rollback_usage(&mut dict, "行", "hang", previous).unwrap();
+            // 不压缩直接丢弃，模拟进程崩溃
+        }
+
+        let (store, dict) = UserDictStore::open(dir.path()).unwrap();
+        assert_eq!(store.pending_events(), 5);
+        assert_eq!(dict.get_count("中国"), 2);
+        assert_eq!(dict.get_record("中国", "zhongguo").unwrap().last_used, 200);
+        assert_eq!(dict.get_reading_count("行", "hang"), 1);
+    }
+
+    #[test]
+    fn test_compaction_and_seq() {
+        let dir = TempDir::new().unwrap();
+        let (mut store, mut dict) = UserDictStore::open(dir.path()).unwrap();
+        store.set_compact_threshold(2);
+        store.record_usage(&mut dict, "你好", "nihao", 1).unwrap();
+        store.record_usage(&mut dict, "你好", "nihao", 2).unwrap();
+        assert_eq!(store.pending_events(), 0);
+        assert_eq!(fs::metadata(dir.path().join(JOURNAL_FILE)).unwrap().len(), 0);
+        store.record_usage(&mut dict, "你好", "nihao", 3).unwrap();
+        drop(store);
+
+        let (mut store, mut dict) = UserDictStore::open(dir.path()).unwrap();
+        assert_eq!(dict.get_count("你好"), 3);
+
+        // 快照已写入但日志尚未清空时崩溃：已包含在快照中的日志不会重复计数
+        let journal_path = dir.path().join(JOURNAL_FILE);
+        let journal = fs::read(&journal_path).unwrap();
+        store.compact(&dict).unwrap();
+        fs::write(&journal_path, journal).unwrap();
+        let (_, reopened) = UserDictStore::open(dir.path()).unwrap();
+        assert_eq!(reopened.get_count("你好"), 3);
+
+        store.record_usage(&mut dict, "你好", "nihao", 4).unwrap();
+        let (_, reopened) = UserDictStore::open(dir.path()).unwrap();
+        assert_eq!(reopened.get_count("你好"), 4);
+    }
+
+    #[test]
+    fn test_truncated_journal_line() {
+        let dir = TempDir::new().unwrap();
+        {
+            let (mut store, mut dict) = UserDictStore::open(dir.path()).unwrap();
+            store.record_usage(&mut dict, "中文", "zhongwen", 1).unwrap();
+        }
+        let journal_path = dir.path().join(JOURNAL_FILE);
+        let mut file = OpenOptions::new().append(true).open(&journal_path).unwrap();
+        file.write_all(r#"{"seq":2,"op":"record","text":"中"#.as_bytes()).unwrap();
+        drop(file);
+
+        let (mut store, mut dict) = UserDictStore::open(dir.path()).unwrap();
+        assert_eq!(dict.get_count("中文"), 1);
+        store.record_usage(&mut dict, "中文", "zhongwen", 2).unwrap();
+        let (_, dict) = UserDictStore::open(dir.path()).unwrap();
+        assert_eq!(dict.get_count("中文"), 2);
+    }
+
+    #[test]
+    fn test_corrupt_line_in_middle_skipped() {
+        let dir = TempDir::new().unwrap();
+        {
+            let (mut store, mut dict) = UserDictStore::open(dir.path()).unwrap();
+            store.record_usage(&mut dict, "中文", "zhongwen", 1).unwrap();
+        }
+        let journal_path = dir.path().join(JOURNAL_FILE);
+        let mut file = OpenOptions::new().append(true).open(&journal_path).unwrap();
+        file.write_all(b"{\"seq\":2,\"op\":\"rec\xff\n").unwrap();
+        file.write_all(r#"{"seq":3,"op":"record","text":"中文","pinyin":"zhongwen","timestamp":3}"#.as_bytes()).unwrap();
+        file.write_all(b"\n").unwrap();
+        drop(file);
+        let len = fs::metadata(&journal_path).unwrap().len();
+
+        // 损坏的行之后的事件照常重放，日志不被截断
+        let (store, dict) = UserDictStore::open(dir.path()).unwrap();
+        assert_eq!(dict.get_count("中文"), 2);
+        assert_eq!(store.pending_events(), 2);
+        assert_eq!(fs::metadata(&journal_path).unwrap().len(), len);
+    }
+
+    #[test]
+    fn test_failed_append_leaves_dict_unchanged() {
+        let dir = TempDir::new().unwrap();
+        let (mut store, mut dict) = UserDictStore::open(dir.path()).unwrap();
+        // 换成只读句柄，模拟写入失败
+        store.journal = File::open(dir.path().join(JOURNAL_FILE)).unwrap();
+        assert!(store.record_usage(&mut dict, "中文", "zhongwen", 1).is_err());
+        assert_eq!(dict.get_count("中文"), 0);
+        assert_eq!(store.pending_events(), 0);
+    }
+
+    #[test]
+    fn test_device_id_stable_across_reopens() {
+        let dir = TempDir::new().unwrap();
+        let folder = TempDir::new().unwrap();
+        let device_id = {
+            let (mut store, mut dict) = UserDictStore::open(dir.path()).unwrap();
+            store.record_usage(&mut dict, "中文", "zhongwen", 1).unwrap();
+            dict.device_id().to_string()
+        };
+
+        // 只有日志、没有快照的存储重新打开后设备 ID 不变，重复同步不会重复计数
+        for _ in 0..2 {
+            let (_, mut dict) = UserDictStore::open(dir.path()).unwrap();
+            assert_eq!(dict.device_id(), device_id);
+            crate::sync::sync_with_folder(&mut dict, folder.path()).unwrap();
+            assert_eq!(dict.get_count("中文"), 1);
+        }
+        assert_eq!(fs::read_dir(folder.path()).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn test_json_import_export() {
+        let dir = TempDir::new().unwrap();
+        let (mut store, mut dict) = UserDictStore::open(dir.path()).unwrap();
+        store.record_usage(&mut dict, "测试", "ceshi", 1).unwrap();
+
+        let json = dir.path().join("export.json");
+        store.export_json(&dict, &json).unwrap();
+
+        let (mut other, mut imported) = UserDictStore::open(&dir.path().join("other")).unwrap();
+        other.import_json(&mut imported, &json).unwrap();
+        assert_eq!(imported.get_count("测试"), 1);
+        let (_, reopened) = UserDictStore::open(&dir.path().join("other")).unwrap();
+        assert_eq!(reopened.get_count("测试"), 1);
+    }
+    #[test]
+    fn test_replay_edit_events() {
+        let dir = TempDir::new().unwrap();
+        {
+            let (mut store, mut dict) = UserDictStore::open(dir.path()).unwrap();
+            store.record_usage(&mut dict, "中国", "zhongguo", 1).unwrap();
+            store.delete(&mut dict, "中国", "zhongguo", 2).unwrap();
+            store.add_word(&mut dict, "灵码", "lm", 3).unwrap();
+            store.pin(&mut dict, "灵码", "lm", Some(0), 4).unwrap();
+        }
+
+        let (_, dict) = UserDictStore::open(dir.path()).unwrap();
+        assert!(dict.is_suppressed("中国", "zhongguo"));
+        assert_eq!(dict.get_reading_count("灵码", "lm"), 1);
+        assert_eq!(dict.pins_for("lm"), vec![("灵码", 0)]);
+    }
+
+    #[test]
+    fn test_purge_compacts_journal() {
+        let dir = TempDir::new().unwrap();
+        let (mut store, mut dict) = UserDictStore::open(dir.path()).unwrap();
+        store.record_usage(&mut dict, "密码", "mima", 100).unwrap();
+        store.record_usage(&mut dict, "你好", "nihao", 300).unwrap();
+
+        assert_eq!(store.purge(&mut dict, 50, 150, 400).unwrap(), 1);
+        assert_eq!(store.pending_events(), 0);
+        let journal = std::fs::read_to_string(dir.path().join(JOURNAL_FILE)).unwrap();
+        assert!(!journal.contains("密码"));
+
+        let snapshot = std::fs::read_to_string(dir.path().join(SNAPSHOT_FILE)).unwrap();
+        assert!(!snapshot.contains("密码") && !snapshot.contains("mima"));
+        // 清除不屏蔽系统词库的同名候选
+        assert!(!dict.is_suppressed("密码", "mima"));
+
+        let sync_dir = TempDir::new().unwrap();
+        crate::sync::sync_with_folder(&mut dict, sync_dir.path()).unwrap();
+        for entry in std::fs::read_dir(sync_dir.path()).unwrap() {
+            let data = std::fs::read_to_string(entry.unwrap().path()).unwrap();
+            assert!(!data.contains("密码") && !data.contains("mima"));
+        }
+
+        let (_, reopened) = UserDictStore::open(dir.path()).unwrap();
+        assert_eq!(reopened.get_count("密码"), 0);
+        assert_eq!(reopened.get_count("你好"), 1);
+    }
+
+    #[test]
+    fn test_encrypted_store() {
+        let dir = TempDir::new().unwrap();
+        {
+            let (mut store, mut dict) = UserDictStore::open_encrypted(dir.path(), DictCipher::from_key([1; 32])).unwrap();
+            store.record_usage(&mut dict, "机密", "jimi", 1).unwrap();
+            store.compact(&dict).unwrap();
+            store.record_usage(&mut dict, "中文", "zhongwen", 2).unwrap();
+        }
+        for file in [SNAPSHOT_FILE, JOURNAL_FILE] {
+            let data = fs::read(dir.path().join(file)).unwrap();
+            assert!(!String::from_utf8_lossy(&data).contains("jimi") && !String::from_utf8_lossy(&data).contains("zhongwen"));
+        }
+
+        // 密钥错误或未提供密钥时报错，数据保持不变
+        let journal_len = fs::metadata(dir.path().join(JOURNAL_FILE)).unwrap().len();
+        let err = UserDictStore::open_encrypted(dir.path(), DictCipher::from_key([2; 32])).unwrap_err();
+        assert!(matches!(err.downcast_ref(), Some(LingCodeError::DecryptionError(_))));
+        assert!(UserDictStore::open(dir.path()).is_err());
+        assert_eq!(fs::metadata(dir.path().join(JOURNAL_FILE)).unwrap().len(), journal_len);
+
+        let (_, dict) = UserDictStore::open_encrypted(dir.path(), DictCipher::from_key([1; 32])).unwrap();
+        assert_eq!(dict.get_count("机密"), 1);
+        assert_eq!(dict.get_count("中文"), 1);
+    }
+
+    #[test]
+    fn test_encrypt_existing_store_and_rotate_key() {
+        let dir = TempDir::new().unwrap();
+        {
+            let (mut store, mut dict) = UserDictStore::open(dir.path()).unwrap();
+            store.record_usage(&mut dict, "中文", "zhongwen", 1).unwrap();
+        }
+
+        // 明文数据在加密打开时立即加密
+        {
+            let (store, dict) = UserDictStore::open_encrypted(dir.path(), DictCipher::from_key([1; 32])).unwrap();
+            assert!(store.is_encrypted());
+            assert_eq!(dict.get_count("中文"), 1);
+            assert!(is_sealed(&fs::read(dir.path().join(SNAPSHOT_FILE)).unwrap()));
+            assert_eq!(fs::metadata(dir.path().join(JOURNAL_FILE)).unwrap().len(), 0);
+        }
+
+        {
+            let (mut store, dict) = UserDictStore::open_encrypted(dir.path(), DictCipher::from_key([1; 32])).unwrap();
+            store.rotate_key(&dict, Some(DictCipher::from_key([3; 32]))).unwrap();
+        }
+        assert!(UserDictStore::open_encrypted(dir.path(), DictCipher::from_key([1; 32])).is_err());
+        let (mut store, dict) = UserDictStore::open_encrypted(dir.path(), DictCipher::from_key([3; 32])).unwrap();
+        assert_eq!(dict.get_count("中文"), 1);
+
+        // 快照写入失败时保留旧密钥
+        fs::remove_file(dir.path().join(SNAPSHOT_FILE)).unwrap();
+        fs::create_dir_all(dir.path().join(SNAPSHOT_FILE).join("blocked")).unwrap();
+        assert!(store.rotate_key(&dict, None).is_err());
+        assert!(store.is_encrypted());
+        fs::remove_dir_all(dir.path().join(SNAPSHOT_FILE)).unwrap();
+        store.compact(&dict).unwrap();
+
+        // 轮换为明文
+        store.rotate_key(&dict, None).unwrap();
+        let (_, dict) = UserDictStore::open(dir.path()).unwrap();
+        assert_eq!(dict.get_count("中文"), 1);
+    }
+}
@@ -0,0 +1,402 @@
+//! N 元语言模型
+//!
+//! 本地一元/二元语言模型，用于候选词排序与整句搜索。
+//! 一元计数来自词库权重与用户词频，二元计数来自用户连续上屏的记录；
+//! 所有得分均为自然对数概率，不同来源的候选可以直接比较。
+//!
+//! 模型可以叠加在共享的基础模型上（见 [`LanguageModel::with_base`]）：
+//! 词库构建的大模型由多个会话共享，每个会话只保存自己的用户计数
+
+use crate::user_dict::UserDict;
+use std::collections::HashMap;
+use std::mem::size_of;
+use std::sync::Arc;
+
+/// 二元概率的插值系数
+const DEFAULT_BIGRAM_LAMBDA: f64 = 0.7;
+
+/// 用户使用一次相当于平均词条权重的倍数
+const USER_COUNT_FACTOR: f64 = 10.0;
+
+/// 整句搜索中每个位置保留的路径数
+const BEAM_SIZE: usize = 8;
+
+/// 整句搜索中单个词最多包含的音节数
+const MAX_WORD_SYLLABLES: usize = 6;
+
+/// 一元/二元语言模型
+#[derive(Debug, Clone)]
+pub struct LanguageModel {
+    /// 词 -> 计数
+    unigrams: HashMap<String, f64>,
+    /// 前一个词 -> (后一个词 -> 计数)
+    bigrams: HashMap<String, HashMap<String, f64>>,
+    /// 前一个词的二元计数总和
+    bigram_totals: HashMap<String, f64>,
+    /// 一元计数总和
+    total: f64,
+    /// 二元概率的插值系数
+    lambda: f64,
+    /// 用户使用一次对应的计数
+    user_scale: f64,
+    /// 共享的基础模型，计数与本模型相加
+    base: Option<Arc<LanguageModel>>,
+    /// 不在基础模型中的词数
+    new_words: usize,
+}
+
+impl LanguageModel {
+    /// 创建空的语言模型
+    pub fn new() -> Self {
+        Self {
+            unigrams: HashMap::new(),
+            bigrams: HashMap::new(),
+            bigram_totals: HashMap::new(),
+            total: 0.0,
+            lambda: DEFAULT_BIGRAM_LAMBDA,
+            user_scale: USER_COUNT_FACTOR,
+            base: None,
+            new_words: 0,
+        }
+    }
+
+    /// 创建叠加在基础模型上的空模型，沿用基础模型的插值系数与用户计数
+    pub fn with_base(base: Arc<LanguageModel>) -> Self {
+        let mut model = Self::new();
+        model.lambda = base.lambda;
+        model.user_scale = base.user_scale;
+        model.base = Some(base);
+        model
+    }
+
+    /// 基础模型
+    pub fn base(&self) -> Option<&Arc<LanguageModel>> {
+        self.base.as_ref()
+    }
+
+    /// 替换基础模型，本模型的计数保持不变
+    pub fn set_base(&mut self, base: Option<Arc<LanguageModel>>) {
+        self.base = base;
+        self.new_words = match &self.base {
+            Some(base) => self.unigrams.keys().filter(|word| !base.contains(word)).count(),
+            None => self.unigrams.len(),
+        };
+    }
+
+    /// 从词条（文字, 权重）构建一元模型，同一个词的权重取最大值
+    pub fn from_entries<'a>(entries: impl IntoIterator<Item = (&'a str, u32)>) -> Self {
+        let mut weights: HashMap<&str, u32> = HashMap::new();
+        for (text, weight) in entries {
+            let entry = weights.entry(text).or_insert(0);
+            *entry = (*entry).max(weight);
+        }
+        let mut model = Self::new();
+        for (text, weight) in weights {
+            model.add_unigram(text, weight as f64);
+        }
+        model.user_scale = model.mean_count() * USER_COUNT_FACTOR;
+        model
+    }
+
+    /// 设置二元概率的插值系数（0~1）
+    pub fn set_bigram_lambda(&mut self, lambda: f64) {
+        self.lambda = lambda.clamp(0.0, 1.0);
+    }
+
+    /// 增加一元计数，计数为负时减少（不低于 0）
+    pub fn add_unigram(&mut self, word: &str, count: f64) {
+        if !self.contains(word) {
+            self.new_words += 1;
+        }
+        let base_count = self.base.as_ref().map_or(0.0, |base| base.unigram_count(word));
+        let entry = self.unigrams.entry(word.to_string()).or_insert(0.0);
+        // 叠加时允许本模型计数为负（撤销基础模型中的计数），合计不低于 0
+        let updated = (*entry + count).max(-base_count);
+        self.total += updated - *entry;
+        *entry = updated;
+    }
+
+    /// 增加二元计数，计数为负时减少（不低于 0）
+    pub fn add_bigram(&mut self, previous: &str, word: &str, count: f64) {
+        let base_count = self.base.as_ref().map_or(0.0, |base| base.bigram_count(previous, word).0);
+        let entry = self
+            .bigrams
+            .entry(previous.to_string())
+            .or_default()
+            .entry(word.to_string())
+            .or_insert(0.0);
+        let updated = (*entry + count).max(-base_count);
+        *self.bigram_totals.entry(previous.to_string()).or_insert(0.0) += updated - *entry;
+        *entry = updated;
+    }
+
+    /// 取走另一个模型自己的二元计数（不含其基础模型的）加入本模型，
+    /// 用于换用新的基础模型重建时保留运行期间记录的二元组
+    pub fn take_bigrams(&mut self, other: &mut LanguageModel) {
+        other.bigram_totals.clear();
+        for (previous, next) in std::mem::take(&mut other.bigrams) {
+            for (word, count) in next {
+                self.add_bigram(&previous, &word, count);
+            }
+        }
+    }
+
+    /// 清除本模型自己的二元计数
+    pub fn clear_bigrams(&mut self) {
+        self.bigrams.clear();
+        self.bigram_totals.clear();
+    }
+
+    /// 用户使用一次对应的计数，构建时取平均词条计数的若干倍，
+    /// 使用户词频与词库权重处于同一量级
+    pub fn user_scale(&self) -> f64 {
+        self.user_scale
+    }
+
+    /// 记录用户使用某个词 `times` 次，次数为负时撤销
+    pub fn add_user_usage(&mut self, word: &str, times: f64) {
+        self.add_unigram(word, times * self.user_scale);
+    }
+
+    /// 加入用户词频
+    pub fn add_user_history(&mut self, user_dict: &UserDict) {
+        for record in user_dict.records() {
+            self.add_user_usage(&record.text, record.count as f64);
+        }
+    }
+
+    /// 一元对数概率（加一平滑，未知词也有非零概率）
+    pub fn log_prob(&self, word: &str) -> f64 {
+        self.unigram_prob(word).ln()
+    }
+
+    /// 在前文条件下的对数概率：二元概率与一元概率插值，前文没有二元记录时退回一元
+    pub fn conditional_log_prob(&self, previous: Option<&str>, word: &str) -> f64 {
+        let unigram = self.unigram_prob(word);
+        let bigram = previous.and_then(|previous| {
+            let (count, total) = self.bigram_count(previous, word);
+            (total > 0.0).then(|| count / total)
+        });
+        match bigram {
+            Some(bigram) => (self.lambda * bigram + (1.0 - self.lambda) * unigram).ln(),
+            None => unigram.ln(),
+        }
+    }
+
+    /// 词序列的对数概率
+    pub fn sentence_log_prob(&self, context: Option<&str>, words: &[&str]) -> f64 {
+        let mut previous = context;
+        let mut score = 0.0;
+        for word in words {
+            score += self.conditional_log_prob(previous, word);
+            previous = Some(word);
+        }
+        score
+    }
+
+    /// 整句搜索：将音节序列切分为词并返回概率最高的句子及其对数概率
+    ///
+    /// `lookup` 返回一段拼音（音节直接相连）对应的词，`context` 为前文
+    pub fn best_sentence<F>(&self, syllables: &[&str], context: Option<&str>, lookup: F) -> Option<(String, f64)>
+    where
+        F: Fn(&str) -> Vec<String>,
+    {
+        // lattice[i]：覆盖前 i 个音节的路径 (句子, 最后一个词, 得分)
+        let mut lattice: Vec<Vec<(String, String, f64)>> = vec![Vec::new(); syllables.len() + 1];
+        lattice[0].push((String::new(), context.unwrap_or_default().to_string(), 0.0));
+
+        for start in 0..syllables.len() {
+            if lattice[start].is_empty() {
+                continue;
+            }
+            let paths = std::mem::take(&mut lattice[start]);
+            let end_limit = syllables.len().min(start + MAX_WORD_SYLLABLES);
+            for end in start + 1..=end_limit {
+                let pinyin = syllables[start..end].concat();
+                for word in lookup(&pinyin) {
+                    for (sentence, last, score) in &paths {
+                        let previous = if last.is_empty() { None } else { Some(last.as_str()) };
+                        let score = score + self.conditional_log_prob(previous, &word);
+                        lattice[end].push((format!("{}{}", sentence, word), word.clone(), score));
+                    }
+                }
+                prune(&mut lattice[end]);
+            }
+            lattice[start] = paths;
+        }
+
+        lattice[syllables.len()]
+            .iter()
+            .max_by(|a, b| a.2.total_cmp(&b.2))
+            .map(|(sentence, _, score)| (sentence.clone(), *score))
+    }
+
+    /// 词汇量
+    pub fn vocabulary_size(&self) -> usize {
+        match &self.base {
+            Some(base) => base.vocabulary_size() + self.new_words,
+            None => self.unigrams.len(),
+        }
+    }
+
+    /// 本模型（不含基础模型）占用的内存（字节，估算）
+    pub fn memory_bytes(&self) -> usize {
+        let counts = |map: &HashMap<String, f64>| map.keys().map(|word| Self::unigram_bytes(word)).sum::<usize>();
+        let bigrams: usize = self
+            .bigrams
+            .iter()
+            .map(|(word, next)| word.len() + size_of::<(String, HashMap<String, f64>)>() + counts(next))
+            .sum();
+        counts(&self.unigrams) + counts(&self.bigram_totals) + bigrams
+    }
+
+    /// 一个一元计数占用的内存（字节，估算）
+    pub fn unigram_bytes(word: &str) -> usize {
+        word.len() + size_of::<(String, f64)>()
+    }
+
+    fn mean_count(&self) -> f64 {
+        if self.unigrams.is_empty() {
+            1.0
+        } else {
+            (self.total / self.unigrams.len() as f64).max(1.0)
+        }
+    }
+
+    /// 词的一元计数（含基础模型）
+    fn unigram_count(&self, word: &str) -> f64 {
+        let own = self.unigrams.get(word).copied().unwrap_or(0.0);
+        own + self.base.as_ref().map_or(0.0, |base| base.unigram_count(word))
+    }
+
+    /// 词是否在本模型或基础模型中
+    fn contains(&self, word: &str) -> bool {
+        self.unigrams.contains_key(word) || self.base.as_ref().is_some_and(|base| base.contains(word))
+    }
+
+    /// 一元计数总和（含基础模型）
+    fn total_count(&self) -> f64 {
+        self.total + self.base.as_ref().map_or(0.0, |base| base.total_count())
+    }
+
+    /// 前一个词的二元计数与总和（含基础模型）
+    fn bigram_count(&self, previous: &str, word: &str) -> (f64, f64) {
+        let count = self.bigrams.get(previous).and_then(|next| next.get(word)).copied().unwrap_or(0.0);
+        let total = self.bigram_totals.get(previous).copied().unwrap_or(0.0);
+        match &self.base {
+            Some(base) => {
+                let (base_count, base_total) = base.bigram_count(previous, word);
+                (count + base_count, total + base_total)
+            }
+            None => (count, total),
+        }
+    }
+
+    fn unigram_prob(&self, word: &str) -> f64 {
+        let count = self.unigram_count(word);
+        (count + 1.0) / (self.total_count() + self.vocabulary_size() as f64 + 1.0)
+    }
+}
+
+impl Default for LanguageModel {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// 只保留得分最高的若干路径，相同结尾词只保留最优的一条
+fn prune(paths: &mut Vec<(String, String, f64)>) {
+    paths.sort_by(|a, b| b.2.total_cmp(&a.2));
+    let mut seen: Vec<String> = Vec::new();
+    paths.retain(|(_, last, _)| {
+        if seen.contains(last) {
+            false
+        } else {
+            seen.push(last.clone());
+            true
+        }
+    });
+    paths.truncate(BEAM_SIZE);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn model() -> LanguageModel {
+        LanguageModel::from_entries([("你好", 100), ("你", 100), ("好", 100), ("中国", 100), ("种", 10), ("过", 50)])
+    }
+
+    #[test]
+    fn test_unigram_log_prob() {
+        let model = model();
+        assert!(model.log_prob("中国") > model.log_prob("种"));
+        assert!(model.log_prob("种") > model.log_prob("不存在"));
+        assert!(model.log_prob("中国") < 0.0);
+    }
+
+    #[test]
+    fn test_bigram_context() {
+        let mut model = model();
+        let without = model.conditional_log_prob(Some("你好"), "种");
+        model.add_bigram("你好", "种", 5.0);
+        let with = model.conditional_log_prob(Some("你好"), "种");
+        assert!(with > without);
+        assert!(with > model.conditional_log_prob(Some("你好"), "中国"));
+
+        model.add_bigram("你好", "种", -5.0);
+        assert_eq!(model.conditional_log_prob(Some("你好"), "种"), model.log_prob("种"));
+    }
+
+    #[test]
+    fn test_user_usage() {
+        let mut model = model();
+        let before = model.log_prob("种");
+        model.add_user_usage("种", 1.0);
+        assert!(model.log_prob("种") > model.log_prob("中国"));
+        model.add_user_usage("种", -1.0);
+        assert!((model.log_prob("种") - before).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_layered_model() {
+        let base = Arc::new(LanguageModel::from_entries(vec![("中国", 100), ("中", 50)]));
+        let mut flat = LanguageModel::from_entries(vec![("中国", 100), ("中", 50)]);
+        let mut layered = LanguageModel::with_base(Arc::clone(&base));
+        for model in [&mut flat, &mut layered] {
+            model.add_user_usage("钟", 2.0);
+            model.add_bigram("中国", "人", 3.0);
+            model.add_unigram("中", -80.0);
+        }
+        assert_eq!(layered.vocabulary_size(), flat.vocabulary_size());
+        for word in ["中国", "中", "钟", "人"] {
+            assert!((layered.log_prob(word) - flat.log_prob(word)).abs() < 1e-12);
+            assert!(
+                (layered.conditional_log_prob(Some("中国"), word) - flat.conditional_log_prob(Some("中国"), word)).abs()
+                    < 1e-12
+            );
+        }
+        assert_eq!(base.vocabulary_size(), 2);
+    }
+
+    #[test]
+    fn test_best_sentence() {
+        let model = model();
+        let lookup = |pinyin: &str| -> Vec<String> {
+            match pinyin {
+                "ni" => vec!["你".to_string()],
+                "hao" => vec!["好".to_string()],
+                "nihao" => vec!["你好".to_string()],
+                "zhong" => vec!["种".to_string()],
+                "guo" => vec!["过".to_string()],
+                "zhongguo" => vec!["中国".to_string()],
+                _ => vec![],
+            }
+        };
+        let (sentence, score) = model.best_sentence(&["ni", "hao", "zhong", "guo"], None, lookup).unwrap();
+        assert_eq!(sentence, "你好中国");
+        assert!((score - model.sentence_log_prob(None, &["你好", "中国"])).abs() < 1e-9);
+
+        assert!(model.best_sentence(&["xx"], None, lookup).is_none());
+    }
+}
@@ -1,18 +1,44 @@
 // This file contains the main implementation for the dictionary library, which handles dictionary-related functionalities. 
 
+pub mod background;
+pub mod corpus;
+pub mod crypto;
 pub mod format;
+pub mod import;
+pub mod journal;
+pub mod language_model;
+pub mod lint;
+pub mod paged;
 pub mod rime_loader;
+pub mod rime_userdb;
+pub mod scoring;
+pub mod sync;
 pub mod user_dict;
 
+pub use background::{BackgroundLoad, LoadProgress, LoadedDict, ProgressCallback};
+pub use corpus::{DictBuildReport, DictBuilder, ReadingTable, Segmenter, Unannotated, WordListEntry};
+pub use crypto::DictCipher;
+pub use import::{import_bytes, import_file, ImportFormat, ImportReport, InvalidLine};
+pub use journal::{JournalEvent, UserDictStore};
+pub use language_model::LanguageModel;
+pub use lint::{lint_file, Diagnostic, LintKind, Severity};
+pub use paged::MemoryBudget;
 pub use rime_loader::{
     RimeDictLoader, 
     DictEntry, 
     DictSource, 
+    DictSpec,
+    DictStack,
     SourcedEntry,
     DictLoadSummary,
     DictStats,
 };
+pub use rime_userdb::RimeUserDbSummary;
+pub use scoring::{Clock, ManualClock, ScoringPolicy, SystemClock};
+pub use sync::{sync_with_folder, sync_with_folder_encrypted, SyncSummary};
 pub use user_dict::{
+    Pin,
+    Tombstone,
     UserDict,
     WordRecord,
     UserDictStats,
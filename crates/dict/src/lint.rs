@@ -0,0 +1,325 @@
+//! 词库检查
+//!
+//! [`RimeDictLoader`](crate::RimeDictLoader) 加载时会静默跳过格式错误的行，
+//! 无法解析的权重按 100 处理。检查器逐行报告这些问题，以及无效音节、
+//! 重复的（词, 编码）、音节数与字数不符和异常字符。诊断可序列化为 JSON
+
+use lingcode_core::error::Result;
+use lingcode_core::syllable::syllable_counts;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// 最长音节的字母数
+const MAX_SYLLABLE_LEN: usize = 6;
+
+/// 严重程度
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    /// 警告：词条可以加载，但可能有误
+    Warning,
+    /// 错误：词条无法按预期加载
+    Error,
+}
+
+/// 诊断类别
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LintKind {
+    /// 没有 YAML 头部结束标记 `...`，所有词条都会被跳过
+    MissingHeader,
+    /// 列数不对或词、编码为空
+    Malformed,
+    /// 权重不是非负整数
+    InvalidWeight,
+    /// 编码含有无效的拼音音节
+    InvalidSyllable,
+    /// 重复的（词, 编码）
+    Duplicate,
+    /// 音节数与字数不符
+    SyllableCountMismatch,
+    /// 异常字符（控制字符、零宽字符、私用区字符、首尾空白等）
+    UnusualCharacter,
+}
+
+impl LintKind {
+    /// 类别标识
+    pub fn id(&self) -> &'static str {
+        match self {
+            LintKind::MissingHeader => "missing_header",
+            LintKind::Malformed => "malformed",
+            LintKind::InvalidWeight => "invalid_weight",
+            LintKind::InvalidSyllable => "invalid_syllable",
+            LintKind::Duplicate => "duplicate",
+            LintKind::SyllableCountMismatch => "syllable_count_mismatch",
+            LintKind::UnusualCharacter => "unusual_character",
+        }
+    }
+
+    fn severity(&self) -> Severity {
+        match self {
+            LintKind::MissingHeader | LintKind::Malformed | LintKind::InvalidWeight => Severity::Error,
+            _ => Severity::Warning,
+        }
+    }
+}
+
+/// 一条诊断
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Diagnostic {
+    /// 文件
+    pub file: PathBuf,
+    /// 行号（从 1 开始）
+    pub line: usize,
+    /// 严重程度
+    pub severity: Severity,
+    /// 类别
+    pub kind: LintKind,
+    /// 说明
+    pub message: String,
+}
+
+impl Diagnostic {
+    /// 序列化为单行 JSON
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).unwrap_or_default()
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let severity = match self.severity {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        };
+        write!(
+            f,
+            "{}:{}: {}[{}]: {}",
+            self.file.display(),
+            self.line,
+            severity,
+            self.kind.id(),
+            self.message
+        )
+    }
+}
+
+/// 检查词库文件
+pub fn lint_file(path: &Path) -> Result<Vec<Diagnostic>> {
+    let data = fs::read(path)?;
+    Ok(lint_str(path, &String::from_utf8_lossy(&data)))
+}
+
+/// 检查词库内容，`file` 用于诊断中的文件名
+pub fn lint_str(file: &Path, text: &str) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+    let mut report = |line: usize, kind: LintKind, message: String| {
+        diagnostics.push(Diagnostic {
+            file: file.to_path_buf(),
+            line,
+            severity: kind.severity(),
+            kind,
+            message,
+        });
+    };
+
+    let mut in_header = true;
+    let mut seen: HashMap<(String, String), usize> = HashMap::new();
+    for (index, raw) in text.lines().enumerate() {
+        let number = index + 1;
+        let line = raw.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        if in_header {
+            in_header = line != "...";
+            continue;
+        }
+
+        let fields: Vec<&str> = line.split('\t').collect();
+        if !(2..=3).contains(&fields.len()) {
+            report(
+                number,
+                LintKind::Malformed,
+                format!("应为 词\\t编码[\\t权重]，实际有 {} 列", fields.len()),
+            );
+            continue;
+        }
+        let (text, code) = (fields[0], fields[1]);
+        if text.trim().is_empty() || code.trim().is_empty() {
+            report(number, LintKind::Malformed, "词或编码为空".to_string());
+            continue;
+        }
+        if let Some(weight) = fields.get(2) {
+            if weight.parse::<u32>().is_err() {
+                report(
+                    number,
+                    LintKind::InvalidWeight,
+                    format!("无效的权重 {:?}，加载时按 100 处理", weight),
+                );
+            }
+        }
+        if let Some(message) = unusual_characters(text) {
+            report(number, LintKind::UnusualCharacter, message);
+        }
+
+        let syllables: Vec<&str> = code.split(' ').filter(|s| !s.is_empty()).collect();
+        let invalid: Vec<&str> = syllables.iter().copied().filter(|s| !is_code(s)).collect();
+        if !invalid.is_empty() {
+            report(
+                number,
+                LintKind::InvalidSyllable,
+                format!("编码 {:?} 含有无效音节: {}", code, invalid.join(", ")),
+            );
+        } else if text.chars().all(is_han) {
+            let chars = text.chars().count();
+            let matches = match syllables.as_slice() {
+                // 无分隔符的编码只要能切分为相同个数的音节即可（如 xian 可以是 西安）
+                [code] => can_split_into(code, chars),
+                _ => syllables.len() == chars,
+            };
+            if !matches {
+                report(
+                    number,
+                    LintKind::SyllableCountMismatch,
+                    format!("{} 有 {} 个字，编码 {:?} 的音节数不同", text, chars, code),
+                );
+            }
+        }
+
+        let key = (text.to_string(), syllables.concat());
+        match seen.get(&key) {
+            Some(first) => report(
+                number,
+                LintKind::Duplicate,
+                format!("{} ({}) 与第 {} 行重复", text, code, first),
+            ),
+            None => {
+                seen.insert(key, number);
+            }
+        }
+    }
+
+    if in_header && !text.trim().is_empty() {
+        report(
+            1,
+            LintKind::MissingHeader,
+            "没有 YAML 头部结束标记 \"...\"，所有词条都会被跳过".to_string(),
+        );
+    }
+    diagnostics
+}
+
+/// 编码是否由有效音节组成（单个音节或无分隔符的多个音节）
+fn is_code(code: &str) -> bool {
+    is_letters(code) && syllable_counts(code).contains(&true)
+}
+
+/// 编码能否恰好切分为 `count` 个音节
+fn can_split_into(code: &str, count: usize) -> bool {
+    if code.is_empty() || count == 0 {
+        return code.is_empty() && count == 0;
+    }
+    if code.len() > count * MAX_SYLLABLE_LEN {
+        return false;
+    }
+    is_letters(code) && syllable_counts(code).get(count).copied().unwrap_or(false)
+}
+
+/// 编码只含小写字母（Rime 编码不用撇号分隔音节）
+fn is_letters(code: &str) -> bool {
+    !code.is_empty() && code.bytes().all(|b| b.is_ascii_lowercase())
+}
+
+/// 检查词中的异常字符，返回说明
+fn unusual_characters(text: &str) -> Option<String> {
+    if text != text.trim() {
+        return Some(format!("{:?} 首尾有空白", text));
+    }
+    let unusual: Vec<String> = text
+        .chars()
+        .filter(|&c| is_unusual(c))
+        .map(|c| format!("U+{:04X}", c as u32))
+        .collect();
+    (!unusual.is_empty()).then(|| format!("{:?} 含有异常字符: {}", text, unusual.join(", ")))
+}
+
+fn is_unusual(c: char) -> bool {
+    c.is_control()
+        || matches!(c,
+            '\u{200b}'..='\u{200f}' | '\u{2028}'..='\u{202e}' | '\u{2060}'..='\u{2064}' | '\u{feff}'
+            | '\u{fffd}' | '\u{3000}' | '\u{e000}'..='\u{f8ff}')
+}
+
+/// 是否为汉字
+pub(crate) fn is_han(c: char) -> bool {
+    matches!(c, '\u{3400}'..='\u{4dbf}' | '\u{4e00}'..='\u{9fff}' | '\u{f900}'..='\u{faff}' | '\u{20000}'..='\u{3134f}')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kinds(text: &str) -> Vec<(usize, LintKind)> {
+        lint_str(Path::new("test.dict.yaml"), text)
+            .into_iter()
+            .map(|d| (d.line, d.kind))
+            .collect()
+    }
+
+    #[test]
+    fn test_lint_entries() {
+        let text = "---\nname: test\n...\n\
+中国\tzhong guo\t100\n\
+西安\txian\t90\n\
+中国\tzhongguo\t80\n\
+坏行\n\
+你好\tni hao\tabc\n\
+你好\tni hzo\n\
+中华人民\tzhong hua\n\
+零\u{200b}宽\tling kuan\n";
+        assert_eq!(
+            kinds(text),
+            vec![
+                (6, LintKind::Duplicate),
+                (7, LintKind::Malformed),
+                (8, LintKind::InvalidWeight),
+                (9, LintKind::InvalidSyllable),
+                (10, LintKind::SyllableCountMismatch),
+                (11, LintKind::UnusualCharacter),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_syllable_split() {
+        assert!(can_split_into("xian", 1) && can_split_into("xian", 2));
+        assert!(!can_split_into("xian", 3));
+        assert!(is_code("zhongguo") && !is_code("zhongguq"));
+        // 可以多种方式切分的长编码不会回溯
+        let long = format!("{}q", "a".repeat(2000));
+        assert!(!is_code(&long));
+        assert!(can_split_into(&"a".repeat(2000), 2000));
+    }
+
+    #[test]
+    fn test_missing_header_and_json() {
+        let diagnostics = lint_str(Path::new("custom.dict.yaml"), "中\tzhong\t1\n");
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].kind, LintKind::MissingHeader);
+        assert_eq!(diagnostics[0].severity, Severity::Error);
+        assert_eq!(
+            diagnostics[0].to_string(),
+            "custom.dict.yaml:1: error[missing_header]: 没有 YAML 头部结束标记 \"...\"，所有词条都会被跳过"
+        );
+        let json: serde_json::Value = serde_json::from_str(&diagnostics[0].to_json()).unwrap();
+        assert_eq!(json["kind"], "missing_header");
+        assert_eq!(json["severity"], "error");
+        assert_eq!(json["line"], 1);
+
+        assert!(kinds("---\n...\n").is_empty());
+    }
+}
@@ -0,0 +1,595 @@
+//! 按内存预算分页的词库存储
+//!
+//! 移动设备无法把雾凇拼音与腾讯词库全部放进内存。设置内存预算（[`MemoryBudget`]）后，
+//! [`RimeDictLoader`](crate::RimeDictLoader) 把词条按编码排序后分成大小有限的页写入临时页文件，
+//! 内存中只保留页表；查询时在缓存锁外从磁盘读入所需的页，最近用过的页留在 LRU 缓存中，
+//! 缓存超出预算时淘汰最久未用的页
+
+use crate::rime_loader::{compare_entries, DictEntry, DictSource, SourcedEntry};
+use std::collections::HashMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::mem::size_of;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+/// 词库内存预算
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct MemoryBudget {
+    /// 词库占用内存的上限（字节，包括缓存页、页表以及由词条构建的语言模型与联想索引），
+    /// 0 表示全部常驻内存
+    pub max_bytes: usize,
+    /// 加载时丢弃权重低于该值的词条，0 表示不丢弃
+    pub min_weight: u32,
+    /// 页文件所在目录，未设置时使用系统临时目录（Android 上应设为应用的缓存目录）
+    pub page_dir: Option<PathBuf>,
+}
+
+impl MemoryBudget {
+    /// 词库占用内存的上限（字节），超出的部分按页从磁盘读取
+    pub fn with_max_bytes(mut self, max_bytes: usize) -> Self {
+        self.max_bytes = max_bytes;
+        self
+    }
+
+    /// 加载时丢弃权重低于 `min_weight` 的词条
+    pub fn with_min_weight(mut self, min_weight: u32) -> Self {
+        self.min_weight = min_weight;
+        self
+    }
+
+    /// 页文件所在目录
+    pub fn with_page_dir(mut self, page_dir: PathBuf) -> Self {
+        self.page_dir = Some(page_dir);
+        self
+    }
+
+    /// 是否按页从磁盘读取词条
+    pub fn is_paged(&self) -> bool {
+        self.max_bytes > 0
+    }
+
+    /// 加载时是否保留该权重的词条
+    pub fn keeps(&self, weight: u32) -> bool {
+        weight >= self.min_weight
+    }
+
+    /// 缓存页与待写入词条可用的内存：预算的一半
+    pub fn cache_bytes(&self) -> usize {
+        self.max_bytes / 2
+    }
+
+    /// 由词条构建的语言模型与联想索引各自可用的内存（预算的五分之一，余下的留给页表），
+    /// 未设置预算时不限
+    pub fn derived_bytes(&self) -> Option<usize> {
+        self.is_paged().then_some(self.max_bytes / 5)
+    }
+
+    /// 每页的目标大小：缓存可容纳约 [`PAGES_PER_CACHE`] 页，一个编码的词条总在同一页
+    fn page_bytes(&self) -> usize {
+        (self.cache_bytes() / PAGES_PER_CACHE).max(1)
+    }
+}
+
+/// 缓存可容纳的页数
+const PAGES_PER_CACHE: usize = 8;
+
+/// 词条占用的内存（字节，估算）
+pub(crate) fn entry_bytes(sourced: &SourcedEntry) -> usize {
+    size_of::<SourcedEntry>() + sourced.entry.text.capacity() + sourced.entry.pinyin.capacity()
+}
+
+/// 编码所在的分组：编码的前两个字符，加载时按分组暂存，整理时每组排序后分页
+fn page_key(code: &str) -> &str {
+    let end = code.char_indices().nth(2).map_or(code.len(), |(i, _)| i);
+    &code[..end]
+}
+
+/// 编码为 `文字\t编码\t权重\t来源序号` 行，来源序号为在 `sources` 中的位置
+fn encode(sources: &mut Vec<DictSource>, entries: &[SourcedEntry], out: &mut Vec<u8>) {
+    for sourced in entries {
+        let source = &sourced.source;
+        let index = match sources.iter().position(|s| s == source && s.priority() == source.priority()) {
+            Some(index) => index,
+            None => {
+                sources.push(source.clone());
+                sources.len() - 1
+            }
+        };
+        let entry = &sourced.entry;
+        out.extend_from_slice(format!("{}\t{}\t{}\t{}\n", entry.text, entry.pinyin, entry.weight, index).as_bytes());
+    }
+}
+
+/// 临时页文件，最后一个引用释放时删除
+struct PageFile {
+    path: PathBuf,
+    file: Mutex<File>,
+}
+
+impl PageFile {
+    fn create(dir: Option<&PathBuf>) -> io::Result<Self> {
+        static COUNTER: AtomicUsize = AtomicUsize::new(0);
+        let dir = dir.cloned().unwrap_or_else(std::env::temp_dir);
+        let name = format!(
+            "lingcode-dict-{}-{}.pages",
+            std::process::id(),
+            COUNTER.fetch_add(1, Ordering::Relaxed)
+        );
+        let path = dir.join(name);
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(&path)?;
+        Ok(Self {
+            path,
+            file: Mutex::new(file),
+        })
+    }
+
+    /// 追加数据，返回写入位置
+    fn append(&self, data: &[u8]) -> io::Result<u64> {
+        let mut file = self.file.lock().unwrap();
+        let offset = file.seek(SeekFrom::End(0))?;
+        file.write_all(data)?;
+        Ok(offset)
+    }
+
+    fn read(&self, offset: u64, len: u32) -> io::Result<Vec<u8>> {
+        let mut file = self.file.lock().unwrap();
+        file.seek(SeekFrom::Start(offset))?;
+        let mut data = vec![0; len as usize];
+        file.read_exact(&mut data)?;
+        Ok(data)
+    }
+}
+
+impl Drop for PageFile {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+/// 整理好的一页在页文件中的位置
+#[derive(Debug, Clone)]
+struct PageRange {
+    /// 页内第一个编码
+    first: Box<str>,
+    offset: u64,
+    len: u32,
+    /// 页内不同编码数
+    codes: usize,
+}
+
+/// 一组编码（前两个字符相同）在页文件中的数据
+#[derive(Debug, Clone, Default)]
+struct Group {
+    /// 整理好的页，按第一个编码排序
+    pages: Vec<PageRange>,
+    /// 上次整理后写入、尚未分页的数据块（位置, 长度）
+    chunks: Vec<(u64, u32)>,
+}
+
+/// 读入内存的一页：编码 → 按来源优先级与权重排序的词条
+struct Page {
+    codes: HashMap<String, Vec<SourcedEntry>>,
+    bytes: usize,
+}
+
+/// 一个编码及其词条读入内存后占用的内存（字节，估算）
+fn code_bytes(code: &str, entries: &[SourcedEntry]) -> usize {
+    code.len() + size_of::<(String, Vec<SourcedEntry>)>() + entries.iter().map(entry_bytes).sum::<usize>()
+}
+
+/// 解析页文件中的数据，按编码加入 `codes`
+fn decode(data: Vec<u8>, sources: &[DictSource], codes: &mut HashMap<String, Vec<SourcedEntry>>) -> io::Result<()> {
+    let text = String::from_utf8(data).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    for line in text.lines() {
+        let mut parts = line.split('\t');
+        let (Some(text), Some(pinyin), Some(weight), Some(index)) = (parts.next(), parts.next(), parts.next(), parts.next())
+        else {
+            continue;
+        };
+        let (Ok(weight), Some(source)) = (weight.parse(), index.parse().ok().and_then(|i: usize| sources.get(i))) else {
+            continue;
+        };
+        codes.entry(pinyin.to_string()).or_default().push(SourcedEntry {
+            entry: DictEntry {
+                text: text.to_string(),
+                pinyin: pinyin.to_string(),
+                weight,
+            },
+            source: source.clone(),
+        });
+    }
+    Ok(())
+}
+
+/// 把按编码排序的词条写成一页
+fn write_page(file: &PageFile, sources: &mut Vec<DictSource>, page: &[(String, Vec<SourcedEntry>)]) -> io::Result<PageRange> {
+    let mut data = Vec::new();
+    for (_, entries) in page {
+        encode(sources, entries, &mut data);
+    }
+    Ok(PageRange {
+        first: page[0].0.as_str().into(),
+        offset: file.append(&data)?,
+        len: data.len() as u32,
+        codes: page.len(),
+    })
+}
+
+/// 读入一组的全部数据，按编码排序后分成不超过 `page_bytes` 的页写入 `file`
+///
+/// 一个编码的词条总在同一页，单个编码超出页大小时独占一页
+fn paginate(
+    old: &PageFile,
+    file: &PageFile,
+    sources: &mut Vec<DictSource>,
+    group: &Group,
+    page_bytes: usize,
+) -> io::Result<Vec<PageRange>> {
+    let mut codes = HashMap::new();
+    for range in &group.pages {
+        decode(old.read(range.offset, range.len)?, sources, &mut codes)?;
+    }
+    for &(offset, len) in &group.chunks {
+        decode(old.read(offset, len)?, sources, &mut codes)?;
+    }
+    let mut codes: Vec<(String, Vec<SourcedEntry>)> = codes.into_iter().collect();
+    codes.sort_unstable_by(|a, b| a.0.cmp(&b.0));
+
+    let mut pages = Vec::new();
+    let mut page = Vec::new();
+    let mut bytes = 0;
+    for (code, entries) in codes {
+        let size = code_bytes(&code, &entries);
+        if !page.is_empty() && bytes + size > page_bytes {
+            pages.push(write_page(file, sources, &page)?);
+            page.clear();
+            bytes = 0;
+        }
+        bytes += size;
+        page.push((code, entries));
+    }
+    if !page.is_empty() {
+        pages.push(write_page(file, sources, &page)?);
+    }
+    Ok(pages)
+}
+
+/// 最近使用的页
+#[derive(Default)]
+struct CacheState {
+    /// 页在页文件中的位置 → (页内容, 最近一次使用的序号)
+    pages: HashMap<u64, (Arc<Page>, u64)>,
+    bytes: usize,
+    tick: u64,
+}
+
+/// 页的 LRU 缓存，复制时不复制缓存内容
+#[derive(Default)]
+struct PageCache(Mutex<CacheState>);
+
+impl Clone for PageCache {
+    fn clone(&self) -> Self {
+        Self::default()
+    }
+}
+
+/// 分页存储的词条
+#[derive(Clone)]
+pub(crate) struct PagedStore {
+    budget: MemoryBudget,
+    file: Arc<PageFile>,
+    /// 编码前两个字符 → 该组的页
+    groups: HashMap<String, Group>,
+    /// 页文件中按序号记录的来源
+    sources: Vec<DictSource>,
+    /// 尚未写入页文件的词条
+    pending: HashMap<String, Vec<SourcedEntry>>,
+    pending_bytes: usize,
+    len: usize,
+    by_source: HashMap<DictSource, usize>,
+    cache: PageCache,
+}
+
+impl PagedStore {
+    pub(crate) fn new(budget: MemoryBudget) -> io::Result<Self> {
+        Ok(Self {
+            file: Arc::new(PageFile::create(budget.page_dir.as_ref())?),
+            budget,
+            groups: HashMap::new(),
+            sources: Vec::new(),
+            pending: HashMap::new(),
+            pending_bytes: 0,
+            len: 0,
+            by_source: HashMap::new(),
+            cache: PageCache::default(),
+        })
+    }
+
+    /// 加入词条，待写入的词条超出缓存预算时写入页文件
+    pub(crate) fn insert(&mut self, sourced: SourcedEntry) -> io::Result<()> {
+        self.len += 1;
+        *self.by_source.entry(sourced.source.clone()).or_insert(0) += 1;
+        self.pending_bytes += entry_bytes(&sourced);
+        self.pending
+            .entry(page_key(&sourced.entry.pinyin).to_string())
+            .or_default()
+            .push(sourced);
+        if self.pending_bytes >= self.budget.cache_bytes() {
+            self.flush()?;
+        }
+        Ok(())
+    }
+
+    /// 写入待写入的词条，并把有新数据的组重新排序分页，写入新的页文件
+    ///
+    /// 每次只读入一组的数据，没有新数据的组原样复制
+    pub(crate) fn finish(&mut self) -> io::Result<()> {
+        self.flush()?;
+        if self.groups.values().all(|group| group.chunks.is_empty()) {
+            return Ok(());
+        }
+        let file = Arc::new(PageFile::create(self.budget.page_dir.as_ref())?);
+        let page_bytes = self.budget.page_bytes();
+        let mut groups = HashMap::with_capacity(self.groups.len());
+        for (key, group) in &self.groups {
+            let pages = if group.chunks.is_empty() {
+                let mut pages = Vec::with_capacity(group.pages.len());
+                for range in &group.pages {
+                    let data = self.file.read(range.offset, range.len)?;
+                    pages.push(PageRange {
+                        offset: file.append(&data)?,
+                        ..range.clone()
+                    });
+                }
+                pages
+            } else {
+                paginate(&self.file, &file, &mut self.sources, group, page_bytes)?
+            };
+            groups.insert(key.clone(), Group { pages, chunks: Vec::new() });
+        }
+        self.file = file;
+        self.groups = groups;
+        *self.cache.0.lock().unwrap() = CacheState::default();
+        Ok(())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        let pending = std::mem::take(&mut self.pending);
+        self.pending_bytes = 0;
+        for (key, entries) in pending {
+            let mut data = Vec::new();
+            encode(&mut self.sources, &entries, &mut data);
+            let offset = self.file.append(&data)?;
+            self.groups.entry(key).or_default().chunks.push((offset, data.len() as u32));
+        }
+        Ok(())
+    }
+
+    fn read_page(&self, range: &PageRange) -> io::Result<Page> {
+        let mut codes = HashMap::with_capacity(range.codes);
+        decode(self.file.read(range.offset, range.len)?, &self.sources, &mut codes)?;
+        let mut bytes = 0;
+        for (code, entries) in codes.iter_mut() {
+            entries.sort_by(compare_entries);
+            bytes += code_bytes(code, entries);
+        }
+        Ok(Page { codes, bytes })
+    }
+
+    /// 编码所在的页
+    fn range(&self, code: &str) -> Option<&PageRange> {
+        let pages = &self.groups.get(page_key(code))?.pages;
+        let index = pages.partition_point(|range| *range.first <= *code);
+        index.checked_sub(1).map(|index| &pages[index])
+    }
+
+    /// 取得一页，先查缓存，未命中时在缓存锁外从页文件读入，再放入缓存并淘汰最久未用的页
+    fn page(&self, range: &PageRange) -> io::Result<Arc<Page>> {
+        if let Some(page) = self.cached(range) {
+            return Ok(page);
+        }
+        let page = Arc::new(self.read_page(range)?);
+        let mut cache = self.cache.0.lock().unwrap();
+        cache.tick += 1;
+        let tick = cache.tick;
+        // 读盘期间其他线程可能已读入同一页
+        if let Some((cached, used)) = cache.pages.get_mut(&range.offset) {
+            *used = tick;
+            return Ok(Arc::clone(cached));
+        }
+        cache.bytes += page.bytes;
+        cache.pages.insert(range.offset, (Arc::clone(&page), tick));
+        while cache.bytes > self.budget.cache_bytes() && cache.pages.len() > 1 {
+            let Some(oldest) = cache.pages.iter().min_by_key(|(_, (_, used))| *used).map(|(offset, _)| *offset) else {
+                break;
+            };
+            if let Some((evicted, _)) = cache.pages.remove(&oldest) {
+                cache.bytes -= evicted.bytes;
+            }
+        }
+        Ok(page)
+    }
+
+    /// 缓存中的页，命中时记为最近使用
+    fn cached(&self, range: &PageRange) -> Option<Arc<Page>> {
+        let mut cache = self.cache.0.lock().unwrap();
+        cache.tick += 1;
+        let tick = cache.tick;
+        let (page, used) = cache.pages.get_mut(&range.offset)?;
+        *used = tick;
+        Some(Arc::clone(page))
+    }
+
+    /// 查询编码的词条
+    pub(crate) fn lookup(&self, code: &str) -> Vec<SourcedEntry> {
+        let mut entries = match self.range(code).map(|range| self.page(range)).transpose() {
+            Ok(page) => page.and_then(|page| page.codes.get(code).cloned()).unwrap_or_default(),
+            Err(e) => {
+                log::warn!("读取词库页 {} 失败: {}", code, e);
+                Vec::new()
+            }
+        };
+        if let Some(pending) = self.pending.get(page_key(code)) {
+            entries.extend(pending.iter().filter(|s| s.entry.pinyin == code).cloned());
+            entries.sort_by(compare_entries);
+        }
+        entries
+    }
+
+    /// 遍历所有词条，逐页读入且不放入缓存
+    pub(crate) fn for_each(&self, f: &mut dyn FnMut(&SourcedEntry)) {
+        for (key, group) in &self.groups {
+            for range in &group.pages {
+                let cached = self.cache.0.lock().unwrap().pages.get(&range.offset).map(|(page, _)| Arc::clone(page));
+                let page = match cached {
+                    Some(page) => page,
+                    None => match self.read_page(range) {
+                        Ok(page) => Arc::new(page),
+                        Err(e) => {
+                            log::warn!("读取词库页 {} 失败: {}", range.first, e);
+                            continue;
+                        }
+                    },
+                };
+                page.codes.values().flatten().for_each(&mut *f);
+            }
+            for &(offset, len) in &group.chunks {
+                let mut codes = HashMap::new();
+                match self.file.read(offset, len).and_then(|data| decode(data, &self.sources, &mut codes)) {
+                    Ok(()) => codes.values().flatten().for_each(&mut *f),
+                    Err(e) => log::warn!("读取词库页 {} 失败: {}", key, e),
+                }
+            }
+        }
+        self.pending.values().flatten().for_each(f);
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.len
+    }
+
+    pub(crate) fn unique_codes(&self) -> usize {
+        self.groups.values().flat_map(|group| &group.pages).map(|range| range.codes).sum()
+    }
+
+    pub(crate) fn by_source(&self) -> &HashMap<DictSource, usize> {
+        &self.by_source
+    }
+
+    /// 页表、待写入词条与缓存页占用的内存（字节，估算）
+    pub(crate) fn memory_bytes(&self) -> usize {
+        let table: usize = self
+            .groups
+            .iter()
+            .map(|(key, group)| {
+                key.capacity()
+                    + size_of::<(String, Group)>()
+                    + group.pages.capacity() * size_of::<PageRange>()
+                    + group.pages.iter().map(|range| range.first.len()).sum::<usize>()
+                    + group.chunks.capacity() * size_of::<(u64, u32)>()
+            })
+            .sum();
+        table + self.pending_bytes + self.cache.0.lock().unwrap().bytes
+    }
+
+    /// 缓存中的页数
+    #[cfg(test)]
+    fn cached_pages(&self) -> usize {
+        self.cache.0.lock().unwrap().pages.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sourced(text: &str, pinyin: &str, weight: u32) -> SourcedEntry {
+        SourcedEntry {
+            entry: DictEntry {
+                text: text.to_string(),
+                pinyin: pinyin.to_string(),
+                weight,
+            },
+            source: DictSource::base(),
+        }
+    }
+
+    #[test]
+    fn test_paged_store() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let budget = MemoryBudget::default()
+            .with_max_bytes(1024)
+            .with_page_dir(dir.path().to_path_buf());
+        let mut store = PagedStore::new(budget).unwrap();
+        for i in 0..50 {
+            store.insert(sourced(&format!("中{}", i), "zhong", i)).unwrap();
+            store.insert(sourced(&format!("你{}", i), "ni", i)).unwrap();
+            store.insert(sourced(&format!("啊{}", i), "a", i)).unwrap();
+        }
+        store.finish().unwrap();
+        assert_eq!(store.len(), 150);
+        assert_eq!(store.unique_codes(), 3);
+
+        // 整页读入，词条按权重排序
+        let zhong = store.lookup("zhong");
+        assert_eq!(zhong.len(), 50);
+        assert_eq!(zhong[0].entry.text, "中49");
+        assert!(store.lookup("zhongguo").is_empty());
+
+        // 缓存超出预算时淘汰最久未用的页（每页都超出预算，只保留最近的一页）
+        store.lookup("ni");
+        store.lookup("a");
+        assert_eq!(store.cached_pages(), 1);
+        assert!(store.memory_bytes() < 150 * size_of::<SourcedEntry>());
+
+        let mut count = 0;
+        store.for_each(&mut |_| count += 1);
+        assert_eq!(count, 150);
+
+        // 页文件随存储释放删除
+        drop(store);
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 0);
+    }
+
+    #[test]
+    fn test_pages_bounded() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let budget = MemoryBudget::default()
+            .with_max_bytes(16 * 1024)
+            .with_page_dir(dir.path().to_path_buf());
+        let mut store = PagedStore::new(budget.clone()).unwrap();
+        // 所有编码同属 zh 一组，分两批加入
+        for i in 0..500 {
+            store.insert(sourced(&format!("之{}", i), &format!("zhi{}", i % 100), i)).unwrap();
+        }
+        store.finish().unwrap();
+        for i in 0..100 {
+            store.insert(sourced(&format!("知{}", i), &format!("zhi{}", i), 1000)).unwrap();
+        }
+        store.finish().unwrap();
+        assert_eq!(store.unique_codes(), 100);
+
+        // 一组按编码分成多页，每页不超过页大小
+        let pages = &store.groups["zh"].pages;
+        assert!(pages.len() > 1);
+        for range in pages {
+            assert!(store.read_page(range).unwrap().bytes <= budget.page_bytes());
+        }
+
+        for i in 0..100 {
+            let entries = store.lookup(&format!("zhi{}", i));
+            assert_eq!(entries.len(), 6);
+            assert_eq!(entries[0].entry.text, format!("知{}", i));
+        }
+        assert!(store.cached_pages() < pages.len());
+        assert!(store.cache.0.lock().unwrap().bytes <= budget.cache_bytes());
+        assert!(store.memory_bytes() <= budget.max_bytes);
+    }
+}
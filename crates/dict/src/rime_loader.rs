@@ -1,13 +1,22 @@
 //! 雾凇拼音词库加载器
 //!
-//! 从 Rime 格式的 YAML 词库加载词条，支持多词库合并
+//! 从 Rime 格式的 YAML 词库加载词条，支持多词库合并。
+//! 加载哪些词库、顺序与优先级由 [`DictStack`] 描述，可以来自方案的
+//! `translator/dictionary`（主词库及其 `import_tables`）与 `translator/dictionaries`
 
 use lingcode_core::candidate::Candidate;
 use lingcode_core::error::Result;
+use lingcode_core::schema_parser::Translator;
+use crate::paged::{entry_bytes, MemoryBudget, PagedStore};
+use serde::Deserialize;
+use std::borrow::Cow;
 use std::collections::HashMap;
 use std::fs::File;
-use std::io::{BufRead, BufReader};
-use std::path::Path;
+use std::hash::{Hash, Hasher};
+use std::io::{self, BufRead, BufReader};
+use std::mem::size_of;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 
 /// 词库条目
 #[derive(Debug, Clone)]
@@ -17,22 +26,241 @@ pub struct DictEntry {
     pub weight: u32,
 }
 
-/// 词库来源
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
-pub enum DictSource {
-    Base,      // 基础词库 8105
-    Ext,       // 扩展词库
-    Tencent,   // 腾讯词库
-    Custom,    // 自定义词库
+/// 词库来源：来源标识与优先级
+///
+/// 标识写入 `Candidate.source` 并用于排序配置（`ranking/boosts`），
+/// 优先级决定同一编码下各词库词条的先后，也是未配置来源加权时的默认加权（优先级 / 100）。
+/// 相等与哈希只比较标识
+#[derive(Debug, Clone)]
+pub struct DictSource {
+    id: Arc<str>,
+    priority: u32,
 }
 
 impl DictSource {
+    /// 基础词库（8105）
+    pub const BASE: &'static str = "base";
+    /// 扩展词库
+    pub const EXT: &'static str = "ext";
+    /// 腾讯词库
+    pub const TENCENT: &'static str = "tencent";
+    /// 自定义词库
+    pub const CUSTOM: &'static str = "custom";
+
+    /// 未指定优先级的词库的默认优先级（与自定义词库相同）
+    pub const DEFAULT_PRIORITY: u32 = 40;
+
+    /// 创建来源
+    pub fn new(id: &str, priority: u32) -> Self {
+        Self {
+            id: Arc::from(id),
+            priority,
+        }
+    }
+
+    /// 基础词库
+    pub fn base() -> Self {
+        Self::new(Self::BASE, 100)
+    }
+
+    /// 扩展词库
+    pub fn ext() -> Self {
+        Self::new(Self::EXT, 80)
+    }
+
+    /// 腾讯词库
+    pub fn tencent() -> Self {
+        Self::new(Self::TENCENT, 60)
+    }
+
+    /// 自定义词库
+    pub fn custom() -> Self {
+        Self::new(Self::CUSTOM, Self::DEFAULT_PRIORITY)
+    }
+
+    /// 预置来源（基础、扩展、腾讯、自定义）
+    pub fn presets() -> [DictSource; 4] {
+        [Self::base(), Self::ext(), Self::tencent(), Self::custom()]
+    }
+
+    /// 来源标识（用于排序配置与候选词来源）
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    /// 优先级，越大越靠前
     pub fn priority(&self) -> u32 {
-        match self {
-            DictSource::Base => 100,
-            DictSource::Ext => 80,
-            DictSource::Tencent => 60,
-            DictSource::Custom => 40,
+        self.priority
+    }
+}
+
+impl PartialEq for DictSource {
+    fn eq(&self, other: &Self) -> bool {
+        self.id == other.id
+    }
+}
+
+impl Eq for DictSource {}
+
+impl Hash for DictSource {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.id.hash(state);
+    }
+}
+
+/// 要加载的一个词库：文件名（相对词库目录，不含 `.dict.yaml`）与来源
+#[derive(Debug, Clone)]
+pub struct DictSpec {
+    /// 文件名，如 `cn_dicts/8105`
+    pub name: String,
+    /// 来源
+    pub source: DictSource,
+}
+
+/// 比较文件名、来源与优先级（[`DictSource`] 的相等只比较来源标识），
+/// 因此只改变优先级的词库组合也视为不同
+impl PartialEq for DictSpec {
+    fn eq(&self, other: &Self) -> bool {
+        self.name == other.name && self.source == other.source && self.source.priority == other.source.priority
+    }
+}
+
+impl DictSpec {
+    /// 创建词库描述
+    pub fn new(name: &str, source: DictSource) -> Self {
+        Self {
+            name: name.to_string(),
+            source,
+        }
+    }
+
+    /// 词库文件路径：名称已带扩展名时原样使用，否则补上 `.dict.yaml`
+    pub fn path(&self, dir: &Path) -> PathBuf {
+        if self.name.ends_with(".yaml") || self.name.ends_with(".txt") {
+            dir.join(&self.name)
+        } else {
+            dir.join(format!("{}.dict.yaml", self.name))
+        }
+    }
+
+    /// 雾凇拼音的默认词库：8105、base、ext、tencent
+    pub fn rime_ice_defaults() -> Vec<DictSpec> {
+        vec![
+            DictSpec::new("8105", DictSource::base()),
+            DictSpec::new("base", DictSource::ext()),
+            DictSpec::new("ext", DictSource::ext()),
+            DictSpec::new("tencent", DictSource::tencent()),
+        ]
+    }
+
+    /// 主词库 `import_tables` 中的词库：雾凇拼音的词库沿用默认来源，
+    /// 其他词库以文件名为来源标识，优先级按顺序从 100 递减
+    fn imported(name: &str, index: usize) -> Self {
+        let stem = name.rsplit('/').next().unwrap_or(name);
+        match Self::rime_ice_defaults().into_iter().find(|spec| spec.name == stem) {
+            Some(spec) => Self::new(name, spec.source),
+            None => Self::new(name, DictSource::new(stem, 100u32.saturating_sub(10 * index as u32).max(10))),
+        }
+    }
+}
+
+/// 词库组合：主词库（展开其 `import_tables`）加上追加的词库
+///
+/// 没有主词库时以雾凇拼音的默认词库为基础；追加的词库与已有词库同名时替换其来源与优先级
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DictStack {
+    /// 主词库名称（`translator/dictionary`）
+    pub main: Option<String>,
+    /// 追加的词库（`translator/dictionaries`），按顺序加载
+    pub extra: Vec<DictSpec>,
+}
+
+impl DictStack {
+    /// 从方案的 translator 配置创建
+    pub fn from_config(config: &Translator) -> Self {
+        let extra = config
+            .dictionaries
+            .iter()
+            .map(|dict| {
+                let id = dict.source.clone().unwrap_or_else(|| {
+                    let stem = dict.name.rsplit('/').next().unwrap_or(&dict.name);
+                    stem.trim_end_matches(".dict.yaml").to_string()
+                });
+                let priority = dict.priority.unwrap_or(DictSource::DEFAULT_PRIORITY);
+                DictSpec::new(&dict.name, DictSource::new(&id, priority))
+            })
+            .collect();
+        Self {
+            main: config.dictionary.clone(),
+            extra,
+        }
+    }
+
+    /// 是否为默认组合（雾凇拼音默认词库）
+    pub fn is_default(&self) -> bool {
+        self.main.is_none() && self.extra.is_empty()
+    }
+
+    /// 按词库目录展开为要加载的词库与其所在目录
+    ///
+    /// 主词库在 `dir` 或其上级目录中查找（雾凇拼音的主词库在 `cn_dicts` 的上级），
+    /// 其 `import_tables` 相对主词库所在目录；找不到主词库时使用默认词库
+    pub fn resolve(&self, dir: &Path) -> (PathBuf, Vec<DictSpec>) {
+        let mut base_dir = dir.to_path_buf();
+        let mut specs = DictSpec::rime_ice_defaults();
+        if let Some(ref main) = self.main {
+            let main_spec = DictSpec::new(main, DictSource::new(main, 100));
+            let found = [Some(dir), dir.parent()]
+                .into_iter()
+                .flatten()
+                .find(|candidate| main_spec.path(candidate).exists());
+            match found {
+                Some(main_dir) => {
+                    let tables = read_import_tables(&main_spec.path(main_dir));
+                    base_dir = main_dir.to_path_buf();
+                    specs = std::iter::once(main_spec)
+                        .chain(tables.iter().enumerate().map(|(i, name)| DictSpec::imported(name, i)))
+                        .collect();
+                }
+                None => log::warn!("未找到主词库 {}.dict.yaml，使用默认词库", main),
+            }
+        }
+        for spec in &self.extra {
+            match specs.iter_mut().find(|existing| existing.name == spec.name) {
+                Some(existing) => existing.source = spec.source.clone(),
+                None => specs.push(spec.clone()),
+            }
+        }
+        (base_dir, specs)
+    }
+}
+
+/// 词库 YAML 头部中用到的字段
+#[derive(Debug, Default, Deserialize)]
+struct DictHeader {
+    #[serde(default)]
+    import_tables: Vec<String>,
+}
+
+/// 读取词库头部的 `import_tables`
+fn read_import_tables(path: &Path) -> Vec<String> {
+    let file = match File::open(path) {
+        Ok(file) => file,
+        Err(_) => return Vec::new(),
+    };
+    let mut header = String::new();
+    for line in BufReader::new(file).lines().map_while(std::result::Result::ok) {
+        if line.trim() == "..." {
+            break;
+        }
+        header.push_str(&line);
+        header.push('\n');
+    }
+    match serde_yaml::from_str::<DictHeader>(&header) {
+        Ok(header) => header.import_tables,
+        Err(e) => {
+            log::warn!("无法解析词库头部 {}: {}", path.display(), e);
+            Vec::new()
         }
     }
 }
@@ -44,11 +272,23 @@ pub struct SourcedEntry {
     pub source: DictSource,
 }
 
+/// 加载大词库时报告进度的间隔（词条数）
+pub const PROGRESS_STEP: usize = 50_000;
+
 /// 雾凇拼音词库加载器（支持多词库）
+///
+/// 默认所有词条常驻内存；设置内存预算（见 [`with_memory_budget`](Self::with_memory_budget)）后
+/// 词条按页存放在磁盘上，查询时按需读入
+#[derive(Clone)]
 pub struct RimeDictLoader {
     entries: Vec<SourcedEntry>,
     pinyin_index: HashMap<String, Vec<SourcedEntry>>,
     loaded_sources: Vec<DictSource>,
+    budget: MemoryBudget,
+    /// 按页存储的词条（设置了内存上限时）
+    paged: Option<PagedStore>,
+    /// 加载时因权重低于阈值丢弃的词条数
+    dropped: usize,
 }
 
 impl RimeDictLoader {
@@ -57,16 +297,52 @@ impl RimeDictLoader {
             entries: Vec::new(),
             pinyin_index: HashMap::new(),
             loaded_sources: Vec::new(),
+            budget: MemoryBudget::default(),
+            paged: None,
+            dropped: 0,
         }
     }
 
+    /// 设置内存预算，应在加载词库前设置；无法创建页文件时词条仍常驻内存
+    pub fn with_memory_budget(mut self, budget: MemoryBudget) -> Self {
+        if budget.is_paged() && self.is_empty() {
+            match PagedStore::new(budget.clone()) {
+                Ok(store) => self.paged = Some(store),
+                Err(e) => log::warn!("无法创建词库页文件，词条将常驻内存: {}", e),
+            }
+        }
+        self.budget = budget;
+        self
+    }
+
+    /// 内存预算
+    pub fn memory_budget(&self) -> &MemoryBudget {
+        &self.budget
+    }
+
+    /// 是否按页从磁盘读取词条
+    pub fn is_paged(&self) -> bool {
+        self.paged.is_some()
+    }
+
     /// 从文件加载词库（指定来源）
     pub fn load_from_file_with_source(&mut self, path: &Path, source: DictSource) -> Result<usize> {
+        self.load_from_file_with_progress(path, source, &mut |_| {})
+    }
+
+    /// 从文件加载词库，每加载 [`PROGRESS_STEP`] 条词条以已加载条数调用一次 `on_progress`
+    pub fn load_from_file_with_progress(
+        &mut self,
+        path: &Path,
+        source: DictSource,
+        on_progress: &mut dyn FnMut(usize),
+    ) -> Result<usize> {
         let file = File::open(path)?;
         let reader = BufReader::new(file);
         
         let mut in_header = true;
         let mut loaded_count = 0;
+        let mut malformed = 0;
         
         for line in reader.lines() {
             let line = line?;
@@ -87,40 +363,103 @@ impl RimeDictLoader {
             
             // 解析词条: 文字\t拼音\t权重
             if let Some(entry) = self.parse_entry(line) {
-                let pinyin = entry.pinyin.clone();
-                let sourced = SourcedEntry { entry, source };
-                self.entries.push(sourced.clone());
-                self.pinyin_index
-                    .entry(pinyin)
-                    .or_insert_with(Vec::new)
-                    .push(sourced);
+                if !self.budget.keeps(entry.weight) {
+                    self.dropped += 1;
+                    continue;
+                }
+                self.insert(entry, source.clone())?;
                 loaded_count += 1;
+                if loaded_count % PROGRESS_STEP == 0 {
+                    on_progress(loaded_count);
+                }
+            } else {
+                malformed += 1;
             }
         }
-        
-        // 对每个拼音的词条按权重排序
-        for entries in self.pinyin_index.values_mut() {
-            entries.sort_by(|a, b| {
-                // 先按来源优先级排序，再按权重排序
-                let priority_cmp = b.source.priority().cmp(&a.source.priority());
-                if priority_cmp != std::cmp::Ordering::Equal {
-                    priority_cmp
-                } else {
-                    b.entry.weight.cmp(&a.entry.weight)
+        if malformed > 0 {
+            log::warn!("{} 中有 {} 行格式错误，已跳过（可用 lingcode dict lint 检查）", path.display(), malformed);
+        }
+
+        self.finish_load(&source, loaded_count)?;
+        Ok(loaded_count)
+    }
+
+    /// 加入其他来源（如导入的其他输入法词库）的词条，返回加入的条数
+    pub fn add_entries(&mut self, entries: impl IntoIterator<Item = DictEntry>, source: DictSource) -> usize {
+        let mut count = 0;
+        let mut result = Ok(());
+        for entry in entries {
+            result = result.and_then(|_| self.insert(entry, source.clone()));
+            count += 1;
+        }
+        if let Err(e) = result.and_then(|_| self.finish_load(&source, count)) {
+            log::warn!("写入词库页文件失败: {}", e);
+        }
+        count
+    }
+
+    /// 合并另一个加载器的词条（如后台分批加载的词库）
+    pub fn merge(&mut self, other: RimeDictLoader) {
+        self.dropped += other.dropped;
+        let mut result = Ok(());
+        if other.is_paged() {
+            other.for_each_entry(|sourced| {
+                if result.is_ok() {
+                    result = self.insert(sourced.entry.clone(), sourced.source.clone());
                 }
             });
+        } else {
+            for sourced in other.entries {
+                result = result.and_then(|_| self.insert(sourced.entry, sourced.source));
+            }
         }
-        
-        if loaded_count > 0 {
-            self.loaded_sources.push(source);
+        if let Err(e) = result.and_then(|_| self.sort_index()) {
+            log::warn!("写入词库页文件失败: {}", e);
         }
-        
-        Ok(loaded_count)
+        for source in other.loaded_sources {
+            if !self.loaded_sources.contains(&source) {
+                self.loaded_sources.push(source);
+            }
+        }
+    }
+
+    fn insert(&mut self, entry: DictEntry, source: DictSource) -> io::Result<()> {
+        let sourced = SourcedEntry { entry, source };
+        if let Some(paged) = &mut self.paged {
+            return paged.insert(sourced);
+        }
+        let pinyin = sourced.entry.pinyin.clone();
+        self.entries.push(sourced.clone());
+        self.pinyin_index
+            .entry(pinyin)
+            .or_insert_with(Vec::new)
+            .push(sourced);
+        Ok(())
+    }
+
+    /// 加载完成：重新排序并记录来源
+    fn finish_load(&mut self, source: &DictSource, loaded_count: usize) -> io::Result<()> {
+        self.sort_index()?;
+        if loaded_count > 0 && !self.loaded_sources.contains(source) {
+            self.loaded_sources.push(source.clone());
+        }
+        Ok(())
+    }
+
+    /// 对每个拼音的词条按权重排序，按页存储时写入剩余词条并整理页文件
+    fn sort_index(&mut self) -> io::Result<()> {
+        if let Some(paged) = &mut self.paged {
+            return paged.finish();
+        }
+        for entries in self.pinyin_index.values_mut() {
+            entries.sort_by(compare_entries);
+        }
+        Ok(())
     }
 
     /// 从文件加载词库（默认 Custom 来源）
     pub fn load_from_file(&mut self, path: &Path) -> Result<usize> {
-        self.load_from_file_with_source(path, DictSource::Custom)
+        self.load_from_file_with_source(path, DictSource::custom())
     }
 
     /// 加载多个雾凇拼音词库文件
@@ -131,35 +470,33 @@ impl RimeDictLoader {
     /// 3. ext.dict.yaml - 扩展词库
     /// 4. tencent.dict.yaml - 腾讯词库
     pub fn load_rime_ice_dicts(&mut self, rime_dict_dir: &Path) -> Result<DictLoadSummary> {
+        self.load_dict_stack(rime_dict_dir, &DictSpec::rime_ice_defaults())
+    }
+
+    /// 按顺序加载多个词库，`dir` 为词库文件所在目录
+    pub fn load_dict_stack(&mut self, dir: &Path, specs: &[DictSpec]) -> Result<DictLoadSummary> {
         let mut summary = DictLoadSummary::new();
-        
-        // 定义要加载的词库文件及其来源
-        let dict_files: Vec<(&str, DictSource)> = vec![
-            ("8105.dict.yaml", DictSource::Base),
-            ("base.dict.yaml", DictSource::Ext),
-            ("ext.dict.yaml", DictSource::Ext),
-            ("tencent.dict.yaml", DictSource::Tencent),
-        ];
-        
-        for (filename, source) in dict_files {
-            let path = rime_dict_dir.join(filename);
+
+        for spec in specs {
+            let path = spec.path(dir);
+            let filename = path.strip_prefix(dir).unwrap_or(&path).display().to_string();
             if path.exists() {
-                match self.load_from_file_with_source(&path, source) {
+                match self.load_from_file_with_source(&path, spec.source.clone()) {
                     Ok(count) => {
                         log::info!("已加载 {}: {} 条词条", filename, count);
-                        summary.add_loaded(filename, count, source);
+                        summary.add_loaded(&filename, count, spec.source.clone());
                     }
                     Err(e) => {
                         log::warn!("加载 {} 失败: {}", filename, e);
-                        summary.add_failed(filename, e.to_string());
+                        summary.add_failed(&filename, e.to_string());
                     }
                 }
             } else {
                 log::debug!("词库文件不存在: {}", path.display());
-                summary.add_missing(filename);
+                summary.add_missing(&filename);
             }
         }
-        
+
         Ok(summary)
     }
 
@@ -190,26 +527,39 @@ impl RimeDictLoader {
     }
 
     /// 根据拼音查询词条
-    pub fn lookup(&self, pinyin: &str) -> Vec<&DictEntry> {
-        self.pinyin_index
-            .get(pinyin)
-            .map(|v| v.iter().map(|s| &s.entry).collect())
-            .unwrap_or_default()
+    pub fn lookup(&self, pinyin: &str) -> Vec<DictEntry> {
+        self.lookup_sourced(pinyin).iter().map(|s| s.entry.clone()).collect()
+    }
+
+    /// 根据拼音查询带来源的词条，按页存储时读入词条所在的页
+    pub fn lookup_sourced(&self, pinyin: &str) -> Cow<'_, [SourcedEntry]> {
+        match &self.paged {
+            Some(paged) => Cow::Owned(paged.lookup(pinyin)),
+            None => Cow::Borrowed(self.pinyin_index.get(pinyin).map(Vec::as_slice).unwrap_or_default()),
+        }
     }
 
-    /// 获取所有词条
+    /// 获取常驻内存的所有词条，按页存储时为空（用 [`for_each_entry`](Self::for_each_entry) 遍历）
     pub fn entries(&self) -> &[SourcedEntry] {
         &self.entries
     }
 
+    /// 遍历所有词条，按页存储时逐页从磁盘读入
+    pub fn for_each_entry(&self, mut f: impl FnMut(&SourcedEntry)) {
+        match &self.paged {
+            Some(paged) => paged.for_each(&mut f),
+            None => self.entries.iter().for_each(f),
+        }
+    }
+
     /// 获取词条数量
     pub fn len(&self) -> usize {
-        self.entries.len()
+        self.paged.as_ref().map_or(self.entries.len(), PagedStore::len)
     }
 
     /// 是否为空
     pub fn is_empty(&self) -> bool {
-        self.entries.is_empty()
+        self.len() == 0
     }
 
     /// 获取已加载的词库来源
@@ -219,26 +569,58 @@ impl RimeDictLoader {
 
     /// 获取统计信息
     pub fn stats(&self) -> DictStats {
+        if let Some(paged) = &self.paged {
+            return DictStats {
+                total_entries: paged.len(),
+                unique_pinyin: paged.unique_codes(),
+                by_source: paged.by_source().clone(),
+                memory_bytes: paged.memory_bytes(),
+                dropped_entries: self.dropped,
+                paged: true,
+            };
+        }
+
         let mut by_source: HashMap<DictSource, usize> = HashMap::new();
         for entry in &self.entries {
-            *by_source.entry(entry.source).or_insert(0) += 1;
+            *by_source.entry(entry.source.clone()).or_insert(0) += 1;
         }
         
         DictStats {
             total_entries: self.entries.len(),
             unique_pinyin: self.pinyin_index.len(),
             by_source,
+            memory_bytes: self.memory_bytes(),
+            dropped_entries: self.dropped,
+            paged: false,
         }
     }
 
+    /// 常驻内存的词条与拼音索引占用的内存（字节，估算）
+    fn memory_bytes(&self) -> usize {
+        let entries = self.entries.capacity() * size_of::<SourcedEntry>()
+            + self.entries.iter().map(|s| entry_bytes(s) - size_of::<SourcedEntry>()).sum::<usize>();
+        let index: usize = self
+            .pinyin_index
+            .iter()
+            .map(|(pinyin, sourced)| {
+                pinyin.capacity()
+                    + size_of::<(String, Vec<SourcedEntry>)>()
+                    + sourced.capacity() * size_of::<SourcedEntry>()
+                    + sourced.iter().map(|s| entry_bytes(s) - size_of::<SourcedEntry>()).sum::<usize>()
+            })
+            .sum();
+        entries + index
+    }
+
     /// 转换为 Candidate 列表
     pub fn to_candidates(&self, pinyin: &str) -> Vec<Candidate> {
-        self.lookup(pinyin)
-            .into_iter()
-            .map(|entry| {
-                Candidate::new(entry.text.clone())
-                    .with_comment(entry.pinyin.clone())
-                    .with_weight(entry.weight)
+        self.lookup_sourced(pinyin)
+            .iter()
+            .map(|sourced| {
+                Candidate::new(sourced.entry.text.clone())
+                    .with_comment(sourced.entry.pinyin.clone())
+                    .with_weight(sourced.entry.weight)
+                    .with_source(sourced.source.id().to_string())
             })
             .collect()
     }
@@ -287,6 +669,21 @@ pub struct DictStats {
     pub total_entries: usize,
     pub unique_pinyin: usize,
     pub by_source: HashMap<DictSource, usize>,
+    /// 词条占用的内存（字节，估算），按页存储时为页表与缓存页；
+    /// 共享词库（`SharedDict`）报告的统计另含由词条构建的语言模型与联想索引
+    pub memory_bytes: usize,
+    /// 加载时因权重低于阈值丢弃的词条数
+    pub dropped_entries: usize,
+    /// 是否按页从磁盘读取词条
+    pub paged: bool,
+}
+
+/// 同一编码下词条的顺序：先按来源优先级，再按权重
+pub(crate) fn compare_entries(a: &SourcedEntry, b: &SourcedEntry) -> std::cmp::Ordering {
+    b.source
+        .priority()
+        .cmp(&a.source.priority())
+        .then_with(|| b.entry.weight.cmp(&a.entry.weight))
 }
 
 impl Default for RimeDictLoader {
@@ -355,5 +752,104 @@ mod tests {
         assert_eq!(candidates.len(), 2);
         assert_eq!(candidates[0].text, "中");
         assert_eq!(candidates[1].text, "种");
+        assert_eq!(candidates[0].source.as_deref(), Some("custom"));
+    }
+
+    #[test]
+    fn test_memory_budget() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("tencent.dict.yaml");
+        let mut content = String::from("---\n...\n");
+        for i in 0..200 {
+            content.push_str(&format!("词{}\tci{}\t{}\n", i, i % 20, i));
+        }
+        std::fs::write(&path, content).unwrap();
+
+        let mut resident = RimeDictLoader::new();
+        resident.load_from_file(&path).unwrap();
+
+        let budget = MemoryBudget::default()
+            .with_max_bytes(2048)
+            .with_min_weight(100)
+            .with_page_dir(dir.path().to_path_buf());
+        let mut paged = RimeDictLoader::new().with_memory_budget(budget);
+        assert_eq!(paged.load_from_file(&path).unwrap(), 100);
+        assert!(paged.is_paged());
+
+        // 低权重词条在加载时丢弃，其余与常驻内存时相同
+        let texts = |loader: &RimeDictLoader| -> Vec<String> {
+            loader.lookup("ci7").into_iter().map(|e| e.text).collect()
+        };
+        assert_eq!(texts(&paged), vec!["词187", "词167", "词147", "词127", "词107"]);
+        assert_eq!(texts(&resident)[..5], texts(&paged)[..]);
+
+        let stats = paged.stats();
+        assert!(stats.paged);
+        assert_eq!((stats.total_entries, stats.unique_pinyin, stats.dropped_entries), (100, 20, 100));
+        assert!(stats.memory_bytes < resident.stats().memory_bytes / 2);
+
+        let mut count = 0;
+        paged.for_each_entry(|_| count += 1);
+        assert_eq!(count, 100);
+
+        // 合并到常驻内存的加载器
+        resident.merge(paged);
+        assert_eq!(resident.len(), 300);
+        assert_eq!(resident.stats().dropped_entries, 100);
+    }
+
+    #[test]
+    fn test_dict_stack() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let cn_dicts = dir.path().join("cn_dicts");
+        std::fs::create_dir_all(&cn_dicts).unwrap();
+        std::fs::write(
+            dir.path().join("rime_ice.dict.yaml"),
+            "---\nname: rime_ice\nimport_tables:\n  - cn_dicts/8105\n  - cn_dicts/others\n...\n",
+        )
+        .unwrap();
+        std::fs::write(cn_dicts.join("8105.dict.yaml"), "---\n...\n种\tzhong\t1000\n").unwrap();
+        std::fs::write(cn_dicts.join("others.dict.yaml"), "---\n...\n冢\tzhong\t5\n").unwrap();
+        std::fs::write(cn_dicts.join("jargon.dict.yaml"), "---\n...\n钟\tzhong\t10\n").unwrap();
+
+        let config = Translator {
+            dictionary: Some("rime_ice".to_string()),
+            dictionaries: vec![lingcode_core::schema_parser::DictionaryConfig {
+                name: "cn_dicts/jargon".to_string(),
+                source: None,
+                priority: Some(120),
+            }],
+            ..Default::default()
+        };
+        let (base_dir, specs) = DictStack::from_config(&config).resolve(&cn_dicts);
+        assert_eq!(base_dir, dir.path());
+        let names: Vec<_> = specs.iter().map(|spec| (spec.name.as_str(), spec.source.id(), spec.source.priority())).collect();
+        assert_eq!(
+            names,
+            vec![
+                ("rime_ice", "rime_ice", 100),
+                ("cn_dicts/8105", "base", 100),
+                ("cn_dicts/others", "others", 90),
+                ("cn_dicts/jargon", "jargon", 120),
+            ]
+        );
+
+        let mut loader = RimeDictLoader::new();
+        let summary = loader.load_dict_stack(&base_dir, &specs).unwrap();
+        assert_eq!(summary.total_entries, 3);
+        let zhong = loader.lookup_sourced("zhong");
+        let texts: Vec<_> = zhong.iter().map(|s| (s.entry.text.as_str(), s.source.id())).collect();
+        assert_eq!(texts, vec![("钟", "jargon"), ("种", "base"), ("冢", "others")]);
+        assert_eq!(loader.to_candidates("zhong")[0].source.as_deref(), Some("jargon"));
+
+        // 没有配置时使用雾凇拼音的默认词库
+        let (base_dir, specs) = DictStack::default().resolve(&cn_dicts);
+        assert_eq!(base_dir, cn_dicts);
+        assert_eq!(specs, DictSpec::rime_ice_defaults());
+
+        // 只改变优先级的组合不相等
+        let mut reprioritized = config.clone();
+        reprioritized.dictionaries[0].priority = Some(80);
+        assert_ne!(DictStack::from_config(&config), DictStack::from_config(&reprioritized));
     }
 }
@@ -0,0 +1,303 @@
+//! Rime 用户词典快照（`*.userdb.txt`）导入导出
+//!
+//! 快照由 `#@/` 开头的元数据与 `编码\t词\tc=次数 d=衰减权重 t=时刻` 的词条组成，
+//! 编码为以空格分隔（并以空格结尾）的音节，次数为负表示已删除。
+//!
+//! 导入时 Rime 安装视为一台单独的设备（`rime-<user_id>`），按 [`UserDict::merge`] 合并，
+//! 重复导入同一快照结果不变。`user_id` 与本机设备 ID 相同（本机导出的快照）时记在本机设备下，
+//! 各设备次数取最大值，导出后再导入不会重复计数。Rime 的时刻 `t` 是提交计数而非时间，
+//! 最后使用时间由衰减权重按默认策略反推：`d = c × e^(-天数/30)`
+
+use crate::import::{normalize_pinyin, InvalidLine};
+use crate::journal::write_atomic;
+use crate::scoring::{DecayScoring, ScoringPolicy, DEFAULT_DECAY_DAYS};
+use crate::user_dict::{Tombstone, UserDict, WordRecord};
+use lingcode_core::syllable::{split_syllables, split_syllables_into};
+use std::fs;
+use std::path::Path;
+
+/// 导出时写入的 Rime 版本
+const RIME_VERSION: &str = "1.5.3";
+
+/// Rime 快照导入结果
+#[derive(Debug, Clone, Default)]
+pub struct RimeUserDbSummary {
+    /// 快照中的词典名称
+    pub db_name: Option<String>,
+    /// 合并时使用的设备 ID
+    pub device_id: String,
+    /// 导入的词条数
+    pub imported: usize,
+    /// 导入的删除记录数
+    pub deleted: usize,
+    /// 无法解析的行
+    pub invalid: Vec<InvalidLine>,
+}
+
+impl UserDict {
+    /// 导入 Rime 用户词典快照并合并到词库
+    pub fn import_rime_userdb(&mut self, path: &Path) -> anyhow::Result<RimeUserDbSummary> {
+        let text = fs::read_to_string(path)?;
+        Ok(self.import_rime_userdb_str(&text, self.now()))
+    }
+
+    /// 从快照文本导入，`now` 为推算最后使用时间的基准
+    pub fn import_rime_userdb_str(&mut self, text: &str, now: u64) -> RimeUserDbSummary {
+        let (imported, summary) = parse_userdb(text, now, self.device_id());
+        self.merge(&imported);
+        summary
+    }
+
+    /// 导出为 Rime 用户词典快照，返回导出的词条数（含删除记录）
+    pub fn export_rime_userdb(&self, path: &Path, db_name: &str) -> anyhow::Result<usize> {
+        let (text, count) = self.to_rime_userdb(db_name);
+        write_atomic(path, text.as_bytes())?;
+        Ok(count)
+    }
+
+    /// 生成 Rime 用户词典快照文本与词条数
+    ///
+    /// 时刻按最后使用时间排序后累计使用次数得到，当前时刻为总次数；
+    /// 仍在屏蔽候选的删除标记写为负次数
+    pub fn to_rime_userdb(&self, db_name: &str) -> (String, usize) {
+        let now = self.now();
+        let decay = DecayScoring::default();
+        let mut records: Vec<&WordRecord> = self.records().collect();
+        records.sort_by(|a, b| a.last_used.cmp(&b.last_used).then_with(|| a.pinyin.cmp(&b.pinyin)));
+
+        let mut tick: u64 = 0;
+        let mut lines = Vec::with_capacity(records.len());
+        for record in records {
+            tick += record.count as u64;
+            let dee = record.count as f64 * decay.time_decay(record, now);
+            lines.push(format!(
+                "{}\t{}\tc={} d={} t={}",
+                rime_code(&record.text, &record.pinyin),
+                record.text,
+                record.count,
+                format_dee(dee),
+                tick
+            ));
+        }
+        for tombstone in self.tombstones().iter().filter(|t| self.is_suppressed(&t.text, &t.pinyin)) {
+            let count = tombstone.counts.values().sum::<u32>().max(1);
+            lines.push(format!("{}\t{}\tc=-{} d=0 t={}", rime_code(&tombstone.text, &tombstone.pinyin), tombstone.text, count, tick));
+        }
+        lines.sort();
+
+        let mut out = format!(
+            "# Rime user dictionary\n#@/db_name\t{}\n#@/db_type\tuserdb\n#@/rime_version\t{}\n#@/tick\t{}\n#@/user_id\t{}\n",
+            db_name,
+            RIME_VERSION,
+            tick,
+            self.device_id()
+        );
+        for line in &lines {
+            out.push_str(line);
+            out.push('\n');
+        }
+        (out, lines.len())
+    }
+}
+
+/// 解析快照为单设备词库，`local_device` 为本机设备 ID
+fn parse_userdb(text: &str, now: u64, local_device: &str) -> (UserDict, RimeUserDbSummary) {
+    let mut summary = RimeUserDbSummary::default();
+    let mut user_id = None;
+    let mut records = Vec::new();
+    let mut deleted = Vec::new();
+
+    for (index, line) in text.lines().enumerate() {
+        let line = line.trim_end_matches(['\r', '\n']);
+        if let Some(meta) = line.strip_prefix("#@/") {
+            match meta.split_once('\t') {
+                Some(("db_name", value)) => summary.db_name = Some(value.trim().to_string()),
+                Some(("user_id", value)) => user_id = Some(value.trim().to_string()),
+                _ => {}
+            }
+            continue;
+        }
+        if line.trim().is_empty() || line.starts_with('#') {
+            continue;
+        }
+        match parse_entry(line) {
+            Ok(entry) if entry.commits > 0 => records.push(entry),
+            Ok(entry) => deleted.push(entry),
+            Err(reason) => summary.invalid.push(InvalidLine {
+                line: index + 1,
+                reason,
+            }),
+        }
+    }
+
+    summary.device_id = match user_id {
+        Some(id) if id == local_device => id,
+        Some(id) if !id.is_empty() => format!("rime-{}", id),
+        _ => "rime".to_string(),
+    };
+    let mut dict = UserDict::new();
+    dict.set_device_id(&summary.device_id);
+    for entry in &records {
+        let count = entry.commits as u32;
+        let mut record = WordRecord::new_at(entry.text.clone(), entry.pinyin.clone(), last_used(entry, now));
+        record.count = count;
+        record.devices.insert(summary.device_id.clone(), count);
+        dict.push_record(record);
+    }
+    for entry in &deleted {
+        dict.add_tombstone(Tombstone {
+            text: entry.text.clone(),
+            pinyin: entry.pinyin.clone(),
+            deleted_at: now,
+            counts: [(summary.device_id.clone(), entry.commits.unsigned_abs() as u32)].into(),
+        });
+    }
+    dict.rebuild_index();
+    summary.imported = records.len();
+    summary.deleted = deleted.len();
+    (dict, summary)
+}
+
+/// 快照中的一条词条
+struct UserDbEntry {
+    pinyin: String,
+    text: String,
+    commits: i64,
+    dee: Option<f64>,
+}
+
+fn parse_entry(line: &str) -> Result<UserDbEntry, String> {
+    let mut fields = line.split('\t');
+    let (code, text, attrs) = match (fields.next(), fields.next(), fields.next()) {
+        (Some(code), Some(text), Some(attrs)) if !text.is_empty() => (code, text, attrs),
+        _ => return Err("应为 编码\\t词\\tc=次数 d=权重 t=时刻".to_string()),
+    };
+    let pinyin = normalize_pinyin(code).ok_or_else(|| format!("无效的编码: {}", code.trim()))?.concat();
+
+    let mut commits = None;
+    let mut dee = None;
+    for attr in attrs.split_whitespace() {
+        match attr.split_once('=') {
+            Some(("c", value)) => commits = Some(value.parse::<i64>().map_err(|_| format!("无效的次数: {}", value))?),
+            Some(("d", value)) => dee = Some(value.parse::<f64>().map_err(|_| format!("无效的权重: {}", value))?),
+            _ => {}
+        }
+    }
+    let commits = commits.ok_or_else(|| "缺少次数 c=".to_string())?;
+    if commits > u32::MAX as i64 || commits < -(u32::MAX as i64) {
+        return Err(format!("次数超出范围: {}", commits));
+    }
+    Ok(UserDbEntry {
+        pinyin,
+        text: text.to_string(),
+        commits,
+        dee,
+    })
+}
+
+/// 由衰减权重反推最后使用时间
+fn last_used(entry: &UserDbEntry, now: u64) -> u64 {
+    let commits = entry.commits as f64;
+    let days = match entry.dee {
+        Some(dee) if dee > 0.0 && dee < commits => DEFAULT_DECAY_DAYS * (commits / dee).ln(),
+        _ => 0.0,
+    };
+    now.saturating_sub((days * 86400.0) as u64)
+}
+
+/// Rime 编码：音节以空格分隔并以空格结尾，有多种切分时取音节数与字数相同的，无法切分的编码原样输出
+fn rime_code(text: &str, pinyin: &str) -> String {
+    match split_syllables_into(pinyin, text.chars().count()).or_else(|| split_syllables(pinyin)) {
+        Some(syllables) => syllables.iter().map(|s| format!("{} ", s)).collect(),
+        None => format!("{} ", pinyin),
+    }
+}
+
+/// 衰减权重保留 4 位小数
+fn format_dee(dee: f64) -> String {
+    let text = format!("{:.4}", dee);
+    text.trim_end_matches('0').trim_end_matches('.').to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DAY: u64 = 86400;
+
+    const SNAPSHOT: &str = "# Rime user dictionary\n\
+#@/db_name\tluna_pinyin.userdb\n\
+#@/db_type\tuserdb\n\
+#@/rime_version\t1.5.3\n\
+#@/tick\t20\n\
+#@/user_id\tabc\n\
+ni hao \t你好\tc=3 d=3 t=18\n\
+zhong guo \t中国\tc=4 d=1.4715 t=5\n\
+lü se \t绿色\tc=-2 d=0 t=7\n\
+broken line\n\
+xx \t啊\tc=abc d=1 t=1\n";
+
+    #[test]
+    fn test_import_rime_userdb() {
+        let now = 100 * DAY;
+        let mut dict = UserDict::new();
+        dict.record_usage_at("你好", "nihao", now - DAY);
+        dict.record_usage_at("绿色", "lvse", now - DAY);
+
+        let summary = dict.import_rime_userdb_str(SNAPSHOT, now);
+        assert_eq!(summary.db_name.as_deref(), Some("luna_pinyin.userdb"));
+        assert_eq!(summary.device_id, "rime-abc");
+        assert_eq!((summary.imported, summary.deleted), (2, 1));
+        assert_eq!(summary.invalid.len(), 2);
+
+        // 本机 1 次 + Rime 3 次
+        let record = dict.get_record("你好", "nihao").unwrap();
+        assert_eq!((record.count, record.last_used), (4, now));
+        // d / c = e^(-1)，约 30 天前使用
+        let record = dict.get_record("中国", "zhongguo").unwrap();
+        assert_eq!(record.count, 4);
+        assert!(((now - record.last_used) as f64 / DAY as f64 - 30.0).abs() < 0.1);
+        // Rime 的删除不影响本机的使用
+        assert_eq!(dict.get_reading_count("绿色", "lvse"), 1);
+
+        // 重复导入结果不变
+        dict.import_rime_userdb_str(SNAPSHOT, now);
+        assert_eq!(dict.get_reading_count("你好", "nihao"), 4);
+    }
+
+    #[test]
+    fn test_export_rime_userdb_round_trip() {
+        let now = 100 * DAY;
+        let mut dict = UserDict::new();
+        dict.set_device_id("laptop");
+        dict.set_clock(std::sync::Arc::new(crate::ManualClock::new(now)));
+        dict.record_usage_at("中国", "zhongguo", now - 30 * DAY);
+        dict.record_usage_at("中国", "zhongguo", now - 30 * DAY);
+        dict.record_usage_at("西安", "xian", now);
+        dict.delete_at("删除", "shanchu", now);
+
+        let (text, count) = dict.to_rime_userdb("luna_pinyin");
+        assert_eq!(count, 3);
+        assert!(text.contains("#@/tick\t3\n"));
+        assert!(text.contains("#@/user_id\tlaptop\n"));
+        assert!(text.contains("zhong guo \t中国\tc=2 d=0.7358 t=2\n"));
+        assert!(text.contains("xi an \t西安\tc=1 d=1 t=3\n"));
+        assert!(text.contains("shan chu \t删除\tc=-1 d=0 t=3\n"));
+
+        let mut other = UserDict::new();
+        let summary = other.import_rime_userdb_str(&text, now);
+        assert_eq!((summary.imported, summary.deleted), (2, 1));
+        let record = other.get_record("中国", "zhongguo").unwrap();
+        assert_eq!(record.count, 2);
+        assert!((now - record.last_used).abs_diff(30 * DAY) < DAY / 10);
+        assert!(other.is_suppressed("删除", "shanchu"));
+
+        // 导入本机导出的快照：记在本机设备下，次数不变
+        let summary = dict.import_rime_userdb_str(&text, now);
+        assert_eq!(summary.device_id, "laptop");
+        assert_eq!(dict.get_reading_count("中国", "zhongguo"), 2);
+        assert_eq!(dict.get_reading_count("西安", "xian"), 1);
+        assert_eq!(dict.get_record("中国", "zhongguo").unwrap().devices.len(), 1);
+        assert!(dict.is_suppressed("删除", "shanchu"));
+    }
+}
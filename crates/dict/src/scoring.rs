@@ -0,0 +1,310 @@
+//! 用户词频评分策略与时钟
+//!
+//! 用户词条的得分由 [`ScoringPolicy`] 根据使用次数与使用时间计算，
+//! 当前时间由 [`Clock`] 提供，测试或重放日志时可以注入固定时钟
+
+use crate::user_dict::{current_timestamp, WordRecord};
+use lingcode_core::schema_parser::UserFrequency;
+use std::fmt::Debug;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+/// 一天的秒数
+const SECONDS_PER_DAY: f64 = 86400.0;
+
+/// 默认策略的衰减时间常数（天）
+pub const DEFAULT_DECAY_DAYS: f64 = 30.0;
+
+/// 半衰期与窗口长度的下限（一秒），避免除以零得到 NaN
+const MIN_DAYS: f64 = 1.0 / SECONDS_PER_DAY;
+
+/// 时间来源
+pub trait Clock: Debug + Send + Sync {
+    /// 当前 Unix 时间戳（秒）
+    fn now(&self) -> u64;
+}
+
+/// 系统时钟
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> u64 {
+        current_timestamp()
+    }
+}
+
+/// 手动控制的时钟，用于测试与重放
+#[derive(Debug, Default)]
+pub struct ManualClock {
+    now: AtomicU64,
+}
+
+impl ManualClock {
+    /// 创建停在 `now` 的时钟
+    pub fn new(now: u64) -> Self {
+        Self { now: AtomicU64::new(now) }
+    }
+
+    /// 设置当前时间
+    pub fn set(&self, now: u64) {
+        self.now.store(now, Ordering::Relaxed);
+    }
+
+    /// 时间前进 `seconds` 秒
+    pub fn advance(&self, seconds: u64) {
+        self.now.fetch_add(seconds, Ordering::Relaxed);
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> u64 {
+        self.now.load(Ordering::Relaxed)
+    }
+}
+
+/// 用户词频评分策略
+pub trait ScoringPolicy: Debug + Send + Sync {
+    /// 策略名称（用于日志与得分明细）
+    fn name(&self) -> &str;
+
+    /// 词条在 `now` 时刻的得分，得分越高排序越靠前
+    fn score(&self, record: &WordRecord, now: u64) -> f64;
+
+    /// 词条在 `now` 时刻的时间衰减因子（0~1），不随时间衰减的策略为 1
+    fn time_decay(&self, _record: &WordRecord, _now: u64) -> f64 {
+        1.0
+    }
+}
+
+/// 距离 `since` 过去的天数
+fn days_since(since: u64, now: u64) -> f64 {
+    now.saturating_sub(since) as f64 / SECONDS_PER_DAY
+}
+
+/// 活跃度因子：平均每天使用次数越多，得分越高
+fn activity_factor(record: &WordRecord, now: u64) -> f64 {
+    let days = days_since(record.first_used, now);
+    let daily_avg = if days > 0.0 {
+        record.count as f64 / days
+    } else {
+        record.count as f64
+    };
+    1.0 + daily_avg.ln_1p()
+}
+
+/// 默认策略：使用次数 × 指数衰减 e^(-天数/30) × 活跃度因子
+#[derive(Debug, Clone, Copy)]
+pub struct DecayScoring {
+    /// 衰减时间常数（天）
+    pub decay_days: f64,
+}
+
+impl Default for DecayScoring {
+    fn default() -> Self {
+        Self {
+            decay_days: DEFAULT_DECAY_DAYS,
+        }
+    }
+}
+
+impl ScoringPolicy for DecayScoring {
+    fn name(&self) -> &str {
+        "decay"
+    }
+
+    fn score(&self, record: &WordRecord, now: u64) -> f64 {
+        record.count as f64 * self.time_decay(record, now) * activity_factor(record, now)
+    }
+
+    fn time_decay(&self, record: &WordRecord, now: u64) -> f64 {
+        (-days_since(record.last_used, now) / self.decay_days).exp()
+    }
+}
+
+/// 半衰期策略：与默认策略相同，但衰减按半衰期计算，`half_life_days` 天后得分减半
+///
+/// 半衰期设得较长时，新学到的专业词汇在较长时间不用后仍能排在前面
+#[derive(Debug, Clone, Copy)]
+pub struct HalfLifeScoring {
+    /// 半衰期（天）
+    pub half_life_days: f64,
+}
+
+impl HalfLifeScoring {
+    /// 创建指定半衰期的策略，非正数或 NaN 按下限一秒处理
+    pub fn new(half_life_days: f64) -> Self {
+        Self {
+            half_life_days: half_life_days.max(MIN_DAYS),
+        }
+    }
+}
+
+impl ScoringPolicy for HalfLifeScoring {
+    fn name(&self) -> &str {
+        "half_life"
+    }
+
+    fn score(&self, record: &WordRecord, now: u64) -> f64 {
+        record.count as f64 * self.time_decay(record, now) * activity_factor(record, now)
+    }
+
+    fn time_decay(&self, record: &WordRecord, now: u64) -> f64 {
+        0.5f64.powf(days_since(record.last_used, now) / self.half_life_days)
+    }
+}
+
+/// 纯次数策略：得分即使用次数，不随时间衰减
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CountScoring;
+
+impl ScoringPolicy for CountScoring {
+    fn name(&self) -> &str {
+        "count"
+    }
+
+    fn score(&self, record: &WordRecord, _now: u64) -> f64 {
+        record.count as f64
+    }
+}
+
+/// 滑动窗口策略：只统计最近 `window_days` 天内的使用次数
+///
+/// 词条只记录首次与最后使用时间，窗口内的次数按两者之间均匀使用估算
+#[derive(Debug, Clone, Copy)]
+pub struct SlidingWindowScoring {
+    /// 窗口长度（天）
+    pub window_days: f64,
+}
+
+impl SlidingWindowScoring {
+    /// 创建指定窗口长度的策略，非正数或 NaN 按下限一秒处理
+    pub fn new(window_days: f64) -> Self {
+        Self {
+            window_days: window_days.max(MIN_DAYS),
+        }
+    }
+
+    /// 窗口起点
+    fn window_start(&self, now: u64) -> u64 {
+        now.saturating_sub((self.window_days * SECONDS_PER_DAY) as u64)
+    }
+}
+
+impl ScoringPolicy for SlidingWindowScoring {
+    fn name(&self) -> &str {
+        "sliding_window"
+    }
+
+    fn score(&self, record: &WordRecord, now: u64) -> f64 {
+        let start = self.window_start(now);
+        if record.last_used < start {
+            return 0.0;
+        }
+        if record.first_used >= start || record.last_used <= record.first_used {
+            return record.count as f64;
+        }
+        let span = (record.last_used - record.first_used) as f64;
+        let in_window = (record.last_used - start) as f64;
+        record.count as f64 * in_window / span
+    }
+
+    fn time_decay(&self, record: &WordRecord, now: u64) -> f64 {
+        if record.last_used < self.window_start(now) {
+            0.0
+        } else {
+            1.0
+        }
+    }
+}
+
+/// 默认评分策略
+pub fn default_scoring() -> Arc<dyn ScoringPolicy> {
+    Arc::new(DecayScoring::default())
+}
+
+/// 默认时钟（系统时钟）
+pub fn default_clock() -> Arc<dyn Clock> {
+    Arc::new(SystemClock)
+}
+
+/// 根据方案配置创建评分策略，参数无效时使用默认策略
+pub fn scoring_from_config(config: &UserFrequency) -> Arc<dyn ScoringPolicy> {
+    let valid = |days: f64| days.is_finite() && days > 0.0;
+    match *config {
+        UserFrequency::Decay { decay_days } if valid(decay_days) => Arc::new(DecayScoring { decay_days }),
+        UserFrequency::HalfLife { half_life_days } if valid(half_life_days) => {
+            Arc::new(HalfLifeScoring::new(half_life_days))
+        }
+        UserFrequency::Count => Arc::new(CountScoring),
+        UserFrequency::SlidingWindow { window_days } if valid(window_days) => {
+            Arc::new(SlidingWindowScoring::new(window_days))
+        }
+        _ => {
+            log::warn!("忽略无效的用户词频策略配置: {:?}", config);
+            default_scoring()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DAY: u64 = 86400;
+
+    fn record(count: u32, first_used: u64, last_used: u64) -> WordRecord {
+        let mut record = WordRecord::new_at("术语".to_string(), "shuyu".to_string(), first_used);
+        record.count = count;
+        record.last_used = last_used;
+        record
+    }
+
+    #[test]
+    fn test_decay_matches_legacy_formula() {
+        let now = 100 * DAY;
+        let record = record(10, 40 * DAY, 70 * DAY);
+        let expected = 10.0 * (-30.0f64 / 30.0).exp() * (1.0 + (10.0f64 / 60.0).ln_1p());
+        assert!((DecayScoring::default().score(&record, now) - expected).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_half_life_keeps_jargon_longer() {
+        let now = 200 * DAY;
+        let record = record(5, 100 * DAY, 110 * DAY);
+        let half_life = HalfLifeScoring::new(90.0);
+        assert!((half_life.time_decay(&record, now) - 0.5).abs() < 1e-9);
+        assert!(half_life.score(&record, now) > DecayScoring::default().score(&record, now));
+    }
+
+    #[test]
+    fn test_count_and_sliding_window() {
+        let now = 100 * DAY;
+        assert_eq!(CountScoring.score(&record(7, 0, DAY), now), 7.0);
+
+        let window = SlidingWindowScoring::new(10.0);
+        assert_eq!(window.score(&record(7, 0, DAY), now), 0.0);
+        assert_eq!(window.score(&record(7, 95 * DAY, 99 * DAY), now), 7.0);
+        // 使用分布在 80~100 天，窗口 90~100 天内约一半
+        assert!((window.score(&record(8, 80 * DAY, 100 * DAY), now) - 4.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_invalid_days_are_clamped() {
+        let now = 100 * DAY;
+        let record = record(3, 90 * DAY, 100 * DAY);
+        for days in [0.0, -5.0, f64::NAN] {
+            assert!(!HalfLifeScoring::new(days).score(&record, now).is_nan());
+            assert!(!SlidingWindowScoring::new(days).score(&record, now).is_nan());
+        }
+    }
+
+    #[test]
+    fn test_manual_clock() {
+        let clock = ManualClock::new(DAY);
+        clock.advance(DAY);
+        assert_eq!(clock.now(), 2 * DAY);
+        clock.set(5);
+        assert_eq!(clock.now(), 5);
+    }
+}
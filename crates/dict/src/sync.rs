@@ -0,0 +1,151 @@
+//! 多设备同步
+//!
+//! 与 Rime 的同步方式相同：各设备把自己的用户词库快照写入共享的同步目录
+//! （如网盘文件夹），同步时先合并其他设备的快照，再写出本机快照。
+//! 合并按设备取最大值，重复同步结果不变。
+//! 本地词库加密时，写入同步目录的快照同样加密
+
+use crate::crypto::{seal_with, DictCipher};
+use crate::journal::write_atomic;
+use crate::user_dict::UserDict;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// 同步目录中用户词库快照的文件名后缀
+pub const SYNC_FILE_SUFFIX: &str = ".userdict.json";
+
+/// 同步结果
+#[derive(Debug, Clone, Default)]
+pub struct SyncSummary {
+    /// 合并了哪些设备的快照
+    pub merged_devices: Vec<String>,
+    /// 无法读取的快照文件
+    pub failed_files: Vec<PathBuf>,
+    /// 同步后的词条数
+    pub total_records: usize,
+}
+
+/// 设备在同步目录中的快照路径
+pub fn snapshot_path(folder: &Path, device_id: &str) -> PathBuf {
+    let name: String = device_id
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' { c } else { '_' })
+        .collect();
+    folder.join(format!("{}{}", name, SYNC_FILE_SUFFIX))
+}
+
+/// 与同步目录同步：合并其他设备的快照，再写出本机快照
+pub fn sync_with_folder(dict: &mut UserDict, folder: &Path) -> anyhow::Result<SyncSummary> {
+    sync_with_folder_encrypted(dict, folder, None)
+}
+
+/// 与同步目录同步，`cipher` 非空时解密其他设备的快照并加密写出本机快照
+///
+/// 无法解密的快照（如其他设备使用不同的密钥）记入 `failed_files`
+pub fn sync_with_folder_encrypted(
+    dict: &mut UserDict,
+    folder: &Path,
+    cipher: Option<&DictCipher>,
+) -> anyhow::Result<SyncSummary> {
+    fs::create_dir_all(folder)?;
+    let own_path = snapshot_path(folder, dict.device_id());
+    let mut summary = SyncSummary::default();
+
+    let mut paths: Vec<PathBuf> = fs::read_dir(folder)?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| {
+            path.file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| name.ends_with(SYNC_FILE_SUFFIX))
+        })
+        .filter(|path| *path != own_path)
+        .collect();
+    paths.sort();
+
+    for path in paths {
+        let loaded = match cipher {
+            Some(cipher) => UserDict::load_encrypted(&path, cipher),
+            None => UserDict::load_from_file(&path),
+        };
+        match loaded {
+            Ok(other) if other.device_id() == dict.device_id() => {}
+            Ok(other) => {
+                dict.merge(&other);
+                summary.merged_devices.push(other.device_id().to_string());
+            }
+            Err(e) => {
+                log::warn!("无法读取同步快照 {}: {}", path.display(), e);
+                summary.failed_files.push(path);
+            }
+        }
+    }
+
+    let data = seal_with(serde_json::to_vec(dict)?, cipher)?;
+    write_atomic(&own_path, &data)?;
+    summary.total_records = dict.stats().total_records;
+    log::info!("用户词库已同步: 合并 {} 台设备", summary.merged_devices.len());
+    Ok(summary)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn device(id: &str) -> UserDict {
+        let mut dict = UserDict::new();
+        dict.set_device_id(id);
+        dict
+    }
+
+    #[test]
+    fn test_sync_two_devices() {
+        let folder = TempDir::new().unwrap();
+        let mut laptop = device("laptop");
+        let mut desktop = device("desktop");
+
+        laptop.record_usage_at("中国", "zhongguo", 100);
+        laptop.record_usage_at("中国", "zhongguo", 300);
+        desktop.record_usage_at("中国", "zhongguo", 50);
+        desktop.record_usage_at("中文", "zhongwen", 200);
+
+        sync_with_folder(&mut laptop, folder.path()).unwrap();
+        let summary = sync_with_folder(&mut desktop, folder.path()).unwrap();
+        assert_eq!(summary.merged_devices, vec!["laptop"]);
+        sync_with_folder(&mut laptop, folder.path()).unwrap();
+
+        for dict in [&laptop, &desktop] {
+            let record = dict.get_record("中国", "zhongguo").unwrap();
+            assert_eq!((record.count, record.first_used, record.last_used), (3, 50, 300));
+            assert_eq!(dict.get_count("中文"), 1);
+        }
+
+        // 重复同步结果不变
+        sync_with_folder(&mut laptop, folder.path()).unwrap();
+        sync_with_folder(&mut desktop, folder.path()).unwrap();
+        assert_eq!(laptop.get_count("中国"), 3);
+        assert_eq!(desktop.get_count("中国"), 3);
+    }
+
+    #[test]
+    fn test_sync_encrypted() {
+        let folder = TempDir::new().unwrap();
+        let cipher = DictCipher::from_key([5; 32]);
+        let mut laptop = device("laptop");
+        let mut desktop = device("desktop");
+        laptop.record_usage_at("机密", "jimi", 100);
+
+        sync_with_folder_encrypted(&mut laptop, folder.path(), Some(&cipher)).unwrap();
+        let data = fs::read(snapshot_path(folder.path(), "laptop")).unwrap();
+        assert!(!String::from_utf8_lossy(&data).contains("jimi"));
+
+        // 没有密钥时无法读取
+        let summary = sync_with_folder(&mut desktop, folder.path()).unwrap();
+        assert_eq!(summary.failed_files.len(), 1);
+        assert_eq!(desktop.get_count("机密"), 0);
+
+        let summary = sync_with_folder_encrypted(&mut desktop, folder.path(), Some(&cipher)).unwrap();
+        assert_eq!(summary.merged_devices, vec!["laptop"]);
+        assert_eq!(desktop.get_count("机密"), 1);
+    }
+}
@@ -2,11 +2,15 @@
 //!
 //! 记录用户输入习惯，动态调整候选词排序
 
+use crate::crypto::{seal_with, unseal_with, DictCipher};
+use crate::journal::write_atomic;
+use crate::scoring::{default_clock, default_scoring, Clock, DecayScoring, ScoringPolicy};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
-use std::fs::{File, OpenOptions};
-use std::io::{BufReader, BufWriter, Write};
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
 use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
 use std::time::{SystemTime, UNIX_EPOCH};
 
 /// 词条使用记录
@@ -22,132 +26,314 @@ pub struct WordRecord {
     pub last_used: u64,
     /// 首次使用时间
     pub first_used: u64,
+    /// 各设备的累计使用次数（设备 ID -> 次数），多设备同步时按设备取最大值合并
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub devices: BTreeMap<String, u32>,
 }
 
 impl WordRecord {
     /// 创建新记录
     pub fn new(text: String, pinyin: String) -> Self {
-        let now = current_timestamp();
+        Self::new_at(text, pinyin, current_timestamp())
+    }
+
+    /// 创建指定使用时间的新记录
+    pub fn new_at(text: String, pinyin: String, timestamp: u64) -> Self {
         Self {
             text,
             pinyin,
             count: 1,
-            last_used: now,
-            first_used: now,
+            last_used: timestamp,
+            first_used: timestamp,
+            devices: BTreeMap::new(),
         }
     }
 
     /// 更新使用记录
     pub fn update(&mut self) {
+        self.update_at(current_timestamp());
+    }
+
+    /// 以指定时间更新使用记录
+    pub fn update_at(&mut self, timestamp: u64) {
         self.count += 1;
-        self.last_used = current_timestamp();
+        self.last_used = self.last_used.max(timestamp);
+    }
+
+    /// 时间衰减因子：最近使用过的词分数更高
+    ///
+    /// 按默认策略（见 [`DecayScoring`]）与系统时间计算
+    pub fn time_decay(&self) -> f64 {
+        DecayScoring::default().time_decay(self, current_timestamp())
     }
 
     /// 计算词频分数（考虑使用次数和时间衰减）
+    ///
+    /// 按默认策略与系统时间计算，词库中的排序使用 [`UserDict::score`]
     pub fn score(&self) -> f64 {
-        let now = current_timestamp();
-        let days_since_last = (now - self.last_used) as f64 / 86400.0;
-        let days_since_first = (now - self.first_used) as f64 / 86400.0;
+        DecayScoring::default().score(self, current_timestamp())
+    }
+}
 
-        // 基础分数：使用次数
-        let count_score = self.count as f64;
+/// 删除标记
+///
+/// 记录删除时各设备的累计次数；同步时其他设备上不超过这些次数的使用视为已删除，
+/// 删除之后的新使用则保留
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Tombstone {
+    /// 文本内容
+    pub text: String,
+    /// 对应的拼音
+    pub pinyin: String,
+    /// 删除时间
+    pub deleted_at: u64,
+    /// 删除时各设备的累计次数
+    #[serde(default)]
+    pub counts: BTreeMap<String, u32>,
+}
 
-        // 时间衰减因子：最近使用过的词分数更高
-        // 使用指数衰减：e^(-days/30)，30天半衰期
-        let time_decay = (-days_since_last / 30.0).exp();
+impl Tombstone {
+    /// 合并另一个删除标记：各设备次数与删除时间取最大值
+    fn merge(&mut self, other: &Tombstone) {
+        self.deleted_at = self.deleted_at.max(other.deleted_at);
+        merge_counts(&mut self.counts, &other.counts);
+    }
+}
 
-        // 活跃度因子：经常使用的词分数更高
-        // 计算平均每天使用次数
-        let daily_avg = if days_since_first > 0.0 {
-            self.count as f64 / days_since_first
-        } else {
-            self.count as f64
-        };
+/// 固定候选位置（置顶）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Pin {
+    /// 文本内容
+    pub text: String,
+    /// 编码
+    pub pinyin: String,
+    /// 固定的位置（从 0 开始），None 表示已取消固定
+    pub position: Option<usize>,
+    /// 更新时间，同步时以较晚的为准
+    pub updated_at: u64,
+}
 
-        count_score * time_decay * (1.0 + daily_avg.ln_1p())
+/// 清除的时间段
+///
+/// 只记录时间，不记录词条内容；同步时其他设备上使用期间与时间段重叠、
+/// 且清除之后没有再使用的词条同样被清除
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+struct PurgeWindow {
+    start: u64,
+    end: u64,
+    purged_at: u64,
+}
+
+impl PurgeWindow {
+    fn covers(&self, record: &WordRecord) -> bool {
+        record.first_used <= self.end && record.last_used >= self.start && record.last_used <= self.purged_at
     }
 }
 
+/// 当前用户词库文件格式版本
+///
+/// 版本 1 的 `text_index` 每个文本只对应一个读音，多音字的其他读音无法记录；
+/// 版本 2 起索引按（文本, 拼音）建立，并在加载时由词条重建，不再写入文件
+pub const USER_DICT_VERSION: u32 = 2;
+
 /// 词频数据库
+///
+/// 词条以（文本, 拼音）为键，同一个字的不同读音（如 行 xing / hang）分别记录
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct UserDict {
+    /// 文件格式版本，旧文件没有此字段（视为版本 1）
+    #[serde(default = "legacy_version")]
+    version: u32,
+    /// 本机设备 ID，多设备同步时区分各设备的使用次数
+    #[serde(default)]
+    device_id: String,
     /// 用户词库：拼音 -> 词条列表
     words: HashMap<String, Vec<WordRecord>>,
-    /// 全局词条索引：文本 -> (拼音, 索引)
-    text_index: HashMap<String, (String, usize)>,
+    /// 全局词条索引：文本 -> (拼音 -> 索引)
+    #[serde(skip)]
+    text_index: HashMap<String, HashMap<String, usize>>,
     /// 总记录数
     total_records: usize,
     /// 最后保存时间
     last_saved: u64,
+    /// 删除标记
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    tombstones: Vec<Tombstone>,
+    /// 固定位置的候选
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pins: Vec<Pin>,
+    /// 清除的时间段
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    purges: Vec<PurgeWindow>,
+    /// 词频评分策略
+    #[serde(skip, default = "default_scoring")]
+    scoring: Arc<dyn ScoringPolicy>,
+    /// 时间来源
+    #[serde(skip, default = "default_clock")]
+    clock: Arc<dyn Clock>,
 }
 
 impl UserDict {
     /// 创建空的用户词库
     pub fn new() -> Self {
         Self {
+            version: USER_DICT_VERSION,
+            device_id: generate_device_id(),
             words: HashMap::new(),
             text_index: HashMap::new(),
             total_records: 0,
             last_saved: 0,
+            tombstones: Vec::new(),
+            pins: Vec::new(),
+            purges: Vec::new(),
+            scoring: default_scoring(),
+            clock: default_clock(),
+        }
+    }
+
+    /// 本机设备 ID
+    pub fn device_id(&self) -> &str {
+        &self.device_id
+    }
+
+    /// 设置本机设备 ID，已有的本机使用次数一并转移到新 ID 下
+    pub fn set_device_id(&mut self, device_id: &str) {
+        let old = std::mem::replace(&mut self.device_id, device_id.to_string());
+        if old == device_id {
+            return;
+        }
+        for record in self.words.values_mut().flatten() {
+            if let Some(count) = record.devices.remove(&old) {
+                *record.devices.entry(device_id.to_string()).or_insert(0) += count;
+            }
+        }
+        for tombstone in &mut self.tombstones {
+            if let Some(count) = tombstone.counts.remove(&old) {
+                *tombstone.counts.entry(device_id.to_string()).or_insert(0) += count;
+            }
         }
     }
 
-    /// 从文件加载用户词库
+    /// 从文件加载用户词库，旧版本文件会被迁移
+    ///
+    /// 文件已加密时返回 [`LingCodeError::DecryptionError`](lingcode_core::error::LingCodeError)
     pub fn load_from_file(path: &Path) -> anyhow::Result<Self> {
+        Self::load_with(path, None)
+    }
+
+    /// 从加密文件加载用户词库，也可以读取未加密的旧文件
+    pub fn load_encrypted(path: &Path, cipher: &DictCipher) -> anyhow::Result<Self> {
+        Self::load_with(path, Some(cipher))
+    }
+
+    fn load_with(path: &Path, cipher: Option<&DictCipher>) -> anyhow::Result<Self> {
         if !path.exists() {
             return Ok(Self::new());
         }
+        let data = unseal_with(fs::read(path)?, cipher)?;
+        let dict = Self::from_json(&data)?;
+        if dict.version < USER_DICT_VERSION {
+            log::info!("迁移用户词库 {}: 版本 {} -> {}", path.display(), dict.version, USER_DICT_VERSION);
+        }
+        Ok(dict)
+    }
 
-        let file = File::open(path)?;
-        let reader = BufReader::new(file);
-        let dict: UserDict = serde_json::from_reader(reader)?;
+    /// 从 JSON 解析用户词库，旧版本数据会被迁移
+    pub(crate) fn from_json(data: &[u8]) -> anyhow::Result<Self> {
+        let mut dict: UserDict = serde_json::from_slice(data)?;
+        if dict.version < USER_DICT_VERSION {
+            dict.version = USER_DICT_VERSION;
+        }
+        if dict.device_id.is_empty() {
+            dict.device_id = generate_device_id();
+        }
+        dict.rebuild_index();
         Ok(dict)
     }
 
-    /// 保存到文件
+    /// 保存到文件（JSON 格式，用于导入导出）
+    ///
+    /// 先写入临时文件再重命名，写入中途崩溃不会破坏原文件
     pub fn save_to_file(&self, path: &Path) -> anyhow::Result<()> {
-        // 确保父目录存在
-        if let Some(parent) = path.parent() {
-            std::fs::create_dir_all(parent)?;
-        }
+        let data = serde_json::to_vec_pretty(self)?;
+        write_atomic(path, &data)
+    }
 
-        let file = OpenOptions::new()
-            .write(true)
-            .create(true)
-            .truncate(true)
-            .open(path)?;
-        
-        let writer = BufWriter::new(file);
-        serde_json::to_writer_pretty(writer, self)?;
-        
-        Ok(())
+    /// 加密保存到文件
+    pub fn save_encrypted(&self, path: &Path, cipher: &DictCipher) -> anyhow::Result<()> {
+        let data = seal_with(serde_json::to_vec(self)?, Some(cipher))?;
+        write_atomic(path, &data)
     }
 
-    /// 记录用户使用某个词条
+    /// 记录用户使用某个词条（按文本与拼音区分读音）
     pub fn record_usage(&mut self, text: &str, pinyin: &str) {
+        self.record_usage_at(text, pinyin, self.now());
+    }
+
+    /// 以指定时间记录用户使用某个词条（用于重放日志）
+    pub fn record_usage_at(&mut self, text: &str, pinyin: &str, timestamp: u64) {
+        let device = self.device_id.clone();
         // 检查是否已存在
-        if let Some((existing_pinyin, index)) = self.text_index.get(text).cloned() {
-            // 更新现有记录
-            if let Some(records) = self.words.get_mut(&existing_pinyin) {
-                if let Some(record) = records.get_mut(index) {
-                    record.update();
-                    return;
-                }
-            }
+        if let Some(record) = self.get_record_mut(text, pinyin) {
+            record.update_at(timestamp);
+            *record.devices.entry(device).or_insert(0) += 1;
+            return;
         }
 
-        // 创建新记录
-        let record = WordRecord::new(text.to_string(), pinyin.to_string());
+        // 创建新记录；删除后再次使用时，设备次数从删除时的次数继续累计
+        let mut record = WordRecord::new_at(text.to_string(), pinyin.to_string(), timestamp);
+        if let Some(tombstone) = self.tombstone(text, pinyin) {
+            record.devices = tombstone.counts.clone();
+        }
+        *record.devices.entry(device).or_insert(0) += 1;
         let pinyin_key = pinyin.to_string();
         
         let records = self.words.entry(pinyin_key.clone()).or_insert_with(Vec::new);
         let index = records.len();
         records.push(record);
         
-        self.text_index.insert(text.to_string(), (pinyin_key, index));
+        self.text_index
+            .entry(text.to_string())
+            .or_default()
+            .insert(pinyin_key, index);
         self.total_records += 1;
     }
 
+    /// 设置词频评分策略
+    pub fn set_scoring_policy(&mut self, scoring: Arc<dyn ScoringPolicy>) {
+        self.scoring = scoring;
+    }
+
+    /// 获取词频评分策略
+    pub fn scoring_policy(&self) -> &Arc<dyn ScoringPolicy> {
+        &self.scoring
+    }
+
+    /// 设置时间来源
+    pub fn set_clock(&mut self, clock: Arc<dyn Clock>) {
+        self.clock = clock;
+    }
+
+    /// 获取时间来源
+    pub fn clock(&self) -> &Arc<dyn Clock> {
+        &self.clock
+    }
+
+    /// 当前时间（由时间来源提供）
+    pub fn now(&self) -> u64 {
+        self.clock.now()
+    }
+
+    /// 按当前评分策略计算词条得分
+    pub fn score(&self, record: &WordRecord) -> f64 {
+        self.scoring.score(record, self.now())
+    }
+
+    /// 按当前评分策略计算词条的时间衰减因子
+    pub fn time_decay(&self, record: &WordRecord) -> f64 {
+        self.scoring.time_decay(record, self.now())
+    }
+
     /// 根据拼音查询词条，返回按词频排序的结果
     pub fn lookup(&self, pinyin: &str) -> Vec<(&WordRecord, f64)> {
         let mut results: Vec<(&WordRecord, f64)> = self
@@ -155,24 +341,286 @@ impl UserDict {
             .get(pinyin)
             .map(|records| {
                 records.iter()
-                    .map(|r| (r, r.score()))
+                    .map(|r| (r, self.score(r)))
                     .collect()
             })
             .unwrap_or_default();
 
         // 按分数降序排序
-        results.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap());
+        results.sort_by(|a, b| b.1.total_cmp(&a.1));
         results
     }
 
-    /// 获取词条的使用次数
-    pub fn get_count(&self, text: &str) -> u32 {
+    /// 遍历所有词条
+    pub fn records(&self) -> impl Iterator<Item = &WordRecord> {
+        self.words.values().flatten()
+    }
+
+    /// 获取某个读音的词条使用记录
+    pub fn get_record(&self, text: &str, pinyin: &str) -> Option<&WordRecord> {
+        let index = *self.text_index.get(text)?.get(pinyin)?;
+        self.words.get(pinyin)?.get(index)
+    }
+
+    fn get_record_mut(&mut self, text: &str, pinyin: &str) -> Option<&mut WordRecord> {
+        let index = *self.text_index.get(text)?.get(pinyin)?;
+        self.words.get_mut(pinyin)?.get_mut(index)
+    }
+
+    /// 获取文本的所有读音记录
+    pub fn readings(&self, text: &str) -> Vec<&WordRecord> {
         self.text_index
             .get(text)
-            .and_then(|(pinyin, index)| {
-                self.words.get(pinyin)?.get(*index).map(|r| r.count)
+            .map(|readings| {
+                readings
+                    .iter()
+                    .filter_map(|(pinyin, index)| self.words.get(pinyin)?.get(*index))
+                    .collect()
             })
-            .unwrap_or(0)
+            .unwrap_or_default()
+    }
+
+    /// 撤销一次 `record_usage`
+    ///
+    /// `previous` 为记录前的词条状态（调用 `get_record` 获得），
+    /// 为 None 表示该词条是这次新建的，撤销时删除
+    pub fn rollback_usage(&mut self, text: &str, pinyin: &str, previous: Option<WordRecord>) {
+        match previous {
+            Some(record) => {
+                if let Some(existing) = self.get_record_mut(text, pinyin) {
+                    *existing = record;
+                }
+            }
+            None => {
+                self.remove_record(text, pinyin);
+            }
+        }
+    }
+
+    /// 删除词条，并留下删除标记使同步时其他设备上的旧记录也被删除
+    ///
+    /// 词条不在用户词库中（如系统词库的词）时同样留下删除标记，用于屏蔽该候选。
+    /// 返回用户词库中是否有该词条
+    pub fn delete(&mut self, text: &str, pinyin: &str) -> bool {
+        self.delete_at(text, pinyin, self.now())
+    }
+
+    /// 以指定时间删除词条
+    pub fn delete_at(&mut self, text: &str, pinyin: &str, timestamp: u64) -> bool {
+        let record = self.remove_record(text, pinyin);
+        let existed = record.is_some();
+        self.add_tombstone(Tombstone {
+            text: text.to_string(),
+            pinyin: pinyin.to_string(),
+            deleted_at: timestamp,
+            counts: record.map(|r| r.devices).unwrap_or_default(),
+        });
+        existed
+    }
+
+    /// 清除在 `[start, end]` 时间段内使用过的词条与设置的固定，返回清除的词条数
+    ///
+    /// 词条只记录首次与最后使用时间，使用期间与时间段有重叠的词条都会被清除。
+    /// 清除不留下删除标记（不保存被清除的文字，也不屏蔽系统词库的同名候选），
+    /// 只记录时间段，同步时其他设备上的这些词条不会恢复
+    pub fn purge(&mut self, start: u64, end: u64) -> usize {
+        self.purge_at(start, end, self.now())
+    }
+
+    /// 以指定时间清除时间段内的使用记录
+    pub fn purge_at(&mut self, start: u64, end: u64, timestamp: u64) -> usize {
+        let window = PurgeWindow { start, end, purged_at: timestamp };
+        let before = self.total_records;
+        if !self.purges.contains(&window) {
+            self.purges.push(window);
+        }
+        self.apply_purges();
+        self.compact_purges();
+        self.pins.retain(|pin| pin.updated_at < start || pin.updated_at > end);
+        before - self.total_records
+    }
+
+    /// 移除清除时间段内的词条
+    fn apply_purges(&mut self) {
+        if self.purges.is_empty() {
+            return;
+        }
+        let purges = &self.purges;
+        let mut removed = false;
+        for records in self.words.values_mut() {
+            let len = records.len();
+            records.retain(|record| !purges.iter().any(|window| window.covers(record)));
+            removed |= records.len() != len;
+        }
+        if removed {
+            self.words.retain(|_, records| !records.is_empty());
+            self.rebuild_index();
+        }
+    }
+
+    /// 压缩清除时间段，避免列表随清除次数无限增长
+    ///
+    /// 清除时间相同且相互重叠或相邻的时间段合并，被另一时间段完全包含且清除时间
+    /// 不晚于它的时间段丢弃。清除早于所有剩余词条首次使用的时间段也不再保留，
+    /// 此后同步来的其他设备上的旧词条不再被它屏蔽
+    fn compact_purges(&mut self) {
+        let mut windows = std::mem::take(&mut self.purges);
+        windows.sort_by_key(|window| (window.purged_at, window.start, window.end));
+        let mut merged: Vec<PurgeWindow> = Vec::with_capacity(windows.len());
+        for window in windows {
+            match merged.last_mut() {
+                Some(last) if last.purged_at == window.purged_at && window.start <= last.end.saturating_add(1) => {
+                    last.end = last.end.max(window.end);
+                }
+                _ => merged.push(window),
+            }
+        }
+
+        let earliest = self.records().map(|record| record.first_used).min();
+        self.purges = merged
+            .iter()
+            .enumerate()
+            .filter(|&(i, window)| {
+                let dominated = merged.iter().enumerate().any(|(j, other)| {
+                    j != i && other.start <= window.start && other.end >= window.end && other.purged_at >= window.purged_at
+                });
+                !dominated && earliest.is_none_or(|earliest| window.purged_at >= earliest)
+            })
+            .map(|(_, window)| *window)
+            .collect();
+    }
+
+    /// 候选是否已被删除（有删除标记且之后没有再使用）
+    pub fn is_suppressed(&self, text: &str, pinyin: &str) -> bool {
+        self.tombstone(text, pinyin).is_some() && self.get_record(text, pinyin).is_none()
+    }
+
+    /// 手动添加词条（如自定义编码的短语），已存在时不改变次数
+    pub fn add_word(&mut self, text: &str, pinyin: &str) {
+        self.add_word_at(text, pinyin, self.now());
+    }
+
+    /// 以指定时间手动添加词条
+    pub fn add_word_at(&mut self, text: &str, pinyin: &str, timestamp: u64) {
+        if self.get_record(text, pinyin).is_none() {
+            self.record_usage_at(text, pinyin, timestamp);
+        }
+    }
+
+    /// 将词条固定在某个编码的候选位置（从 0 开始），`position` 为 None 时取消固定
+    pub fn pin(&mut self, text: &str, pinyin: &str, position: Option<usize>) {
+        self.pin_at(text, pinyin, position, self.now());
+    }
+
+    /// 以指定时间固定或取消固定词条
+    pub fn pin_at(&mut self, text: &str, pinyin: &str, position: Option<usize>, timestamp: u64) {
+        self.add_pin(Pin {
+            text: text.to_string(),
+            pinyin: pinyin.to_string(),
+            position,
+            updated_at: timestamp,
+        });
+    }
+
+    /// 某个编码下固定的候选（文本, 位置），按位置排序
+    pub fn pins_for(&self, pinyin: &str) -> Vec<(&str, usize)> {
+        let mut pins: Vec<(&str, usize)> = self
+            .pins
+            .iter()
+            .filter(|pin| pin.pinyin == pinyin)
+            .filter_map(|pin| pin.position.map(|position| (pin.text.as_str(), position)))
+            .collect();
+        pins.sort_by_key(|(_, position)| *position);
+        pins
+    }
+
+    /// 所有固定记录（含已取消的）
+    pub fn pins(&self) -> &[Pin] {
+        &self.pins
+    }
+
+    /// 加入固定记录，同一词条以更新时间较晚的为准
+    fn add_pin(&mut self, pin: Pin) {
+        match self
+            .pins
+            .iter_mut()
+            .find(|p| p.text == pin.text && p.pinyin == pin.pinyin)
+        {
+            Some(existing) if existing.updated_at <= pin.updated_at => *existing = pin,
+            Some(_) => {}
+            None => self.pins.push(pin),
+        }
+    }
+
+    /// 词条的删除标记
+    pub fn tombstone(&self, text: &str, pinyin: &str) -> Option<&Tombstone> {
+        self.tombstones
+            .iter()
+            .find(|t| t.text == text && t.pinyin == pinyin)
+    }
+
+    /// 所有删除标记
+    pub fn tombstones(&self) -> &[Tombstone] {
+        &self.tombstones
+    }
+
+    /// 添加删除标记，已有时合并
+    pub(crate) fn add_tombstone(&mut self, tombstone: Tombstone) {
+        match self
+            .tombstones
+            .iter_mut()
+            .find(|t| t.text == tombstone.text && t.pinyin == tombstone.pinyin)
+        {
+            Some(existing) => existing.merge(&tombstone),
+            None => self.tombstones.push(tombstone),
+        }
+    }
+
+    /// 直接加入词条（用于由其他格式构造词库），加入后需调用 [`rebuild_index`](Self::rebuild_index)
+    pub(crate) fn push_record(&mut self, record: WordRecord) {
+        self.words.entry(record.pinyin.clone()).or_default().push(record);
+    }
+
+    /// 移除词条并维护索引
+    fn remove_record(&mut self, text: &str, pinyin: &str) -> Option<WordRecord> {
+        let index = *self.text_index.get(text)?.get(pinyin)?;
+        let records = self.words.get_mut(pinyin)?;
+        if index >= records.len() {
+            return None;
+        }
+
+        let removed = records.remove(index);
+        // 同一拼音下后面的词条索引前移
+        for record in &records[index..] {
+            if let Some(entry) = self
+                .text_index
+                .get_mut(&record.text)
+                .and_then(|readings| readings.get_mut(pinyin))
+            {
+                *entry -= 1;
+            }
+        }
+        if records.is_empty() {
+            self.words.remove(pinyin);
+        }
+        if let Some(readings) = self.text_index.get_mut(text) {
+            readings.remove(pinyin);
+            if readings.is_empty() {
+                self.text_index.remove(text);
+            }
+        }
+        self.total_records -= 1;
+        Some(removed)
+    }
+
+    /// 获取词条所有读音的使用次数之和
+    pub fn get_count(&self, text: &str) -> u32 {
+        self.readings(text).iter().map(|r| r.count).sum()
+    }
+
+    /// 获取词条某个读音的使用次数
+    pub fn get_reading_count(&self, text: &str, pinyin: &str) -> u32 {
+        self.get_record(text, pinyin).map(|r| r.count).unwrap_or(0)
     }
 
     /// 清理低频词条（保留最近使用或高频的词条）
@@ -185,12 +633,12 @@ impl UserDict {
         let mut all_records: Vec<(f64, String, usize)> = Vec::new();
         for (pinyin, records) in &self.words {
             for (index, record) in records.iter().enumerate() {
-                all_records.push((record.score(), pinyin.clone(), index));
+                all_records.push((self.score(record), pinyin.clone(), index));
             }
         }
 
         // 按分数排序
-        all_records.sort_by(|a, b| b.0.partial_cmp(&a.0).unwrap());
+        all_records.sort_by(|a, b| b.0.total_cmp(&a.0));
 
         // 保留前 max_entries 个
         let to_keep: std::collections::HashSet<(String, usize)> = all_records
@@ -201,36 +649,83 @@ impl UserDict {
 
         // 重建数据结构
         let mut new_words: HashMap<String, Vec<WordRecord>> = HashMap::new();
-        let mut new_index: HashMap<String, (String, usize)> = HashMap::new();
-        let mut new_total = 0;
-
         for (pinyin, records) in &self.words {
-            let mut new_records: Vec<WordRecord> = Vec::new();
-            for (old_index, record) in records.iter().enumerate() {
-                if to_keep.contains(&(pinyin.clone(), old_index)) {
-                    let new_index_val = new_records.len();
-                    new_index.insert(record.text.clone(), (pinyin.clone(), new_index_val));
-                    new_records.push(record.clone());
-                    new_total += 1;
-                }
-            }
+            let new_records: Vec<WordRecord> = records
+                .iter()
+                .enumerate()
+                .filter(|(old_index, _)| to_keep.contains(&(pinyin.clone(), *old_index)))
+                .map(|(_, record)| record.clone())
+                .collect();
             if !new_records.is_empty() {
                 new_words.insert(pinyin.clone(), new_records);
             }
         }
 
         self.words = new_words;
-        self.text_index = new_index;
-        self.total_records = new_total;
+        self.rebuild_index();
     }
 
-    /// 合并另一个用户词库
+    /// 合并另一台设备的用户词库
+    ///
+    /// 各设备的使用次数取最大值后求和，`last_used` 取最大值，`first_used` 取最小值，
+    /// 删除标记同样取最大值合并，因此重复合并同一个词库结果不变
     pub fn merge(&mut self, other: &UserDict) {
-        for (pinyin, records) in &other.words {
-            for record in records {
-                self.record_usage(&record.text, pinyin);
+        for tombstone in &other.tombstones {
+            self.add_tombstone(tombstone.clone());
+        }
+        for pin in &other.pins {
+            self.add_pin(pin.clone());
+        }
+        for window in &other.purges {
+            if !self.purges.contains(window) {
+                self.purges.push(*window);
+            }
+        }
+
+        for record in other.records() {
+            let mut incoming = record.clone();
+            if incoming.devices.is_empty() {
+                incoming.devices.insert(other.device_id.clone(), incoming.count);
+            }
+            match self.get_record_mut(&incoming.text, &incoming.pinyin) {
+                Some(local) => {
+                    merge_counts(&mut local.devices, &incoming.devices);
+                    local.last_used = local.last_used.max(incoming.last_used);
+                    local.first_used = local.first_used.min(incoming.first_used);
+                }
+                None => {
+                    let records = self.words.entry(incoming.pinyin.clone()).or_default();
+                    self.text_index
+                        .entry(incoming.text.clone())
+                        .or_default()
+                        .insert(incoming.pinyin.clone(), records.len());
+                    records.push(incoming);
+                    self.total_records += 1;
+                }
+            }
+        }
+
+        self.apply_tombstones();
+        self.apply_purges();
+        self.compact_purges();
+    }
+
+    /// 按删除标记重新计算各词条的次数，删除后没有新使用的词条被移除
+    fn apply_tombstones(&mut self) {
+        let tombstones = &self.tombstones;
+        for records in self.words.values_mut() {
+            for record in records.iter_mut() {
+                let tombstone = tombstones
+                    .iter()
+                    .find(|t| t.text == record.text && t.pinyin == record.pinyin);
+                if let Some(tombstone) = tombstone {
+                    merge_counts(&mut record.devices, &tombstone.counts);
+                }
+                record.count = effective_count(&record.devices, tombstone.map(|t| &t.counts));
             }
+            records.retain(|record| record.count > 0);
         }
+        self.rebuild_index();
     }
 
     /// 获取统计信息
@@ -241,6 +736,43 @@ impl UserDict {
             unique_words: self.text_index.len(),
         }
     }
+
+    /// 由词条重建索引
+    ///
+    /// 同一拼音下重复的（文本, 拼音）词条合并：次数相加，时间取最早与最近
+    pub(crate) fn rebuild_index(&mut self) {
+        self.text_index.clear();
+        self.total_records = 0;
+        for (pinyin, records) in self.words.iter_mut() {
+            let mut merged: Vec<WordRecord> = Vec::with_capacity(records.len());
+            for record in records.drain(..) {
+                match merged.iter_mut().find(|r| r.text == record.text) {
+                    Some(existing) => {
+                        existing.count += record.count;
+                        existing.last_used = existing.last_used.max(record.last_used);
+                        existing.first_used = existing.first_used.min(record.first_used);
+                        for (device, count) in record.devices {
+                            *existing.devices.entry(device).or_insert(0) += count;
+                        }
+                    }
+                    None => merged.push(record),
+                }
+            }
+            // 没有设备次数的旧记录，次数归属本机
+            for record in merged.iter_mut().filter(|r| r.devices.is_empty()) {
+                record.devices.insert(self.device_id.clone(), record.count);
+            }
+            for (index, record) in merged.iter().enumerate() {
+                self.text_index
+                    .entry(record.text.clone())
+                    .or_default()
+                    .insert(pinyin.clone(), index);
+            }
+            self.total_records += merged.len();
+            *records = merged;
+        }
+        self.words.retain(|_, records| !records.is_empty());
+    }
 }
 
 impl Default for UserDict {
@@ -257,8 +789,42 @@ pub struct UserDictStats {
     pub unique_words: usize,
 }
 
+/// 按设备取最大值合并使用次数
+fn merge_counts(target: &mut BTreeMap<String, u32>, other: &BTreeMap<String, u32>) {
+    for (device, count) in other {
+        let entry = target.entry(device.clone()).or_insert(0);
+        *entry = (*entry).max(*count);
+    }
+}
+
+/// 扣除删除前的次数后的有效使用次数
+fn effective_count(devices: &BTreeMap<String, u32>, deleted: Option<&BTreeMap<String, u32>>) -> u32 {
+    devices
+        .iter()
+        .map(|(device, count)| {
+            let base = deleted.and_then(|counts| counts.get(device)).copied().unwrap_or(0);
+            count.saturating_sub(base)
+        })
+        .sum()
+}
+
+/// 生成设备 ID（时间、进程号与计数器混合）
+pub(crate) fn generate_device_id() -> String {
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_nanos() as u64;
+    let salt = COUNTER.fetch_add(1, Ordering::Relaxed).wrapping_mul(0x9e37_79b9_7f4a_7c15);
+    format!("device-{:016x}", nanos ^ ((std::process::id() as u64) << 32) ^ salt)
+}
+
+fn legacy_version() -> u32 {
+    1
+}
+
 /// 获取当前 Unix 时间戳
-fn current_timestamp() -> u64 {
+pub fn current_timestamp() -> u64 {
     SystemTime::now()
         .duration_since(UNIX_EPOCH)
         .unwrap_or_default()
@@ -329,6 +895,190 @@ mod tests {
         assert_eq!(dict.total_records, 50);
     }
 
+    #[test]
+    fn test_rollback_usage() {
+        let mut dict = UserDict::new();
+        dict.record_usage("中国", "zhongguo");
+        dict.record_usage("中国", "zhongguo");
+
+        let previous = dict.get_record("中国", "zhongguo").cloned();
+        dict.record_usage("中国", "zhongguo");
+        dict.rollback_usage("中国", "zhongguo", previous);
+        assert_eq!(dict.get_count("中国"), 2);
+
+        // 新建的词条撤销后删除，同拼音下其他词条仍可查到
+        dict.record_usage("种过", "zhongguo");
+        dict.record_usage("中过", "zhongguo");
+        dict.rollback_usage("种过", "zhongguo", None);
+        assert_eq!(dict.get_count("种过"), 0);
+        assert_eq!(dict.get_count("中过"), 1);
+        assert_eq!(dict.lookup("zhongguo").len(), 2);
+        assert_eq!(dict.stats().total_records, 2);
+    }
+
+    #[test]
+    fn test_purge_time_window() {
+        let mut dict = UserDict::new();
+        dict.record_usage_at("早", "zao", 100);
+        dict.record_usage_at("中", "zhong", 200);
+        dict.record_usage_at("晚", "wan", 300);
+        dict.record_usage_at("早", "zao", 400);
+        dict.pin_at("中", "zhong", Some(0), 250);
+
+        // 「早」的使用期间（100~400）与时间段重叠，同样被清除
+        assert_eq!(dict.purge_at(150, 250, 500), 2);
+        assert_eq!(dict.get_count("早"), 0);
+        assert_eq!(dict.get_count("中"), 0);
+        assert_eq!(dict.get_count("晚"), 1);
+        assert!(dict.pins_for("zhong").is_empty());
+        // 不留下删除标记，系统词库的同名候选不受影响
+        assert!(dict.tombstones().is_empty());
+        assert!(!dict.is_suppressed("中", "zhong"));
+
+        // 其他设备上同一时间段的使用不会经同步恢复，清除之后的新使用保留
+        let mut other = UserDict::new();
+        other.record_usage_at("中", "zhong", 200);
+        other.record_usage_at("晚", "wan", 600);
+        dict.merge(&other);
+        assert_eq!(dict.get_count("中"), 0);
+        assert_eq!(dict.get_count("晚"), 2);
+    }
+
+    #[test]
+    fn test_purge_windows_compacted() {
+        let mut dict = UserDict::new();
+        dict.record_usage_at("早", "zao", 100);
+        dict.record_usage_at("晚", "wan", 1000);
+
+        // 同一时刻清除的相邻时间段合并
+        dict.purge_at(200, 300, 2000);
+        dict.purge_at(301, 400, 2000);
+        assert_eq!(dict.purges, vec![PurgeWindow { start: 200, end: 400, purged_at: 2000 }]);
+
+        // 之后清除更大的时间段，被包含的旧时间段丢弃
+        dict.purge_at(150, 500, 3000);
+        assert_eq!(dict.purges, vec![PurgeWindow { start: 150, end: 500, purged_at: 3000 }]);
+
+        // 清除时间不同且互不包含的时间段都保留
+        dict.purge_at(450, 600, 4000);
+        assert_eq!(dict.purges.len(), 2);
+
+        // 重复清除不会使列表增长
+        for _ in 0..10 {
+            dict.purge_at(450, 600, 4000);
+        }
+        assert_eq!(dict.purges.len(), 2);
+
+        // 清空后重新输入，早于所有剩余词条的清除不再保留
+        dict.purge_at(0, 5000, 5000);
+        assert_eq!(dict.stats().total_records, 0);
+        dict.record_usage_at("新", "xin", 7000);
+        dict.purge_at(6500, 6600, 8000);
+        assert_eq!(dict.get_count("新"), 1);
+        assert_eq!(dict.purges, vec![PurgeWindow { start: 6500, end: 6600, purged_at: 8000 }]);
+    }
+
+    #[test]
+    fn test_scoring_policy_and_clock() {
+        use crate::scoring::{CountScoring, HalfLifeScoring, ManualClock};
+        const DAY: u64 = 86400;
+
+        let clock = Arc::new(ManualClock::new(DAY));
+        let mut dict = UserDict::new();
+        dict.set_clock(clock.clone());
+        for _ in 0..3 {
+            dict.record_usage("术语", "shuyu");
+        }
+        clock.set(61 * DAY);
+        dict.record_usage("数域", "shuyu");
+        assert_eq!(dict.get_record("数域", "shuyu").unwrap().last_used, 61 * DAY);
+
+        let top = |dict: &UserDict| dict.lookup("shuyu")[0].0.text.clone();
+        // 默认策略：两个月没用的词被最近用过的词超过
+        assert_eq!(top(&dict), "数域");
+        dict.set_scoring_policy(Arc::new(HalfLifeScoring::new(365.0)));
+        assert_eq!(top(&dict), "术语");
+        dict.set_scoring_policy(Arc::new(CountScoring));
+        assert_eq!(top(&dict), "术语");
+    }
+
+    #[test]
+    fn test_nan_scores_do_not_panic() {
+        use crate::scoring::ScoringPolicy;
+
+        #[derive(Debug)]
+        struct NanScoring;
+
+        impl ScoringPolicy for NanScoring {
+            fn name(&self) -> &str {
+                "nan"
+            }
+
+            fn score(&self, _record: &WordRecord, _now: u64) -> f64 {
+                f64::NAN
+            }
+        }
+
+        let mut dict = UserDict::new();
+        dict.record_usage("术语", "shuyu");
+        dict.record_usage("数域", "shuyu");
+        dict.set_scoring_policy(Arc::new(NanScoring));
+        assert_eq!(dict.lookup("shuyu").len(), 2);
+        dict.cleanup(1);
+        assert_eq!(dict.stats().total_records, 1);
+    }
+
+    #[test]
+    fn test_polyphone_readings() {
+        let mut dict = UserDict::new();
+        dict.record_usage("行", "xing");
+        dict.record_usage("行", "hang");
+        dict.record_usage("行", "hang");
+
+        assert_eq!(dict.get_reading_count("行", "xing"), 1);
+        assert_eq!(dict.get_reading_count("行", "hang"), 2);
+        assert_eq!(dict.get_count("行"), 3);
+        assert_eq!(dict.lookup("hang")[0].0.text, "行");
+
+        let stats = dict.stats();
+        assert_eq!(stats.total_records, 2);
+        assert_eq!(stats.unique_words, 1);
+
+        dict.rollback_usage("行", "xing", None);
+        assert_eq!(dict.get_reading_count("行", "xing"), 0);
+        assert_eq!(dict.get_reading_count("行", "hang"), 2);
+    }
+
+    #[test]
+    fn test_migrate_legacy_file() {
+        // 版本 1 的文件：没有 version 字段，text_index 每个文本只有一个读音
+        let legacy = r#"{
+            "words": {
+                "xing": [{"text": "行", "pinyin": "xing", "count": 3, "last_used": 100, "first_used": 10}],
+                "hang": [
+                    {"text": "行", "pinyin": "hang", "count": 1, "last_used": 50, "first_used": 50},
+                    {"text": "行", "pinyin": "hang", "count": 2, "last_used": 80, "first_used": 20}
+                ]
+            },
+            "text_index": {"行": ["xing", 0]},
+            "total_records": 2,
+            "last_saved": 0
+        }"#;
+        let temp_file = NamedTempFile::new().unwrap();
+        std::fs::write(temp_file.path(), legacy).unwrap();
+
+        let mut dict = UserDict::load_from_file(temp_file.path()).unwrap();
+        assert_eq!(dict.version, USER_DICT_VERSION);
+        assert_eq!(dict.get_reading_count("行", "xing"), 3);
+        let hang = dict.get_record("行", "hang").unwrap();
+        assert_eq!((hang.count, hang.first_used, hang.last_used), (3, 20, 80));
+        assert_eq!(dict.stats().total_records, 2);
+
+        dict.record_usage("行", "hang");
+        assert_eq!(dict.get_reading_count("行", "hang"), 4);
+        assert_eq!(dict.get_reading_count("行", "xing"), 3);
+    }
+
     #[test]
     fn test_merge() {
         let mut dict1 = UserDict::new();
@@ -343,4 +1093,79 @@ mod tests {
         assert_eq!(dict1.get_count("中国"), 2); // 1 + 1
         assert_eq!(dict1.get_count("中文"), 1);
     }
+
+    #[test]
+    fn test_merge_idempotent() {
+        let mut a = UserDict::new();
+        a.record_usage_at("中国", "zhongguo", 100);
+        a.record_usage_at("中国", "zhongguo", 200);
+        let mut b = UserDict::new();
+        for t in [10, 20, 30] {
+            b.record_usage_at("中国", "zhongguo", t);
+        }
+
+        a.merge(&b);
+        a.merge(&b);
+        let record = a.get_record("中国", "zhongguo").unwrap();
+        assert_eq!((record.count, record.first_used, record.last_used), (5, 10, 200));
+
+        // 合并回对方后两边一致
+        b.merge(&a);
+        assert_eq!(b.get_count("中国"), 5);
+    }
+
+    #[test]
+    fn test_merge_tombstones() {
+        let mut a = UserDict::new();
+        a.record_usage_at("错词", "cuoci", 100);
+        let mut b = UserDict::new();
+        b.merge(&a);
+        assert_eq!(b.get_count("错词"), 1);
+
+        // a 删除后，b 同步时旧记录也被删除
+        assert!(a.delete_at("错词", "cuoci", 200));
+        assert!(!a.delete("错词", "cuoci"));
+        b.merge(&a);
+        assert_eq!(b.get_count("错词"), 0);
+        a.merge(&b);
+        assert_eq!(a.get_count("错词"), 0);
+
+        // 删除后 b 的新使用在两边都保留，且只计新使用的次数
+        b.record_usage_at("错词", "cuoci", 300);
+        a.merge(&b);
+        assert_eq!(a.get_count("错词"), 1);
+        b.merge(&a);
+        assert_eq!(b.get_count("错词"), 1);
+        assert_eq!(b.tombstones().len(), 1);
+    }
+
+    #[test]
+    fn test_delete_suppresses_and_relearns() {
+        let mut dict = UserDict::new();
+        assert!(!dict.delete("种", "zhong"));
+        assert!(dict.is_suppressed("种", "zhong"));
+
+        dict.record_usage("种", "zhong");
+        assert!(!dict.is_suppressed("种", "zhong"));
+        assert_eq!(dict.get_count("种"), 1);
+    }
+
+    #[test]
+    fn test_pin_and_add_word() {
+        let mut dict = UserDict::new();
+        dict.add_word("灵码", "lm");
+        dict.add_word("灵码", "lm");
+        assert_eq!(dict.get_reading_count("灵码", "lm"), 1);
+
+        dict.pin_at("重", "zhong", Some(0), 10);
+        dict.pin_at("众", "zhong", Some(2), 10);
+        assert_eq!(dict.pins_for("zhong"), vec![("重", 0), ("众", 2)]);
+
+        // 同步时较晚的取消固定生效，较早的固定被忽略
+        let mut other = UserDict::new();
+        other.pin_at("重", "zhong", None, 20);
+        other.pin_at("众", "zhong", Some(1), 5);
+        dict.merge(&other);
+        assert_eq!(dict.pins_for("zhong"), vec![("众", 2)]);
+    }
 }
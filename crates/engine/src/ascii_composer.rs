@@ -0,0 +1,141 @@
+//! 中西文切换
+//!
+//! 对应 Rime 方案中的 `ascii_composer`：单独按下并释放 Shift、或按下 Caps Lock
+//! 时切换 `ascii_mode` 开关，并按 `switch_key` 配置处理未上屏的输入
+
+use lingcode_core::keys::{keysym, keysym_from_name};
+use lingcode_core::schema_parser::{AsciiComposer as AsciiComposerConfig, SwitchStyle};
+use lingcode_core::types::KeyEvent;
+use std::collections::HashMap;
+
+/// 开关：西文模式
+pub const ASCII_MODE: &str = "ascii_mode";
+
+/// 中西文切换处理器
+#[derive(Debug, Clone)]
+pub struct AsciiComposer {
+    /// 切换键 keysym -> 切换方式
+    switch_keys: HashMap<u32, SwitchStyle>,
+    /// 西文模式下保留 Caps Lock 的大写行为
+    good_old_caps_lock: bool,
+    /// 已按下、尚未释放且期间没有其他按键的切换键
+    pending_key: Option<u32>,
+    /// 当前西文模式是否由 Caps Lock 开启
+    caps_lock_on: bool,
+}
+
+impl AsciiComposer {
+    /// 使用默认配置创建（与 Rime 默认预设一致）
+    pub fn new() -> Self {
+        let switch_keys = [
+            (keysym::SHIFT_L, SwitchStyle::InlineAscii),
+            (keysym::SHIFT_R, SwitchStyle::CommitText),
+            (keysym::CONTROL_L, SwitchStyle::Noop),
+            (keysym::CONTROL_R, SwitchStyle::Noop),
+            (keysym::CAPS_LOCK, SwitchStyle::Clear),
+        ]
+        .into_iter()
+        .collect();
+        Self {
+            switch_keys,
+            good_old_caps_lock: false,
+            pending_key: None,
+            caps_lock_on: false,
+        }
+    }
+
+    /// 从方案配置创建，未配置的切换键沿用默认值
+    pub fn from_config(config: &AsciiComposerConfig) -> Self {
+        let mut composer = Self::new();
+        composer.good_old_caps_lock = config.good_old_caps_lock;
+        for (name, style) in &config.switch_key {
+            match keysym_from_name(name) {
+                Some((code, _)) => {
+                    composer.switch_keys.insert(code, *style);
+                }
+                None => log::warn!("未知的切换键: {}", name),
+            }
+        }
+        composer
+    }
+
+    /// 处理按键，返回需要执行的切换方式
+    ///
+    /// Shift/Control 在单独按下并释放时触发，Caps Lock 在按下时触发
+    pub fn process(&mut self, key: &KeyEvent) -> Option<SwitchStyle> {
+        let style = self.switch_keys.get(&key.keycode).copied();
+
+        if key.keycode == keysym::CAPS_LOCK {
+            if key.is_release() {
+                return None;
+            }
+            self.pending_key = None;
+            self.caps_lock_on = !self.caps_lock_on;
+            return style;
+        }
+
+        if key.is_release() {
+            let lone = self.pending_key.take() == Some(key.keycode);
+            return if lone { style } else { None };
+        }
+
+        self.pending_key = style.map(|_| key.keycode);
+        None
+    }
+
+    /// 是否为切换键（按下和释放都由切换处理器消费）
+    pub fn is_switch_key(&self, key: &KeyEvent) -> bool {
+        self.switch_keys.contains_key(&key.keycode)
+    }
+
+    /// 西文模式下的字母输出：Caps Lock 开启且不保留大写行为时转为小写
+    pub fn ascii_letter(&self, key: &KeyEvent) -> Option<char> {
+        if self.caps_lock_on && !self.good_old_caps_lock && key.key.is_ascii_uppercase() && !key.modifiers.shift {
+            Some(key.key.to_ascii_lowercase())
+        } else {
+            None
+        }
+    }
+}
+
+impl Default for AsciiComposer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn release(keycode: u32) -> KeyEvent {
+        let mut key = KeyEvent::from_keysym(keycode);
+        key.modifiers.release = true;
+        key
+    }
+
+    #[test]
+    fn test_lone_shift() {
+        let mut composer = AsciiComposer::new();
+        assert_eq!(composer.process(&KeyEvent::from_keysym(keysym::SHIFT_L)), None);
+        assert_eq!(composer.process(&release(keysym::SHIFT_L)), Some(SwitchStyle::InlineAscii));
+    }
+
+    #[test]
+    fn test_shift_chord_does_not_switch() {
+        let mut composer = AsciiComposer::new();
+        composer.process(&KeyEvent::from_keysym(keysym::SHIFT_R));
+        composer.process(&KeyEvent::from_char('A'));
+        assert_eq!(composer.process(&release(keysym::SHIFT_R)), None);
+    }
+
+    #[test]
+    fn test_config_overrides() {
+        let mut config = AsciiComposerConfig::default();
+        config.switch_key.insert("Shift_L".to_string(), SwitchStyle::CommitCode);
+        let mut composer = AsciiComposer::from_config(&config);
+        composer.process(&KeyEvent::from_keysym(keysym::SHIFT_L));
+        assert_eq!(composer.process(&release(keysym::SHIFT_L)), Some(SwitchStyle::CommitCode));
+        assert_eq!(composer.process(&KeyEvent::from_keysym(keysym::CAPS_LOCK)), Some(SwitchStyle::Clear));
+    }
+}
@@ -0,0 +1,122 @@
+//! 上屏历史
+//!
+//! 记录最近几次上屏的输入与候选词，用于撤销上屏并重新转换
+
+use lingcode_core::candidate::Candidate;
+use lingcode_dict::WordRecord;
+use std::collections::VecDeque;
+
+/// 默认保留的上屏记录数
+pub const DEFAULT_HISTORY_SIZE: usize = 10;
+
+/// 一次上屏记录
+#[derive(Debug, Clone)]
+pub struct CommitRecord {
+    /// 上屏时的原始输入
+    pub input: String,
+    /// 上屏的候选词
+    pub candidate: Candidate,
+    /// 消耗的输入长度（字节）
+    pub consumed: usize,
+    /// 是否记录了词频
+    pub learned: bool,
+    /// 记录词频前的用户词条状态，None 表示词条为新建
+    pub previous_record: Option<WordRecord>,
+}
+
+impl CommitRecord {
+    /// 撤销时前端需要删除的字符数
+    pub fn committed_len(&self) -> usize {
+        self.candidate.text.chars().count()
+    }
+}
+
+/// 上屏历史，超出容量时丢弃最早的记录
+#[derive(Debug, Clone)]
+pub struct CommitHistory {
+    records: VecDeque<CommitRecord>,
+    capacity: usize,
+}
+
+impl CommitHistory {
+    /// 创建指定容量的上屏历史
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            records: VecDeque::with_capacity(capacity),
+            capacity: capacity.max(1),
+        }
+    }
+
+    /// 添加记录
+    pub fn push(&mut self, record: CommitRecord) {
+        if self.records.len() == self.capacity {
+            self.records.pop_front();
+        }
+        self.records.push_back(record);
+    }
+
+    /// 取出最近一次记录
+    pub fn pop(&mut self) -> Option<CommitRecord> {
+        self.records.pop_back()
+    }
+
+    /// 最近一次记录
+    pub fn last(&self) -> Option<&CommitRecord> {
+        self.records.back()
+    }
+
+    /// 从旧到新遍历记录
+    pub fn iter(&self) -> impl Iterator<Item = &CommitRecord> {
+        self.records.iter()
+    }
+
+    /// 记录数
+    pub fn len(&self) -> usize {
+        self.records.len()
+    }
+
+    /// 是否没有记录
+    pub fn is_empty(&self) -> bool {
+        self.records.is_empty()
+    }
+
+    /// 清空历史
+    pub fn clear(&mut self) {
+        self.records.clear();
+    }
+}
+
+impl Default for CommitHistory {
+    fn default() -> Self {
+        Self::new(DEFAULT_HISTORY_SIZE)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(input: &str, text: &str) -> CommitRecord {
+        CommitRecord {
+            input: input.to_string(),
+            candidate: Candidate::new(text.to_string()),
+            consumed: input.len(),
+            learned: false,
+            previous_record: None,
+        }
+    }
+
+    #[test]
+    fn test_history_capacity() {
+        let mut history = CommitHistory::new(2);
+        history.push(record("ni", "你"));
+        history.push(record("hao", "好"));
+        history.push(record("zhongguo", "中国"));
+        assert_eq!(history.len(), 2);
+        assert_eq!(history.iter().next().unwrap().input, "hao");
+
+        let last = history.pop().unwrap();
+        assert_eq!(last.committed_len(), 2);
+        assert_eq!(history.last().unwrap().input, "hao");
+    }
+}
@@ -0,0 +1,173 @@
+//! 按键绑定
+//!
+//! 对应 Rime 方案中的 `key_binder/bindings`，在常规按键处理之前执行，
+//! 可以把一个按键映射为另一个按键（或按键序列），或切换开关
+
+use lingcode_core::schema_parser::{BindingAction, BindingCondition, CommitStyle, KeyBinder as KeyBinderConfig};
+use lingcode_core::types::KeyEvent;
+
+/// 绑定触发后执行的动作
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum KeyAction {
+    /// 依次发送按键
+    Send(Vec<KeyEvent>),
+    /// 切换开关
+    Toggle(String),
+    /// 打开开关
+    SetOption(String),
+    /// 关闭开关
+    UnsetOption(String),
+    /// 按指定形式上屏当前输入
+    Commit(CommitStyle),
+    /// 撤销上一次上屏
+    Undo,
+}
+
+/// 已解析的按键绑定
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyBinding {
+    /// 生效条件
+    pub when: BindingCondition,
+    /// 触发按键
+    pub accept: KeyEvent,
+    /// 执行的动作
+    pub action: KeyAction,
+}
+
+/// 按键绑定表
+#[derive(Debug, Clone, Default)]
+pub struct KeyBinder {
+    bindings: Vec<KeyBinding>,
+}
+
+impl KeyBinder {
+    /// 创建空的绑定表
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 从方案配置构建绑定表，无法解析的绑定会被跳过
+    pub fn from_config(config: &KeyBinderConfig) -> Self {
+        let mut binder = Self::new();
+        for binding in &config.bindings {
+            let accept = match KeyEvent::parse(&binding.accept) {
+                Some(key) => key,
+                None => {
+                    log::warn!("无法解析按键绑定: {}", binding.accept);
+                    continue;
+                }
+            };
+            let action = match &binding.action {
+                BindingAction::Send(spec) => KeyEvent::parse(spec).map(|key| KeyAction::Send(vec![key])),
+                BindingAction::SendSequence(spec) => parse_key_sequence(spec).map(KeyAction::Send),
+                BindingAction::Toggle(name) => Some(KeyAction::Toggle(name.clone())),
+                BindingAction::SetOption(name) => Some(KeyAction::SetOption(name.clone())),
+                BindingAction::UnsetOption(name) => Some(KeyAction::UnsetOption(name.clone())),
+                BindingAction::Commit(style) => Some(KeyAction::Commit(*style)),
+                BindingAction::Undo => Some(KeyAction::Undo),
+            };
+            match action {
+                Some(action) => binder.add(KeyBinding { when: binding.when, accept, action }),
+                None => log::warn!("无法解析按键绑定动作: {:?}", binding.action),
+            }
+        }
+        binder
+    }
+
+    /// 添加绑定
+    pub fn add(&mut self, binding: KeyBinding) {
+        self.bindings.push(binding);
+    }
+
+    /// 查找当前条件下与按键匹配的第一个绑定动作
+    pub fn lookup(&self, key: &KeyEvent, active: &[BindingCondition]) -> Option<&KeyAction> {
+        self.bindings
+            .iter()
+            .find(|b| b.accept.same_key(key) && active.contains(&b.when))
+            .map(|b| &b.action)
+    }
+
+    /// 所有绑定
+    pub fn bindings(&self) -> &[KeyBinding] {
+        &self.bindings
+    }
+
+    /// 是否没有任何绑定
+    pub fn is_empty(&self) -> bool {
+        self.bindings.is_empty()
+    }
+}
+
+/// 解析按键序列：`{Name}` 表示命名按键，其余字符按原样发送
+fn parse_key_sequence(spec: &str) -> Option<Vec<KeyEvent>> {
+    let mut keys = Vec::new();
+    let mut chars = spec.chars();
+    while let Some(c) = chars.next() {
+        if c == '{' {
+            let name: String = chars.by_ref().take_while(|&c| c != '}').collect();
+            keys.push(KeyEvent::parse(&name)?);
+        } else {
+            keys.push(KeyEvent::from_char(c));
+        }
+    }
+    if keys.is_empty() {
+        None
+    } else {
+        Some(keys)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lingcode_core::keys::keysym;
+    use lingcode_core::schema_parser::KeyBinding as KeyBindingConfig;
+
+    fn config(bindings: Vec<(BindingCondition, &str, BindingAction)>) -> KeyBinderConfig {
+        KeyBinderConfig {
+            import_preset: None,
+            bindings: bindings
+                .into_iter()
+                .map(|(when, accept, action)| KeyBindingConfig {
+                    when,
+                    accept: accept.to_string(),
+                    action,
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn test_lookup_respects_condition() {
+        let binder = KeyBinder::from_config(&config(vec![(
+            BindingCondition::HasMenu,
+            "Tab",
+            BindingAction::Send("Page_Down".to_string()),
+        )]));
+
+        let tab = KeyEvent::parse("Tab").unwrap();
+        assert!(binder.lookup(&tab, &[BindingCondition::Always]).is_none());
+
+        let action = binder
+            .lookup(&tab, &[BindingCondition::Always, BindingCondition::HasMenu])
+            .unwrap();
+        assert_eq!(action, &KeyAction::Send(vec![KeyEvent::from_keysym(keysym::PAGE_DOWN)]));
+    }
+
+    #[test]
+    fn test_invalid_bindings_skipped() {
+        let binder = KeyBinder::from_config(&config(vec![
+            (BindingCondition::Always, "NoSuchKey", BindingAction::Toggle("x".to_string())),
+            (BindingCondition::Always, "Control+n", BindingAction::Send("Bogus".to_string())),
+        ]));
+        assert!(binder.is_empty());
+    }
+
+    #[test]
+    fn test_parse_key_sequence() {
+        let keys = parse_key_sequence("{Left}ab").unwrap();
+        assert_eq!(keys.len(), 3);
+        assert_eq!(keys[0].keycode, keysym::LEFT);
+        assert_eq!(keys[2].key, 'b');
+    }
+}
@@ -4,10 +4,37 @@
 
 use lingcode_core::types::{InputState, KeyEvent, SchemeType};
 use lingcode_core::candidate::Candidate;
-use lingcode_pinyin::{PinyinEngine, SimplifiedPinyinEngine};
+use lingcode_core::keys::keysym;
+use lingcode_core::schema_parser::{BindingCondition, CommitStyle, Schema, SwitchStyle};
+use lingcode_dict::DictStack;
+use lingcode_pinyin::tones::spaced_pinyin;
+use lingcode_pinyin::{PinyinEngine, RankingPolicy, ScoreBreakdown, SharedDict, SimplifiedPinyinEngine};
+use std::sync::Arc;
 
+pub mod ascii_composer;
 pub mod candidate;
+pub mod commit_history;
 pub mod input_state;
+pub mod key_binder;
+pub mod predictor;
+pub mod privacy;
+pub mod punctuator;
+pub mod reload;
+pub mod session;
+pub mod switches;
+
+pub use ascii_composer::AsciiComposer;
+pub use commit_history::{CommitHistory, CommitRecord};
+pub use key_binder::{KeyAction, KeyBinder, KeyBinding};
+pub use predictor::Predictor;
+pub use privacy::PrivacyGuard;
+pub use punctuator::{PunctResult, Punctuator};
+pub use reload::{ReloadEvent, ReloadHandle, ReloadService, ReloadUpdate};
+pub use session::{SessionId, SessionManager};
+pub use switches::Switches;
+
+/// 默认每页候选词数量
+pub const DEFAULT_PAGE_SIZE: usize = 5;
 
 /// 引擎输出类型
 #[derive(Debug, Clone)]
@@ -20,8 +47,16 @@ pub enum EngineOutput {
     Candidates(Vec<Candidate>),
     /// 清空输入
     Clear,
+    /// 撤销上屏：前端删除光标前 `delete` 个字符，并重新显示候选词
+    Revert {
+        delete: usize,
+        candidates: Vec<Candidate>,
+    },
 }
 
+/// 简繁转换函数
+pub type TextConverter = Box<dyn Fn(&str) -> String>;
+
 /// 输入法引擎
 pub struct Engine {
     /// 当前状态
@@ -32,8 +67,45 @@ pub struct Engine {
     candidates: Vec<Candidate>,
     /// 选中的候选词索引
     selected_index: usize,
+    /// 每页候选词数量
+    page_size: usize,
     /// 拼音引擎
     pinyin_engine: SimplifiedPinyinEngine,
+    /// 开关状态
+    switches: Switches,
+    /// 按键绑定
+    key_binder: KeyBinder,
+    /// 标点转换器
+    punctuator: Punctuator,
+    /// 正在选择的标点按键（标点有多个候选时）
+    punct_key: Option<char>,
+    /// 中西文切换处理器
+    ascii_composer: AsciiComposer,
+    /// 是否处于内嵌西文输入（按原样上屏，回车结束）
+    inline_ascii: bool,
+    /// 简体转繁体，未设置时繁体上屏输出原文
+    traditional_converter: Option<TextConverter>,
+    /// 转换器使用的 OpenCC 配置目录，热重载时按此目录重新创建转换器
+    #[cfg(feature = "opencc")]
+    opencc_dir: Option<std::path::PathBuf>,
+    /// 上屏历史
+    commit_history: CommitHistory,
+    /// 上一个按键是否刚上屏（此时 Backspace 撤销上屏）
+    just_committed: bool,
+    /// 已记录的上屏次数，用于识别没有记入上屏历史的上屏
+    commits_recorded: u64,
+    /// 联想候选生成器
+    predictor: Predictor,
+    /// 是否已取得词库的联想词组索引（首次联想时开始在后台构建）
+    phrases_loaded: bool,
+    /// 是否记录候选词的得分明细（调试用）
+    explain_mode: bool,
+    /// 候选词的得分明细，与拼音候选词一一对应
+    explanations: Vec<ScoreBreakdown>,
+    /// 学习隐私控制（无痕模式与屏蔽规则）
+    privacy: PrivacyGuard,
+    /// 资源热重载句柄
+    reload: Option<ReloadHandle>,
 }
 
 impl Engine {
@@ -49,10 +121,203 @@ impl Engine {
             input_buffer: String::new(),
             candidates: Vec::new(),
             selected_index: 0,
+            page_size: DEFAULT_PAGE_SIZE,
             pinyin_engine,
+            switches: Switches::new(),
+            key_binder: KeyBinder::new(),
+            punctuator: Punctuator::new(),
+            punct_key: None,
+            ascii_composer: AsciiComposer::new(),
+            inline_ascii: false,
+            traditional_converter: None,
+            #[cfg(feature = "opencc")]
+            opencc_dir: None,
+            commit_history: CommitHistory::default(),
+            just_committed: false,
+            commits_recorded: 0,
+            predictor: Predictor::new(),
+            phrases_loaded: false,
+            explain_mode: false,
+            explanations: Vec::new(),
+            privacy: PrivacyGuard::new(),
+            reload: None,
+        }
+    }
+
+    /// 创建与其他引擎共享词库的引擎实例（见 [`SharedDict`]）
+    pub fn with_shared_dict(dict: Arc<SharedDict>) -> Self {
+        Self::with_pinyin_engine(SimplifiedPinyinEngine::with_shared_dict(dict))
+    }
+
+    /// 使用 OpenCC 作为繁体上屏的转换器
+    #[cfg(feature = "opencc")]
+    pub fn with_opencc(mut self) -> Self {
+        let converter = converters::ChineseConverter::new();
+        self.set_traditional_converter(Box::new(move |text| converter.s2t(text)));
+        self
+    }
+
+    /// 使用指定目录中的 OpenCC 配置作为繁体上屏的转换器，目录中的文件变化时随热重载重新创建
+    #[cfg(feature = "opencc")]
+    pub fn with_opencc_dir(mut self, dir: &std::path::Path) -> Self {
+        self.load_opencc_dir(dir);
+        self
+    }
+
+    #[cfg(feature = "opencc")]
+    fn load_opencc_dir(&mut self, dir: &std::path::Path) {
+        let converter = converters::ChineseConverter::from_dir(dir);
+        self.set_traditional_converter(Box::new(move |text| converter.s2t(text)));
+        self.opencc_dir = Some(dir.to_path_buf());
+    }
+
+    /// 设置繁体上屏使用的简繁转换函数
+    pub fn set_traditional_converter(&mut self, converter: TextConverter) {
+        self.traditional_converter = Some(converter);
+        #[cfg(feature = "opencc")]
+        {
+            self.opencc_dir = None;
         }
     }
 
+    /// 应用输入方案（开关、按键绑定等）
+    pub fn with_schema(mut self, schema: &Schema) -> Self {
+        self.apply_schema(schema);
+        self
+    }
+
+    /// 应用输入方案（开关、按键绑定等），方案的词库组合与当前词库不同时重新加载词库
+    pub fn apply_schema(&mut self, schema: &Schema) {
+        self.pinyin_engine.set_dict_stack(
+            schema
+                .translator
+                .as_ref()
+                .map(DictStack::from_config)
+                .unwrap_or_default(),
+        );
+        self.apply_schema_options(schema);
+    }
+
+    /// 应用方案中除词库组合以外的设置
+    fn apply_schema_options(&mut self, schema: &Schema) {
+        self.switches = Switches::from_schema(&schema.switches);
+        self.key_binder = schema
+            .key_binder
+            .as_ref()
+            .map(KeyBinder::from_config)
+            .unwrap_or_default();
+        self.punctuator = schema
+            .punctuator
+            .as_ref()
+            .map(Punctuator::from_config)
+            .unwrap_or_default();
+        self.ascii_composer = schema
+            .ascii_composer
+            .as_ref()
+            .map(AsciiComposer::from_config)
+            .unwrap_or_default();
+        self.pinyin_engine.set_ranking_policy(
+            schema
+                .ranking
+                .as_ref()
+                .map(RankingPolicy::from_config)
+                .unwrap_or_default(),
+        );
+        // 无痕模式由前端按输入场景设置，不随方案重置
+        let incognito = self.privacy.is_incognito();
+        self.privacy = schema
+            .privacy
+            .as_ref()
+            .map(PrivacyGuard::from_config)
+            .unwrap_or_default();
+        self.privacy.set_incognito(incognito);
+    }
+
+    /// 接收热重载服务发布的方案与词库（见 [`ReloadService`]）
+    pub fn with_reload_handle(mut self, handle: ReloadHandle) -> Self {
+        self.set_reload_handle(handle);
+        self
+    }
+
+    /// 接收热重载服务发布的方案与词库，新版本在空闲时的下一次按键前应用
+    pub fn set_reload_handle(&mut self, handle: ReloadHandle) {
+        self.reload = Some(handle);
+    }
+
+    /// 应用热重载服务发布的新版本，返回是否有更新
+    ///
+    /// 输入中不应用，避免候选词在编辑过程中变化。词库由重载服务按方案的词库组合在后台构建，
+    /// 应用方案时不在本线程重新加载词库（词库构建失败时继续使用原有词库）。
+    /// 词库更新后联想表在下次联想时按新词库重建
+    pub fn apply_reload(&mut self) -> bool {
+        if !self.is_idle() {
+            return false;
+        }
+        let Some(update) = self.reload.as_mut().and_then(ReloadHandle::take_update) else {
+            return false;
+        };
+        if let Some(dict) = update.dicts {
+            self.set_shared_dict(dict);
+        }
+        if let Some(schema) = update.schema {
+            self.apply_schema_options(&schema);
+        }
+        #[cfg(feature = "opencc")]
+        if update.opencc {
+            if let Some(dir) = self.opencc_dir.clone() {
+                self.load_opencc_dir(&dir);
+            }
+        }
+        true
+    }
+
+    /// 当前使用的共享词库
+    pub fn shared_dict(&self) -> &Arc<SharedDict> {
+        self.pinyin_engine.shared_dict()
+    }
+
+    /// 替换共享词库，用户词与输入历史保留，联想表在下次联想时按新词库重建
+    pub fn set_shared_dict(&mut self, dict: Arc<SharedDict>) {
+        self.pinyin_engine.set_shared_dict(dict);
+        self.predictor.clear_phrases();
+        self.phrases_loaded = false;
+    }
+
+    /// 设置每页候选词数量
+    pub fn set_page_size(&mut self, page_size: usize) {
+        self.page_size = page_size.max(1);
+    }
+
+    /// 获取每页候选词数量
+    pub fn page_size(&self) -> usize {
+        self.page_size
+    }
+
+    /// 获取开关状态
+    pub fn switches(&self) -> &Switches {
+        &self.switches
+    }
+
+    /// 获取开关值
+    pub fn get_option(&self, name: &str) -> bool {
+        self.switches.get(name)
+    }
+
+    /// 设置开关值
+    pub fn set_option(&mut self, name: &str, value: bool) {
+        self.switches.set(name, value);
+    }
+
+    /// 获取按键绑定表
+    pub fn key_binder(&self) -> &KeyBinder {
+        &self.key_binder
+    }
+
+    /// 替换按键绑定表
+    pub fn set_key_binder(&mut self, key_binder: KeyBinder) {
+        self.key_binder = key_binder;
+    }
+
     /// 获取当前状态
     pub fn state(&self) -> InputState {
         self.state
@@ -73,23 +338,475 @@ impl Engine {
         self.selected_index
     }
 
+    /// 是否处于西文模式
+    pub fn is_ascii_mode(&self) -> bool {
+        self.switches.get(ascii_composer::ASCII_MODE)
+    }
+
+    /// 是否正在输入内嵌西文
+    pub fn is_inline_ascii(&self) -> bool {
+        self.inline_ascii
+    }
+
+    /// 获取上屏历史
+    pub fn commit_history(&self) -> &CommitHistory {
+        &self.commit_history
+    }
+
+    /// 开启或关闭得分明细（调试用），开启后重新计算当前候选词
+    pub fn set_explain_mode(&mut self, enabled: bool) {
+        self.explain_mode = enabled;
+        if self.state == InputState::Composing && !self.inline_ascii {
+            self.update_candidates();
+        }
+    }
+
+    /// 是否记录得分明细
+    pub fn is_explain_mode(&self) -> bool {
+        self.explain_mode
+    }
+
+    /// 当前候选词的得分明细，与 `candidates()` 一一对应
+    ///
+    /// 仅在开启得分明细且候选词来自拼音转换时非空（标点、联想等候选没有明细）
+    pub fn candidate_explanations(&self) -> &[ScoreBreakdown] {
+        &self.explanations
+    }
+
+    /// 学习隐私控制
+    pub fn privacy(&self) -> &PrivacyGuard {
+        &self.privacy
+    }
+
+    /// 设置学习隐私控制（保留当前的无痕模式）
+    pub fn set_privacy(&mut self, mut privacy: PrivacyGuard) {
+        privacy.set_incognito(self.privacy.is_incognito());
+        self.privacy = privacy;
+    }
+
+    /// 设置无痕模式，前端在密码框等安全输入场景开启
+    ///
+    /// 无痕模式下不记录词频、联想与上屏历史；开启时清空已有的上屏历史，
+    /// 避免之前的上屏被当作前文或被撤销
+    pub fn set_incognito(&mut self, incognito: bool) {
+        if incognito {
+            self.commit_history.clear();
+            self.just_committed = false;
+        }
+        self.privacy.set_incognito(incognito);
+    }
+
+    /// 是否处于无痕模式
+    pub fn is_incognito(&self) -> bool {
+        self.privacy.is_incognito()
+    }
+
+    /// 清除 `[start, end]` 时间段（Unix 时间戳）内的学习记录，返回清除的用户词条数
+    ///
+    /// 用户词库中该时间段内使用过的词条会被删除并立即写入磁盘；
+    /// 上屏历史与联想二元组没有时间信息，全部清除
+    pub fn purge_history(&mut self, start: u64, end: u64) -> anyhow::Result<usize> {
+        let purged = self.pinyin_engine.purge_user_history(start, end)?;
+        self.commit_history.clear();
+        self.predictor.clear_learned();
+        self.just_committed = false;
+        Ok(purged)
+    }
+
+    /// 是否开启联想
+    pub fn is_prediction_enabled(&self) -> bool {
+        self.switches.get(predictor::PREDICTION)
+    }
+
+    /// 开启或关闭联想（等同于设置 `prediction` 开关）
+    pub fn set_prediction_enabled(&mut self, enabled: bool) {
+        self.switches.set(predictor::PREDICTION, enabled);
+    }
+
+    /// 获取联想候选生成器
+    pub fn predictor(&self) -> &Predictor {
+        &self.predictor
+    }
+
+    /// 替换联想候选生成器（如预先构建好的联想表）
+    pub fn set_predictor(&mut self, predictor: Predictor) {
+        self.predictor = predictor;
+        self.phrases_loaded = true;
+    }
+
     /// 处理按键事件，返回引擎输出
+    ///
+    /// 前端需要同时传入按键释放事件（`modifiers.release`），用于识别单独按下的 Shift。
+    /// 开启联想时，上屏后状态变为 `Predicting`，`candidates()` 为联想候选。
+    /// 处理按键前先并入后台加载完成的词库与热重载发布的新版本
     pub fn process_key(&mut self, key: KeyEvent) -> EngineOutput {
+        self.apply_reload();
+        if self.pinyin_engine.poll_dict_loading() {
+            self.predictor.clear_phrases();
+            self.phrases_loaded = false;
+        }
+        // 中西文切换键最先处理
+        if let Some(style) = self.ascii_composer.process(&key) {
+            return self.switch_ascii_mode(style);
+        }
+        if key.is_release() || self.ascii_composer.is_switch_key(&key) {
+            return EngineOutput::None;
+        }
+        let just_committed = std::mem::take(&mut self.just_committed);
+        let recorded = self.commits_recorded;
+
+        // 按键绑定优先于常规处理
+        if let Some(action) = self.key_binder.lookup(&key, &self.binding_conditions()).cloned() {
+            let output = self.perform_key_action(action);
+            return self.check_unrecorded_commit(recorded, output);
+        }
+
+        // 刚上屏后按 Backspace：撤销上屏
+        if just_committed && self.is_idle() && matches!(key.key, '\u{8}' | '\u{7f}') {
+            return self.undo_commit();
+        }
+
+        // 西文模式：按键直接交给前端
+        let output = if self.is_ascii_mode() && self.state == InputState::Idle {
+            match self.ascii_composer.ascii_letter(&key) {
+                Some(c) => EngineOutput::Commit(c.to_string()),
+                None => EngineOutput::None,
+            }
+        } else {
+            self.dispatch_key(key)
+        };
+        self.check_unrecorded_commit(recorded, output)
+    }
+
+    /// 上屏了没有记入上屏历史的文字（标点、内嵌西文等）时清空上屏历史
+    ///
+    /// 此后撤销会删除错误的文字，之前的上屏也不再与光标前的文字相邻，不再作为前文
+    fn check_unrecorded_commit(&mut self, recorded: u64, output: EngineOutput) -> EngineOutput {
+        if matches!(output, EngineOutput::Commit(_)) && self.commits_recorded == recorded {
+            self.commit_history.clear();
+            self.just_committed = false;
+        }
+        output
+    }
+
+    /// 切换中西文模式，并按切换方式处理未上屏的输入
+    fn switch_ascii_mode(&mut self, style: SwitchStyle) -> EngineOutput {
+        if self.state == InputState::Predicting {
+            self.reset();
+        }
+        let composing = self.state != InputState::Idle;
+        let output = match style {
+            SwitchStyle::Noop => return EngineOutput::None,
+            // 编辑中切换为内嵌西文，不改变西文模式
+            SwitchStyle::InlineAscii if composing => {
+                if !self.inline_ascii && self.punct_key.is_none() {
+                    self.inline_ascii = true;
+                    self.update_inline_candidates();
+                }
+                return EngineOutput::Candidates(self.candidates.clone());
+            }
+            SwitchStyle::CommitCode if composing => {
+                let text = self.input_buffer.clone();
+                self.reset();
+                EngineOutput::Commit(text)
+            }
+            SwitchStyle::CommitText if composing => {
+                let text = self.highlighted_text();
+                self.reset();
+                EngineOutput::Commit(text)
+            }
+            SwitchStyle::Clear if composing => {
+                self.reset();
+                EngineOutput::Clear
+            }
+            _ => EngineOutput::None,
+        };
+        self.switches.toggle(ascii_composer::ASCII_MODE);
+        output
+    }
+
+    /// 当前高亮的候选词文字，无候选时为原始输入
+    fn highlighted_text(&self) -> String {
+        match self.candidates.get(self.selected_index) {
+            Some(candidate) => candidate.text.clone(),
+            None => self.input_buffer.clone(),
+        }
+    }
+
+    /// 执行按键绑定动作
+    ///
+    /// 发送的按键不再经过按键绑定，避免循环映射；
+    /// 发送多个按键时，序列中所有上屏的文字合并为一次上屏，没有上屏时返回最后一个有效输出
+    fn perform_key_action(&mut self, action: KeyAction) -> EngineOutput {
+        match action {
+            KeyAction::Send(keys) => {
+                let mut committed: Option<String> = None;
+                let mut output = EngineOutput::None;
+                for key in keys {
+                    let recorded = self.commits_recorded;
+                    let key_output = self.dispatch_key(key);
+                    match self.check_unrecorded_commit(recorded, key_output) {
+                        EngineOutput::None => {}
+                        EngineOutput::Commit(text) => committed.get_or_insert_with(String::new).push_str(&text),
+                        other => output = other,
+                    }
+                }
+                match committed {
+                    Some(text) => EngineOutput::Commit(text),
+                    None => output,
+                }
+            }
+            KeyAction::Toggle(name) => {
+                self.switches.toggle(&name);
+                EngineOutput::None
+            }
+            KeyAction::SetOption(name) => {
+                self.switches.set(&name, true);
+                EngineOutput::None
+            }
+            KeyAction::UnsetOption(name) => {
+                self.switches.set(&name, false);
+                EngineOutput::None
+            }
+            KeyAction::Commit(style) => self.commit_as(style),
+            KeyAction::Undo => self.undo_commit(),
+        }
+    }
+
+    /// 撤销上一次上屏：回滚词频记录，恢复原始输入并重新转换
+    ///
+    /// 仅在空闲状态下有效，前端需按 `EngineOutput::Revert` 删除已上屏的文字。
+    /// 联想候选的上屏没有原始输入，撤销后回到空闲状态
+    pub fn undo_commit(&mut self) -> EngineOutput {
+        if !self.is_idle() {
+            return EngineOutput::None;
+        }
+        let record = match self.commit_history.pop() {
+            Some(record) => record,
+            None => return EngineOutput::None,
+        };
+        let predicted = record.input.is_empty();
+        if record.learned {
+            if !predicted {
+                self.pinyin_engine
+                    .rollback_usage(&record.candidate.text, &record.input, record.previous_record.clone());
+            }
+            if let Some(previous) = self.commit_history.last() {
+                self.predictor.forget(&previous.candidate.text, &record.candidate.text);
+                if !predicted {
+                    self.pinyin_engine
+                        .rollback_bigram(&previous.candidate.text, &record.candidate.text);
+                }
+            }
+        }
+
+        self.reset();
+        if !predicted {
+            self.input_buffer = record.input.clone();
+            self.state = InputState::Composing;
+            self.update_candidates();
+        }
+        EngineOutput::Revert {
+            delete: record.committed_len(),
+            candidates: self.candidates.clone(),
+        }
+    }
+
+    /// 记录一次拼音输入的上屏，`learn` 为是否记录词频
+    fn record_commit(&mut self, candidate: Candidate, learn: bool) {
+        if !self.privacy.allows(&candidate.text, &self.input_buffer) {
+            // 不学习也不保留历史：之前的上屏不再作为前文，也无法撤销
+            self.commit_history.clear();
+            return;
+        }
+        let previous_record = self
+            .pinyin_engine
+            .user_dict()
+            .and_then(|dict| dict.get_record(&candidate.text, &self.input_buffer).cloned());
+        let learned = learn && self.pinyin_engine.user_dict().is_some();
+        if learned {
+            self.pinyin_engine.record_usage(&candidate.text, &self.input_buffer);
+            if let Some(previous) = self.commit_history.last() {
+                self.predictor.learn(&previous.candidate.text, &candidate.text);
+                self.pinyin_engine.record_bigram(&previous.candidate.text, &candidate.text);
+            }
+        }
+        self.push_commit(CommitRecord {
+            input: self.input_buffer.clone(),
+            candidate,
+            consumed: self.input_buffer.len(),
+            learned,
+            previous_record,
+        });
+    }
+
+    /// 记录一次联想候选的上屏，以上一次上屏为前文记录联想
+    fn record_prediction(&mut self, text: &str) {
+        if !self.privacy.allows(text, "") {
+            self.commit_history.clear();
+            return;
+        }
+        if let Some(previous) = self.commit_history.last() {
+            let previous = previous.candidate.text.clone();
+            self.predictor.learn(&previous, text);
+        }
+        self.push_commit(CommitRecord {
+            input: String::new(),
+            candidate: Candidate::new(text.to_string()).with_source("prediction".to_string()),
+            consumed: 0,
+            learned: true,
+            previous_record: None,
+        });
+    }
+
+    fn push_commit(&mut self, record: CommitRecord) {
+        self.commit_history.push(record);
+        self.commits_recorded += 1;
+        self.just_committed = true;
+    }
+
+    /// 按指定形式上屏当前输入，没有输入时不做处理
+    ///
+    /// 原始输入与拼音形式基于输入缓冲区，繁体基于当前高亮的候选词
+    pub fn commit_as(&mut self, style: CommitStyle) -> EngineOutput {
+        if self.is_idle() {
+            return EngineOutput::None;
+        }
+        let text = if self.inline_ascii || self.punct_key.is_some() {
+            // 内嵌西文与标点没有拼音，各形式均按当前文字上屏
+            self.highlighted_text()
+        } else {
+            match style {
+                CommitStyle::RawInput => self.input_buffer.clone(),
+                CommitStyle::SpacedPinyin => spaced_pinyin(&self.input_buffer),
+                CommitStyle::ToneMarks => {
+                    let text = self
+                        .candidates
+                        .get(self.selected_index)
+                        .map(|candidate| candidate.text.as_str())
+                        .unwrap_or_default();
+                    self.pinyin_engine.tone_marked_pinyin(text, &self.input_buffer)
+                }
+                CommitStyle::Traditional => {
+                    let text = self.highlighted_text();
+                    match &self.traditional_converter {
+                        Some(convert) => convert(&text),
+                        None => {
+                            log::warn!("未设置简繁转换（需启用 opencc 特性或调用 set_traditional_converter），按简体上屏");
+                            text
+                        }
+                    }
+                }
+            }
+        };
+        if !self.inline_ascii && self.punct_key.is_none() {
+            self.record_commit(Candidate::new(text.clone()), false);
+        }
+        self.reset();
+        EngineOutput::Commit(text)
+    }
+
+    /// 当前满足的按键绑定条件
+    fn binding_conditions(&self) -> Vec<BindingCondition> {
+        let mut conditions = vec![BindingCondition::Always];
+        if self.state != InputState::Idle {
+            if self.state != InputState::Predicting {
+                conditions.push(BindingCondition::Composing);
+            }
+            if !self.candidates.is_empty() {
+                conditions.push(BindingCondition::HasMenu);
+            }
+            if self.selected_index >= self.page_size {
+                conditions.push(BindingCondition::Paging);
+            }
+        }
+        conditions
+    }
+
+    /// 是否没有正在编辑的输入（空闲或仅显示联想候选）
+    fn is_idle(&self) -> bool {
+        matches!(self.state, InputState::Idle | InputState::Predicting)
+    }
+
+    /// 按当前状态分发按键
+    fn dispatch_key(&mut self, key: KeyEvent) -> EngineOutput {
+        // Shift+Delete：删除高亮的候选词
+        if key.keycode == keysym::DELETE && key.modifiers.shift && self.has_pinyin_candidates() {
+            self.delete_candidate(self.selected_index);
+            return EngineOutput::Candidates(self.candidates.clone());
+        }
         match self.state {
             InputState::Idle => self.handle_idle(key),
             InputState::Composing => self.handle_composing(key),
             InputState::Selecting => self.handle_selecting(key),
+            InputState::Predicting => self.handle_predicting(key),
+        }
+    }
+
+    /// 处理联想状态下的按键：数字键选择联想候选，其他按键关闭联想后按空闲状态处理
+    fn handle_predicting(&mut self, key: KeyEvent) -> EngineOutput {
+        if let Some(output) = self.handle_navigation(&key) {
+            return output;
+        }
+        let modified = key.modifiers.ctrl || key.modifiers.alt || key.modifiers.meta;
+        match key.key {
+            '1'..='9' if !modified => {
+                self.selected_index = self.index_on_page(key.key);
+                let text = self.candidates[self.selected_index].text.clone();
+                self.record_prediction(&text);
+                self.reset();
+                self.start_prediction(&text);
+                EngineOutput::Commit(text)
+            }
+            '\u{1b}' => {
+                self.reset();
+                EngineOutput::Clear
+            }
+            _ => {
+                self.reset();
+                self.handle_idle(key)
+            }
         }
     }
 
+    /// 根据刚上屏的文字显示联想候选，未开启联想或没有联想时保持空闲
+    ///
+    /// 词组联想索引在后台构建，构建完成前只有用户二元组联想
+    fn start_prediction(&mut self, context: &str) {
+        if !self.is_prediction_enabled() {
+            return;
+        }
+        if !self.phrases_loaded {
+            if let Some(index) = SharedDict::phrase_index(self.shared_dict()) {
+                self.predictor.set_phrases(index);
+                self.phrases_loaded = true;
+            }
+        }
+        let predictions = self.predictor.predict(context);
+        if predictions.is_empty() {
+            return;
+        }
+        self.explanations.clear();
+        self.candidates = predictions
+            .into_iter()
+            .map(|text| Candidate::new(text).with_source("prediction".to_string()))
+            .collect();
+        self.selected_index = 0;
+        self.state = InputState::Predicting;
+    }
+
     /// 处理空闲状态下的按键
     fn handle_idle(&mut self, key: KeyEvent) -> EngineOutput {
-        // 只响应字母键（开始输入）
-        if key.key.is_ascii_alphabetic() && key.modifiers.is_empty() {
+        // 字母键开始输入，大写字母开始内嵌西文，标点键转换为中文标点
+        if is_inline_ascii_trigger(&key) {
+            self.start_inline_ascii(String::new(), key.key)
+        } else if key.key.is_ascii_alphabetic() && key.modifiers.is_empty() {
             self.input_buffer.push(key.key);
             self.state = InputState::Composing;
             self.update_candidates();
             EngineOutput::Candidates(self.candidates.clone())
+        } else if self.is_punct_key(&key) {
+            self.handle_punct(String::new(), key.key)
         } else {
             EngineOutput::None
         }
@@ -97,7 +814,18 @@ impl Engine {
 
     /// 处理编辑状态下的按键
     fn handle_composing(&mut self, key: KeyEvent) -> EngineOutput {
+        if self.inline_ascii {
+            return self.handle_inline_ascii(key);
+        }
+        if let Some(output) = self.handle_navigation(&key) {
+            return output;
+        }
         match key.key {
+            // 大写字母：上屏当前候选并开始内嵌西文
+            c if is_inline_ascii_trigger(&key) => {
+                let prefix = self.highlighted_text();
+                self.start_inline_ascii(prefix, c)
+            }
             // 字母：继续输入
             c if c.is_ascii_alphabetic() => {
                 if key.modifiers.is_empty() {
@@ -113,10 +841,9 @@ impl Engine {
                 if !self.candidates.is_empty() {
                     self.state = InputState::Selecting;
                     if key.key == ' ' {
-                        self.selected_index = 0; // 空格选择第一个
+                        // 空格选择当前高亮的候选词（未翻页时即第一个）
                     } else {
-                        self.selected_index = (key.key as usize - '1' as usize)
-                            .min(self.candidates.len() - 1);
+                        self.selected_index = self.index_on_page(key.key);
                     }
                     // 立即提交选中的候选词
                     self.commit_selected()
@@ -141,6 +868,14 @@ impl Engine {
                 self.reset();
                 EngineOutput::Clear
             }
+            // 回车：按原样上屏输入的字母
+            '\r' => self.commit_as(CommitStyle::RawInput),
+            // 标点：先上屏当前候选（无候选时上屏原始输入），再输出标点
+            // 撇号在编辑中是音节分隔符，不作为标点处理
+            c if c != '\'' && self.is_punct_key(&key) => {
+                let prefix = self.highlighted_text();
+                self.handle_punct(prefix, c)
+            }
             // 其他键：忽略
             _ => EngineOutput::None,
         }
@@ -148,12 +883,28 @@ impl Engine {
 
     /// 处理选择状态下的按键
     fn handle_selecting(&mut self, key: KeyEvent) -> EngineOutput {
+        if let Some(output) = self.handle_navigation(&key) {
+            return output;
+        }
+        if let Some(punct) = self.punct_key {
+            match key.key {
+                // 重复按同一标点键：循环切换候选
+                c if c == punct => {
+                    self.selected_index = (self.selected_index + 1) % self.candidates.len();
+                    return EngineOutput::Candidates(self.candidates.clone());
+                }
+                // Esc / Backspace：放弃标点
+                '\u{1b}' | '\u{8}' | '\u{7f}' => {
+                    self.reset();
+                    return EngineOutput::Clear;
+                }
+                _ => {}
+            }
+        }
         match key.key {
             // 数字 1-9：选择候选词
             '1'..='9' => {
-                let index = (key.key as usize - '1' as usize)
-                    .min(self.candidates.len() - 1);
-                self.selected_index = index;
+                self.selected_index = self.index_on_page(key.key);
                 self.commit_selected()
             }
             // 空格：选择当前高亮的候选词
@@ -178,6 +929,24 @@ impl Engine {
                 self.state = InputState::Composing;
                 EngineOutput::Candidates(self.candidates.clone())
             }
+            // 标点：提交当前选择并输出标点
+            c if self.is_punct_key(&key) => {
+                let prefix = self
+                    .candidates
+                    .get(self.selected_index)
+                    .map(|candidate| candidate.text.clone())
+                    .unwrap_or_default();
+                self.handle_punct(prefix, c)
+            }
+            // 大写字母：提交当前选择并开始内嵌西文
+            c if is_inline_ascii_trigger(&key) => {
+                let prefix = self
+                    .candidates
+                    .get(self.selected_index)
+                    .map(|candidate| candidate.text.clone())
+                    .unwrap_or_default();
+                self.start_inline_ascii(prefix, c)
+            }
             // 其他键：提交当前选择并处理新按键
             c => {
                 let output = self.commit_selected();
@@ -192,11 +961,142 @@ impl Engine {
         }
     }
 
+    /// 处理翻页与上下移动高亮，非导航键返回 None
+    fn handle_navigation(&mut self, key: &KeyEvent) -> Option<EngineOutput> {
+        if !key.is_function_key() || self.candidates.is_empty() {
+            return None;
+        }
+        let page_start = self.selected_index / self.page_size * self.page_size;
+        match key.keycode {
+            keysym::PAGE_DOWN => {
+                if page_start + self.page_size < self.candidates.len() {
+                    self.selected_index = page_start + self.page_size;
+                }
+            }
+            keysym::PAGE_UP => {
+                self.selected_index = page_start.saturating_sub(self.page_size);
+            }
+            keysym::DOWN => {
+                if self.selected_index + 1 < self.candidates.len() {
+                    self.selected_index += 1;
+                }
+            }
+            keysym::UP => {
+                self.selected_index = self.selected_index.saturating_sub(1);
+            }
+            _ => return None,
+        }
+        Some(EngineOutput::Candidates(self.candidates.clone()))
+    }
+
+    /// 数字键在当前页中对应的候选词索引
+    fn index_on_page(&self, digit: char) -> usize {
+        let page_start = self.selected_index / self.page_size * self.page_size;
+        (page_start + (digit as usize - '1' as usize)).min(self.candidates.len() - 1)
+    }
+
+    /// 开始内嵌西文输入，`prefix` 为需要先上屏的文字
+    fn start_inline_ascii(&mut self, prefix: String, first: char) -> EngineOutput {
+        self.reset();
+        self.inline_ascii = true;
+        self.state = InputState::Composing;
+        self.input_buffer.push(first);
+        self.update_inline_candidates();
+        if prefix.is_empty() {
+            EngineOutput::Candidates(self.candidates.clone())
+        } else {
+            EngineOutput::Commit(prefix)
+        }
+    }
+
+    /// 处理内嵌西文输入：可见字符与空格按原样追加，回车上屏
+    fn handle_inline_ascii(&mut self, key: KeyEvent) -> EngineOutput {
+        match key.key {
+            '\r' => {
+                let text = self.input_buffer.clone();
+                self.reset();
+                EngineOutput::Commit(text)
+            }
+            '\u{1b}' => {
+                self.reset();
+                EngineOutput::Clear
+            }
+            '\u{8}' | '\u{7f}' => {
+                self.input_buffer.pop();
+                if self.input_buffer.is_empty() {
+                    self.reset();
+                    EngineOutput::Clear
+                } else {
+                    self.update_inline_candidates();
+                    EngineOutput::Candidates(self.candidates.clone())
+                }
+            }
+            c if (c.is_ascii_graphic() || c == ' ')
+                && !key.modifiers.ctrl
+                && !key.modifiers.alt
+                && !key.modifiers.meta =>
+            {
+                self.input_buffer.push(c);
+                self.update_inline_candidates();
+                EngineOutput::Candidates(self.candidates.clone())
+            }
+            _ => EngineOutput::None,
+        }
+    }
+
+    /// 内嵌西文的候选即原始输入
+    fn update_inline_candidates(&mut self) {
+        self.candidates = vec![Candidate::new(self.input_buffer.clone()).with_source("ascii".to_string())];
+        self.explanations.clear();
+        self.selected_index = 0;
+    }
+
+    /// 是否为标点按键（不含 Ctrl/Alt/Meta 组合）
+    fn is_punct_key(&self, key: &KeyEvent) -> bool {
+        !key.modifiers.ctrl
+            && !key.modifiers.alt
+            && !key.modifiers.meta
+            && self.punctuator.is_punct(key.key)
+    }
+
+    /// 处理标点按键，`prefix` 为需要先上屏的文字
+    ///
+    /// 标点有多个候选时进入选择状态，否则与 `prefix` 一起上屏
+    fn handle_punct(&mut self, prefix: String, key: char) -> EngineOutput {
+        self.reset();
+        match self.punctuator.translate(key, &self.switches) {
+            Some(PunctResult::Commit(text)) => EngineOutput::Commit(prefix + &text),
+            Some(PunctResult::Candidates(texts)) => {
+                self.input_buffer.push(key);
+                self.candidates = texts
+                    .into_iter()
+                    .map(|text| Candidate::new(text).with_source("punct".to_string()))
+                    .collect();
+                self.punct_key = Some(key);
+                self.state = InputState::Selecting;
+                if prefix.is_empty() {
+                    EngineOutput::Candidates(self.candidates.clone())
+                } else {
+                    EngineOutput::Commit(prefix)
+                }
+            }
+            None if prefix.is_empty() => EngineOutput::None,
+            None => EngineOutput::Commit(prefix),
+        }
+    }
+
     /// 提交选中的候选词
     fn commit_selected(&mut self) -> EngineOutput {
-        if let Some(candidate) = self.candidates.get(self.selected_index) {
+        if let Some(candidate) = self.candidates.get(self.selected_index).cloned() {
             let text = candidate.text.clone();
+            let from_pinyin = !self.inline_ascii && self.punct_key.is_none();
+            if from_pinyin {
+                self.record_commit(candidate, true);
+            }
             self.reset();
+            if from_pinyin {
+                self.start_prediction(&text);
+            }
             EngineOutput::Commit(text)
         } else {
             EngineOutput::None
@@ -205,8 +1105,21 @@ impl Engine {
 
     /// 更新候选词列表
     fn update_candidates(&mut self) {
-        // 使用真实的拼音引擎获取候选词
-        match self.pinyin_engine.get_candidates(&self.input_buffer) {
+        // 使用真实的拼音引擎获取候选词，以上一次上屏的文字作为前文
+        let context = self.commit_history.last().map(|record| record.candidate.text.as_str());
+        if self.explain_mode {
+            let (candidates, explanations) = self
+                .pinyin_engine
+                .explain_candidates(&self.input_buffer, context)
+                .into_iter()
+                .unzip();
+            self.candidates = candidates;
+            self.explanations = explanations;
+            self.selected_index = 0;
+            return;
+        }
+        self.explanations.clear();
+        match self.pinyin_engine.get_candidates_with_context(&self.input_buffer, context) {
             Ok(candidates_list) => {
                 self.candidates = candidates_list.iter().cloned().collect();
             }
@@ -217,6 +1130,47 @@ impl Engine {
         self.selected_index = 0;
     }
 
+    /// 当前候选是否来自拼音查询（而非标点或内嵌西文）
+    fn has_pinyin_candidates(&self) -> bool {
+        matches!(self.state, InputState::Composing | InputState::Selecting)
+            && !self.inline_ascii
+            && self.punct_key.is_none()
+            && !self.candidates.is_empty()
+    }
+
+    /// 从用户词库删除当前输入下的第 `index` 个候选，系统词库的候选会被屏蔽
+    ///
+    /// 删除后刷新候选列表，高亮位置保持不变。没有对应候选时返回 false
+    pub fn delete_candidate(&mut self, index: usize) -> bool {
+        if !self.has_pinyin_candidates() || index >= self.candidates.len() {
+            return false;
+        }
+        let text = self.candidates[index].text.clone();
+        self.pinyin_engine.delete_word(&text, &self.input_buffer);
+        self.update_candidates();
+        self.selected_index = index.min(self.candidates.len().saturating_sub(1));
+        true
+    }
+
+    /// 将当前输入下的第 `index` 个候选固定到 `position`（从 0 开始），`position` 为 None 时取消固定
+    pub fn pin_candidate(&mut self, index: usize, position: Option<usize>) -> bool {
+        if !self.has_pinyin_candidates() || index >= self.candidates.len() {
+            return false;
+        }
+        let text = self.candidates[index].text.clone();
+        self.pinyin_engine.pin_word(&text, &self.input_buffer, position);
+        self.update_candidates();
+        true
+    }
+
+    /// 手动添加用户词，`code` 为输入该词使用的编码
+    pub fn add_user_word(&mut self, text: &str, code: &str) {
+        self.pinyin_engine.add_word(text, code);
+        if self.has_pinyin_candidates() && self.input_buffer == code {
+            self.update_candidates();
+        }
+    }
+
     /// 检查是否是有效的拼音输入
     pub fn is_valid_input(&self, input: &str) -> bool {
         self.pinyin_engine.is_valid_pinyin(input)
@@ -227,10 +1181,18 @@ impl Engine {
         self.state = InputState::Idle;
         self.input_buffer.clear();
         self.candidates.clear();
+        self.explanations.clear();
         self.selected_index = 0;
+        self.punct_key = None;
+        self.inline_ascii = false;
     }
 }
 
+/// Shift+字母（或大写字母）开始内嵌西文
+fn is_inline_ascii_trigger(key: &KeyEvent) -> bool {
+    key.key.is_ascii_uppercase() && !key.modifiers.ctrl && !key.modifiers.alt && !key.modifiers.meta
+}
+
 impl Default for Engine {
     fn default() -> Self {
         Self::new()
@@ -308,4 +1270,456 @@ mod tests {
         assert!(matches!(output, EngineOutput::Commit(_)));
         assert_eq!(engine.state(), InputState::Idle);
     }
+
+    fn key(c: char) -> KeyEvent {
+        KeyEvent::from_char(c)
+    }
+
+    fn type_str(engine: &mut Engine, input: &str) {
+        for c in input.chars() {
+            engine.process_key(key(c));
+        }
+    }
+
+    const BINDER_SCHEMA: &str = r#"
+schema:
+  schema_id: test
+  name: Test
+switches:
+  - name: ascii_punct
+    reset: 0
+key_binder:
+  bindings:
+    - {when: has_menu, accept: Tab, send: Page_Down}
+    - {when: paging, accept: minus, send: Page_Up}
+    - {when: always, accept: Control+period, toggle: ascii_punct}
+"#;
+
+    #[test]
+    fn test_key_binder_send() {
+        let schema = Schema::from_yaml(BINDER_SCHEMA).unwrap();
+        let mut engine = Engine::new().with_schema(&schema);
+        engine.set_page_size(2);
+
+        // 空闲状态下没有候选菜单，Tab 不触发绑定
+        engine.process_key(KeyEvent::parse("Tab").unwrap());
+        assert_eq!(engine.state(), InputState::Idle);
+
+        type_str(&mut engine, "zhong");
+        engine.process_key(KeyEvent::parse("Tab").unwrap());
+        assert_eq!(engine.selected_index(), 2);
+
+        // 翻页后 '-' 向前翻页，数字键在当前页内选择
+        engine.process_key(key('-'));
+        assert_eq!(engine.selected_index(), 0);
+        engine.process_key(KeyEvent::parse("Tab").unwrap());
+        let output = engine.process_key(key('2'));
+        let expected = engine_candidate_text("zhong", 3);
+        assert!(matches!(output, EngineOutput::Commit(text) if text == expected));
+    }
+
+    #[test]
+    fn test_key_binder_send_sequence_keeps_commits() {
+        let schema = Schema::from_yaml(
+            r#"
+schema:
+  schema_id: test
+  name: Test
+key_binder:
+  bindings:
+    - {when: always, accept: Control+h, send_sequence: "nihao ma"}
+"#,
+        )
+        .unwrap();
+        let mut engine = Engine::new().with_schema(&schema);
+
+        // 序列中途的上屏不被之后的候选输出覆盖
+        let output = engine.process_key(KeyEvent::parse("Control+h").unwrap());
+        assert!(matches!(output, EngineOutput::Commit(text) if text == "你好"));
+        assert_eq!(engine.state(), InputState::Composing);
+        assert_eq!(engine.input_buffer(), "ma");
+    }
+
+    #[test]
+    fn test_key_binder_toggle() {
+        let schema = Schema::from_yaml(BINDER_SCHEMA).unwrap();
+        let mut engine = Engine::new().with_schema(&schema);
+        assert!(!engine.get_option("ascii_punct"));
+
+        engine.process_key(KeyEvent::parse("Control+period").unwrap());
+        assert!(engine.get_option("ascii_punct"));
+        engine.process_key(KeyEvent::parse("Control+period").unwrap());
+        assert!(!engine.get_option("ascii_punct"));
+    }
+
+    #[test]
+    fn test_punct_in_idle() {
+        let mut engine = Engine::new();
+        let output = engine.process_key(key(','));
+        assert!(matches!(output, EngineOutput::Commit(text) if text == "，"));
+        assert_eq!(engine.state(), InputState::Idle);
+
+        engine.set_option(punctuator::ASCII_PUNCT, true);
+        let output = engine.process_key(key('.'));
+        assert!(matches!(output, EngineOutput::Commit(text) if text == "."));
+    }
+
+    #[test]
+    fn test_punct_alternatives() {
+        let mut engine = Engine::new();
+        let output = engine.process_key(key('/'));
+        assert!(matches!(output, EngineOutput::Candidates(ref c) if c.len() == 3));
+        assert_eq!(engine.state(), InputState::Selecting);
+
+        // 再按一次切换到下一个候选，数字键选择
+        engine.process_key(key('/'));
+        assert_eq!(engine.selected_index(), 1);
+        let output = engine.process_key(key('1'));
+        assert!(matches!(output, EngineOutput::Commit(text) if text == "、"));
+        assert_eq!(engine.state(), InputState::Idle);
+    }
+
+    #[test]
+    fn test_punct_commits_composition() {
+        let mut engine = Engine::new();
+        type_str(&mut engine, "nihao");
+        let output = engine.process_key(key(','));
+        assert!(matches!(output, EngineOutput::Commit(text) if text == "你好，"));
+        assert_eq!(engine.state(), InputState::Idle);
+    }
+
+    fn release(keycode: u32) -> KeyEvent {
+        KeyEvent::new(keycode, '\0', KeyModifiers::new().with_release())
+    }
+
+    fn shift_tap(engine: &mut Engine, keycode: u32) -> EngineOutput {
+        engine.process_key(KeyEvent::from_keysym(keycode));
+        engine.process_key(release(keycode))
+    }
+
+    #[test]
+    fn test_shift_toggles_ascii_mode() {
+        let mut engine = Engine::new();
+        shift_tap(&mut engine, keysym::SHIFT_L);
+        assert!(engine.is_ascii_mode());
+
+        // 西文模式下按键交给前端
+        let output = engine.process_key(key('a'));
+        assert!(matches!(output, EngineOutput::None));
+        assert_eq!(engine.state(), InputState::Idle);
+
+        shift_tap(&mut engine, keysym::SHIFT_L);
+        assert!(!engine.is_ascii_mode());
+    }
+
+    #[test]
+    fn test_shift_switch_styles() {
+        let mut engine = Engine::new();
+        type_str(&mut engine, "nihao");
+        let output = shift_tap(&mut engine, keysym::SHIFT_R);
+        assert!(matches!(output, EngineOutput::Commit(text) if text == "你好"));
+        assert!(engine.is_ascii_mode());
+
+        let schema = Schema::from_yaml(
+            r#"
+schema:
+  schema_id: test
+  name: Test
+ascii_composer:
+  switch_key:
+    Shift_L: commit_code
+"#,
+        )
+        .unwrap();
+        let mut engine = Engine::new().with_schema(&schema);
+        type_str(&mut engine, "nihao");
+        let output = shift_tap(&mut engine, keysym::SHIFT_L);
+        assert!(matches!(output, EngineOutput::Commit(text) if text == "nihao"));
+
+        let output = engine.process_key(KeyEvent::from_keysym(keysym::CAPS_LOCK));
+        assert!(matches!(output, EngineOutput::None));
+        assert!(!engine.is_ascii_mode());
+    }
+
+    #[test]
+    fn test_release_events_ignored() {
+        let mut engine = Engine::new();
+        type_str(&mut engine, "ni");
+        let mut released = key('i');
+        released.modifiers.release = true;
+        assert!(matches!(engine.process_key(released), EngineOutput::None));
+        assert_eq!(engine.input_buffer(), "ni");
+    }
+
+    #[test]
+    fn test_inline_ascii_segment() {
+        let mut engine = Engine::new();
+        let shift_h = KeyEvent::new('H' as u32, 'H', KeyModifiers::new().with_shift());
+        engine.process_key(shift_h);
+        assert!(engine.is_inline_ascii());
+        type_str(&mut engine, "ello world");
+        let output = engine.process_key(KeyEvent::from_char('\r'));
+        assert!(matches!(output, EngineOutput::Commit(text) if text == "Hello world"));
+        assert_eq!(engine.state(), InputState::Idle);
+        assert!(!engine.is_ascii_mode());
+    }
+
+    #[test]
+    fn test_return_commits_raw_input() {
+        let mut engine = Engine::new();
+        type_str(&mut engine, "zhongwen");
+        let output = engine.process_key(KeyEvent::parse("Return").unwrap());
+        assert!(matches!(output, EngineOutput::Commit(text) if text == "zhongwen"));
+        assert_eq!(engine.state(), InputState::Idle);
+
+        // 空闲状态下回车交给前端
+        assert!(matches!(engine.process_key(KeyEvent::parse("Return").unwrap()), EngineOutput::None));
+    }
+
+    #[test]
+    fn test_commit_variants_bindable() {
+        let schema = Schema::from_yaml(
+            r#"
+schema:
+  schema_id: test
+  name: Test
+key_binder:
+  bindings:
+    - {when: composing, accept: Shift+Return, commit: tone_marks}
+    - {when: composing, accept: Control+Return, commit: spaced_pinyin}
+    - {when: has_menu, accept: Control+t, commit: traditional}
+"#,
+        )
+        .unwrap();
+        let mut engine = Engine::new().with_schema(&schema);
+        engine.set_traditional_converter(Box::new(|text| text.replace('国', "國")));
+
+        type_str(&mut engine, "zhongwen");
+        let output = engine.process_key(KeyEvent::parse("Shift+Return").unwrap());
+        assert!(matches!(output, EngineOutput::Commit(text) if text == "zhōngwén"));
+
+        type_str(&mut engine, "zhongwen");
+        let output = engine.process_key(KeyEvent::parse("Control+Return").unwrap());
+        assert!(matches!(output, EngineOutput::Commit(text) if text == "zhong wen"));
+
+        type_str(&mut engine, "zhongguo");
+        let output = engine.process_key(KeyEvent::parse("Control+t").unwrap());
+        assert!(matches!(output, EngineOutput::Commit(text) if text == "中國"));
+    }
+
+    #[test]
+    fn test_backspace_after_commit_reverts() {
+        let dir = tempfile::tempdir().unwrap();
+        let user_dict = dir.path().join("user_dict.json");
+        let pinyin_engine = SimplifiedPinyinEngine::new().with_user_dict(user_dict.to_str().unwrap());
+        let mut engine = Engine::with_pinyin_engine(pinyin_engine);
+
+        type_str(&mut engine, "zhong");
+        let output = engine.process_key(key('2'));
+        let committed = match output {
+            EngineOutput::Commit(text) => text,
+            other => panic!("unexpected output: {:?}", other),
+        };
+        assert_eq!(engine.commit_history().len(), 1);
+
+        let output = engine.process_key(key('\u{8}'));
+        assert!(matches!(output, EngineOutput::Revert { delete: 1, ref candidates } if !candidates.is_empty()));
+        assert_eq!(engine.state(), InputState::Composing);
+        assert_eq!(engine.input_buffer(), "zhong");
+        assert!(engine.commit_history().is_empty());
+
+        // 词频记录已回滚，候选顺序与首次输入一致
+        assert_ne!(engine.candidates()[0].text, committed);
+    }
+
+    fn prediction_engine() -> Engine {
+        let mut pinyin_engine = SimplifiedPinyinEngine::new();
+        pinyin_engine.add_entry("zhongguorenmin", "中国人民", 80);
+        pinyin_engine.add_entry("zhongguoyinhang", "中国银行", 90);
+        let mut engine = Engine::with_pinyin_engine(pinyin_engine);
+        engine.set_prediction_enabled(true);
+        SharedDict::wait_for_phrase_index(engine.shared_dict());
+        engine
+    }
+
+    #[test]
+    fn test_prediction_after_commit() {
+        let mut engine = prediction_engine();
+        type_str(&mut engine, "zhongguo");
+        let output = engine.process_key(key(' '));
+        assert!(matches!(output, EngineOutput::Commit(text) if text == "中国"));
+        assert_eq!(engine.state(), InputState::Predicting);
+        let predictions: Vec<&str> = engine.candidates().iter().map(|c| c.text.as_str()).collect();
+        assert_eq!(predictions, vec!["银行", "人民"]);
+
+        let output = engine.process_key(key('2'));
+        assert!(matches!(output, EngineOutput::Commit(text) if text == "人民"));
+        assert_eq!(engine.state(), InputState::Idle);
+
+        // 联想上屏记入上屏历史，撤销删除联想的文字
+        assert_eq!(engine.commit_history().len(), 2);
+        let output = engine.process_key(key('\u{8}'));
+        assert!(matches!(output, EngineOutput::Revert { delete: 2, .. }));
+        assert_eq!(engine.state(), InputState::Idle);
+        assert_eq!(engine.commit_history().last().unwrap().candidate.text, "中国");
+    }
+
+    #[test]
+    fn test_unrecorded_commit_clears_history() {
+        let schema = Schema::from_yaml(
+            r#"
+schema:
+  schema_id: test
+  name: Test
+key_binder:
+  bindings:
+    - {when: always, accept: Control+z, undo: ~}
+"#,
+        )
+        .unwrap();
+        let mut engine = Engine::new().with_schema(&schema);
+        type_str(&mut engine, "nihao ");
+        assert_eq!(engine.commit_history().len(), 1);
+
+        // 标点没有记入上屏历史，之后撤销不会删除错误的文字
+        engine.process_key(key(','));
+        assert!(engine.commit_history().is_empty());
+        assert!(matches!(engine.process_key(key('\u{8}')), EngineOutput::None));
+        assert!(matches!(
+            engine.process_key(KeyEvent::parse("Control+z").unwrap()),
+            EngineOutput::None
+        ));
+
+        type_str(&mut engine, "nihao ");
+        let output = engine.process_key(KeyEvent::parse("Control+z").unwrap());
+        assert!(matches!(output, EngineOutput::Revert { delete: 2, .. }));
+        assert_eq!(engine.input_buffer(), "nihao");
+    }
+
+    #[test]
+    fn test_prediction_dismissed_by_other_keys() {
+        let mut engine = prediction_engine();
+        type_str(&mut engine, "zhongguo");
+        engine.process_key(key(' '));
+        assert_eq!(engine.state(), InputState::Predicting);
+
+        // 字母键关闭联想并开始新的输入
+        engine.process_key(key('n'));
+        assert_eq!(engine.state(), InputState::Composing);
+        assert_eq!(engine.input_buffer(), "n");
+
+        // 关闭联想时上屏后保持空闲
+        engine.process_key(key('\u{1b}'));
+        engine.set_prediction_enabled(false);
+        type_str(&mut engine, "zhongguo");
+        engine.process_key(key(' '));
+        assert_eq!(engine.state(), InputState::Idle);
+        assert!(engine.candidates().is_empty());
+    }
+
+    #[test]
+    fn test_explain_mode() {
+        let mut engine = Engine::new();
+        type_str(&mut engine, "zhong");
+        assert!(engine.candidate_explanations().is_empty());
+
+        engine.set_explain_mode(true);
+        let explanations = engine.candidate_explanations();
+        assert_eq!(explanations.len(), engine.candidates().len());
+        assert_eq!(explanations[0].source, "builtin");
+        assert_eq!(explanations[0].raw_weight, 100.0);
+        assert!(explanations.windows(2).all(|w| w[0].total >= w[1].total));
+
+        engine.process_key(key(' '));
+        assert!(engine.candidate_explanations().is_empty());
+    }
+
+    #[test]
+    fn test_shift_delete_and_pin_candidate() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let dir = temp_dir.path();
+        let pinyin_engine = SimplifiedPinyinEngine::new().with_user_dict_store(dir.to_str().unwrap());
+        let mut engine = Engine::with_pinyin_engine(pinyin_engine);
+        type_str(&mut engine, "zhong");
+        let first = engine.candidates()[0].text.clone();
+
+        let mut shift_delete = KeyEvent::from_keysym(keysym::DELETE);
+        shift_delete.modifiers.shift = true;
+        assert!(matches!(engine.process_key(shift_delete), EngineOutput::Candidates(_)));
+        assert!(engine.candidates().iter().all(|c| c.text != first));
+        assert_eq!(engine.state(), InputState::Composing);
+
+        let last = engine.candidates().len() - 1;
+        let text = engine.candidates()[last].text.clone();
+        assert!(engine.pin_candidate(last, Some(0)));
+        assert_eq!(engine.candidates()[0].text, text);
+
+        engine.add_user_word("灵码", "zhong");
+        assert!(engine.candidates().iter().any(|c| c.text == "灵码"));
+    }
+
+    #[test]
+    fn test_schema_dictionaries() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let dir = temp_dir.path();
+        std::fs::write(dir.join("8105.dict.yaml"), "---\n...\n种\tzhong\t1000\n").unwrap();
+        std::fs::write(dir.join("jargon.dict.yaml"), "---\n...\n锺\tzhong\t10\n").unwrap();
+
+        let pinyin_engine = SimplifiedPinyinEngine::with_rime_dicts(dir.to_str().unwrap());
+        let mut engine = Engine::with_pinyin_engine(pinyin_engine);
+        let schema = Schema::from_yaml(
+            "schema:\n  schema_id: test\n  name: Test\ntranslator:\n  dictionaries:\n    - name: jargon\n      priority: 300\n",
+        )
+        .unwrap();
+        engine.apply_schema(&schema);
+
+        type_str(&mut engine, "zhong");
+        let first = &engine.candidates()[0];
+        assert_eq!((first.text.as_str(), first.source.as_deref()), ("锺", Some("jargon")));
+    }
+
+    #[test]
+    fn test_incognito_and_blocklist() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let dir = temp_dir.path();
+        let pinyin_engine = SimplifiedPinyinEngine::new().with_user_dict_store(dir.to_str().unwrap());
+        let mut engine = Engine::with_pinyin_engine(pinyin_engine);
+        let learned = |engine: &Engine| engine.pinyin_engine.user_dict_stats().unwrap().total_records;
+
+        type_str(&mut engine, "nihao ");
+        assert_eq!(learned(&engine), 1);
+        assert_eq!(engine.commit_history().len(), 1);
+
+        engine.set_incognito(true);
+        assert!(engine.commit_history().is_empty());
+        type_str(&mut engine, "zhong ");
+        assert_eq!(learned(&engine), 1);
+        assert!(engine.commit_history().is_empty());
+        // 无痕模式下 Backspace 不撤销上屏
+        assert!(!matches!(engine.process_key(key('\u{8}')), EngineOutput::Revert { .. }));
+
+        engine.set_incognito(false);
+        let mut privacy = PrivacyGuard::new();
+        privacy.add_pattern("^zh").unwrap();
+        engine.set_privacy(privacy);
+        type_str(&mut engine, "zhong ");
+        assert_eq!(learned(&engine), 1);
+        type_str(&mut engine, "nihao ");
+        assert_eq!(learned(&engine), 1);
+        // 被屏蔽的上屏清空了历史，之后的上屏正常记录
+        assert_eq!(engine.commit_history().len(), 1);
+
+        assert_eq!(engine.purge_history(0, u64::MAX).unwrap(), 1);
+        assert_eq!(learned(&engine), 0);
+        assert!(engine.commit_history().is_empty());
+        // 清除的词条不屏蔽系统词库的候选
+        type_str(&mut engine, "nihao");
+        assert!(engine.candidates().iter().any(|c| c.text == "你好"));
+    }
+
+    fn engine_candidate_text(input: &str, index: usize) -> String {
+        let mut engine = Engine::new();
+        type_str(&mut engine, input);
+        engine.candidates()[index].text.clone()
+    }
 }
@@ -0,0 +1,194 @@
+//! 联想输入
+//!
+//! 上屏后根据刚上屏的文字提供后续词语。联想来源有两个：
+//! 词库中以上屏文字开头的词组（如 `中国人民` 提供 `人民`），
+//! 以及用户上屏历史中相邻两次上屏组成的二元组
+
+use lingcode_pinyin::PhraseIndex;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// 开关：上屏后显示联想候选
+pub const PREDICTION: &str = "prediction";
+
+/// 默认联想候选数量
+pub const DEFAULT_MAX_PREDICTIONS: usize = 10;
+
+/// 匹配上下文时使用的最长后缀字数
+const MAX_CONTEXT_CHARS: usize = 4;
+
+/// 联想候选生成器
+#[derive(Debug, Clone)]
+pub struct Predictor {
+    /// 词组联想索引，可由多个会话共享
+    phrases: Arc<PhraseIndex>,
+    /// 用户二元组：前一次上屏 -> (后一次上屏 -> 次数)
+    user_bigrams: HashMap<String, HashMap<String, u32>>,
+    /// 最多返回的联想候选数
+    max_predictions: usize,
+}
+
+impl Predictor {
+    /// 创建空的联想表
+    pub fn new() -> Self {
+        Self {
+            phrases: Arc::new(PhraseIndex::new()),
+            user_bigrams: HashMap::new(),
+            max_predictions: DEFAULT_MAX_PREDICTIONS,
+        }
+    }
+
+    /// 从词组（文字, 权重）构建联想表
+    pub fn from_phrases<'a>(phrases: impl IntoIterator<Item = (&'a str, u32)>) -> Self {
+        let mut predictor = Self::new();
+        predictor.set_phrases(Arc::new(PhraseIndex::from_phrases(phrases)));
+        predictor
+    }
+
+    /// 设置最多返回的联想候选数
+    pub fn set_max_predictions(&mut self, max_predictions: usize) {
+        self.max_predictions = max_predictions.max(1);
+    }
+
+    /// 添加词组：每个真前缀都可以联想出剩余部分
+    ///
+    /// 联想索引与其他会话共享时先复制一份
+    pub fn add_phrase(&mut self, text: &str, weight: u32) {
+        Arc::make_mut(&mut self.phrases).insert(text, weight);
+    }
+
+    /// 替换词组联想索引（保留用户二元组）
+    pub fn set_phrases(&mut self, phrases: Arc<PhraseIndex>) {
+        self.phrases = phrases;
+    }
+
+    /// 词组联想索引
+    pub fn phrases(&self) -> &Arc<PhraseIndex> {
+        &self.phrases
+    }
+
+    /// 清空词组联想表（保留用户二元组），用于词库重载后重新构建
+    pub fn clear_phrases(&mut self) {
+        self.phrases = Arc::new(PhraseIndex::new());
+    }
+
+    /// 记录用户连续上屏的两段文字
+    pub fn learn(&mut self, previous: &str, next: &str) {
+        if previous.is_empty() || next.is_empty() {
+            return;
+        }
+        *self
+            .user_bigrams
+            .entry(previous.to_string())
+            .or_default()
+            .entry(next.to_string())
+            .or_insert(0) += 1;
+    }
+
+    /// 撤销一次 `learn`
+    pub fn forget(&mut self, previous: &str, next: &str) {
+        if let Some(nexts) = self.user_bigrams.get_mut(previous) {
+            if let Some(count) = nexts.get_mut(next) {
+                *count -= 1;
+                if *count == 0 {
+                    nexts.remove(next);
+                }
+            }
+            if nexts.is_empty() {
+                self.user_bigrams.remove(previous);
+            }
+        }
+    }
+
+    /// 清除所有用户二元组
+    pub fn clear_learned(&mut self) {
+        self.user_bigrams.clear();
+    }
+
+    /// 用户二元组的次数
+    pub fn bigram_count(&self, previous: &str, next: &str) -> u32 {
+        self.user_bigrams
+            .get(previous)
+            .and_then(|nexts| nexts.get(next))
+            .copied()
+            .unwrap_or(0)
+    }
+
+    /// 根据上下文生成联想候选
+    ///
+    /// 用户二元组优先，其次为词组联想；上下文较长时依次尝试更短的后缀
+    pub fn predict(&self, context: &str) -> Vec<String> {
+        let chars: Vec<char> = context.chars().collect();
+        let mut results: Vec<String> = Vec::new();
+
+        let start = chars.len().saturating_sub(MAX_CONTEXT_CHARS);
+        for i in start..chars.len() {
+            let suffix: String = chars[i..].iter().collect();
+
+            if let Some(nexts) = self.user_bigrams.get(&suffix) {
+                let mut learned: Vec<(&String, &u32)> = nexts.iter().collect();
+                learned.sort_by(|a, b| b.1.cmp(a.1).then_with(|| a.0.cmp(b.0)));
+                push_unique(&mut results, learned.into_iter().map(|(text, _)| text.as_str()));
+            }
+            // 单字前缀可能有上万个词组，只对权重最高的几个排序
+            let by_weight = |a: &(&str, u32), b: &(&str, u32)| b.1.cmp(&a.1).then_with(|| a.0.cmp(b.0));
+            let mut phrases: Vec<(&str, u32)> = self.phrases.continuations(&suffix).collect();
+            if phrases.len() > self.max_predictions {
+                phrases.select_nth_unstable_by(self.max_predictions, by_weight);
+                phrases.truncate(self.max_predictions);
+            }
+            phrases.sort_by(by_weight);
+            push_unique(&mut results, phrases.into_iter().map(|(text, _)| text));
+            if results.len() >= self.max_predictions {
+                break;
+            }
+        }
+
+        results.truncate(self.max_predictions);
+        results
+    }
+
+    /// 词组联想表是否为空
+    pub fn has_phrases(&self) -> bool {
+        !self.phrases.is_empty()
+    }
+}
+
+impl Default for Predictor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn push_unique<'a>(results: &mut Vec<String>, texts: impl Iterator<Item = &'a str>) {
+    for text in texts {
+        if !results.iter().any(|r| r == text) {
+            results.push(text.to_string());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_phrase_predictions() {
+        let predictor = Predictor::from_phrases([("中国人民", 80), ("中国银行", 90), ("中国", 100)]);
+        assert_eq!(predictor.predict("中国"), vec!["银行", "人民"]);
+        // 较长的上下文按后缀匹配
+        assert_eq!(predictor.predict("我爱中国")[0], "银行");
+        assert!(predictor.predict("你好").is_empty());
+    }
+
+    #[test]
+    fn test_user_bigrams_first() {
+        let mut predictor = Predictor::from_phrases([("中国银行", 90)]);
+        predictor.learn("中国", "队");
+        assert_eq!(predictor.predict("中国"), vec!["队", "银行"]);
+
+        predictor.forget("中国", "队");
+        assert_eq!(predictor.bigram_count("中国", "队"), 0);
+        assert_eq!(predictor.predict("中国"), vec!["银行"]);
+    }
+}
@@ -0,0 +1,88 @@
+//! 学习隐私控制
+//!
+//! 无痕模式（前端在密码框等安全输入场景开启）下不记录词频、联想与上屏历史；
+//! 上屏文字或输入编码匹配屏蔽规则（正则表达式）时同样不学习
+
+use lingcode_core::schema_parser::Privacy as PrivacyConfig;
+use regex::Regex;
+
+/// 学习隐私控制
+#[derive(Debug, Clone, Default)]
+pub struct PrivacyGuard {
+    /// 无痕模式
+    incognito: bool,
+    /// 屏蔽规则
+    blocklist: Vec<Regex>,
+}
+
+impl PrivacyGuard {
+    /// 创建不限制学习的隐私控制
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 从方案配置创建，无效的正则表达式记录警告后忽略
+    pub fn from_config(config: &PrivacyConfig) -> Self {
+        let mut guard = Self::new();
+        for pattern in &config.blocklist {
+            if let Err(e) = guard.add_pattern(pattern) {
+                log::warn!("忽略无效的屏蔽规则 {}: {}", pattern, e);
+            }
+        }
+        guard
+    }
+
+    /// 添加屏蔽规则
+    pub fn add_pattern(&mut self, pattern: &str) -> Result<(), regex::Error> {
+        self.blocklist.push(Regex::new(pattern)?);
+        Ok(())
+    }
+
+    /// 屏蔽规则
+    pub fn patterns(&self) -> impl Iterator<Item = &str> {
+        self.blocklist.iter().map(Regex::as_str)
+    }
+
+    /// 设置无痕模式
+    pub fn set_incognito(&mut self, incognito: bool) {
+        self.incognito = incognito;
+    }
+
+    /// 是否处于无痕模式
+    pub fn is_incognito(&self) -> bool {
+        self.incognito
+    }
+
+    /// 文字或输入编码是否匹配屏蔽规则
+    pub fn is_blocked(&self, text: &str, input: &str) -> bool {
+        self.blocklist
+            .iter()
+            .any(|pattern| pattern.is_match(text) || (!input.is_empty() && pattern.is_match(input)))
+    }
+
+    /// 是否允许学习并记录这次上屏
+    pub fn allows(&self, text: &str, input: &str) -> bool {
+        !self.incognito && !self.is_blocked(text, input)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_blocklist_and_incognito() {
+        let config = PrivacyConfig {
+            blocklist: vec![r"^\d{6,}$".to_string(), "机密".to_string(), "(".to_string()],
+        };
+        let mut guard = PrivacyGuard::from_config(&config);
+        assert_eq!(guard.patterns().count(), 2);
+
+        assert!(!guard.allows("123456", ""));
+        assert!(!guard.allows("机密文件", "jimiwenjian"));
+        assert!(guard.allows("你好", "nihao"));
+
+        guard.set_incognito(true);
+        assert!(!guard.allows("你好", "nihao"));
+    }
+}
@@ -0,0 +1,246 @@
+//! 标点符号转换
+//!
+//! 对应 Rime 方案中的 `punctuator`，将 ASCII 标点映射为中文标点。
+//! 受 `ascii_punct`（输出 ASCII 标点）与 `full_shape`（全角）两个开关控制
+
+use crate::switches::Switches;
+use lingcode_core::schema_parser::{PunctRule, Punctuator as PunctuatorConfig};
+use std::collections::HashMap;
+
+/// 开关：输出 ASCII 标点
+pub const ASCII_PUNCT: &str = "ascii_punct";
+/// 开关：全角模式
+pub const FULL_SHAPE: &str = "full_shape";
+
+/// 默认半角模式标点表
+const DEFAULT_HALF_SHAPE: &[(char, &[&str])] = &[
+    (',', &["，"]),
+    ('.', &["。"]),
+    ('?', &["？"]),
+    ('!', &["！"]),
+    (';', &["；"]),
+    (':', &["："]),
+    ('\\', &["、"]),
+    ('/', &["、", "/", "／"]),
+    ('<', &["《", "〈", "<"]),
+    ('>', &["》", "〉", ">"]),
+    ('(', &["（"]),
+    (')', &["）"]),
+    ('[', &["【", "「", "["]),
+    (']', &["】", "」", "]"]),
+    ('{', &["『", "〖", "{"]),
+    ('}', &["』", "〗", "}"]),
+    ('~', &["～"]),
+    ('^', &["……"]),
+    ('_', &["——"]),
+    ('$', &["￥", "$"]),
+    ('|', &["·", "|"]),
+    ('`', &["`"]),
+    ('@', &["@"]),
+    ('#', &["#"]),
+    ('%', &["%"]),
+    ('&', &["&"]),
+    ('*', &["*", "×"]),
+    ('+', &["+"]),
+    ('=', &["="]),
+    ('-', &["-"]),
+];
+
+/// 默认成对标点（开、闭交替输出）
+const DEFAULT_PAIRS: &[(char, &str, &str)] = &[('"', "“", "”"), ('\'', "‘", "’")];
+
+/// 标点转换结果
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PunctResult {
+    /// 直接上屏
+    Commit(String),
+    /// 多个候选供选择
+    Candidates(Vec<String>),
+}
+
+/// 标点转换器
+#[derive(Debug, Clone)]
+pub struct Punctuator {
+    half_shape: HashMap<char, PunctRule>,
+    full_shape: HashMap<char, PunctRule>,
+    /// 成对标点的状态：true 表示下一个输出闭合符号
+    pair_state: HashMap<char, bool>,
+}
+
+impl Punctuator {
+    /// 使用默认标点表创建
+    pub fn new() -> Self {
+        let mut half_shape = HashMap::new();
+        for (key, texts) in DEFAULT_HALF_SHAPE {
+            let rule = if texts.len() == 1 {
+                PunctRule::Commit { commit: texts[0].to_string() }
+            } else {
+                PunctRule::Alternatives(texts.iter().map(|t| t.to_string()).collect())
+            };
+            half_shape.insert(*key, rule);
+        }
+        for (key, open, close) in DEFAULT_PAIRS {
+            half_shape.insert(*key, PunctRule::Pair { pair: vec![open.to_string(), close.to_string()] });
+        }
+
+        // 全角模式：中文标点不变，ASCII 符号替换为对应的全角字符
+        let full_shape = half_shape
+            .iter()
+            .map(|(key, rule)| (*key, to_full_shape_rule(rule)))
+            .collect();
+
+        Self {
+            half_shape,
+            full_shape,
+            pair_state: HashMap::new(),
+        }
+    }
+
+    /// 从方案配置创建，配置中的规则覆盖默认标点表
+    pub fn from_config(config: &PunctuatorConfig) -> Self {
+        let mut punctuator = Self::new();
+        for (key, rule) in &config.half_shape {
+            if let Some(c) = single_char(key) {
+                punctuator.half_shape.insert(c, rule.clone());
+            }
+        }
+        for (key, rule) in &config.full_shape {
+            if let Some(c) = single_char(key) {
+                punctuator.full_shape.insert(c, rule.clone());
+            }
+        }
+        punctuator
+    }
+
+    /// 是否为可转换的标点按键
+    pub fn is_punct(&self, key: char) -> bool {
+        self.half_shape.contains_key(&key) || self.full_shape.contains_key(&key)
+    }
+
+    /// 转换标点按键，未定义的按键返回 None
+    pub fn translate(&mut self, key: char, switches: &Switches) -> Option<PunctResult> {
+        if !self.is_punct(key) {
+            return None;
+        }
+        if switches.get(ASCII_PUNCT) {
+            return Some(PunctResult::Commit(key.to_string()));
+        }
+
+        let table = if switches.get(FULL_SHAPE) {
+            &self.full_shape
+        } else {
+            &self.half_shape
+        };
+        let rule = table.get(&key)?.clone();
+
+        match rule {
+            PunctRule::Commit { commit } | PunctRule::Text(commit) => Some(PunctResult::Commit(commit)),
+            PunctRule::Pair { pair } => {
+                let closing = self.pair_state.entry(key).or_default();
+                let text = pair.get(*closing as usize).or_else(|| pair.first())?.clone();
+                *closing = !*closing;
+                Some(PunctResult::Commit(text))
+            }
+            PunctRule::Alternatives(texts) => match texts.len() {
+                0 => None,
+                1 => Some(PunctResult::Commit(texts[0].clone())),
+                _ => Some(PunctResult::Candidates(texts)),
+            },
+        }
+    }
+
+    /// 重置成对标点状态
+    pub fn reset(&mut self) {
+        self.pair_state.clear();
+    }
+}
+
+impl Default for Punctuator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn single_char(key: &str) -> Option<char> {
+    let mut chars = key.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) => Some(c),
+        _ => None,
+    }
+}
+
+/// ASCII 可见字符转全角（U+FF01..U+FF5E）
+fn to_full_width(text: &str) -> String {
+    text.chars()
+        .map(|c| match c {
+            '!'..='~' => char::from_u32(c as u32 + 0xfee0).unwrap_or(c),
+            _ => c,
+        })
+        .collect()
+}
+
+fn to_full_shape_rule(rule: &PunctRule) -> PunctRule {
+    match rule {
+        PunctRule::Commit { commit } => PunctRule::Commit { commit: to_full_width(commit) },
+        PunctRule::Text(text) => PunctRule::Text(to_full_width(text)),
+        PunctRule::Pair { pair } => PunctRule::Pair { pair: pair.iter().map(|t| to_full_width(t)).collect() },
+        PunctRule::Alternatives(texts) => {
+            let mut result: Vec<String> = Vec::new();
+            for text in texts.iter().map(|t| to_full_width(t)) {
+                if !result.contains(&text) {
+                    result.push(text);
+                }
+            }
+            PunctRule::Alternatives(result)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_punct() {
+        let mut punctuator = Punctuator::new();
+        let switches = Switches::new();
+        assert_eq!(punctuator.translate(',', &switches), Some(PunctResult::Commit("，".to_string())));
+        assert_eq!(
+            punctuator.translate('/', &switches),
+            Some(PunctResult::Candidates(vec!["、".to_string(), "/".to_string(), "／".to_string()]))
+        );
+        assert_eq!(punctuator.translate('a', &switches), None);
+    }
+
+    #[test]
+    fn test_paired_quotes() {
+        let mut punctuator = Punctuator::new();
+        let switches = Switches::new();
+        assert_eq!(punctuator.translate('"', &switches), Some(PunctResult::Commit("“".to_string())));
+        assert_eq!(punctuator.translate('"', &switches), Some(PunctResult::Commit("”".to_string())));
+        assert_eq!(punctuator.translate('"', &switches), Some(PunctResult::Commit("“".to_string())));
+    }
+
+    #[test]
+    fn test_shape_switches() {
+        let mut punctuator = Punctuator::new();
+        let mut switches = Switches::new();
+
+        switches.set(FULL_SHAPE, true);
+        assert_eq!(punctuator.translate('@', &switches), Some(PunctResult::Commit("＠".to_string())));
+        assert_eq!(punctuator.translate(',', &switches), Some(PunctResult::Commit("，".to_string())));
+
+        switches.set(ASCII_PUNCT, true);
+        assert_eq!(punctuator.translate(',', &switches), Some(PunctResult::Commit(",".to_string())));
+    }
+
+    #[test]
+    fn test_config_overrides_defaults() {
+        let mut config = PunctuatorConfig::default();
+        config.half_shape.insert(".".to_string(), PunctRule::Text("．".to_string()));
+        let mut punctuator = Punctuator::from_config(&config);
+        let switches = Switches::new();
+        assert_eq!(punctuator.translate('.', &switches), Some(PunctResult::Commit("．".to_string())));
+        assert_eq!(punctuator.translate(',', &switches), Some(PunctResult::Commit("，".to_string())));
+    }
+}
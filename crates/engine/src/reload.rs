@@ -0,0 +1,534 @@
+//! 资源热重载
+//!
+//! [`ReloadService`] 定期扫描 [`ResourceLoader`] 的资源目录，文件变化稳定后在后台重新解析方案、
+//! 构建词库，成功后整体发布为新版本（词库以 [`SharedDict`] 在所有引擎间共享）；引擎通过 [`ReloadHandle`] 在空闲时取得新版本并一次性替换
+//! （见 [`Engine::set_reload_handle`](crate::Engine::set_reload_handle)）。
+//! 构建失败时保留上一版本，通过日志与监听函数报告错误。
+//! OpenCC 资源变化时通知引擎重新创建转换器（见 [`ReloadUpdate::opencc`]）
+
+use lingcode_core::resource_loader::{ResourceChange, ResourceKind, ResourceLoader, ResourceSnapshot};
+use lingcode_core::schema_parser::Schema;
+use lingcode_dict::{DictStack, MemoryBudget, RimeDictLoader};
+use lingcode_pinyin::SharedDict;
+use std::path::PathBuf;
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+/// 默认扫描间隔
+pub const DEFAULT_RELOAD_INTERVAL: Duration = Duration::from_secs(1);
+
+/// 重载事件监听函数
+pub type ReloadListener = Box<dyn Fn(&ReloadEvent) + Send + Sync>;
+
+/// 重载事件
+#[derive(Debug, Clone)]
+pub enum ReloadEvent {
+    /// 已发布新版本
+    Reloaded {
+        /// 版本号
+        generation: u64,
+        /// 触发重载的文件
+        changes: Vec<ResourceChange>,
+        /// 是否重新解析了方案
+        schema: bool,
+        /// 重新构建的词库条数，未重建词库时为 `None`
+        dict_entries: Option<usize>,
+        /// OpenCC 资源是否有变化
+        opencc: bool,
+    },
+    /// 构建失败，继续使用上一版本
+    Failed {
+        /// 触发重载的文件
+        changes: Vec<ResourceChange>,
+        /// 构建失败的资源
+        kind: ResourceKind,
+        /// 错误说明
+        error: String,
+    },
+}
+
+/// 引擎尚未应用的新版本资源
+#[derive(Clone, Default)]
+pub struct ReloadUpdate {
+    /// 重新解析的方案
+    pub schema: Option<Arc<Schema>>,
+    /// 重新构建的词库，由所有句柄共享
+    pub dicts: Option<Arc<SharedDict>>,
+    /// OpenCC 资源有变化，使用资源目录中 OpenCC 配置的转换器需重新创建
+    pub opencc: bool,
+}
+
+impl ReloadUpdate {
+    /// 是否没有新资源
+    pub fn is_empty(&self) -> bool {
+        self.schema.is_none() && self.dicts.is_none() && !self.opencc
+    }
+}
+
+/// 已发布的资源，各自带有发布时的版本号
+#[derive(Default)]
+struct Published {
+    generation: u64,
+    schema: Option<(u64, Arc<Schema>)>,
+    dicts: Option<(u64, Arc<SharedDict>)>,
+    /// OpenCC 资源最后变化时的版本号
+    opencc: u64,
+}
+
+/// 扫描状态与配置（配置可以在启动线程、创建句柄之后修改，下次扫描时生效）
+struct Scanner {
+    /// 已处理的快照
+    snapshot: ResourceSnapshot,
+    /// 检测到变化、等待稳定的快照
+    pending: Option<ResourceSnapshot>,
+    /// 当前方案的词库组合
+    stack: DictStack,
+    /// 监视的方案名称
+    schema_name: Option<String>,
+    /// 重新构建词库时使用的内存预算
+    memory_budget: MemoryBudget,
+    /// 事件监听函数
+    listener: Option<ReloadListener>,
+}
+
+struct Shared {
+    loader: ResourceLoader,
+    scanner: Mutex<Scanner>,
+    published: Mutex<Published>,
+}
+
+/// 资源热重载服务
+///
+/// 创建时记录资源目录的快照，之后的变化才会触发重载。
+/// 调用 [`spawn`](Self::spawn) 在后台线程定期扫描，也可以由前端调用 [`check_now`](Self::check_now) 立即扫描。
+/// 服务销毁时停止后台线程，已发布的资源仍可由引擎使用
+pub struct ReloadService {
+    shared: Arc<Shared>,
+    interval: Duration,
+    stop: Option<Sender<()>>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl ReloadService {
+    /// 监视资源目录
+    pub fn new(loader: ResourceLoader) -> Self {
+        let scanner = Scanner {
+            snapshot: loader.snapshot(),
+            pending: None,
+            stack: DictStack::default(),
+            schema_name: None,
+            memory_budget: MemoryBudget::default(),
+            listener: None,
+        };
+        Self {
+            shared: Arc::new(Shared {
+                loader,
+                scanner: Mutex::new(scanner),
+                published: Mutex::new(Published::default()),
+            }),
+            interval: DEFAULT_RELOAD_INTERVAL,
+            stop: None,
+            thread: None,
+        }
+    }
+
+    /// 同时监视方案文件 `<name>.schema.yaml`，方案或根目录配置变化时重新解析方案
+    pub fn with_schema(self, name: &str) -> Self {
+        let stack = parse_schema(&self.shared.loader, name).map(|schema| dict_stack_of(&schema)).unwrap_or_default();
+        let mut scanner = self.shared.scanner.lock().unwrap();
+        scanner.schema_name = Some(name.to_string());
+        scanner.stack = stack;
+        drop(scanner);
+        self
+    }
+
+    /// 重新构建词库时使用的内存预算
+    pub fn with_memory_budget(self, budget: MemoryBudget) -> Self {
+        self.shared.scanner.lock().unwrap().memory_budget = budget;
+        self
+    }
+
+    /// 设置事件监听函数（在扫描线程中调用）
+    pub fn with_listener(self, listener: impl Fn(&ReloadEvent) + Send + Sync + 'static) -> Self {
+        self.shared.scanner.lock().unwrap().listener = Some(Box::new(listener));
+        self
+    }
+
+    /// 设置扫描间隔，文件连续两次扫描不变后才重载
+    pub fn with_interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    /// 启动后台扫描线程，重复调用无效
+    pub fn spawn(&mut self) -> std::io::Result<()> {
+        if self.thread.is_some() {
+            return Ok(());
+        }
+        let (stop, stopped) = mpsc::channel::<()>();
+        let shared = Arc::clone(&self.shared);
+        let interval = self.interval;
+        let thread = std::thread::Builder::new()
+            .name("lingcode-reload".to_string())
+            .spawn(move || {
+                // 服务销毁时发送端被丢弃，等待立即结束
+                while let Err(RecvTimeoutError::Timeout) = stopped.recv_timeout(interval) {
+                    shared.scan(true);
+                }
+            })?;
+        self.stop = Some(stop);
+        self.thread = Some(thread);
+        Ok(())
+    }
+
+    /// 后台线程是否在运行
+    pub fn is_running(&self) -> bool {
+        self.thread.is_some()
+    }
+
+    /// 立即扫描并重载变化的资源（不等待文件稳定），返回产生的事件
+    pub fn check_now(&self) -> Vec<ReloadEvent> {
+        self.shared.scan(false)
+    }
+
+    /// 为一个引擎创建句柄，句柄只会取得创建之后发布的资源
+    pub fn handle(&self) -> ReloadHandle {
+        let published = self.shared.published.lock().unwrap();
+        ReloadHandle {
+            shared: Arc::clone(&self.shared),
+            schema_generation: published.schema.as_ref().map_or(0, |(g, _)| *g),
+            dicts_generation: published.dicts.as_ref().map_or(0, |(g, _)| *g),
+            opencc_generation: published.opencc,
+        }
+    }
+
+    /// 最新发布的版本号，尚未发布时为 0
+    pub fn generation(&self) -> u64 {
+        self.shared.published.lock().unwrap().generation
+    }
+
+    fn shutdown(&mut self) {
+        self.stop.take();
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl Drop for ReloadService {
+    fn drop(&mut self) {
+        self.shutdown();
+    }
+}
+
+impl Shared {
+    /// 扫描资源目录，`debounce` 时等到连续两次扫描结果相同再重载
+    fn scan(&self, debounce: bool) -> Vec<ReloadEvent> {
+        let mut scanner = self.scanner.lock().unwrap();
+        let current = self.loader.snapshot();
+        if current == scanner.snapshot {
+            scanner.pending = None;
+            return Vec::new();
+        }
+        if debounce && scanner.pending.as_ref() != Some(&current) {
+            scanner.pending = Some(current);
+            return Vec::new();
+        }
+        let changes = scanner.snapshot.changes(&current, &self.loader);
+        scanner.snapshot = current;
+        scanner.pending = None;
+
+        let events = self.rebuild(&mut scanner, changes);
+        for event in &events {
+            match event {
+                ReloadEvent::Reloaded { generation, changes, .. } => {
+                    log::info!("资源已重载（版本 {}，{} 个文件变化）", generation, changes.len())
+                }
+                ReloadEvent::Failed { kind, error, .. } => log::warn!("重载{:?}失败，继续使用上一版本: {}", kind, error),
+            }
+            if let Some(listener) = &scanner.listener {
+                listener(event);
+            }
+        }
+        events
+    }
+
+    /// 重新构建受影响的资源并发布
+    fn rebuild(&self, scanner: &mut Scanner, changes: Vec<ResourceChange>) -> Vec<ReloadEvent> {
+        let schema_file = scanner.schema_name.as_ref().map(|name| schema_path(&self.loader, name));
+        let schema_changed = changes.iter().any(|change| match change.kind {
+            ResourceKind::Schema => Some(&change.path) == schema_file.as_ref(),
+            ResourceKind::Config => schema_file.is_some(),
+            _ => false,
+        });
+        let mut dicts_changed = changes.iter().any(|change| change.kind == ResourceKind::Dict);
+        let opencc_changed = changes.iter().any(|change| change.kind == ResourceKind::OpenCC);
+
+        let mut events = Vec::new();
+        let mut schema = None;
+        if schema_changed {
+            match parse_schema(&self.loader, scanner.schema_name.as_deref().unwrap_or_default()) {
+                Ok(parsed) => {
+                    let stack = dict_stack_of(&parsed);
+                    dicts_changed |= stack != scanner.stack;
+                    scanner.stack = stack;
+                    schema = Some(Arc::new(parsed));
+                }
+                Err(error) => events.push(ReloadEvent::Failed {
+                    changes: changes.clone(),
+                    kind: ResourceKind::Schema,
+                    error,
+                }),
+            }
+        }
+
+        let mut dicts = None;
+        if dicts_changed {
+            match build_dicts(&self.loader, &scanner.stack, &scanner.memory_budget) {
+                Ok(built) => dicts = Some(Arc::new(built)),
+                Err(error) => events.push(ReloadEvent::Failed {
+                    changes: changes.clone(),
+                    kind: ResourceKind::Dict,
+                    error,
+                }),
+            }
+        }
+
+        // 只有重新构建了资源时才发布新版本
+        if schema.is_some() || dicts.is_some() || opencc_changed {
+            let mut published = self.published.lock().unwrap();
+            published.generation += 1;
+            let generation = published.generation;
+            let reloaded_schema = schema.is_some();
+            let dict_entries = dicts.as_ref().and_then(|d| d.stats()).map(|stats| stats.total_entries);
+            if let Some(schema) = schema {
+                published.schema = Some((generation, schema));
+            }
+            if let Some(dicts) = dicts {
+                published.dicts = Some((generation, dicts));
+            }
+            if opencc_changed {
+                published.opencc = generation;
+            }
+            events.push(ReloadEvent::Reloaded {
+                generation,
+                changes,
+                schema: reloaded_schema,
+                dict_entries,
+                opencc: opencc_changed,
+            });
+        }
+        events
+    }
+}
+
+/// 引擎持有的重载句柄，记录已应用的版本
+pub struct ReloadHandle {
+    shared: Arc<Shared>,
+    schema_generation: u64,
+    dicts_generation: u64,
+    opencc_generation: u64,
+}
+
+impl ReloadHandle {
+    /// 取得上次调用后发布的资源，没有新资源时返回 `None`
+    pub fn take_update(&mut self) -> Option<ReloadUpdate> {
+        let published = self.shared.published.lock().unwrap();
+        let mut update = ReloadUpdate::default();
+        if let Some((generation, schema)) = &published.schema {
+            if *generation > self.schema_generation {
+                self.schema_generation = *generation;
+                update.schema = Some(Arc::clone(schema));
+            }
+        }
+        if let Some((generation, dicts)) = &published.dicts {
+            if *generation > self.dicts_generation {
+                self.dicts_generation = *generation;
+                update.dicts = Some(Arc::clone(dicts));
+            }
+        }
+        if published.opencc > self.opencc_generation {
+            self.opencc_generation = published.opencc;
+            update.opencc = true;
+        }
+        (!update.is_empty()).then_some(update)
+    }
+}
+
+fn schema_path(loader: &ResourceLoader, name: &str) -> PathBuf {
+    loader.schemas_dir().join(format!("{}.schema.yaml", name))
+}
+
+fn parse_schema(loader: &ResourceLoader, name: &str) -> Result<Schema, String> {
+    let yaml = loader
+        .load_schema(name)
+        .map_err(|e| format!("{}: {}", schema_path(loader, name).display(), e))?;
+    Schema::from_yaml(&yaml).map_err(|e| format!("{}: {}", schema_path(loader, name).display(), e))
+}
+
+fn dict_stack_of(schema: &Schema) -> DictStack {
+    schema.translator.as_ref().map(DictStack::from_config).unwrap_or_default()
+}
+
+/// 按词库组合从词库目录构建词库，没有任何词条时视为失败
+fn build_dicts(loader: &ResourceLoader, stack: &DictStack, budget: &MemoryBudget) -> Result<SharedDict, String> {
+    let dir = loader.dicts_dir();
+    let (base_dir, specs) = stack.resolve(&dir);
+    let mut dicts = RimeDictLoader::new().with_memory_budget(budget.clone());
+    let summary = dicts.load_dict_stack(&base_dir, &specs).map_err(|e| e.to_string())?;
+    if summary.total_entries == 0 {
+        return Err(format!("{} 中没有可加载的词条", dir.display()));
+    }
+    let dict = SharedDict::new().with_memory_budget(budget.clone());
+    Ok(dict.with_rime(dicts, &dir.to_string_lossy(), stack.clone()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Engine;
+    use lingcode_core::types::KeyEvent;
+    use lingcode_pinyin::SimplifiedPinyinEngine;
+    use std::fs;
+    use std::time::Instant;
+    use tempfile::TempDir;
+
+    const SCHEMA: &str = "schema:\n  schema_id: test\n  name: Test\n";
+
+    fn resources() -> (TempDir, ResourceLoader) {
+        let root = TempDir::new().unwrap();
+        let loader = ResourceLoader::new(root.path());
+        fs::create_dir_all(loader.schemas_dir()).unwrap();
+        fs::create_dir_all(loader.dicts_dir()).unwrap();
+        fs::write(loader.schemas_dir().join("test.schema.yaml"), SCHEMA).unwrap();
+        fs::write(loader.dicts_dir().join("8105.dict.yaml"), "---\n...\n种\tzhong\t1000\n").unwrap();
+        (root, loader)
+    }
+
+    fn first_candidate(engine: &mut Engine, input: &str) -> String {
+        for c in input.chars() {
+            engine.process_key(KeyEvent::from_char(c));
+        }
+        let text = engine.candidates()[0].text.clone();
+        engine.process_key(KeyEvent::from_char('\u{1b}'));
+        text
+    }
+
+    #[test]
+    fn test_reload_dicts_and_schema() {
+        let (_root, loader) = resources();
+        let dicts_dir = loader.dicts_dir();
+        let schema_file = loader.schemas_dir().join("test.schema.yaml");
+        let service = ReloadService::new(ResourceLoader::new(loader.resource_dir())).with_schema("test");
+        let pinyin_engine = SimplifiedPinyinEngine::with_rime_dicts(dicts_dir.to_str().unwrap());
+        let mut engine = Engine::with_pinyin_engine(pinyin_engine).with_reload_handle(service.handle());
+        assert_eq!(first_candidate(&mut engine, "zhong"), "种");
+        assert!(service.check_now().is_empty());
+
+        fs::write(dicts_dir.join("8105.dict.yaml"), "---\n...\n种\tzhong\t1000\n中\tzhong\t5000\n").unwrap();
+        let events = service.check_now();
+        assert!(matches!(
+            &events[..],
+            [ReloadEvent::Reloaded { generation: 1, schema: false, dict_entries: Some(2), .. }]
+        ));
+        // 输入中不替换
+        engine.process_key(KeyEvent::from_char('z'));
+        assert!(!engine.apply_reload());
+        engine.process_key(KeyEvent::from_char('\u{1b}'));
+        assert_eq!(first_candidate(&mut engine, "zhong"), "中");
+
+        // 方案加入新词库：重新解析方案并按新的词库组合重建词库
+        fs::write(dicts_dir.join("jargon.dict.yaml"), "---\n...\n锺\tzhong\t1\n").unwrap();
+        let schema = format!("{}translator:\n  dictionaries:\n    - name: jargon\n      priority: 1000\n", SCHEMA);
+        fs::write(&schema_file, schema).unwrap();
+        let events = service.check_now();
+        assert!(matches!(
+            &events[..],
+            [ReloadEvent::Reloaded { generation: 2, schema: true, dict_entries: Some(3), .. }]
+        ));
+        assert_eq!(first_candidate(&mut engine, "zhong"), "锺");
+
+        // 构建失败时保留上一版本
+        fs::write(&schema_file, "schema: [").unwrap();
+        fs::write(dicts_dir.join("8105.dict.yaml"), "").unwrap();
+        fs::write(dicts_dir.join("jargon.dict.yaml"), "").unwrap();
+        let events = service.check_now();
+        assert!(matches!(
+            &events[..],
+            [
+                ReloadEvent::Failed { kind: ResourceKind::Schema, .. },
+                ReloadEvent::Failed { kind: ResourceKind::Dict, .. }
+            ]
+        ));
+        assert_eq!(service.generation(), 2);
+        assert!(!engine.apply_reload());
+        assert_eq!(first_candidate(&mut engine, "zhong"), "锺");
+    }
+
+    #[test]
+    fn test_background_reload() {
+        let (_root, loader) = resources();
+        let dict_file = loader.dicts_dir().join("8105.dict.yaml");
+        let (sender, receiver) = mpsc::channel();
+        let sender = Mutex::new(sender);
+        let mut service = ReloadService::new(ResourceLoader::new(loader.resource_dir()))
+            .with_interval(Duration::from_millis(10))
+            .with_listener(move |event| {
+                let _ = sender.lock().unwrap().send(event.clone());
+            });
+        let mut handle = service.handle();
+        service.spawn().unwrap();
+        assert!(service.is_running());
+
+        fs::write(&dict_file, "---\n...\n中\tzhong\t5000\n国\tguo\t100\n").unwrap();
+        let deadline = Instant::now() + Duration::from_secs(5);
+        let event = receiver.recv_timeout(deadline - Instant::now()).unwrap();
+        assert!(matches!(event, ReloadEvent::Reloaded { dict_entries: Some(2), .. }));
+        let update = handle.take_update().unwrap();
+        assert!(update.schema.is_none());
+        assert_eq!(update.dicts.unwrap().stats().unwrap().total_entries, 2);
+        assert!(handle.take_update().is_none());
+
+        drop(service);
+    }
+
+    #[test]
+    fn test_schema_reload_without_dicts() {
+        let (_root, loader) = resources();
+        let dicts_dir = loader.dicts_dir();
+        let schema_file = loader.schemas_dir().join("test.schema.yaml");
+        let service = ReloadService::new(ResourceLoader::new(loader.resource_dir()));
+        let mut handle = service.handle();
+        // 创建句柄之后仍可配置
+        let service = service.with_schema("test").with_memory_budget(MemoryBudget::default());
+
+        let pinyin_engine = SimplifiedPinyinEngine::with_rime_dicts(dicts_dir.to_str().unwrap());
+        let mut engine = Engine::with_pinyin_engine(pinyin_engine).with_reload_handle(service.handle());
+        let dict = Arc::clone(engine.shared_dict());
+
+        // 方案换用不存在的词库：方案照常发布，词库构建失败，引擎不在按键时自行加载词库
+        let schema = format!("{}translator:\n  dictionary: missing\n", SCHEMA);
+        fs::write(&schema_file, schema).unwrap();
+        fs::write(dicts_dir.join("8105.dict.yaml"), "").unwrap();
+        let events = service.check_now();
+        assert!(matches!(
+            &events[..],
+            [ReloadEvent::Failed { kind: ResourceKind::Dict, .. }, ReloadEvent::Reloaded { schema: true, .. }]
+        ));
+        assert!(engine.apply_reload());
+        assert!(Arc::ptr_eq(engine.shared_dict(), &dict));
+        assert!(handle.take_update().unwrap().dicts.is_none());
+
+        // OpenCC 资源变化时通知引擎，不影响的文件变化不发布新版本
+        fs::create_dir_all(loader.opencc_dir()).unwrap();
+        fs::write(loader.opencc_dir().join("s2t.json"), "{}").unwrap();
+        let events = service.check_now();
+        assert!(matches!(&events[..], [ReloadEvent::Reloaded { opencc: true, schema: false, dict_entries: None, .. }]));
+        assert!(handle.take_update().unwrap().opencc);
+        fs::write(loader.schemas_dir().join("other.schema.yaml"), SCHEMA).unwrap();
+        assert!(service.check_now().is_empty());
+        assert!(handle.take_update().is_none());
+    }
+}
@@ -0,0 +1,191 @@
+//! 多会话管理
+//!
+//! 输入法服务通常为每个输入框创建一个会话。[`SessionManager`] 持有一份共享词库
+//! （[`SharedDict`]）与输入方案，新会话只创建输入缓冲、候选词、开关等会话状态，
+//! 词库数据在所有会话间共享，内存占用不随会话数增长
+
+use crate::Engine;
+use lingcode_core::schema_parser::Schema;
+use lingcode_dict::{DictEntry, DictStack};
+use lingcode_pinyin::{SharedDict, SimplifiedPinyinEngine};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// 会话标识
+pub type SessionId = u64;
+
+/// 基于共享词库的会话管理器
+pub struct SessionManager {
+    /// 所有会话共享的词库
+    dict: Arc<SharedDict>,
+    /// 新会话使用的输入方案
+    schema: Option<Arc<Schema>>,
+    /// 活动会话
+    sessions: HashMap<SessionId, Engine>,
+    /// 下一个会话标识
+    next_id: SessionId,
+}
+
+impl SessionManager {
+    /// 创建会话管理器，所有会话共享 `dict`
+    pub fn new(dict: Arc<SharedDict>) -> Self {
+        Self {
+            dict,
+            schema: None,
+            sessions: HashMap::new(),
+            next_id: 1,
+        }
+    }
+
+    /// 新会话使用的输入方案
+    ///
+    /// 方案的词库组合与当前词库不同时，按方案重新加载一次词库，之后由所有会话共享
+    pub fn with_schema(mut self, schema: Arc<Schema>) -> Self {
+        let stack = schema
+            .translator
+            .as_ref()
+            .map(DictStack::from_config)
+            .unwrap_or_default();
+        if stack != *self.dict.dict_stack() {
+            let mut engine = SimplifiedPinyinEngine::with_shared_dict(Arc::clone(&self.dict));
+            engine.set_dict_stack(stack);
+            self.dict = Arc::clone(engine.shared_dict());
+        }
+        self.schema = Some(schema);
+        self
+    }
+
+    /// 创建新会话
+    pub fn create_session(&mut self) -> SessionId {
+        let id = self.next_id;
+        self.next_id += 1;
+        let engine = self.new_engine();
+        self.sessions.insert(id, engine);
+        id
+    }
+
+    /// 创建共享词库的引擎，不由管理器持有
+    pub fn new_engine(&self) -> Engine {
+        let engine = Engine::with_shared_dict(Arc::clone(&self.dict));
+        match &self.schema {
+            Some(schema) => engine.with_schema(schema),
+            None => engine,
+        }
+    }
+
+    /// 获取会话
+    pub fn session(&mut self, id: SessionId) -> Option<&mut Engine> {
+        self.sessions.get_mut(&id)
+    }
+
+    /// 关闭会话，返回会话是否存在
+    pub fn close_session(&mut self, id: SessionId) -> bool {
+        self.sessions.remove(&id).is_some()
+    }
+
+    /// 活动会话数
+    pub fn len(&self) -> usize {
+        self.sessions.len()
+    }
+
+    /// 是否没有活动会话
+    pub fn is_empty(&self) -> bool {
+        self.sessions.is_empty()
+    }
+
+    /// 共享词库
+    pub fn shared_dict(&self) -> &Arc<SharedDict> {
+        &self.dict
+    }
+
+    /// 替换共享词库，已有会话随之切换
+    pub fn set_shared_dict(&mut self, dict: Arc<SharedDict>) {
+        for engine in self.sessions.values_mut() {
+            engine.set_shared_dict(Arc::clone(&dict));
+        }
+        self.dict = dict;
+    }
+
+    /// 向共享词库加入自定义词条，返回加入的条数
+    ///
+    /// 只复制并修改一次词库，所有会话随后共用修改后的词库
+    pub fn add_custom_entries(&mut self, entries: Vec<DictEntry>) -> usize {
+        if entries.is_empty() {
+            return 0;
+        }
+        let mut dict = SharedDict::clone(&self.dict);
+        let count = dict.add_custom_entries(entries);
+        self.set_shared_dict(Arc::new(dict));
+        count
+    }
+
+    /// 向共享词库的内置词典添加词条，所有会话随后共用修改后的词库
+    pub fn add_entry(&mut self, pinyin: &str, text: &str, weight: u32) {
+        let mut dict = SharedDict::clone(&self.dict);
+        dict.add_entry(pinyin, text, weight);
+        self.set_shared_dict(Arc::new(dict));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lingcode_core::keys::keysym;
+    use lingcode_core::types::KeyEvent;
+
+    #[test]
+    fn test_sessions_share_dict() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<SharedDict>();
+
+        let mut manager = SessionManager::new(Arc::new(SharedDict::new()));
+        let first = manager.create_session();
+        let second = manager.create_session();
+        assert_ne!(first, second);
+        assert_eq!(manager.len(), 2);
+
+        let dict = Arc::clone(manager.shared_dict());
+        assert!(Arc::ptr_eq(manager.session(first).unwrap().shared_dict(), &dict));
+        assert!(Arc::ptr_eq(manager.session(second).unwrap().shared_dict(), &dict));
+
+        // 输入状态各自独立
+        for c in "ni".chars() {
+            manager.session(first).unwrap().process_key(KeyEvent::from_char(c));
+        }
+        assert_eq!(manager.session(first).unwrap().input_buffer(), "ni");
+        assert_eq!(manager.session(second).unwrap().input_buffer(), "");
+        manager.session(first).unwrap().process_key(KeyEvent::from_keysym(keysym::ESCAPE));
+
+        // 替换词库后所有会话切换到新词库
+        let replaced = Arc::new(SharedDict::new());
+        manager.set_shared_dict(Arc::clone(&replaced));
+        assert!(Arc::ptr_eq(manager.session(second).unwrap().shared_dict(), &replaced));
+
+        // 通过管理器修改词库只复制一次，各会话共用同一份词库与联想索引
+        let added = manager.add_custom_entries(vec![DictEntry {
+            text: "灵码".to_string(),
+            pinyin: "lingma".to_string(),
+            weight: 500,
+        }]);
+        assert_eq!(added, 1);
+        manager.add_entry("ceshi", "测试", 300);
+        let dict = Arc::clone(manager.shared_dict());
+        assert!(Arc::ptr_eq(manager.session(first).unwrap().shared_dict(), &dict));
+        assert!(Arc::ptr_eq(manager.session(second).unwrap().shared_dict(), &dict));
+        assert!(Arc::ptr_eq(
+            &SharedDict::wait_for_phrase_index(manager.session(first).unwrap().shared_dict()),
+            &SharedDict::wait_for_phrase_index(manager.session(second).unwrap().shared_dict()),
+        ));
+        let engine = manager.session(second).unwrap();
+        for c in "lingma".chars() {
+            engine.process_key(KeyEvent::from_char(c));
+        }
+        assert!(engine.candidates().iter().any(|c| c.text == "灵码"));
+        engine.process_key(KeyEvent::from_keysym(keysym::ESCAPE));
+
+        assert!(manager.close_session(first));
+        assert!(!manager.close_session(first));
+        assert!(manager.session(first).is_none());
+        assert_eq!(manager.len(), 1);
+    }
+}
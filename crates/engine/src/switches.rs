@@ -0,0 +1,73 @@
+//! 开关状态
+//!
+//! 对应 Rime 方案中的 `switches`，如 `ascii_mode`、`full_shape`、`ascii_punct`
+
+use lingcode_core::schema_parser::Switch;
+use std::collections::HashMap;
+
+/// 开关状态表
+#[derive(Debug, Clone, Default)]
+pub struct Switches {
+    states: HashMap<String, bool>,
+}
+
+impl Switches {
+    /// 创建空的开关表（所有开关默认关闭）
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 根据方案中的开关定义初始化（`reset` 非 0 表示开启）
+    pub fn from_schema(switches: &[Switch]) -> Self {
+        let mut result = Self::new();
+        for switch in switches {
+            result.set(&switch.name, switch.reset != 0);
+        }
+        result
+    }
+
+    /// 获取开关状态，未定义的开关视为关闭
+    pub fn get(&self, name: &str) -> bool {
+        self.states.get(name).copied().unwrap_or(false)
+    }
+
+    /// 设置开关状态
+    pub fn set(&mut self, name: &str, value: bool) {
+        self.states.insert(name.to_string(), value);
+    }
+
+    /// 切换开关状态，返回切换后的值
+    pub fn toggle(&mut self, name: &str) -> bool {
+        let value = !self.get(name);
+        self.set(name, value);
+        value
+    }
+
+    /// 遍历所有已定义的开关
+    pub fn iter(&self) -> impl Iterator<Item = (&str, bool)> {
+        self.states.iter().map(|(name, value)| (name.as_str(), *value))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_switches_from_schema() {
+        let switches = Switches::from_schema(&[
+            Switch { name: "ascii_mode".to_string(), reset: 0, states: vec![] },
+            Switch { name: "full_shape".to_string(), reset: 1, states: vec![] },
+        ]);
+        assert!(!switches.get("ascii_mode"));
+        assert!(switches.get("full_shape"));
+        assert!(!switches.get("undefined"));
+    }
+
+    #[test]
+    fn test_toggle() {
+        let mut switches = Switches::new();
+        assert!(switches.toggle("ascii_punct"));
+        assert!(!switches.toggle("ascii_punct"));
+    }
+}
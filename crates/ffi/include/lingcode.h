@@ -6,6 +6,33 @@
 #include <stdint.h>
 #include <stdlib.h>
 
+/**
+ * Dictionary loading progress, see `lingcode_engine_with_dicts_async`
+ */
+typedef struct LingCodeLoadProgress {
+  /**
+   * Number of dictionary files loaded so far
+   */
+  int files_done;
+  /**
+   * Number of dictionary files to load
+   */
+  int files_total;
+  /**
+   * Number of entries loaded so far
+   */
+  uint64_t entries_loaded;
+  /**
+   * 1 once every file has been loaded
+   */
+  int finished;
+} LingCodeLoadProgress;
+
+/**
+ * Progress callback, invoked on the loading thread with the `user_data` passed at creation
+ */
+typedef void (*LingCodeLoadCallback)(const struct LingCodeLoadProgress *progress, void *user_data);
+
 /**
  * Create a new input method engine
  *
@@ -16,9 +43,102 @@ void *lingcode_engine_new(void);
 
 /**
  * Create engine with Rime dictionaries
+ *
+ * Engines created for the same `dict_dir` share one copy of the dictionaries,
+ * which is loaded by the first call and released with the last engine using it.
  */
 void *lingcode_engine_with_dicts(const char *dict_dir);
 
+/**
+ * Create engine with Rime dictionaries under a memory budget, for memory-constrained devices
+ *
+ * With `max_bytes` > 0, entries are stored in page files under `page_dir` (NULL for the
+ * system temp directory; on Android pass the app's cache directory) and read on demand.
+ * Recently used pages, the page table, the language model and the prediction index built
+ * from the entries together stay within about `max_bytes`. Entries with a weight below
+ * `min_weight` are dropped at load time. Use `lingcode_get_dict_memory_usage` to check
+ * the memory actually used.
+ *
+ * # Safety
+ * `dict_dir` and `page_dir` must be NULL or NUL-terminated UTF-8 strings.
+ * The returned pointer must be freed with `lingcode_engine_free`
+ */
+void *lingcode_engine_with_dicts_budget(const char *dict_dir,
+                                        uint64_t max_bytes,
+                                        uint32_t min_weight,
+                                        const char *page_dir);
+
+/**
+ * Get the memory used by the engine's dictionaries in bytes (estimated), 0 without dictionaries
+ *
+ * Includes the language model and prediction index built from the dictionaries.
+ *
+ * # Safety
+ * `engine` must be a valid engine handle
+ */
+uint64_t lingcode_get_dict_memory_usage(void *engine);
+
+/**
+ * Create a lightweight session sharing the dictionaries of `engine`
+ *
+ * The session has its own input buffer, candidates and user dictionary.
+ *
+ * # Safety
+ * `engine` must be a valid engine handle. The returned pointer (NULL if `engine`
+ * is NULL) must be freed with `lingcode_engine_free`
+ */
+void *lingcode_session_new(void *engine);
+
+/**
+ * Create engine and load Rime dictionaries on a background thread
+ *
+ * The engine is usable immediately with the built-in dictionary. Files are loaded
+ * smallest first and merged as they finish, on the next `lingcode_process_key`.
+ * `callback` (may be NULL) is called from the loading thread after each file and
+ * periodically while large files load; poll `lingcode_get_load_progress` instead
+ * to update a status indicator from the UI thread.
+ *
+ * # Safety
+ * `dict_dir` must be NULL or a NUL-terminated UTF-8 string; `user_data` must stay
+ * valid until loading finishes. The returned pointer must be freed with `lingcode_engine_free`
+ */
+void *lingcode_engine_with_dicts_async(const char *dict_dir,
+                                       LingCodeLoadCallback callback,
+                                       void *user_data);
+
+/**
+ * Create engine and load Rime dictionaries under a memory budget on a background thread
+ *
+ * Combines `lingcode_engine_with_dicts_budget` and `lingcode_engine_with_dicts_async`:
+ * the engine is usable immediately and the dictionaries are loaded into page files as
+ * described for the budget variant.
+ *
+ * # Safety
+ * `dict_dir` and `page_dir` must be NULL or NUL-terminated UTF-8 strings; `user_data`
+ * must stay valid until loading finishes. The returned pointer must be freed with
+ * `lingcode_engine_free`
+ */
+void *lingcode_engine_with_dicts_budget_async(const char *dict_dir,
+                                              uint64_t max_bytes,
+                                              uint32_t min_weight,
+                                              const char *page_dir,
+                                              LingCodeLoadCallback callback,
+                                              void *user_data);
+
+/**
+ * Get the background dictionary loading progress
+ *
+ * Merges files that have finished loading. `progress` (may be NULL) is filled with
+ * the current progress, or zeroes with `finished` set when nothing is loading.
+ *
+ * # Returns
+ * 1 while dictionaries are loading, 0 otherwise
+ *
+ * # Safety
+ * `engine` must be a valid engine handle and `progress` NULL or writable
+ */
+int lingcode_get_load_progress(void *engine, struct LingCodeLoadProgress *progress);
+
 /**
  * Free the input method engine
  *
@@ -81,6 +201,99 @@ int lingcode_backspace(void *engine);
  */
 void lingcode_clear(void *engine);
 
+/**
+ * Load (or create) the journaled user dictionary stored in `dir`
+ *
+ * Usage recorded afterwards, as well as deleted, pinned and added words,
+ * is persisted to this directory.
+ *
+ * # Returns
+ * 1 on success, -1 if `dir` holds encrypted data (see
+ * `lingcode_load_user_dict_encrypted`), 0 on other errors.
+ * On failure usage is still learned for this session but not persisted.
+ *
+ * # Safety
+ * `engine` must be a valid engine handle and `dir` a NUL-terminated UTF-8 string
+ */
+int lingcode_load_user_dict(void *engine, const char *dir);
+
+/**
+ * Load (or create) an encrypted journaled user dictionary stored in `dir`
+ *
+ * The key is either `key_len` raw bytes at `key` (32 bytes, e.g. kept in the
+ * system keychain) or, when `key` is null, derived from `passphrase`.
+ * Plaintext data already in `dir` is encrypted on load.
+ *
+ * # Returns
+ * 1 on success, -1 if the key is wrong, 0 on other errors.
+ * On failure the previously loaded user dictionary is kept.
+ *
+ * # Safety
+ * `engine` must be a valid engine handle, `key` null or valid for `key_len` bytes,
+ * and `dir` / `passphrase` NUL-terminated UTF-8 strings
+ */
+int lingcode_load_user_dict_encrypted(void *engine,
+                                      const char *dir,
+                                      const uint8_t *key,
+                                      uintptr_t key_len,
+                                      const char *passphrase);
+
+/**
+ * Re-encrypt the user dictionary with a new key
+ *
+ * The key is given as for `lingcode_load_user_dict_encrypted`; when both
+ * `key` and `passphrase` are null the dictionary is stored unencrypted.
+ *
+ * # Returns
+ * 1 on success, 0 on error
+ *
+ * # Safety
+ * `engine` must be a valid engine handle, `key` null or valid for `key_len` bytes,
+ * and `passphrase` null or a NUL-terminated UTF-8 string
+ */
+int lingcode_rotate_user_dict_key(void *engine,
+                                  const uint8_t *key,
+                                  uintptr_t key_len,
+                                  const char *passphrase);
+
+/**
+ * Delete the candidate at `index` for the current input from the user dictionary
+ *
+ * Candidates coming from the system dictionaries are suppressed for this
+ * input until the user selects them again.
+ *
+ * # Returns
+ * 1 if a candidate was deleted, 0 otherwise
+ *
+ * # Safety
+ * `engine` must be a valid engine handle
+ */
+int lingcode_delete_candidate(void *engine, int index);
+
+/**
+ * Pin the candidate at `index` for the current input to `position` (0-based)
+ *
+ * A negative `position` removes the pin.
+ *
+ * # Returns
+ * 1 if the candidate was pinned or unpinned, 0 otherwise
+ *
+ * # Safety
+ * `engine` must be a valid engine handle
+ */
+int lingcode_pin_candidate(void *engine, int index, int position);
+
+/**
+ * Add a user word `text` typed with `code`
+ *
+ * # Returns
+ * 1 if the word was added, 0 if the arguments are invalid or no user dictionary is loaded
+ *
+ * # Safety
+ * `engine` must be a valid engine handle; `text` and `code` must be NUL-terminated UTF-8 strings
+ */
+int lingcode_add_user_word(void *engine, const char *text, const char *code);
+
 /**
  * Free a string returned by the API
  *
@@ -6,9 +6,14 @@
 
 use libc::{c_char, c_int, c_void};
 use std::ffi::CString;
+use std::collections::HashMap;
+use std::path::PathBuf;
 use std::ptr;
+use std::sync::{Arc, Mutex, OnceLock, Weak};
 
-use lingcode_pinyin::PinyinEngine;
+use lingcode_core::error::LingCodeError;
+use lingcode_dict::{DictCipher, LoadProgress, MemoryBudget, ProgressCallback};
+use lingcode_pinyin::{PinyinEngine, SharedDict};
 use lingcode_pinyin::simplified::SimplifiedPinyinEngine;
 
 /// Opaque handle to the input method engine
@@ -23,42 +28,250 @@ pub struct LingCodeEngine {
 /// The returned pointer must be freed with `lingcode_engine_free`
 #[no_mangle]
 pub extern "C" fn lingcode_engine_new() -> *mut c_void {
-    let engine = SimplifiedPinyinEngine::new();
-
-    let engine = LingCodeEngine {
-        engine,
-        input_buffer: String::new(),
-    };
-    let boxed = Box::new(engine);
-    Box::into_raw(boxed) as *mut c_void
+    new_engine(SimplifiedPinyinEngine::with_shared_dict(shared_dict(None, MemoryBudget::default())))
 }
 
 /// Create engine with Rime dictionaries
+///
+/// Engines created for the same `dict_dir` share one copy of the dictionaries,
+/// which is loaded by the first call and released with the last engine using it.
 #[no_mangle]
 pub extern "C" fn lingcode_engine_with_dicts(dict_dir: *const c_char) -> *mut c_void {
-    let dict_dir = if dict_dir.is_null() {
-        None
-    } else {
-        unsafe {
-            std::ffi::CStr::from_ptr(dict_dir)
-                .to_str()
-                .ok()
-                .map(|s| s.to_string())
-        }
-    };
+    let dict_dir = unsafe { c_str(dict_dir) };
+    new_engine(SimplifiedPinyinEngine::with_shared_dict(shared_dict(dict_dir, MemoryBudget::default())))
+}
 
-    let mut engine = SimplifiedPinyinEngine::new();
+/// Create engine with Rime dictionaries under a memory budget, for memory-constrained devices
+///
+/// With `max_bytes` > 0, entries are stored in page files under `page_dir` (NULL for the
+/// system temp directory; on Android pass the app's cache directory) and read on demand.
+/// Recently used pages, the page table, the language model and the prediction index built
+/// from the entries together stay within about `max_bytes`. Entries with a weight below
+/// `min_weight` are dropped at load time. Use `lingcode_get_dict_memory_usage` to check
+/// the memory actually used.
+///
+/// # Safety
+/// `dict_dir` and `page_dir` must be NULL or NUL-terminated UTF-8 strings.
+/// The returned pointer must be freed with `lingcode_engine_free`
+#[no_mangle]
+pub unsafe extern "C" fn lingcode_engine_with_dicts_budget(
+    dict_dir: *const c_char,
+    max_bytes: u64,
+    min_weight: u32,
+    page_dir: *const c_char,
+) -> *mut c_void {
+    let budget = memory_budget(max_bytes, min_weight, page_dir);
+    new_engine(SimplifiedPinyinEngine::with_shared_dict(shared_dict(c_str(dict_dir), budget)))
+}
 
-    if let Some(dir) = dict_dir {
-        engine.load_rime_dicts(&dir);
+/// Memory budget from the C arguments, see `lingcode_engine_with_dicts_budget`
+unsafe fn memory_budget(max_bytes: u64, min_weight: u32, page_dir: *const c_char) -> MemoryBudget {
+    let budget = MemoryBudget::default()
+        .with_max_bytes(max_bytes as usize)
+        .with_min_weight(min_weight);
+    match c_str(page_dir) {
+        Some(page_dir) => budget.with_page_dir(PathBuf::from(page_dir)),
+        None => budget,
     }
+}
 
+/// Get the memory used by the engine's dictionaries in bytes (estimated), 0 without dictionaries
+///
+/// Includes the language model and prediction index built from the dictionaries.
+///
+/// # Safety
+/// `engine` must be a valid engine handle
+#[no_mangle]
+pub unsafe extern "C" fn lingcode_get_dict_memory_usage(engine: *mut c_void) -> u64 {
+    if engine.is_null() {
+        return 0;
+    }
+    let engine = &*(engine as *mut LingCodeEngine);
+    engine.engine.dict_stats().map_or(0, |stats| stats.memory_bytes as u64)
+}
+
+/// Create a lightweight session sharing the dictionaries of `engine`
+///
+/// The session has its own input buffer, candidates and user dictionary.
+///
+/// # Safety
+/// `engine` must be a valid engine handle. The returned pointer (NULL if `engine`
+/// is NULL) must be freed with `lingcode_engine_free`
+#[no_mangle]
+pub unsafe extern "C" fn lingcode_session_new(engine: *mut c_void) -> *mut c_void {
+    if engine.is_null() {
+        return ptr::null_mut();
+    }
+    let engine = &*(engine as *mut LingCodeEngine);
+    let dict = Arc::clone(engine.engine.shared_dict());
+    new_engine(SimplifiedPinyinEngine::with_shared_dict(dict))
+}
+
+/// Dictionary directory ("" for the built-in dictionary) and memory budget
+type DictKey = (String, MemoryBudget);
+
+/// Dictionaries shared by engines
+static SHARED_DICTS: OnceLock<Mutex<HashMap<DictKey, Weak<SharedDict>>>> = OnceLock::new();
+
+/// Get the shared dictionaries for `dict_dir`, loading them if no engine uses them
+fn shared_dict(dict_dir: Option<&str>, budget: MemoryBudget) -> Arc<SharedDict> {
+    let key = (dict_dir.unwrap_or_default().to_string(), budget.clone());
+    let mut dicts = SHARED_DICTS.get_or_init(Default::default).lock().unwrap();
+    if let Some(dict) = dicts.get(&key).and_then(Weak::upgrade) {
+        return dict;
+    }
+    let dict = match dict_dir {
+        Some(dir) => {
+            let mut engine = SimplifiedPinyinEngine::new().with_memory_budget(budget);
+            engine.load_rime_dicts(dir);
+            Arc::clone(engine.shared_dict())
+        }
+        None => Arc::new(SharedDict::new().with_memory_budget(budget)),
+    };
+    dicts.retain(|_, dict| dict.strong_count() > 0);
+    dicts.insert(key, Arc::downgrade(&dict));
+    dict
+}
+
+fn new_engine(engine: SimplifiedPinyinEngine) -> *mut c_void {
     let engine = LingCodeEngine {
         engine,
         input_buffer: String::new(),
     };
-    let boxed = Box::new(engine);
-    Box::into_raw(boxed) as *mut c_void
+    Box::into_raw(Box::new(engine)) as *mut c_void
+}
+
+/// Dictionary loading progress, see `lingcode_engine_with_dicts_async`
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LingCodeLoadProgress {
+    /// Number of dictionary files loaded so far
+    pub files_done: c_int,
+    /// Number of dictionary files to load
+    pub files_total: c_int,
+    /// Number of entries loaded so far
+    pub entries_loaded: u64,
+    /// 1 once every file has been loaded
+    pub finished: c_int,
+}
+
+impl From<&LoadProgress> for LingCodeLoadProgress {
+    fn from(progress: &LoadProgress) -> Self {
+        Self {
+            files_done: progress.files_done as c_int,
+            files_total: progress.files_total as c_int,
+            entries_loaded: progress.entries_loaded as u64,
+            finished: progress.finished as c_int,
+        }
+    }
+}
+
+/// Progress callback, invoked on the loading thread with the `user_data` passed at creation
+pub type LingCodeLoadCallback = Option<extern "C" fn(progress: *const LingCodeLoadProgress, user_data: *mut c_void)>;
+
+/// `user_data` is owned by the frontend, which must keep it valid while loading
+struct UserData(*mut c_void);
+
+unsafe impl Send for UserData {}
+
+impl UserData {
+    fn get(&self) -> *mut c_void {
+        self.0
+    }
+}
+
+/// Create engine and load Rime dictionaries on a background thread
+///
+/// The engine is usable immediately with the built-in dictionary. Files are loaded
+/// smallest first and merged as they finish, on the next `lingcode_process_key`.
+/// `callback` (may be NULL) is called from the loading thread after each file and
+/// periodically while large files load; poll `lingcode_get_load_progress` instead
+/// to update a status indicator from the UI thread.
+///
+/// # Safety
+/// `dict_dir` must be NULL or a NUL-terminated UTF-8 string; `user_data` must stay
+/// valid until loading finishes. The returned pointer must be freed with `lingcode_engine_free`
+#[no_mangle]
+pub unsafe extern "C" fn lingcode_engine_with_dicts_async(
+    dict_dir: *const c_char,
+    callback: LingCodeLoadCallback,
+    user_data: *mut c_void,
+) -> *mut c_void {
+    load_dicts_async(c_str(dict_dir), MemoryBudget::default(), callback, user_data)
+}
+
+/// Create engine and load Rime dictionaries under a memory budget on a background thread
+///
+/// Combines `lingcode_engine_with_dicts_budget` and `lingcode_engine_with_dicts_async`:
+/// the engine is usable immediately and the dictionaries are loaded into page files as
+/// described for the budget variant.
+///
+/// # Safety
+/// `dict_dir` and `page_dir` must be NULL or NUL-terminated UTF-8 strings; `user_data`
+/// must stay valid until loading finishes. The returned pointer must be freed with
+/// `lingcode_engine_free`
+#[no_mangle]
+pub unsafe extern "C" fn lingcode_engine_with_dicts_budget_async(
+    dict_dir: *const c_char,
+    max_bytes: u64,
+    min_weight: u32,
+    page_dir: *const c_char,
+    callback: LingCodeLoadCallback,
+    user_data: *mut c_void,
+) -> *mut c_void {
+    let budget = memory_budget(max_bytes, min_weight, page_dir);
+    load_dicts_async(c_str(dict_dir), budget, callback, user_data)
+}
+
+fn load_dicts_async(
+    dict_dir: Option<&str>,
+    budget: MemoryBudget,
+    callback: LingCodeLoadCallback,
+    user_data: *mut c_void,
+) -> *mut c_void {
+    let mut engine = SimplifiedPinyinEngine::with_shared_dict(shared_dict(None, budget));
+
+    if let Some(dir) = dict_dir {
+        let on_progress = callback.map(|callback| {
+            let user_data = UserData(user_data);
+            Box::new(move |progress: &LoadProgress| {
+                let progress = LingCodeLoadProgress::from(progress);
+                callback(&progress, user_data.get());
+            }) as ProgressCallback
+        });
+        engine.load_rime_dicts_in_background(dir, on_progress);
+    }
+    new_engine(engine)
+}
+
+/// Get the background dictionary loading progress
+///
+/// Merges files that have finished loading. `progress` (may be NULL) is filled with
+/// the current progress, or zeroes with `finished` set when nothing is loading.
+///
+/// # Returns
+/// 1 while dictionaries are loading, 0 otherwise
+///
+/// # Safety
+/// `engine` must be a valid engine handle and `progress` NULL or writable
+#[no_mangle]
+pub unsafe extern "C" fn lingcode_get_load_progress(engine: *mut c_void, progress: *mut LingCodeLoadProgress) -> c_int {
+    if engine.is_null() {
+        return 0;
+    }
+    let engine = &mut *(engine as *mut LingCodeEngine);
+    engine.engine.poll_dict_loading();
+    let current = engine.engine.dict_load_progress();
+    if !progress.is_null() {
+        *progress = match &current {
+            Some(current) => current.into(),
+            None => LingCodeLoadProgress {
+                finished: 1,
+                ..Default::default()
+            },
+        };
+    }
+    current.is_some() as c_int
 }
 
 /// Free the input method engine
@@ -90,6 +303,7 @@ pub extern "C" fn lingcode_process_key(engine: *mut c_void, key: c_char) -> c_in
     }
 
     let engine = unsafe { &mut *(engine as *mut LingCodeEngine) };
+    engine.engine.poll_dict_loading();
     let key = key as u8 as char;
 
     // Handle lowercase letters
@@ -238,6 +452,230 @@ pub extern "C" fn lingcode_clear(engine: *mut c_void) {
     engine.input_buffer.clear();
 }
 
+/// Load (or create) the journaled user dictionary stored in `dir`
+///
+/// Usage recorded afterwards, as well as deleted, pinned and added words,
+/// is persisted to this directory.
+///
+/// # Returns
+/// 1 on success, -1 if `dir` holds encrypted data (see
+/// `lingcode_load_user_dict_encrypted`), 0 on other errors.
+/// On failure usage is still learned for this session but not persisted.
+///
+/// # Safety
+/// `engine` must be a valid engine handle and `dir` a NUL-terminated UTF-8 string
+#[no_mangle]
+pub unsafe extern "C" fn lingcode_load_user_dict(engine: *mut c_void, dir: *const c_char) -> c_int {
+    if engine.is_null() {
+        return 0;
+    }
+    let dir = match c_str(dir) {
+        Some(dir) => dir,
+        None => return 0,
+    };
+
+    let engine = &mut *(engine as *mut LingCodeEngine);
+    status_code(engine.engine.load_user_dict_store(dir))
+}
+
+/// Load (or create) an encrypted journaled user dictionary stored in `dir`
+///
+/// The key is either `key_len` raw bytes at `key` (32 bytes, e.g. kept in the
+/// system keychain) or, when `key` is null, derived from `passphrase`.
+/// Plaintext data already in `dir` is encrypted on load.
+///
+/// # Returns
+/// 1 on success, -1 if the key is wrong, 0 on other errors.
+/// On failure the previously loaded user dictionary is kept.
+///
+/// # Safety
+/// `engine` must be a valid engine handle, `key` null or valid for `key_len` bytes,
+/// and `dir` / `passphrase` NUL-terminated UTF-8 strings
+#[no_mangle]
+pub unsafe extern "C" fn lingcode_load_user_dict_encrypted(
+    engine: *mut c_void,
+    dir: *const c_char,
+    key: *const u8,
+    key_len: usize,
+    passphrase: *const c_char,
+) -> c_int {
+    if engine.is_null() {
+        return 0;
+    }
+    let dir = match c_str(dir) {
+        Some(dir) => dir,
+        None => return 0,
+    };
+    let cipher = match cipher_from(key, key_len, passphrase) {
+        Some(Ok(cipher)) => cipher,
+        _ => return 0,
+    };
+
+    let engine = &mut *(engine as *mut LingCodeEngine);
+    status_code(engine.engine.load_encrypted_user_dict_store(dir, cipher))
+}
+
+/// Re-encrypt the user dictionary with a new key
+///
+/// The key is given as for `lingcode_load_user_dict_encrypted`; when both
+/// `key` and `passphrase` are null the dictionary is stored unencrypted.
+///
+/// # Returns
+/// 1 on success, 0 on error
+///
+/// # Safety
+/// `engine` must be a valid engine handle, `key` null or valid for `key_len` bytes,
+/// and `passphrase` null or a NUL-terminated UTF-8 string
+#[no_mangle]
+pub unsafe extern "C" fn lingcode_rotate_user_dict_key(
+    engine: *mut c_void,
+    key: *const u8,
+    key_len: usize,
+    passphrase: *const c_char,
+) -> c_int {
+    if engine.is_null() {
+        return 0;
+    }
+    let cipher = match cipher_from(key, key_len, passphrase) {
+        Some(Ok(cipher)) => Some(cipher),
+        Some(Err(_)) => return 0,
+        None => None,
+    };
+
+    let engine = &mut *(engine as *mut LingCodeEngine);
+    engine.engine.rotate_user_dict_key(cipher).is_ok() as c_int
+}
+
+/// Delete the candidate at `index` for the current input from the user dictionary
+///
+/// Candidates coming from the system dictionaries are suppressed for this
+/// input until the user selects them again.
+///
+/// # Returns
+/// 1 if a candidate was deleted, 0 otherwise
+///
+/// # Safety
+/// `engine` must be a valid engine handle
+#[no_mangle]
+pub unsafe extern "C" fn lingcode_delete_candidate(engine: *mut c_void, index: c_int) -> c_int {
+    if engine.is_null() {
+        return 0;
+    }
+
+    let engine = &mut *(engine as *mut LingCodeEngine);
+    if engine.engine.user_dict().is_none() {
+        return 0;
+    }
+    let text = match candidate_text(engine, index) {
+        Some(text) => text,
+        None => return 0,
+    };
+    engine.engine.delete_word(&text, &engine.input_buffer);
+    1
+}
+
+/// Pin the candidate at `index` for the current input to `position` (0-based)
+///
+/// A negative `position` removes the pin.
+///
+/// # Returns
+/// 1 if the candidate was pinned or unpinned, 0 otherwise
+///
+/// # Safety
+/// `engine` must be a valid engine handle
+#[no_mangle]
+pub unsafe extern "C" fn lingcode_pin_candidate(engine: *mut c_void, index: c_int, position: c_int) -> c_int {
+    if engine.is_null() {
+        return 0;
+    }
+
+    let engine = &mut *(engine as *mut LingCodeEngine);
+    if engine.engine.user_dict().is_none() {
+        return 0;
+    }
+    let text = match candidate_text(engine, index) {
+        Some(text) => text,
+        None => return 0,
+    };
+    let position = usize::try_from(position).ok();
+    engine.engine.pin_word(&text, &engine.input_buffer, position);
+    1
+}
+
+/// Add a user word `text` typed with `code`
+///
+/// # Returns
+/// 1 if the word was added, 0 if the arguments are invalid or no user dictionary is loaded
+///
+/// # Safety
+/// `engine` must be a valid engine handle; `text` and `code` must be NUL-terminated UTF-8 strings
+#[no_mangle]
+pub unsafe extern "C" fn lingcode_add_user_word(engine: *mut c_void, text: *const c_char, code: *const c_char) -> c_int {
+    if engine.is_null() {
+        return 0;
+    }
+    let (text, code) = match (c_str(text), c_str(code)) {
+        (Some(text), Some(code)) if !text.is_empty() && !code.is_empty() => (text, code),
+        _ => return 0,
+    };
+
+    let engine = &mut *(engine as *mut LingCodeEngine);
+    if engine.engine.user_dict().is_none() {
+        return 0;
+    }
+    engine.engine.add_word(text, code);
+    1
+}
+
+/// Text of the candidate at `index` for the current input
+fn candidate_text(engine: &LingCodeEngine, index: c_int) -> Option<String> {
+    if engine.input_buffer.is_empty() {
+        return None;
+    }
+    let index = usize::try_from(index).ok()?;
+    let candidates = engine.engine.get_candidates(&engine.input_buffer).ok()?;
+    candidates.get(index).map(|c| c.text.clone())
+}
+
+/// Build a cipher from raw key bytes or, when `key` is null, a passphrase
+///
+/// Returns `None` when neither is given.
+///
+/// # Safety
+/// `key` must be null or valid for `key_len` bytes, `passphrase` null or a NUL-terminated string
+unsafe fn cipher_from(
+    key: *const u8,
+    key_len: usize,
+    passphrase: *const c_char,
+) -> Option<Result<DictCipher, LingCodeError>> {
+    if !key.is_null() {
+        return Some(DictCipher::from_key_bytes(std::slice::from_raw_parts(key, key_len)));
+    }
+    c_str(passphrase).map(DictCipher::from_passphrase)
+}
+
+/// Map a load result to 1 (success), -1 (wrong key) or 0 (other errors)
+fn status_code(result: anyhow::Result<()>) -> c_int {
+    match result {
+        Ok(()) => 1,
+        Err(e) => match e.downcast_ref::<LingCodeError>() {
+            Some(LingCodeError::DecryptionError(_)) => -1,
+            _ => 0,
+        },
+    }
+}
+
+/// Borrow a C string as UTF-8, `None` for null or invalid strings
+///
+/// # Safety
+/// `s` must be null or a valid NUL-terminated string that outlives the result
+unsafe fn c_str<'a>(s: *const c_char) -> Option<&'a str> {
+    if s.is_null() {
+        return None;
+    }
+    std::ffi::CStr::from_ptr(s).to_str().ok()
+}
+
 /// Free a string returned by the API
 ///
 /// # Safety
@@ -3,10 +3,17 @@ pub mod simplified;
 pub mod traditional;
 pub mod dict;
 pub mod matcher;
+pub mod phrase_index;
+pub mod ranking;
+pub mod shared;
+pub mod tones;
 
 pub use simplified::SimplifiedPinyinEngine;
 pub use traditional::TraditionalPinyinEngine;
 pub use matcher::PinyinMatcher;
+pub use phrase_index::PhraseIndex;
+pub use ranking::{RankingPolicy, ScoreBreakdown};
+pub use shared::SharedDict;
 pub use dict::PinyinDict;
 
 use lingcode_core::{
@@ -0,0 +1,105 @@
+//! 联想词组索引
+//!
+//! 按文字排序的词组表，以二分查找得到以某段文字开头的所有词组，
+//! 每个词组只存一份，不为每个前缀复制后续文字
+
+use std::mem::size_of;
+
+/// 参与联想的词组最大字数
+pub const MAX_PHRASE_CHARS: usize = 8;
+
+/// 按文字排序的词组（文字, 权重）
+#[derive(Debug, Clone, Default)]
+pub struct PhraseIndex {
+    phrases: Vec<(Box<str>, u32)>,
+}
+
+impl PhraseIndex {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 由词组（文字, 权重）构建，同一词组保留最高权重，单字与过长的词组不收录
+    pub fn from_phrases<'a>(phrases: impl IntoIterator<Item = (&'a str, u32)>) -> Self {
+        Self::from_list(
+            phrases
+                .into_iter()
+                .filter(|(text, _)| is_phrase(text))
+                .map(|(text, weight)| (Box::from(text), weight))
+                .collect(),
+        )
+    }
+
+    /// 由未排序的词组表构建
+    pub(crate) fn from_list(mut list: Vec<(Box<str>, u32)>) -> Self {
+        list.sort_unstable_by(|a, b| a.0.cmp(&b.0).then_with(|| b.1.cmp(&a.1)));
+        list.dedup_by(|next, kept| next.0 == kept.0);
+        list.shrink_to_fit();
+        Self { phrases: list }
+    }
+
+    /// 加入一个词组，已有时保留较高的权重
+    pub fn insert(&mut self, text: &str, weight: u32) {
+        if !is_phrase(text) {
+            return;
+        }
+        match self.phrases.binary_search_by(|(t, _)| (**t).cmp(text)) {
+            Ok(i) => self.phrases[i].1 = self.phrases[i].1.max(weight),
+            Err(i) => self.phrases.insert(i, (Box::from(text), weight)),
+        }
+    }
+
+    /// 以 `prefix` 开头的词组的后续文字与权重（不含 `prefix` 本身）
+    pub fn continuations<'a>(&'a self, prefix: &'a str) -> impl Iterator<Item = (&'a str, u32)> + 'a {
+        let start = self.phrases.partition_point(|(text, _)| **text < *prefix);
+        self.phrases[start..]
+            .iter()
+            .take_while(move |(text, _)| text.starts_with(prefix))
+            .filter(move |(text, _)| text.len() > prefix.len())
+            .map(move |(text, weight)| (&text[prefix.len()..], *weight))
+    }
+
+    /// 词组数
+    pub fn len(&self) -> usize {
+        self.phrases.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.phrases.is_empty()
+    }
+
+    /// 占用的内存（字节，估算）
+    pub fn memory_bytes(&self) -> usize {
+        self.phrases.capacity() * size_of::<(Box<str>, u32)>()
+            + self.phrases.iter().map(|(text, _)| text.len()).sum::<usize>()
+    }
+}
+
+/// 一个词组占用的内存（字节，估算）
+pub(crate) fn phrase_bytes(text: &str) -> usize {
+    text.len() + size_of::<(Box<str>, u32)>()
+}
+
+/// 是否为参与联想的词组：2 到 [`MAX_PHRASE_CHARS`] 个字
+pub(crate) fn is_phrase(text: &str) -> bool {
+    (2..=MAX_PHRASE_CHARS).contains(&text.chars().count())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_continuations() {
+        let mut index = PhraseIndex::from_phrases([("中国人民", 80), ("中国", 100), ("中国人民", 60), ("中", 50)]);
+        index.insert("中国银行", 90);
+        index.insert("中华", 70);
+        assert_eq!(index.len(), 4);
+
+        let mut found: Vec<(&str, u32)> = index.continuations("中国").collect();
+        found.sort();
+        assert_eq!(found, vec![("人民", 80), ("银行", 90)]);
+        assert_eq!(index.continuations("中").count(), 4);
+        assert_eq!(index.continuations("你").count(), 0);
+    }
+}
@@ -0,0 +1,348 @@
+//! 候选词排序策略
+//!
+//! 各来源的权重量级不同（词库权重、用户词频、内置词典），排序前先按来源归一化为
+//! 对数概率，再乘以可配置的来源加权。最终得分记录在 `Candidate.weight` 中，
+//! 来源记录在 `Candidate.source` 中，便于解释与调整排序
+
+use lingcode_core::schema_parser::Ranking;
+use lingcode_dict::scoring::{default_scoring, scoring_from_config};
+use lingcode_dict::{DictSource, ScoringPolicy};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+
+/// 来源：用户词库
+pub const SOURCE_USER: &str = "user";
+/// 来源：内置词典
+pub const SOURCE_BUILTIN: &str = "builtin";
+/// 来源：整句搜索
+pub const SOURCE_SENTENCE: &str = "sentence";
+
+/// 默认前缀匹配折扣
+const DEFAULT_PREFIX_PENALTY: f64 = 0.5;
+
+/// 默认用户词库加权
+const DEFAULT_USER_BOOST: f64 = 20.0;
+
+/// 写入 `Candidate.weight` 时的得分偏移，使常见的对数概率映射为正整数
+const SCORE_OFFSET: f64 = 100.0;
+
+/// 写入 `Candidate.weight` 时的得分精度
+const SCORE_SCALE: f64 = 1000.0;
+
+/// 排序策略：来源加权、前缀匹配折扣与用户词频评分策略
+#[derive(Debug, Clone)]
+pub struct RankingPolicy {
+    /// 来源 -> 概率乘数
+    boosts: HashMap<String, f64>,
+    /// 词库来源 -> 按优先级得到的默认概率乘数（优先级 / 100），未配置加权时使用
+    source_priorities: HashMap<String, f64>,
+    /// 前缀匹配候选的概率乘数
+    prefix_penalty: f64,
+    /// 用户词频评分策略
+    user_scoring: Arc<dyn ScoringPolicy>,
+}
+
+impl RankingPolicy {
+    /// 默认策略：用户词库优先，外部词库按来源优先级加权
+    pub fn new() -> Self {
+        let boosts: HashMap<String, f64> = [
+            (SOURCE_USER, DEFAULT_USER_BOOST),
+            (SOURCE_BUILTIN, 1.0),
+            (SOURCE_SENTENCE, 1.0),
+        ]
+        .iter()
+        .map(|(source, boost)| (source.to_string(), *boost))
+        .collect();
+        let mut policy = Self {
+            boosts,
+            source_priorities: HashMap::new(),
+            prefix_penalty: DEFAULT_PREFIX_PENALTY,
+            user_scoring: default_scoring(),
+        };
+        for source in DictSource::presets() {
+            policy.set_source_priority(&source);
+        }
+        policy
+    }
+
+    /// 从方案配置创建，未配置的项沿用默认值
+    pub fn from_config(config: &Ranking) -> Self {
+        let mut policy = Self::new();
+        for (source, boost) in &config.boosts {
+            policy.set_boost(source, *boost);
+        }
+        if let Some(penalty) = config.prefix_penalty {
+            policy.prefix_penalty = penalty;
+        }
+        if let Some(ref user_frequency) = config.user_frequency {
+            policy.user_scoring = scoring_from_config(user_frequency);
+        }
+        policy
+    }
+
+    /// 设置来源加权（概率乘数，必须为正）
+    pub fn set_boost(&mut self, source: &str, boost: f64) {
+        if boost > 0.0 {
+            self.boosts.insert(source.to_string(), boost);
+        } else {
+            log::warn!("忽略无效的来源加权: {} = {}", source, boost);
+        }
+    }
+
+    /// 登记词库来源的优先级，未配置加权时按优先级 / 100 加权
+    pub fn set_source_priority(&mut self, source: &DictSource) {
+        self.source_priorities
+            .insert(source.id().to_string(), source.priority() as f64 / 100.0);
+    }
+
+    /// 清除已登记的词库优先级，恢复预设来源的优先级（更换词库组合时调用）
+    pub fn reset_source_priorities(&mut self) {
+        self.source_priorities.clear();
+        for source in DictSource::presets() {
+            self.set_source_priority(&source);
+        }
+    }
+
+    /// 来源加权：优先使用配置的加权，其次是词库优先级，其他来源为 1
+    pub fn boost(&self, source: &str) -> f64 {
+        self.boosts
+            .get(source)
+            .or_else(|| self.source_priorities.get(source))
+            .copied()
+            .unwrap_or(1.0)
+    }
+
+    /// 设置用户词频评分策略
+    pub fn set_user_scoring(&mut self, scoring: Arc<dyn ScoringPolicy>) {
+        self.user_scoring = scoring;
+    }
+
+    /// 用户词频评分策略
+    pub fn user_scoring(&self) -> &Arc<dyn ScoringPolicy> {
+        &self.user_scoring
+    }
+
+    /// 前缀匹配折扣
+    pub fn prefix_penalty(&self) -> f64 {
+        self.prefix_penalty
+    }
+
+    /// 对来源内归一化后的对数概率加上来源加权
+    pub fn score(&self, source: &str, log_prob: f64) -> f64 {
+        log_prob + self.boost(source).ln()
+    }
+}
+
+impl Default for RankingPolicy {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// 各来源的权重统计，用于把权重归一化为来源内的概率
+///
+/// 统计的必须是排序时使用的同一个量：词库来源为词条权重，用户词库为评分策略给出的得分
+#[derive(Debug, Clone, Default)]
+pub struct SourceNormalizer {
+    /// 来源 -> (权重总和, 词条数)
+    totals: HashMap<String, (f64, usize)>,
+}
+
+impl SourceNormalizer {
+    /// 创建空的统计
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 累加一个词条的权重，权重为负时扣除（用于撤销）
+    pub fn add(&mut self, source: &str, weight: f64) {
+        let (total, count) = self.totals.entry(source.to_string()).or_insert((0.0, 0));
+        *total = (*total + weight).max(0.0);
+        if weight >= 0.0 {
+            *count += 1;
+        } else {
+            *count = count.saturating_sub(1);
+        }
+    }
+
+    /// 把一个词条的权重从 `old` 换为 `new`，None 表示词条不存在（新增或删除词条）
+    pub fn replace(&mut self, source: &str, old: Option<f64>, new: Option<f64>) {
+        let (total, count) = self.totals.entry(source.to_string()).or_insert((0.0, 0));
+        *total = (*total - old.unwrap_or(0.0) + new.unwrap_or(0.0)).max(0.0);
+        match (old, new) {
+            (None, Some(_)) => *count += 1,
+            (Some(_), None) => *count = count.saturating_sub(1),
+            _ => {}
+        }
+    }
+
+    /// 来源内的对数概率（加一平滑，不超过 0）
+    pub fn log_prob(&self, source: &str, weight: f64) -> f64 {
+        let (total, count) = self.totals.get(source).copied().unwrap_or((0.0, 0));
+        ((weight.max(0.0) + 1.0) / (total + count as f64 + 1.0)).ln().min(0.0)
+    }
+}
+
+/// 候选词的得分明细
+///
+/// 总分 = 来源内对数概率 + ln(来源加权) + ln(匹配折扣) + 语言模型贡献
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ScoreBreakdown {
+    /// 来源（词库标识、`user`、`builtin` 或 `sentence`）
+    pub source: String,
+    /// 来源中的原始权重（用户词库为时间衰减后的词频分数）
+    pub raw_weight: f64,
+    /// 用户使用次数
+    pub user_count: Option<u32>,
+    /// 用户词频的时间衰减因子
+    pub time_decay: Option<f64>,
+    /// 来源内归一化后的对数概率（整句为语言模型给出的整句概率）
+    pub log_prob: f64,
+    /// 来源加权的对数
+    pub boost: f64,
+    /// 前缀匹配等折扣的对数（无折扣为 0）
+    pub penalty: f64,
+    /// 语言模型根据前文给出的提升
+    pub lm_contribution: f64,
+    /// 总分
+    pub total: f64,
+}
+
+impl ScoreBreakdown {
+    /// 创建得分明细
+    pub fn new(source: &str, raw_weight: f64, log_prob: f64) -> Self {
+        Self {
+            source: source.to_string(),
+            raw_weight,
+            user_count: None,
+            time_decay: None,
+            log_prob,
+            boost: 0.0,
+            penalty: 0.0,
+            lm_contribution: 0.0,
+            total: log_prob,
+        }
+    }
+
+    /// 设置来源加权（概率乘数）
+    pub fn with_boost(mut self, boost: f64) -> Self {
+        self.boost = boost.ln();
+        self.update_total()
+    }
+
+    /// 设置匹配折扣（概率乘数）
+    pub fn with_penalty(mut self, penalty: f64) -> Self {
+        self.penalty = penalty.ln();
+        self.update_total()
+    }
+
+    /// 设置语言模型贡献
+    pub fn with_lm_contribution(mut self, contribution: f64) -> Self {
+        self.lm_contribution = contribution;
+        self.update_total()
+    }
+
+    /// 设置用户词频信息
+    pub fn with_user_frequency(mut self, count: u32, time_decay: f64) -> Self {
+        self.user_count = Some(count);
+        self.time_decay = Some(time_decay);
+        self
+    }
+
+    fn update_total(mut self) -> Self {
+        self.total = self.log_prob + self.boost + self.penalty + self.lm_contribution;
+        self
+    }
+}
+
+impl fmt::Display for ScoreBreakdown {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "总分 {:.3} = 概率 {:.3} + 加权 {:.3} + 折扣 {:.3} + 前文 {:.3} [来源 {}, 权重 {:.1}",
+            self.total, self.log_prob, self.boost, self.penalty, self.lm_contribution, self.source, self.raw_weight
+        )?;
+        if let (Some(count), Some(decay)) = (self.user_count, self.time_decay) {
+            write!(f, ", 使用 {} 次, 衰减 {:.3}", count, decay)?;
+        }
+        write!(f, "]")
+    }
+}
+
+/// 将得分写入 `Candidate.weight`（保持大小顺序）
+pub fn score_to_weight(score: f64) -> u32 {
+    ((score + SCORE_OFFSET).max(0.0) * SCORE_SCALE).round() as u32
+}
+
+/// 从 `Candidate.weight` 还原得分
+pub fn weight_to_score(weight: u32) -> f64 {
+    weight as f64 / SCORE_SCALE - SCORE_OFFSET
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalization_makes_sources_comparable() {
+        let mut normalizer = SourceNormalizer::new();
+        // 词库 A 的权重比词库 B 大 1000 倍，归一化后同等常用的词得分相同
+        normalizer.add("base", 100_000.0);
+        normalizer.add("base", 100_000.0);
+        normalizer.add("ext", 100.0);
+        normalizer.add("ext", 100.0);
+        let a = normalizer.log_prob("base", 100_000.0);
+        let b = normalizer.log_prob("ext", 100.0);
+        assert!((a - b).abs() < 0.01);
+        assert!(a <= 0.0);
+    }
+
+    #[test]
+    fn test_normalizer_replace() {
+        let mut normalizer = SourceNormalizer::new();
+        normalizer.replace("user", None, Some(2.0));
+        normalizer.replace("user", Some(2.0), Some(5.0));
+        assert_eq!(normalizer.log_prob("user", 5.0), (6.0f64 / 7.0).ln());
+        normalizer.replace("user", Some(5.0), None);
+        assert_eq!(normalizer.log_prob("user", 0.0), 0.0);
+    }
+
+    #[test]
+    fn test_policy_boosts() {
+        let mut config = Ranking::default();
+        config.boosts.insert("ext".to_string(), 2.0);
+        config.prefix_penalty = Some(0.1);
+        let policy = RankingPolicy::from_config(&config);
+        assert_eq!(policy.boost("ext"), 2.0);
+        assert_eq!(policy.boost("base"), 1.0);
+        assert_eq!(policy.boost("unknown"), 1.0);
+        assert_eq!(policy.prefix_penalty(), 0.1);
+        assert!(policy.score("user", -3.0) > policy.score("tencent", -3.0));
+
+        // 新词库按优先级加权，配置的加权优先
+        let mut policy = RankingPolicy::from_config(&config);
+        policy.set_source_priority(&DictSource::new("jargon", 120));
+        policy.set_source_priority(&DictSource::new("ext", 10));
+        assert_eq!(policy.boost("jargon"), 1.2);
+        assert_eq!(policy.boost("ext"), 2.0);
+    }
+
+    #[test]
+    fn test_score_breakdown_total() {
+        let breakdown = ScoreBreakdown::new("base", 500.0, -4.0)
+            .with_boost(2.0)
+            .with_penalty(0.5)
+            .with_lm_contribution(0.25);
+        assert!((breakdown.total - (-4.0 + 0.25)).abs() < 1e-9);
+        assert!(breakdown.to_string().contains("来源 base"));
+    }
+
+    #[test]
+    fn test_score_weight_round_trip() {
+        let weight = score_to_weight(-4.25);
+        assert!((weight_to_score(weight) + 4.25).abs() < 1e-3);
+        assert!(score_to_weight(-1.0) > score_to_weight(-2.0));
+    }
+}
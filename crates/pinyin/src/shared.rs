@@ -0,0 +1,409 @@
+//! 共享词库数据
+//!
+//! 内置词典、外部词库以及由它们构建的语言模型与来源权重统计在加载后只读，
+//! 以 `Arc<SharedDict>` 在多个引擎（会话）与线程间共享；用户词库、排序策略等会话状态
+//! 留在各自的 [`SimplifiedPinyinEngine`](crate::SimplifiedPinyinEngine) 中。
+//! 内置词典、外部词库与语言模型各自以 `Arc` 持有，复制词库时不复制这些数据，
+//! 修改时只复制被修改的部分。单个引擎修改词库（如加入自定义词条）时写时复制，不影响其他会话；
+//! 多会话时应由会话管理器修改一次后发布给所有会话，避免每个会话各复制一份
+
+use crate::phrase_index::{is_phrase, phrase_bytes, PhraseIndex};
+use crate::ranking::{SourceNormalizer, SOURCE_BUILTIN};
+use crate::tones::ToneTable;
+use lingcode_core::candidate::Candidate;
+use lingcode_dict::{DictEntry, DictSource, DictStack, DictStats, LanguageModel, MemoryBudget, RimeDictLoader};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, OnceLock};
+
+/// 可在会话与线程间共享的只读词库数据
+#[derive(Clone)]
+pub struct SharedDict {
+    /// 拼音到汉字的映射表（内置基础词典）
+    pub(crate) builtin: Arc<HashMap<String, Vec<(Candidate, u32)>>>,
+    /// 雾凇拼音词库
+    pub(crate) rime: Option<Arc<RimeDictLoader>>,
+    /// 词库路径
+    pub(crate) dict_path: Option<String>,
+    /// 加载时使用的词库组合
+    pub(crate) dict_stack: DictStack,
+    /// 加入的自定义词条，更换外部词库时重新加入
+    pub(crate) custom_entries: Vec<DictEntry>,
+    /// 由词库构建的基础语言模型
+    pub(crate) model: Arc<LanguageModel>,
+    /// 词库各来源的权重统计
+    pub(crate) normalizer: SourceNormalizer,
+    /// 外部词库中带声调词条的汉字读音
+    pub(crate) tones: ToneTable,
+    /// 加载外部词库时使用的内存预算
+    pub(crate) memory_budget: MemoryBudget,
+    /// 联想词组索引，首次使用时在后台构建
+    phrase_index: LazyPhraseIndex,
+}
+
+/// 按需构建的联想词组索引
+#[derive(Default)]
+struct LazyPhraseIndex {
+    index: OnceLock<Arc<PhraseIndex>>,
+    building: AtomicBool,
+}
+
+/// 复制时只复制已构建好的索引
+impl Clone for LazyPhraseIndex {
+    fn clone(&self) -> Self {
+        let lazy = Self::default();
+        if let Some(index) = self.index.get() {
+            let _ = lazy.index.set(Arc::clone(index));
+        }
+        lazy
+    }
+}
+
+/// 按权重收集的词（文字 → 最高权重）
+///
+/// 设置了内存上限时，超出上限后丢弃权重较低的词，只留下约一半的上限，此后不再收录低于丢弃线的词
+struct TopWeights {
+    weights: HashMap<Box<str>, u32>,
+    /// 一个词占用的内存
+    word_bytes: fn(&str) -> usize,
+    bytes: usize,
+    limit: Option<usize>,
+    /// 收录的最低权重
+    floor: u32,
+}
+
+impl TopWeights {
+    fn new(limit: Option<usize>, word_bytes: fn(&str) -> usize) -> Self {
+        Self {
+            weights: HashMap::new(),
+            word_bytes,
+            bytes: 0,
+            limit,
+            floor: 0,
+        }
+    }
+
+    fn add(&mut self, text: &str, weight: u32) {
+        if weight < self.floor {
+            return;
+        }
+        if let Some(max) = self.weights.get_mut(text) {
+            *max = (*max).max(weight);
+            return;
+        }
+        self.bytes += (self.word_bytes)(text);
+        self.weights.insert(text.into(), weight);
+        if let Some(limit) = self.limit.filter(|limit| self.bytes > *limit) {
+            self.prune(limit / 2);
+        }
+    }
+
+    /// 丢弃权重较低的词，使占用的内存不超过 `target`
+    fn prune(&mut self, target: usize) {
+        let word_bytes = self.word_bytes;
+        let mut by_weight: Vec<(u32, usize)> = self.weights.iter().map(|(text, weight)| (*weight, word_bytes(text))).collect();
+        by_weight.sort_unstable_by_key(|&(weight, _)| std::cmp::Reverse(weight));
+        let mut bytes = 0;
+        for (weight, size) in by_weight {
+            bytes += size;
+            if bytes > target {
+                self.floor = self.floor.max(weight.saturating_add(1));
+                break;
+            }
+        }
+        let floor = self.floor;
+        self.weights.retain(|_, weight| *weight >= floor);
+        self.weights.shrink_to_fit();
+        self.bytes = self.weights.keys().map(|text| word_bytes(text)).sum();
+    }
+}
+
+impl SharedDict {
+    /// 只包含内置基础词典
+    pub fn new() -> Self {
+        let mut dict = Self {
+            builtin: Arc::new(HashMap::new()),
+            rime: None,
+            dict_path: None,
+            dict_stack: DictStack::default(),
+            custom_entries: Vec::new(),
+            model: Arc::new(LanguageModel::new()),
+            normalizer: SourceNormalizer::new(),
+            tones: ToneTable::new(),
+            memory_budget: MemoryBudget::default(),
+            phrase_index: LazyPhraseIndex::default(),
+        };
+        dict.load_builtin_dict();
+        dict.rebuild();
+        dict
+    }
+
+    /// 设置加载外部词库时使用的内存预算
+    pub fn with_memory_budget(mut self, budget: MemoryBudget) -> Self {
+        self.memory_budget = budget;
+        self
+    }
+
+    /// 以本词库的内置词典加上外部词库构建新的共享词库，`stack` 为加载外部词库时使用的词库组合
+    ///
+    /// 本词库中加入的自定义词条同样加入新的外部词库
+    pub fn with_rime(&self, mut loader: RimeDictLoader, dict_path: &str, stack: DictStack) -> Self {
+        if !self.custom_entries.is_empty() {
+            loader.add_entries(self.custom_entries.iter().cloned(), DictSource::custom());
+        }
+        let mut dict = Self {
+            builtin: Arc::clone(&self.builtin),
+            rime: Some(Arc::new(loader)),
+            dict_path: Some(dict_path.to_string()),
+            dict_stack: stack,
+            custom_entries: self.custom_entries.clone(),
+            model: Arc::new(LanguageModel::new()),
+            normalizer: SourceNormalizer::new(),
+            tones: ToneTable::new(),
+            memory_budget: self.memory_budget.clone(),
+            phrase_index: LazyPhraseIndex::default(),
+        };
+        dict.rebuild();
+        dict
+    }
+
+    /// 外部词库
+    pub fn rime(&self) -> Option<&RimeDictLoader> {
+        self.rime.as_deref()
+    }
+
+    /// 词库路径
+    pub fn dict_path(&self) -> Option<&str> {
+        self.dict_path.as_deref()
+    }
+
+    /// 加载时使用的词库组合
+    pub fn dict_stack(&self) -> &DictStack {
+        &self.dict_stack
+    }
+
+    /// 加载外部词库时使用的内存预算
+    pub fn memory_budget(&self) -> &MemoryBudget {
+        &self.memory_budget
+    }
+
+    /// 由词库构建的基础语言模型
+    pub fn language_model(&self) -> &Arc<LanguageModel> {
+        &self.model
+    }
+
+    /// 汉字读音表，用于标注声调
+    pub fn tone_table(&self) -> &ToneTable {
+        &self.tones
+    }
+
+    /// 是否已加载外部词库
+    pub fn has_external_dict(&self) -> bool {
+        self.rime.is_some()
+    }
+
+    /// 外部词库统计信息，占用的内存包括语言模型与联想索引
+    pub fn stats(&self) -> Option<DictStats> {
+        self.rime.as_ref().map(|loader| DictStats {
+            memory_bytes: self.memory_bytes(),
+            ..loader.stats()
+        })
+    }
+
+    /// 外部词库、语言模型与已构建的联想索引占用的内存（字节，估算）
+    pub fn memory_bytes(&self) -> usize {
+        self.rime.as_ref().map_or(0, |loader| loader.stats().memory_bytes)
+            + self.model.memory_bytes()
+            + self.phrase_index.index.get().map_or(0, |index| index.memory_bytes())
+    }
+
+    /// 遍历内置词典与外部词库中的所有词条（文字, 权重），外部词库按页存储时逐页从磁盘读入
+    pub fn for_each_phrase(&self, mut f: impl FnMut(&str, u32)) {
+        for (candidate, weight) in self.builtin.values().flatten() {
+            f(&candidate.text, *weight);
+        }
+        if let Some(ref loader) = self.rime {
+            loader.for_each_entry(|sourced| f(&sourced.entry.text, sourced.entry.weight));
+        }
+    }
+
+    /// 联想词组索引，尚未构建时在后台线程中开始构建并返回 None
+    ///
+    /// 索引由共享同一词库的所有会话共用，构建完成前联想只有用户二元组
+    pub fn phrase_index(dict: &Arc<Self>) -> Option<Arc<PhraseIndex>> {
+        if let Some(index) = dict.phrase_index.index.get() {
+            return Some(Arc::clone(index));
+        }
+        if !dict.phrase_index.building.swap(true, Ordering::AcqRel) {
+            let dict = Arc::clone(dict);
+            std::thread::spawn(move || {
+                Self::wait_for_phrase_index(&dict);
+            });
+        }
+        None
+    }
+
+    /// 联想词组索引，尚未构建时在当前线程构建（或等待正在进行的构建完成）
+    ///
+    /// 设置了内存预算时只收录权重较高的词组，使索引不超出预算中的份额
+    pub fn wait_for_phrase_index(dict: &Arc<Self>) -> Arc<PhraseIndex> {
+        let index = dict.phrase_index.index.get_or_init(|| {
+            let mut phrases = TopWeights::new(dict.memory_budget.derived_bytes(), phrase_bytes);
+            dict.for_each_phrase(|text, weight| {
+                if is_phrase(text) {
+                    phrases.add(text, weight);
+                }
+            });
+            let index = PhraseIndex::from_list(phrases.weights.into_iter().collect());
+            log::debug!("联想词组索引构建完成: {} 个词组", index.len());
+            Arc::new(index)
+        });
+        Arc::clone(index)
+    }
+
+    /// 加入自定义词库词条（如从其他输入法导入的词库）并重建，返回加入的条数
+    ///
+    /// 更换外部词库时这些词条会重新加入（见 [`with_rime`](Self::with_rime)）
+    pub fn add_custom_entries(&mut self, entries: Vec<DictEntry>) -> usize {
+        if entries.is_empty() {
+            return 0;
+        }
+        self.custom_entries.extend(entries.iter().cloned());
+        let loader = Arc::make_mut(self.rime.get_or_insert_with(Default::default));
+        let count = loader.add_entries(entries, DictSource::custom());
+        self.rebuild();
+        count
+    }
+
+    /// 并入另一个外部词库（如后台分批加载的词库）并重建
+    pub(crate) fn merge_rime(&mut self, other: RimeDictLoader) {
+        Arc::make_mut(self.rime.get_or_insert_with(Default::default)).merge(other);
+        self.rebuild();
+    }
+
+    /// 根据词条重建基础语言模型与来源权重统计
+    ///
+    /// 设置了内存预算时语言模型只收录权重较高的词，不超出预算中的份额
+    pub(crate) fn rebuild(&mut self) {
+        let mut words = TopWeights::new(self.memory_budget.derived_bytes(), LanguageModel::unigram_bytes);
+        self.for_each_phrase(|text, weight| words.add(text, weight));
+        let model = LanguageModel::from_entries(words.weights.iter().map(|(text, weight)| (&**text, *weight)));
+        let mut normalizer = SourceNormalizer::new();
+        for (_, weight) in self.builtin.values().flatten() {
+            normalizer.add(SOURCE_BUILTIN, *weight as f64);
+        }
+        let mut tones = ToneTable::new();
+        if let Some(ref loader) = self.rime {
+            loader.for_each_entry(|sourced| {
+                normalizer.add(sourced.source.id(), sourced.entry.weight as f64);
+                tones.add_entry(&sourced.entry.text, &sourced.entry.pinyin);
+            });
+        }
+        self.model = Arc::new(model);
+        self.normalizer = normalizer;
+        self.tones = tones;
+        self.phrase_index = LazyPhraseIndex::default();
+    }
+
+    /// 向内置词典添加词条，内置词典与语言模型只被本词库引用时原地更新
+    pub fn add_entry(&mut self, pinyin: &str, text: &str, weight: u32) {
+        let candidate = Candidate::new(text.to_string())
+            .with_comment(pinyin.to_string())
+            .with_weight(weight);
+        Arc::make_mut(&mut self.builtin)
+            .entry(pinyin.to_string())
+            .or_default()
+            .push((candidate, weight));
+        Arc::make_mut(&mut self.model).add_unigram(text, weight as f64);
+        self.normalizer.add(SOURCE_BUILTIN, weight as f64);
+        self.phrase_index = LazyPhraseIndex::default();
+    }
+
+    /// 加载内置基础词典
+    fn load_builtin_dict(&mut self) {
+        // 基础单字映射
+        let builtin_data = vec![
+            // 中
+            ("zhong", vec![("中", 100), ("种", 90), ("重", 80), ("众", 70), ("钟", 60)]),
+            // 文
+            ("wen", vec![("文", 100), ("问", 90), ("闻", 80), ("稳", 70)]),
+            // 国
+            ("guo", vec![("国", 100), ("过", 90), ("果", 80)]),
+            // 人
+            ("ren", vec![("人", 100), ("任", 90), ("认", 80), ("仁", 70)]),
+            // 大
+            ("da", vec![("大", 100), ("打", 90), ("达", 80)]),
+            // 小
+            ("xiao", vec![("小", 100), ("笑", 90), ("校", 80)]),
+            // 的
+            ("de", vec![("的", 100), ("得", 90), ("地", 80)]),
+            // 是
+            ("shi", vec![("是", 100), ("时", 90), ("事", 80), ("十", 70)]),
+            // 我
+            ("wo", vec![("我", 100), ("握", 50)]),
+            // 你
+            ("ni", vec![("你", 100), ("您", 90), ("尼", 50)]),
+            // 好
+            ("hao", vec![("好", 100), ("号", 90), ("毫", 50)]),
+            // 在
+            ("zai", vec![("在", 100), ("再", 90), ("载", 50)]),
+            // 有
+            ("you", vec![("有", 100), ("又", 90), ("由", 80)]),
+            // 和
+            ("he", vec![("和", 100), ("合", 90), ("河", 80)]),
+            // 了
+            ("le", vec![("了", 100), ("乐", 80)]),
+            // 不
+            ("bu", vec![("不", 100), ("部", 90), ("步", 80)]),
+            // 一
+            ("yi", vec![("一", 100), ("以", 90), ("已", 80), ("意", 70)]),
+            // 个
+            ("ge", vec![("个", 100), ("各", 90), ("歌", 80)]),
+            // 上
+            ("shang", vec![("上", 100), ("商", 90), ("伤", 80)]),
+            // 下
+            ("xia", vec![("下", 100), ("夏", 80)]),
+            // 来
+            ("lai", vec![("来", 100), ("赖", 60)]),
+            // 去
+            ("qu", vec![("去", 100), ("取", 90), ("趣", 60)]),
+            // 到
+            ("dao", vec![("到", 100), ("道", 90), ("倒", 80)]),
+            // 说
+            ("shuo", vec![("说", 100), ("硕", 60)]),
+            // 要
+            ("yao", vec![("要", 100), ("药", 90), ("遥", 60)]),
+            // 会
+            ("hui", vec![("会", 100), ("回", 90), ("汇", 80)]),
+            // 能
+            ("neng", vec![("能", 100), ("农", 50)]),
+            // 可以
+            ("keyi", vec![("可以", 100)]),
+            // 中国
+            ("zhongguo", vec![("中国", 100)]),
+            // 中文
+            ("zhongwen", vec![("中文", 100)]),
+            // 你好
+            ("nihao", vec![("你好", 100)]),
+        ];
+
+        for (pinyin, chars) in builtin_data {
+            let candidates: Vec<(Candidate, u32)> = chars
+                .into_iter()
+                .map(|(ch, weight)| {
+                    let candidate = Candidate::new(ch.to_string())
+                        .with_comment(pinyin.to_string())
+                        .with_weight(weight);
+                    (candidate, weight)
+                })
+                .collect();
+            Arc::make_mut(&mut self.builtin).insert(pinyin.to_string(), candidates);
+        }
+    }
+}
+
+impl Default for SharedDict {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -2,44 +2,68 @@
 //!
 //! 提供基于拼音的汉字输入支持，集成用户词频学习
 
+use crate::ranking::{score_to_weight, RankingPolicy, ScoreBreakdown, SourceNormalizer, SOURCE_BUILTIN, SOURCE_SENTENCE, SOURCE_USER};
+use crate::shared::SharedDict;
 use crate::PinyinEngine;
 use lingcode_core::{
     candidate::{Candidate, Candidates},
     error::{Result},
     types::SchemeType,
 };
-use lingcode_dict::{RimeDictLoader, DictStats, UserDict};
-use std::collections::HashMap;
+use lingcode_core::syllable::split_syllables;
+use lingcode_dict::scoring::default_clock;
+use lingcode_dict::{BackgroundLoad, Clock, DictCipher, DictEntry, DictStack, LanguageModel, RimeDictLoader, DictStats, LoadProgress, MemoryBudget, ProgressCallback, UserDict, UserDictStore, WordRecord};
 use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::sync::{Arc, Mutex};
 
 /// 简体拼音引擎
 pub struct SimplifiedPinyinEngine {
-    /// 拼音到汉字的映射表（内置基础词典）
-    pinyin_dict: HashMap<String, Vec<(Candidate, u32)>>,
-    /// 雾凇拼音词库加载器（支持多词库）
-    rime_loader: Option<RimeDictLoader>,
-    /// 词库路径
-    dict_path: Option<String>,
-    /// 是否已加载外部词库
-    has_external_dict: bool,
+    /// 共享的只读词库（内置词典、外部词库与基础语言模型）
+    dict: Arc<SharedDict>,
+    /// 正在后台加载的词库
+    pending_dicts: Option<PendingDicts>,
     /// 用户词频数据库
     user_dict: Option<UserDict>,
     /// 用户词库路径
     user_dict_path: Option<String>,
+    /// 用户词库 JSON 文件的加密器，未加密时为 None
+    user_dict_cipher: Option<DictCipher>,
+    /// 用户词库的日志式存储（启用时代替 JSON 文件）
+    user_dict_store: Option<UserDictStore>,
+    /// 语言模型（整句搜索与前文排序）
+    language_model: LanguageModel,
+    /// 排序策略
+    ranking_policy: RankingPolicy,
+    /// 各来源的权重统计
+    normalizer: SourceNormalizer,
+    /// 用户词库使用的时间来源
+    clock: Arc<dyn Clock>,
 }
 
 impl SimplifiedPinyinEngine {
     /// 创建新引擎实例，加载内置基础词典
     pub fn new() -> Self {
+        Self::with_shared_dict(Arc::new(SharedDict::new()))
+    }
+
+    /// 使用共享词库创建引擎（会话），不复制词库数据
+    pub fn with_shared_dict(dict: Arc<SharedDict>) -> Self {
         let mut engine = Self {
-            pinyin_dict: HashMap::new(),
-            rime_loader: None,
-            dict_path: None,
-            has_external_dict: false,
+            dict,
+            pending_dicts: None,
             user_dict: None,
             user_dict_path: None,
+            user_dict_cipher: None,
+            user_dict_store: None,
+            language_model: LanguageModel::new(),
+            ranking_policy: RankingPolicy::new(),
+            normalizer: SourceNormalizer::new(),
+            clock: default_clock(),
         };
-        engine.load_builtin_dict();
+        engine.register_dict_sources();
+        engine.rebuild_language_model();
         engine
     }
 
@@ -50,6 +74,13 @@ impl SimplifiedPinyinEngine {
         engine
     }
 
+    /// 从雾凇拼音词库目录创建引擎，词库在后台加载（见 [`load_rime_dicts_in_background`](Self::load_rime_dicts_in_background)）
+    pub fn with_rime_dicts_in_background(dict_dir: &str, on_progress: Option<ProgressCallback>) -> Self {
+        let mut engine = Self::new();
+        engine.load_rime_dicts_in_background(dict_dir, on_progress);
+        engine
+    }
+
     /// 启用用户词频学习
     pub fn with_user_dict(mut self, path: &str) -> Self {
         self.load_user_dict(path);
@@ -57,68 +88,627 @@ impl SimplifiedPinyinEngine {
     }
 
     /// 加载用户词库
+    ///
+    /// 文件无法读取（如已加密）时使用空词库且本次不保存，避免覆盖原文件
     pub fn load_user_dict(&mut self, path: &str) {
-        let dict_path = Path::new(path);
-        match UserDict::load_from_file(dict_path) {
+        match UserDict::load_from_file(Path::new(path)) {
             Ok(dict) => {
                 let stats = dict.stats();
                 log::info!("已加载用户词库: {} 条记录", stats.total_records);
-                self.user_dict = Some(dict);
+                self.set_user_dict(dict);
                 self.user_dict_path = Some(path.to_string());
             }
             Err(e) => {
-                log::warn!("加载用户词库失败: {}, 将创建新词库", e);
-                self.user_dict = Some(UserDict::new());
-                self.user_dict_path = Some(path.to_string());
+                log::warn!("加载用户词库失败: {}, 本次不保存词频", e);
+                self.set_user_dict(UserDict::new());
+                self.user_dict_path = None;
+            }
+        }
+        self.user_dict_cipher = None;
+        self.user_dict_store = None;
+        self.rebuild_language_model();
+    }
+
+    /// 加载加密的用户词库 JSON 文件，之后按同一密钥保存；未加密的旧文件在下次保存时加密
+    ///
+    /// 密钥错误时返回 [`LingCodeError::DecryptionError`](lingcode_core::error::LingCodeError)，
+    /// 已加载的用户词库保持不变
+    pub fn load_encrypted_user_dict(&mut self, path: &str, cipher: DictCipher) -> anyhow::Result<()> {
+        let dict = UserDict::load_encrypted(Path::new(path), &cipher)?;
+        log::info!("已加载加密用户词库: {} 条记录", dict.stats().total_records);
+        self.set_user_dict(dict);
+        self.user_dict_path = Some(path.to_string());
+        self.user_dict_cipher = Some(cipher);
+        self.user_dict_store = None;
+        self.rebuild_language_model();
+        Ok(())
+    }
+
+    /// 启用用户词频学习，使用日志式存储目录
+    pub fn with_user_dict_store(mut self, dir: &str) -> Self {
+        // 失败时已记录日志并改用不保存的空词库
+        let _ = self.load_user_dict_store(dir);
+        self
+    }
+
+    /// 从日志式存储目录加载用户词库，之后的词频记录实时写入日志
+    ///
+    /// 目录无法打开时使用空词库且本次不保存，并返回打开时的错误
+    pub fn load_user_dict_store(&mut self, dir: &str) -> anyhow::Result<()> {
+        let result = match UserDictStore::open(Path::new(dir)) {
+            Ok((store, dict)) => {
+                log::info!("已加载用户词库: {} 条记录", dict.stats().total_records);
+                self.set_user_dict(dict);
+                self.user_dict_store = Some(store);
+                Ok(())
             }
+            Err(e) => {
+                log::warn!("打开用户词库存储失败: {}, 本次不保存词频", e);
+                self.set_user_dict(UserDict::new());
+                self.user_dict_store = None;
+                Err(e)
+            }
+        };
+        self.user_dict_path = None;
+        self.user_dict_cipher = None;
+        self.rebuild_language_model();
+        result
+    }
+
+    /// 从加密的日志式存储目录加载用户词库，目录中的明文数据会被加密
+    ///
+    /// 密钥错误时返回 [`LingCodeError::DecryptionError`](lingcode_core::error::LingCodeError)，
+    /// 已加载的用户词库保持不变
+    pub fn load_encrypted_user_dict_store(&mut self, dir: &str, cipher: DictCipher) -> anyhow::Result<()> {
+        let (store, dict) = UserDictStore::open_encrypted(Path::new(dir), cipher)?;
+        log::info!("已加载加密用户词库: {} 条记录", dict.stats().total_records);
+        self.set_user_dict(dict);
+        self.user_dict_store = Some(store);
+        self.user_dict_path = None;
+        self.user_dict_cipher = None;
+        self.rebuild_language_model();
+        Ok(())
+    }
+
+    /// 轮换用户词库密钥，`cipher` 为 None 时改为明文存储
+    ///
+    /// 使用 JSON 文件存储时按新密钥重写文件，之后的保存使用新密钥；写入失败时保留旧密钥
+    pub fn rotate_user_dict_key(&mut self, cipher: Option<DictCipher>) -> anyhow::Result<()> {
+        let dict = match self.user_dict.as_ref() {
+            Some(dict) => dict,
+            None => return Ok(()),
+        };
+        if let Some(store) = self.user_dict_store.as_mut() {
+            store.rotate_key(dict, cipher)?;
+        } else if let Some(path) = self.user_dict_path.as_ref() {
+            save_user_dict_file(dict, Path::new(path), cipher.as_ref())?;
+            self.user_dict_cipher = cipher;
         }
+        log::info!("用户词库密钥已轮换");
+        Ok(())
+    }
+
+    /// 使用当前评分策略与时间来源设置用户词库
+    fn set_user_dict(&mut self, mut dict: UserDict) {
+        dict.set_scoring_policy(self.ranking_policy.user_scoring().clone());
+        dict.set_clock(self.clock.clone());
+        self.user_dict = Some(dict);
+    }
+
+    /// 设置时间来源（用于测试与重放），同时作用于已加载的用户词库
+    pub fn set_clock(&mut self, clock: Arc<dyn Clock>) {
+        if let Some(ref mut dict) = self.user_dict {
+            dict.set_clock(clock.clone());
+        }
+        self.clock = clock;
+        self.rebuild_normalizer();
     }
 
     /// 记录用户使用某个词条
     pub fn record_usage(&mut self, text: &str, pinyin: &str) {
+        let before = self.user_score(text, pinyin);
         if let Some(ref mut dict) = self.user_dict {
-            dict.record_usage(text, pinyin);
+            let now = dict.now();
+            match self.user_dict_store {
+                Some(ref mut store) => {
+                    if let Err(e) = store.record_usage(dict, text, pinyin, now) {
+                        log::warn!("写入用户词库日志失败: {}", e);
+                    }
+                }
+                None => dict.record_usage(text, pinyin),
+            }
+            self.language_model.add_user_usage(text, 1.0);
+            self.update_user_normalizer(text, pinyin, before);
             log::debug!("记录词频: {} ({})", text, pinyin);
         }
     }
 
+    /// 撤销一次词频记录，`previous` 为记录前的词条状态
+    pub fn rollback_usage(&mut self, text: &str, pinyin: &str, previous: Option<WordRecord>) {
+        let before = self.user_score(text, pinyin);
+        if let Some(ref mut dict) = self.user_dict {
+            match self.user_dict_store {
+                Some(ref mut store) => {
+                    if let Err(e) = store.rollback_usage(dict, text, pinyin, previous) {
+                        log::warn!("写入用户词库日志失败: {}", e);
+                    }
+                }
+                None => dict.rollback_usage(text, pinyin, previous),
+            }
+            self.language_model.add_user_usage(text, -1.0);
+            self.update_user_normalizer(text, pinyin, before);
+            log::debug!("撤销词频记录: {}", text);
+        }
+    }
+
+    /// 删除或屏蔽某个编码下的候选词，之后该候选不再出现，直到再次被选用
+    ///
+    /// 返回用户词库中是否有该词条；未加载用户词库时返回 false
+    pub fn delete_word(&mut self, text: &str, pinyin: &str) -> bool {
+        let dict = match self.user_dict.as_mut() {
+            Some(dict) => dict,
+            None => return false,
+        };
+        let existed = dict.get_record(text, pinyin).is_some();
+        let now = dict.now();
+        match self.user_dict_store {
+            Some(ref mut store) => {
+                if let Err(e) = store.delete(dict, text, pinyin, now) {
+                    log::warn!("写入用户词库日志失败: {}", e);
+                }
+            }
+            None => {
+                dict.delete(text, pinyin);
+            }
+        }
+        if existed {
+            self.rebuild_language_model();
+        }
+        log::debug!("删除候选: {} ({})", text, pinyin);
+        existed
+    }
+
+    /// 手动添加词条，`pinyin` 可以是任意编码（如自定义缩写）
+    pub fn add_word(&mut self, text: &str, pinyin: &str) {
+        let dict = match self.user_dict.as_mut() {
+            Some(dict) => dict,
+            None => return,
+        };
+        if dict.get_record(text, pinyin).is_some() {
+            return;
+        }
+        let now = dict.now();
+        match self.user_dict_store {
+            Some(ref mut store) => {
+                if let Err(e) = store.add_word(dict, text, pinyin, now) {
+                    log::warn!("写入用户词库日志失败: {}", e);
+                }
+            }
+            None => dict.add_word(text, pinyin),
+        }
+        self.language_model.add_user_usage(text, 1.0);
+        self.update_user_normalizer(text, pinyin, None);
+        log::debug!("添加用户词: {} ({})", text, pinyin);
+    }
+
+    /// 将候选固定在某个编码的指定位置（从 0 开始），`position` 为 None 时取消固定
+    pub fn pin_word(&mut self, text: &str, pinyin: &str, position: Option<usize>) {
+        let dict = match self.user_dict.as_mut() {
+            Some(dict) => dict,
+            None => return,
+        };
+        let now = dict.now();
+        match self.user_dict_store {
+            Some(ref mut store) => {
+                if let Err(e) = store.pin(dict, text, pinyin, position, now) {
+                    log::warn!("写入用户词库日志失败: {}", e);
+                }
+            }
+            None => dict.pin(text, pinyin, position),
+        }
+        log::debug!("固定候选: {} ({}) -> {:?}", text, pinyin, position);
+    }
+
+    /// 清除 `[start, end]` 时间段内的用户词频记录并立即写入磁盘，返回清除的词条数
+    ///
+    /// 运行期间记录的二元组没有时间信息，一并清除
+    pub fn purge_user_history(&mut self, start: u64, end: u64) -> anyhow::Result<usize> {
+        self.language_model.clear_bigrams();
+        let dict = match self.user_dict.as_mut() {
+            Some(dict) => dict,
+            None => return Ok(0),
+        };
+        let purged = match self.user_dict_store {
+            Some(ref mut store) => {
+                let now = dict.now();
+                store.purge(dict, start, end, now)?
+            }
+            None => dict.purge(start, end),
+        };
+        self.rebuild_language_model();
+        self.save_user_dict()?;
+        log::info!("已清除 {} 条用户词频记录", purged);
+        Ok(purged)
+    }
+
+    /// 导入 Rime 用户词典快照（`*.userdb.txt`），与现有词频合并后写入磁盘
+    pub fn import_rime_userdb(&mut self, path: &str) -> anyhow::Result<lingcode_dict::RimeUserDbSummary> {
+        let mut dict = self.user_dict.take().unwrap_or_default();
+        let result = dict.import_rime_userdb(Path::new(path));
+        self.set_user_dict(dict);
+        let summary = result?;
+        self.rebuild_language_model();
+        self.save_user_dict()?;
+        log::info!("已导入 Rime 用户词典: {} 条词条", summary.imported);
+        Ok(summary)
+    }
+
+    /// 导出为 Rime 用户词典快照，返回导出的词条数
+    pub fn export_rime_userdb(&self, path: &str, db_name: &str) -> anyhow::Result<usize> {
+        match self.user_dict {
+            Some(ref dict) => dict.export_rime_userdb(Path::new(path), db_name),
+            None => Ok(0),
+        }
+    }
+
+    /// 记录用户连续上屏的两个词（用于按前文排序）
+    pub fn record_bigram(&mut self, previous: &str, text: &str) {
+        self.language_model.add_bigram(previous, text, 1.0);
+    }
+
+    /// 撤销一次 `record_bigram`
+    pub fn rollback_bigram(&mut self, previous: &str, text: &str) {
+        self.language_model.add_bigram(previous, text, -1.0);
+    }
+
+    /// 获取语言模型
+    pub fn language_model(&self) -> &LanguageModel {
+        &self.language_model
+    }
+
+    /// 在共享词库的基础语言模型上叠加用户词频，重建语言模型与各来源的权重统计
+    ///
+    /// 运行期间记录的二元组（见 [`record_bigram`](Self::record_bigram)）移到新模型上保留
+    pub fn rebuild_language_model(&mut self) {
+        let mut model = LanguageModel::with_base(Arc::clone(&self.dict.model));
+        if let Some(ref dict) = self.user_dict {
+            model.add_user_history(dict);
+        }
+        model.take_bigrams(&mut self.language_model);
+        self.language_model = model;
+        self.rebuild_normalizer();
+    }
+
+    /// 重建各来源的权重统计，用户词库按评分策略的当前得分统计，与排序使用的得分一致
+    ///
+    /// 随时间衰减的得分在两次重建之间不会随时间更新，词频变化时按得分差更新
+    fn rebuild_normalizer(&mut self) {
+        let mut normalizer = self.dict.normalizer.clone();
+        if let Some(ref dict) = self.user_dict {
+            for record in dict.records() {
+                normalizer.add(SOURCE_USER, dict.score(record));
+            }
+        }
+        self.normalizer = normalizer;
+    }
+
+    /// 用户词条按评分策略的当前得分，没有该词条时为 None
+    fn user_score(&self, text: &str, pinyin: &str) -> Option<f64> {
+        let dict = self.user_dict.as_ref()?;
+        dict.get_record(text, pinyin).map(|record| dict.score(record))
+    }
+
+    /// 用户词条变化后更新权重统计，`before` 为变化前的得分
+    fn update_user_normalizer(&mut self, text: &str, pinyin: &str, before: Option<f64>) {
+        let after = self.user_score(text, pinyin);
+        self.normalizer.replace(SOURCE_USER, before, after);
+    }
+
+    /// 共享词库
+    pub fn shared_dict(&self) -> &Arc<SharedDict> {
+        &self.dict
+    }
+
+    /// 换用另一份共享词库（如热重载或会话管理器发布的词库），取消正在进行的后台加载
+    pub fn set_shared_dict(&mut self, dict: Arc<SharedDict>) {
+        self.dict = dict;
+        self.pending_dicts = None;
+        self.register_dict_sources();
+        self.rebuild_language_model();
+    }
+
+    /// 修改词库前取得可写的词库：与其他会话共享时复制一份
+    fn dict_mut(&mut self) -> &mut SharedDict {
+        // 先释放语言模型对基础模型的引用，避免不必要的复制
+        self.language_model.set_base(None);
+        Arc::make_mut(&mut self.dict)
+    }
+
+    /// 获取候选词，`context` 为前文（通常是上一次上屏的文字），用于按二元概率排序
+    ///
+    /// 各来源的权重先按来源归一化为对数概率并乘以来源加权（见 [`RankingPolicy`]），
+    /// 有前文时再加上语言模型给出的二元提升；输入可以切分为多个音节时，额外提供整句搜索的结果。
+    /// 最终得分写入 `Candidate.weight`（见 [`score_to_weight`]），来源写入 `Candidate.source`
+    pub fn get_candidates_with_context(&self, pinyin: &str, context: Option<&str>) -> Result<Candidates> {
+        let mut candidates = Candidates::new();
+        for (candidate, _) in self.explain_candidates(pinyin, context) {
+            candidates.add(candidate);
+        }
+        Ok(candidates)
+    }
+
+    /// 获取候选词及每个候选的得分明细，排序与 `get_candidates_with_context` 一致
+    pub fn explain_candidates(&self, pinyin: &str, context: Option<&str>) -> Vec<(Candidate, ScoreBreakdown)> {
+        let mut scored_candidates: Vec<(Candidate, ScoreBreakdown)> = Vec::new();
+        let explain = |source: &str, raw_weight: f64, log_prob: f64, penalty: f64, text: &str| {
+            ScoreBreakdown::new(source, raw_weight, log_prob)
+                .with_boost(self.ranking_policy.boost(source))
+                .with_penalty(penalty)
+                .with_lm_contribution(self.context_lift(context, text))
+        };
+
+        // 1. 用户词库
+        if let Some(ref user_dict) = self.user_dict {
+            for (record, user_score) in user_dict.lookup(pinyin) {
+                let candidate = Candidate::new(record.text.clone()).with_comment(format!("{} ⭐", pinyin));
+                let log_prob = self.normalizer.log_prob(SOURCE_USER, user_score);
+                let breakdown = explain(SOURCE_USER, user_score, log_prob, 1.0, &candidate.text)
+                    .with_user_frequency(record.count, user_dict.time_decay(record));
+                push_best(&mut scored_candidates, candidate, breakdown);
+            }
+        }
+
+        // 2. 雾凇拼音词库（按词条所属词库归一化）
+        if let Some(ref loader) = self.dict.rime {
+            for sourced in loader.lookup_sourced(pinyin).iter() {
+                let source = sourced.source.id();
+                let weight = sourced.entry.weight as f64;
+                let candidate = Candidate::new(sourced.entry.text.clone()).with_comment(sourced.entry.pinyin.clone());
+                let breakdown = explain(source, weight, self.normalizer.log_prob(source, weight), 1.0, &candidate.text);
+                push_best(&mut scored_candidates, candidate, breakdown);
+            }
+        }
+
+        // 3. 内置词典
+        if let Some(matches) = self.dict.builtin.get(pinyin) {
+            for (candidate, weight) in matches {
+                let weight = *weight as f64;
+                let log_prob = self.normalizer.log_prob(SOURCE_BUILTIN, weight);
+                let breakdown = explain(SOURCE_BUILTIN, weight, log_prob, 1.0, &candidate.text);
+                push_best(&mut scored_candidates, candidate.clone(), breakdown);
+            }
+        }
+
+        // 4. 整句搜索（多音节输入），得分为语言模型给出的整句概率
+        if let Some((sentence, log_prob)) = self.search_sentence(pinyin, context) {
+            // 整句得分已包含前文，前文贡献单独列出
+            let lift = self.context_lift(context, &sentence);
+            let breakdown = explain(SOURCE_SENTENCE, 0.0, log_prob - lift, 1.0, &sentence);
+            let candidate = Candidate::new(sentence).with_comment(pinyin.to_string());
+            push_best(&mut scored_candidates, candidate, breakdown);
+        }
+
+        // 5. 前缀匹配（如果结果太少）
+        if scored_candidates.len() < 5 {
+            let penalty = self.ranking_policy.prefix_penalty();
+            for (key, matches) in self.dict.builtin.iter() {
+                if key.starts_with(pinyin) && key.as_str() != pinyin {
+                    for (candidate, weight) in matches.iter().take(2) {
+                        let weight = *weight as f64;
+                        let log_prob = self.normalizer.log_prob(SOURCE_BUILTIN, weight);
+                        let breakdown = explain(SOURCE_BUILTIN, weight, log_prob, penalty, &candidate.text);
+                        push_best(&mut scored_candidates, candidate.clone(), breakdown);
+                    }
+                }
+                if scored_candidates.len() >= 10 {
+                    break;
+                }
+            }
+        }
+
+        // 按得分排序，去掉用户删除的候选后按用户固定的位置调整
+        scored_candidates.sort_by(|a, b| b.1.total.total_cmp(&a.1.total));
+        if let Some(ref user_dict) = self.user_dict {
+            scored_candidates.retain(|(candidate, _)| !user_dict.is_suppressed(&candidate.text, pinyin));
+        }
+        scored_candidates.truncate(20);
+        if let Some(ref user_dict) = self.user_dict {
+            apply_pins(&mut scored_candidates, &user_dict.pins_for(pinyin), pinyin);
+        }
+        scored_candidates
+            .into_iter()
+            .map(|(candidate, breakdown)| (candidate.with_weight(score_to_weight(breakdown.total)), breakdown))
+            .collect()
+    }
+
+    /// 前文带来的对数概率提升（二元概率相对一元概率），无前文时为 0
+    fn context_lift(&self, context: Option<&str>, text: &str) -> f64 {
+        match context {
+            Some(_) => self.language_model.conditional_log_prob(context, text) - self.language_model.log_prob(text),
+            None => 0.0,
+        }
+    }
+
+    /// 设置排序策略
+    pub fn set_ranking_policy(&mut self, policy: RankingPolicy) {
+        if let Some(ref mut dict) = self.user_dict {
+            dict.set_scoring_policy(policy.user_scoring().clone());
+        }
+        self.ranking_policy = policy;
+        self.register_dict_sources();
+        self.rebuild_normalizer();
+    }
+
+    /// 把已加载词库的优先级登记到排序策略，之前词库组合的优先级不再保留
+    fn register_dict_sources(&mut self) {
+        self.ranking_policy.reset_source_priorities();
+        if let Some(ref loader) = self.dict.rime {
+            for source in loader.loaded_sources() {
+                self.ranking_policy.set_source_priority(source);
+            }
+        }
+    }
+
+    /// 获取排序策略
+    pub fn ranking_policy(&self) -> &RankingPolicy {
+        &self.ranking_policy
+    }
+
+    /// 整句搜索：输入至少包含两个音节时，返回概率最高的整句
+    ///
+    /// 每次按键都对整个输入切分，切分与柱搜索的耗时都与输入长度成正比
+    fn search_sentence(&self, pinyin: &str, context: Option<&str>) -> Option<(String, f64)> {
+        let syllables = split_syllables(pinyin)?;
+        if syllables.len() < 2 {
+            return None;
+        }
+        self.language_model
+            .best_sentence(&syllables, context, |segment| self.exact_matches(segment))
+    }
+
+    /// 某段拼音在各来源中完全匹配的词
+    fn exact_matches(&self, pinyin: &str) -> Vec<String> {
+        let mut words: Vec<String> = Vec::new();
+        let mut add = |text: &str| {
+            if !words.iter().any(|w| w == text) {
+                words.push(text.to_string());
+            }
+        };
+        if let Some(ref user_dict) = self.user_dict {
+            for (record, _) in user_dict.lookup(pinyin) {
+                add(&record.text);
+            }
+        }
+        if let Some(ref loader) = self.dict.rime {
+            for sourced in loader.lookup_sourced(pinyin).iter() {
+                add(&sourced.entry.text);
+            }
+        }
+        if let Some(matches) = self.dict.builtin.get(pinyin) {
+            for (candidate, _) in matches {
+                add(&candidate.text);
+            }
+        }
+        words
+    }
+
+    /// 获取用户词库
+    pub fn user_dict(&self) -> Option<&UserDict> {
+        self.user_dict.as_ref()
+    }
+
     /// 保存用户词库
-    pub fn save_user_dict(&self) -> anyhow::Result<()> {
+    ///
+    /// 使用日志式存储时压缩为新快照，否则写入 JSON 文件
+    pub fn save_user_dict(&mut self) -> anyhow::Result<()> {
+        if let (Some(ref dict), Some(ref mut store)) = (&self.user_dict, self.user_dict_store.as_mut()) {
+            store.compact(dict)?;
+            log::info!("用户词库已保存到: {}", store.dir().display());
+            return Ok(());
+        }
         if let (Some(ref dict), Some(ref path)) = (&self.user_dict, self.user_dict_path.as_ref()) {
-            let dict_path = Path::new(path);
-            dict.save_to_file(dict_path)?;
+            save_user_dict_file(dict, Path::new(path), self.user_dict_cipher.as_ref())?;
             log::info!("用户词库已保存到: {}", path);
         }
         Ok(())
     }
 
+    /// 与多设备同步目录同步用户词库，合并后重建语言模型并保存
+    ///
+    /// 存储已加密时同步目录中的快照使用相同的密钥加密
+    pub fn sync_user_dict(&mut self, folder: &str) -> anyhow::Result<Option<lingcode_dict::SyncSummary>> {
+        let dict = match self.user_dict.as_mut() {
+            Some(dict) => dict,
+            None => return Ok(None),
+        };
+        let cipher = match self.user_dict_store.as_ref() {
+            Some(store) => store.cipher(),
+            None => self.user_dict_cipher.as_ref(),
+        };
+        let summary = lingcode_dict::sync_with_folder_encrypted(dict, Path::new(folder), cipher)?;
+        self.rebuild_language_model();
+        self.save_user_dict()?;
+        Ok(Some(summary))
+    }
+
     /// 获取用户词库统计
     pub fn user_dict_stats(&self) -> Option<lingcode_dict::UserDictStats> {
         self.user_dict.as_ref().map(|d| d.stats())
     }
 
-    /// 加载雾凇拼音多词库
+    /// 设置词库内存预算，应在加载词库前设置
+    pub fn with_memory_budget(mut self, budget: MemoryBudget) -> Self {
+        self.set_memory_budget(budget);
+        self
+    }
+
+    /// 设置词库内存预算；已从词库目录加载时按新预算重新加载
+    pub fn set_memory_budget(&mut self, budget: MemoryBudget) {
+        if budget == self.dict.memory_budget {
+            return;
+        }
+        self.dict_mut().memory_budget = budget;
+        self.rebuild_language_model();
+        if let Some(dir) = self.dict.dict_path.clone().filter(|dir| Path::new(dir).is_dir()) {
+            self.load_rime_dicts(&dir);
+        }
+    }
+
+    /// 词库内存预算
+    pub fn memory_budget(&self) -> &MemoryBudget {
+        &self.dict.memory_budget
+    }
+
+    /// 设置要加载的词库组合；已从词库目录加载时按新组合重新加载
+    ///
+    /// 正在后台加载其他组合时丢弃该任务，按新组合重新在后台加载
+    pub fn set_dict_stack(&mut self, stack: DictStack) {
+        if let Some(pending) = self.pending_dicts.take() {
+            if pending.stack == stack {
+                self.pending_dicts = Some(pending);
+            } else {
+                self.spawn_dict_load(&pending.dir, stack, Arc::clone(&pending.on_progress));
+            }
+            return;
+        }
+        if stack == self.dict.dict_stack {
+            return;
+        }
+        match self.dict.dict_path.clone().filter(|dir| Path::new(dir).is_dir()) {
+            Some(dir) => self.load_dict_stack(&dir, stack),
+            None => {
+                self.dict_mut().dict_stack = stack;
+                self.rebuild_language_model();
+            }
+        }
+    }
+
+    /// 词库组合
+    pub fn dict_stack(&self) -> &DictStack {
+        &self.dict.dict_stack
+    }
+
+    /// 加载雾凇拼音多词库，加载哪些词库由词库组合（见 [`set_dict_stack`](Self::set_dict_stack)）决定
     pub fn load_rime_dicts(&mut self, dict_dir: &str) {
-        let mut loader = RimeDictLoader::new();
+        let stack = self.dict.dict_stack.clone();
+        self.load_dict_stack(dict_dir, stack);
+    }
+
+    fn load_dict_stack(&mut self, dict_dir: &str, stack: DictStack) {
+        let mut loader = RimeDictLoader::new().with_memory_budget(self.dict.memory_budget.clone());
         let path = Path::new(dict_dir);
 
         if path.exists() && path.is_dir() {
-            match loader.load_rime_ice_dicts(path) {
+            let (base_dir, specs) = stack.resolve(path);
+            match loader.load_dict_stack(&base_dir, &specs) {
                 Ok(summary) => {
                     if summary.total_entries > 0 {
                         log::info!("已加载 {} 条词条", summary.total_entries);
                         for (name, count, source) in &summary.loaded {
-                            let source_name = match source {
-                                lingcode_dict::DictSource::Base => "基础",
-                                lingcode_dict::DictSource::Ext => "扩展",
-                                lingcode_dict::DictSource::Tencent => "腾讯",
-                                lingcode_dict::DictSource::Custom => "自定义",
-                            };
-                            log::info!("  • {}: {} 条 ({})", name, count, source_name);
+                            log::info!("  • {}: {} 条 ({}, 优先级 {})", name, count, source.id(), source.priority());
                         }
-                        self.rime_loader = Some(loader);
-                        self.dict_path = Some(dict_dir.to_string());
-                        self.has_external_dict = true;
+                        let dict = self.dict.with_rime(loader, dict_dir, stack);
+                        self.set_shared_dict(Arc::new(dict));
                     } else {
                         log::warn!("未找到词库文件");
                     }
@@ -132,6 +722,97 @@ impl SimplifiedPinyinEngine {
         }
     }
 
+    /// 在后台加载雾凇拼音多词库，返回是否开始加载
+    ///
+    /// 词库按文件从小到大加载，每个文件加载完成后在后台并入并构建新的共享词库，
+    /// [`poll_dict_loading`](Self::poll_dict_loading) 时只替换词库；基础词库就绪后即可输入，
+    /// 第一个文件并入前继续使用原有词库。`on_progress` 在加载线程中调用
+    pub fn load_rime_dicts_in_background(&mut self, dict_dir: &str, on_progress: Option<ProgressCallback>) -> bool {
+        let stack = self.dict.dict_stack.clone();
+        self.spawn_dict_load(dict_dir, stack, Arc::new(Mutex::new(on_progress)))
+    }
+
+    fn spawn_dict_load(&mut self, dict_dir: &str, stack: DictStack, on_progress: SharedProgressCallback) -> bool {
+        if !Path::new(dict_dir).is_dir() {
+            log::warn!("词库目录不存在: {}, 使用内置词典", dict_dir);
+            return false;
+        }
+        match PendingDicts::spawn(Arc::clone(&self.dict), dict_dir, stack, on_progress) {
+            Ok(pending) => {
+                log::info!("开始在后台加载 {} 个词库文件", pending.progress().files_total);
+                self.pending_dicts = Some(pending);
+                true
+            }
+            Err(e) => {
+                log::warn!("无法启动词库加载线程: {}", e);
+                false
+            }
+        }
+    }
+
+    /// 换用后台已构建好的最新词库，返回是否有新词库
+    pub fn poll_dict_loading(&mut self) -> bool {
+        let Some(pending) = self.pending_dicts.as_ref() else {
+            return false;
+        };
+        let mut latest = None;
+        let finished = loop {
+            match pending.receiver.try_recv() {
+                Ok(dict) => latest = Some(dict),
+                Err(TryRecvError::Empty) => break false,
+                Err(TryRecvError::Disconnected) => break true,
+            }
+        };
+        self.swap_loaded_dict(latest, finished)
+    }
+
+    /// 阻塞直到后台词库全部加载并并入
+    pub fn wait_for_dicts(&mut self) {
+        if let Some(pending) = self.pending_dicts.as_ref() {
+            let latest = pending.receiver.iter().last();
+            self.swap_loaded_dict(latest, true);
+        }
+    }
+
+    fn swap_loaded_dict(&mut self, latest: Option<Arc<SharedDict>>, finished: bool) -> bool {
+        if finished {
+            if let Some(pending) = self.pending_dicts.take() {
+                let progress = pending.progress();
+                log::info!("词库加载完成：{} 个文件，{} 条词条", progress.files_done, progress.entries_loaded);
+            }
+        }
+        let Some(dict) = latest else {
+            return false;
+        };
+        self.dict = dict;
+        self.register_dict_sources();
+        self.rebuild_language_model();
+        true
+    }
+
+    /// 是否正在后台加载词库
+    pub fn is_loading_dicts(&self) -> bool {
+        self.pending_dicts.is_some()
+    }
+
+    /// 后台加载进度，没有正在加载的词库时为 `None`
+    pub fn dict_load_progress(&self) -> Option<LoadProgress> {
+        self.pending_dicts.as_ref().map(PendingDicts::progress)
+    }
+
+    /// 加入自定义词库词条（如从其他输入法导入的词库），返回加入的条数
+    ///
+    /// 词库与其他会话共享时复制一份；多个会话应通过会话管理器加入，所有会话共用修改后的词库
+    pub fn add_custom_entries(&mut self, entries: Vec<DictEntry>) -> usize {
+        if entries.is_empty() {
+            return 0;
+        }
+        let count = self.dict_mut().add_custom_entries(entries);
+        self.register_dict_sources();
+        self.rebuild_language_model();
+        count
+    }
+
     /// 加载雾凇拼音词库（兼容旧接口，加载单个文件）
     pub fn load_rime_dict(&mut self, dict_path: &str) {
         let path = Path::new(dict_path);
@@ -140,13 +821,12 @@ impl SimplifiedPinyinEngine {
             self.load_rime_dicts(dict_path);
         } else if path.exists() {
             // 如果是文件，加载单个文件
-            let mut loader = RimeDictLoader::new();
+            let mut loader = RimeDictLoader::new().with_memory_budget(self.dict.memory_budget.clone());
             match loader.load_from_file(path) {
                 Ok(_) => {
                     log::info!("已加载词库: {}", dict_path);
-                    self.rime_loader = Some(loader);
-                    self.dict_path = Some(dict_path.to_string());
-                    self.has_external_dict = true;
+                    let dict = self.dict.with_rime(loader, dict_path, self.dict.dict_stack.clone());
+                    self.set_shared_dict(Arc::new(dict));
                 }
                 Err(e) => {
                     log::warn!("加载词库失败: {}, 使用内置词典", e);
@@ -157,114 +837,168 @@ impl SimplifiedPinyinEngine {
         }
     }
 
-    /// 加载内置基础词典
-    fn load_builtin_dict(&mut self) {
-        // 基础单字映射
-        let builtin_data = vec![
-            // 中
-            ("zhong", vec![("中", 100), ("种", 90), ("重", 80), ("众", 70), ("钟", 60)]),
-            // 文
-            ("wen", vec![("文", 100), ("问", 90), ("闻", 80), ("稳", 70)]),
-            // 国
-            ("guo", vec![("国", 100), ("过", 90), ("果", 80)]),
-            // 人
-            ("ren", vec![("人", 100), ("任", 90), ("认", 80), ("仁", 70)]),
-            // 大
-            ("da", vec![("大", 100), ("打", 90), ("达", 80)]),
-            // 小
-            ("xiao", vec![("小", 100), ("笑", 90), ("校", 80)]),
-            // 的
-            ("de", vec![("的", 100), ("得", 90), ("地", 80)]),
-            // 是
-            ("shi", vec![("是", 100), ("时", 90), ("事", 80), ("十", 70)]),
-            // 我
-            ("wo", vec![("我", 100), ("握", 50)]),
-            // 你
-            ("ni", vec![("你", 100), ("您", 90), ("尼", 50)]),
-            // 好
-            ("hao", vec![("好", 100), ("号", 90), ("毫", 50)]),
-            // 在
-            ("zai", vec![("在", 100), ("再", 90), ("载", 50)]),
-            // 有
-            ("you", vec![("有", 100), ("又", 90), ("由", 80)]),
-            // 和
-            ("he", vec![("和", 100), ("合", 90), ("河", 80)]),
-            // 了
-            ("le", vec![("了", 100), ("乐", 80)]),
-            // 不
-            ("bu", vec![("不", 100), ("部", 90), ("步", 80)]),
-            // 一
-            ("yi", vec![("一", 100), ("以", 90), ("已", 80), ("意", 70)]),
-            // 个
-            ("ge", vec![("个", 100), ("各", 90), ("歌", 80)]),
-            // 上
-            ("shang", vec![("上", 100), ("商", 90), ("伤", 80)]),
-            // 下
-            ("xia", vec![("下", 100), ("夏", 80)]),
-            // 来
-            ("lai", vec![("来", 100), ("赖", 60)]),
-            // 去
-            ("qu", vec![("去", 100), ("取", 90), ("趣", 60)]),
-            // 到
-            ("dao", vec![("到", 100), ("道", 90), ("倒", 80)]),
-            // 说
-            ("shuo", vec![("说", 100), ("硕", 60)]),
-            // 要
-            ("yao", vec![("要", 100), ("药", 90), ("遥", 60)]),
-            // 会
-            ("hui", vec![("会", 100), ("回", 90), ("汇", 80)]),
-            // 能
-            ("neng", vec![("能", 100), ("农", 50)]),
-            // 可以
-            ("keyi", vec![("可以", 100)]),
-            // 中国
-            ("zhongguo", vec![("中国", 100)]),
-            // 中文
-            ("zhongwen", vec![("中文", 100)]),
-            // 你好
-            ("nihao", vec![("你好", 100)]),
-        ];
-
-        for (pinyin, chars) in builtin_data {
-            let candidates: Vec<(Candidate, u32)> = chars
-                .into_iter()
-                .map(|(ch, weight)| {
-                    let candidate = Candidate::new(ch.to_string())
-                        .with_comment(pinyin.to_string())
-                        .with_weight(weight);
-                    (candidate, weight)
-                })
-                .collect();
-            self.pinyin_dict.insert(pinyin.to_string(), candidates);
-        }
-    }
-
     /// 从文件加载词典（扩展用）
     pub fn load_dict_from_file(&mut self, _path: &str) -> Result<()> {
         // TODO: 实现从文件加载词典
         Ok(())
     }
 
-    /// 添加自定义词条
+    /// 添加自定义词条（词库与其他会话共享时复制一份）
     pub fn add_entry(&mut self, pinyin: &str, text: &str, weight: u32) {
-        let candidate = Candidate::new(text.to_string())
-            .with_comment(pinyin.to_string())
-            .with_weight(weight);
-        
-        self.pinyin_dict
-            .entry(pinyin.to_string())
-            .or_insert_with(Vec::new)
-            .push((candidate, weight));
+        self.dict_mut().add_entry(pinyin, text, weight);
+        self.language_model.set_base(Some(Arc::clone(&self.dict.model)));
+        self.normalizer.add(SOURCE_BUILTIN, weight as f64);
+    }
+
+    /// 遍历内置词典与外部词库中的所有词条（文字, 权重）
+    pub fn for_each_phrase(&self, f: impl FnMut(&str, u32)) {
+        self.dict.for_each_phrase(f)
+    }
+
+    /// 将拼音转换为带声调符号的形式，读音取自词库中带声调的词条与内置读音表
+    pub fn tone_marked_pinyin(&self, text: &str, pinyin: &str) -> String {
+        self.dict.tone_table().mark(text, pinyin)
     }
 
     /// 检查是否已加载外部词库
     pub fn has_external_dict(&self) -> bool {
-        self.has_external_dict
+        self.dict.has_external_dict()
     }
 
     /// 获取词库统计信息
     pub fn dict_stats(&self) -> Option<DictStats> {
-        self.rime_loader.as_ref().map(|loader| loader.stats())
+        self.dict.stats()
+    }
+}
+
+/// 可在重新开始加载时沿用的进度回调
+type SharedProgressCallback = Arc<Mutex<Option<ProgressCallback>>>;
+
+/// 正在后台加载的词库
+///
+/// 加载线程逐个加载文件，并入线程把每个文件并入词库并重建，构建好的共享词库通过通道交给引擎；
+/// 引擎丢弃任务时通道关闭，两个线程在当前文件处理完后结束
+struct PendingDicts {
+    /// 每并入一个文件发送一次新的共享词库
+    receiver: Receiver<Arc<SharedDict>>,
+    /// 词库目录
+    dir: String,
+    /// 加载使用的词库组合
+    stack: DictStack,
+    /// 当前进度
+    progress: Arc<Mutex<LoadProgress>>,
+    /// 进度回调
+    on_progress: SharedProgressCallback,
+    /// 任务已丢弃，不再报告进度
+    cancelled: Arc<AtomicBool>,
+}
+
+impl PendingDicts {
+    /// 开始在后台加载，`base` 提供内置词典、自定义词条与内存预算
+    fn spawn(base: Arc<SharedDict>, dir: &str, stack: DictStack, on_progress: SharedProgressCallback) -> std::io::Result<Self> {
+        let (base_dir, specs) = stack.resolve(Path::new(dir));
+        let progress = Arc::new(Mutex::new(LoadProgress::default()));
+        let cancelled = Arc::new(AtomicBool::new(false));
+        let (recorded, callback, stopped) = (Arc::clone(&progress), Arc::clone(&on_progress), Arc::clone(&cancelled));
+        let load = BackgroundLoad::spawn_with_budget(
+            &base_dir,
+            &specs,
+            base.memory_budget.clone(),
+            Some(Box::new(move |p: &LoadProgress| {
+                if stopped.load(Ordering::Acquire) {
+                    return;
+                }
+                *recorded.lock().unwrap() = p.clone();
+                if let Some(callback) = callback.lock().unwrap().as_ref() {
+                    callback(p);
+                }
+            })),
+        )?;
+        *progress.lock().unwrap() = load.progress();
+
+        let (sender, receiver) = mpsc::channel();
+        let (dict_dir, dict_stack) = (dir.to_string(), stack.clone());
+        std::thread::Builder::new()
+            .name("lingcode-dict-merge".to_string())
+            .spawn(move || {
+                let mut current: Option<Arc<SharedDict>> = None;
+                while let Some(dict) = load.recv() {
+                    let Ok(loader) = dict.result else {
+                        continue;
+                    };
+                    log::info!("  • {}: {} 条 ({}, 优先级 {})", dict.name, loader.len(), dict.source.id(), dict.source.priority());
+                    let next = match current.take() {
+                        None => base.with_rime(loader, &dict_dir, dict_stack.clone()),
+                        Some(previous) => {
+                            // 引擎仍在使用上一版本时在本线程复制一份
+                            let mut shared = Arc::unwrap_or_clone(previous);
+                            shared.merge_rime(loader);
+                            shared
+                        }
+                    };
+                    let next = Arc::new(next);
+                    if sender.send(Arc::clone(&next)).is_err() {
+                        return;
+                    }
+                    current = Some(next);
+                }
+            })?;
+        Ok(Self {
+            receiver,
+            dir: dir.to_string(),
+            stack,
+            progress,
+            on_progress,
+            cancelled,
+        })
+    }
+
+    fn progress(&self) -> LoadProgress {
+        self.progress.lock().unwrap().clone()
+    }
+}
+
+impl Drop for PendingDicts {
+    fn drop(&mut self) {
+        self.cancelled.store(true, Ordering::Release);
+    }
+}
+
+/// 将固定的候选移动到指定位置，不在候选列表中的固定词条作为用户词插入
+///
+/// 位置超出列表长度时放在末尾；插入的候选沿用原位置候选的得分
+fn apply_pins(scored: &mut Vec<(Candidate, ScoreBreakdown)>, pins: &[(&str, usize)], pinyin: &str) {
+    for &(text, position) in pins {
+        let entry = match scored.iter().position(|(c, _)| c.text == text) {
+            Some(index) => scored.remove(index),
+            None => {
+                let total = scored.get(position.min(scored.len().saturating_sub(1))).map_or(0.0, |(_, b)| b.total);
+                let candidate = Candidate::new(text.to_string())
+                    .with_comment(format!("{} ⭐", pinyin))
+                    .with_source(SOURCE_USER.to_string());
+                (candidate, ScoreBreakdown::new(SOURCE_USER, 0.0, total))
+            }
+        };
+        let position = position.min(scored.len());
+        scored.insert(position, entry);
+    }
+}
+
+/// 加入候选词，同一个词出现在多个来源时保留得分最高的一个
+fn push_best(scored: &mut Vec<(Candidate, ScoreBreakdown)>, candidate: Candidate, breakdown: ScoreBreakdown) {
+    let candidate = candidate.with_source(breakdown.source.clone());
+    match scored.iter_mut().find(|(c, _)| c.text == candidate.text) {
+        Some(existing) if existing.1.total < breakdown.total => *existing = (candidate, breakdown),
+        Some(_) => {}
+        None => scored.push((candidate, breakdown)),
+    }
+}
+
+/// 写入用户词库 JSON 文件，`cipher` 非空时加密
+fn save_user_dict_file(dict: &UserDict, path: &Path, cipher: Option<&DictCipher>) -> anyhow::Result<()> {
+    match cipher {
+        Some(cipher) => dict.save_encrypted(path, cipher),
+        None => dict.save_to_file(path),
     }
 }
 
@@ -280,68 +1014,7 @@ impl PinyinEngine for SimplifiedPinyinEngine {
     }
 
     fn get_candidates(&self, pinyin: &str) -> Result<Candidates> {
-        let mut candidates = Candidates::new();
-        let mut scored_candidates: Vec<(Candidate, f64)> = Vec::new();
-        
-        // 1. 从用户词库查询（最高优先级）
-        if let Some(ref user_dict) = self.user_dict {
-            let user_results = user_dict.lookup(pinyin);
-            for (record, score) in user_results {
-                let candidate = Candidate::new(record.text.clone())
-                    .with_comment(format!("{} ⭐", pinyin))
-                    .with_weight((score * 1000.0) as u32);
-                scored_candidates.push((candidate, score * 1000.0)); // 用户词库加权
-            }
-        }
-        
-        // 2. 从雾凇拼音词库查询
-        if let Some(ref loader) = self.rime_loader {
-            let rime_candidates = loader.to_candidates(pinyin);
-            for candidate in rime_candidates {
-                // 检查是否已在用户词库中
-                let already_exists = scored_candidates.iter().any(|(c, _)| c.text == candidate.text);
-                if !already_exists {
-                    scored_candidates.push((candidate, 100.0)); // 词库基础权重
-                }
-            }
-        }
-        
-        // 3. 补充内置词典
-        if scored_candidates.len() < 10 {
-            if let Some(matches) = self.pinyin_dict.get(pinyin) {
-                for (candidate, weight) in matches {
-                    let already_exists = scored_candidates.iter().any(|(c, _)| c.text == candidate.text);
-                    if !already_exists {
-                        scored_candidates.push((candidate.clone(), *weight as f64));
-                    }
-                }
-            }
-        }
-        
-        // 4. 前缀匹配（如果结果太少）
-        if scored_candidates.len() < 5 {
-            for (key, matches) in &self.pinyin_dict {
-                if key.starts_with(pinyin) && key.as_str() != pinyin {
-                    for (candidate, weight) in matches.iter().take(2) {
-                        let already_exists = scored_candidates.iter().any(|(c, _)| c.text == candidate.text);
-                        if !already_exists {
-                            scored_candidates.push((candidate.clone(), *weight as f64 * 0.5)); // 前缀匹配降权
-                        }
-                    }
-                }
-                if scored_candidates.len() >= 10 {
-                    break;
-                }
-            }
-        }
-        
-        // 按分数排序并添加到结果
-        scored_candidates.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap());
-        for (candidate, _) in scored_candidates.into_iter().take(20) {
-            candidates.add(candidate);
-        }
-        
-        Ok(candidates)
+        self.get_candidates_with_context(pinyin, None)
     }
 
     fn is_valid_pinyin(&self, pinyin: &str) -> bool {
@@ -362,20 +1035,21 @@ impl PinyinEngine for SimplifiedPinyinEngine {
         }
         
         // 检查是否在词库中
-        if let Some(ref loader) = self.rime_loader {
-            if !loader.lookup(pinyin).is_empty() {
+        if let Some(ref loader) = self.dict.rime {
+            if !loader.lookup_sourced(pinyin).is_empty() {
                 return true;
             }
         }
         
         // 检查是否在词典中或有前缀匹配
-        self.pinyin_dict.contains_key(pinyin)
-            || self.pinyin_dict.keys().any(|k| k.starts_with(pinyin))
+        self.dict.builtin.contains_key(pinyin)
+            || self.dict.builtin.keys().any(|k| k.starts_with(pinyin))
     }
 
     fn get_pinyin_completion(&self, prefix: &str) -> Result<Vec<String>> {
         let completions: Vec<String> = self
-            .pinyin_dict
+            .dict
+            .builtin
             .keys()
             .filter(|k| k.starts_with(prefix) && k.as_str() != prefix)
             .cloned()
@@ -444,6 +1118,435 @@ mod tests {
         assert_eq!(candidates.get(0).unwrap().text, "测试");
     }
 
+    #[test]
+    fn test_shared_dict() {
+        let dict = Arc::new(SharedDict::new());
+        let mut first = SimplifiedPinyinEngine::with_shared_dict(Arc::clone(&dict));
+        let second = SimplifiedPinyinEngine::with_shared_dict(Arc::clone(&dict));
+        assert!(Arc::ptr_eq(first.shared_dict(), second.shared_dict()));
+
+        // 修改词库时写时复制，不影响其他会话
+        first.add_entry("test", "测试", 100);
+        assert!(!Arc::ptr_eq(first.shared_dict(), &dict));
+        assert!(Arc::ptr_eq(second.shared_dict(), &dict));
+        assert_eq!(first.get_candidates("test").unwrap().get(0).unwrap().text, "测试");
+        assert!(second
+            .get_candidates("test")
+            .unwrap()
+            .iter()
+            .all(|c| c.text != "测试"));
+    }
+
+    #[test]
+    fn test_phrase_index_built_once() {
+        let mut engine = SimplifiedPinyinEngine::new();
+        engine.add_entry("zhongguoyinhang", "中国银行", 90);
+        let dict = Arc::clone(engine.shared_dict());
+
+        // 首次取索引时在后台构建，不阻塞调用方
+        let index = SharedDict::phrase_index(&dict)
+            .unwrap_or_else(|| SharedDict::wait_for_phrase_index(&dict));
+        assert!(index.continuations("中国").any(|(text, _)| text == "银行"));
+        assert!(Arc::ptr_eq(&SharedDict::phrase_index(&dict).unwrap(), &index));
+
+        // 修改词库后索引按新词库重建
+        engine.add_entry("zhongguorenmin", "中国人民", 80);
+        let rebuilt = SharedDict::wait_for_phrase_index(engine.shared_dict());
+        assert_eq!(rebuilt.continuations("中国").count(), 2);
+    }
+
+    #[test]
+    fn test_sentence_candidate() {
+        let engine = SimplifiedPinyinEngine::new();
+        let candidates = engine.get_candidates("nihaozhongguo").unwrap();
+        assert_eq!(candidates.get(0).unwrap().text, "你好中国");
+        assert_eq!(candidates.get(0).unwrap().source.as_deref(), Some("sentence"));
+    }
+
+    #[test]
+    fn test_long_mistyped_sentence() {
+        // 每个 xian 都有两种切分，末尾的错字使整串无法切分；切分耗时应与输入长度成正比
+        let engine = SimplifiedPinyinEngine::new();
+        let start = std::time::Instant::now();
+        for len in 1..=40 {
+            let _ = engine.get_candidates(&format!("{}q", "xian".repeat(len)));
+        }
+        assert!(!engine.get_candidates(&"nihao".repeat(40)).unwrap().is_empty());
+        assert!(start.elapsed() < std::time::Duration::from_secs(2));
+    }
+
+    #[test]
+    fn test_context_reranking() {
+        let mut engine = SimplifiedPinyinEngine::new();
+        let first = engine.get_candidates("zhong").unwrap().get(0).unwrap().text.clone();
+        assert_eq!(first, "中");
+
+        // 前文为“你好”时，用户曾连续输入过“你好”“种”
+        engine.record_bigram("你好", "种");
+        let with_context = engine.get_candidates_with_context("zhong", Some("你好")).unwrap();
+        assert_eq!(with_context.get(0).unwrap().text, "种");
+        let without_context = engine.get_candidates("zhong").unwrap();
+        assert_eq!(without_context.get(0).unwrap().text, "中");
+
+        // 换用新词库等重建语言模型后仍保留记录的二元组
+        engine.set_shared_dict(Arc::new(SharedDict::new()));
+        engine.rebuild_language_model();
+        assert_eq!(engine.get_candidates("zhong").unwrap().get(0).unwrap().text, "中");
+        let with_context = engine.get_candidates_with_context("zhong", Some("你好")).unwrap();
+        assert_eq!(with_context.get(0).unwrap().text, "种");
+
+        // 清除学习记录时一并清除
+        engine.purge_user_history(0, u64::MAX).unwrap();
+        let with_context = engine.get_candidates_with_context("zhong", Some("你好")).unwrap();
+        assert_eq!(with_context.get(0).unwrap().text, "中");
+    }
+
+    #[test]
+    fn test_ranking_policy_recorded_and_tunable() {
+        let mut engine = SimplifiedPinyinEngine::new();
+        let candidates = engine.get_candidates("zhong").unwrap();
+        let first = candidates.get(0).unwrap();
+        assert_eq!(first.source.as_deref(), Some(SOURCE_BUILTIN));
+        // 得分按顺序写入权重
+        let weights: Vec<u32> = candidates.iter().map(|c| c.weight).collect();
+        assert!(weights.windows(2).all(|w| w[0] >= w[1]));
+
+        // 整句是该输入唯一的完整匹配，降低整句加权后仍排在首位，但写入的得分随加权变化
+        let mut config = lingcode_core::schema_parser::Ranking::default();
+        config.boosts.insert(SOURCE_SENTENCE.to_string(), 0.001);
+        engine.set_ranking_policy(RankingPolicy::from_config(&config));
+        let candidates = engine.get_candidates("nihaozhongguo").unwrap();
+        assert_eq!(candidates.get(0).unwrap().source.as_deref(), Some(SOURCE_SENTENCE));
+        let before = candidates.get(0).unwrap().weight;
+        config.boosts.insert(SOURCE_SENTENCE.to_string(), 1.0);
+        engine.set_ranking_policy(RankingPolicy::from_config(&config));
+        let after = engine.get_candidates("nihaozhongguo").unwrap().get(0).unwrap().weight;
+        assert!(after > before);
+    }
+
+    #[test]
+    fn test_explain_candidates() {
+        let mut engine = SimplifiedPinyinEngine::new();
+        engine.user_dict = Some(UserDict::new());
+        engine.record_usage("种", "zhong");
+
+        let explained = engine.explain_candidates("zhong", None);
+        let (candidate, breakdown) = &explained[0];
+        assert_eq!(candidate.text, "种");
+        assert_eq!(breakdown.source, SOURCE_USER);
+        assert_eq!(breakdown.user_count, Some(1));
+        assert!(breakdown.time_decay.unwrap() > 0.99);
+        assert_eq!(candidate.weight, score_to_weight(breakdown.total));
+        // 用户来源按排序使用的得分归一化：唯一的用户词条得分即来源总分
+        let raw = breakdown.raw_weight;
+        assert!((breakdown.log_prob - ((raw + 1.0) / (raw + 2.0)).ln()).abs() < 1e-9);
+        engine.record_usage("种", "zhong");
+        let (_, breakdown) = &engine.explain_candidates("zhong", None)[0];
+        let raw = breakdown.raw_weight;
+        assert!((breakdown.log_prob - ((raw + 1.0) / (raw + 2.0)).ln()).abs() < 1e-9);
+
+        // 前缀匹配的折扣单独列出
+        let prefix = engine.explain_candidates("zhon", None);
+        assert!(prefix.iter().all(|(_, b)| b.penalty < 0.0));
+    }
+
+    #[test]
+    fn test_user_dict_store() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let dir = temp_dir.path();
+        let dir_str = dir.to_str().unwrap();
+        {
+            let mut engine = SimplifiedPinyinEngine::new().with_user_dict_store(dir_str);
+            engine.record_usage("种", "zhong");
+            engine.record_usage("种", "zhong");
+            // 未保存即退出，词频仍从日志恢复
+        }
+        let mut engine = SimplifiedPinyinEngine::new().with_user_dict_store(dir_str);
+        assert_eq!(engine.user_dict().unwrap().get_count("种"), 2);
+        engine.save_user_dict().unwrap();
+        assert_eq!(engine.get_candidates("zhong").unwrap().get(0).unwrap().text, "种");
+    }
+
+    #[test]
+    fn test_add_custom_entries() {
+        let mut engine = SimplifiedPinyinEngine::new();
+        let report = lingcode_dict::import_bytes("'shu'ju'ku 数据库".as_bytes(), lingcode_dict::ImportFormat::Sogou).unwrap();
+        assert_eq!(engine.add_custom_entries(report.entries), 1);
+        let candidates = engine.get_candidates("shujuku").unwrap();
+        assert_eq!(candidates.get(0).unwrap().text, "数据库");
+        assert_eq!(candidates.get(0).unwrap().source.as_deref(), Some("custom"));
+    }
+
+    #[test]
+    fn test_dict_stack_priority_and_custom_entries() {
+        use lingcode_dict::{DictSource, DictSpec};
+
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let dir = temp_dir.path();
+        std::fs::write(dir.join("8105.dict.yaml"), "---\n...\n种\tzhong\t1000\n").unwrap();
+        std::fs::write(dir.join("jargon.dict.yaml"), "---\n...\n钟\tzhong\t10\n").unwrap();
+        let stack = |priority| DictStack {
+            main: None,
+            extra: vec![DictSpec::new("jargon", DictSource::new("jargon", priority))],
+        };
+
+        let mut engine = SimplifiedPinyinEngine::with_rime_dicts(dir.to_str().unwrap());
+        let report = lingcode_dict::import_bytes("'shu'ju'ku 数据库".as_bytes(), lingcode_dict::ImportFormat::Sogou).unwrap();
+        engine.add_custom_entries(report.entries);
+
+        engine.set_dict_stack(stack(120));
+        assert_eq!(engine.ranking_policy().boost("jargon"), 1.2);
+        // 只改变优先级时同样重新加载
+        engine.set_dict_stack(stack(50));
+        assert_eq!(engine.ranking_policy().boost("jargon"), 0.5);
+        // 自定义词条在重新加载后保留
+        assert_eq!(engine.get_candidates("shujuku").unwrap().get(0).unwrap().text, "数据库");
+
+        // 换用不含该词库的组合后不再保留其优先级
+        engine.set_dict_stack(DictStack::default());
+        assert_eq!(engine.ranking_policy().boost("jargon"), 1.0);
+        assert_eq!(engine.get_candidates("shujuku").unwrap().get(0).unwrap().text, "数据库");
+    }
+
+    #[test]
+    fn test_background_dict_loading() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let dir = temp_dir.path();
+        std::fs::write(dir.join("8105.dict.yaml"), "---\n...\n锺\tzhong\t1\n").unwrap();
+        std::fs::write(dir.join("tencent.dict.yaml"), "---\n...\n中华\tzhonghua\t10\n钟声\tzhongsheng\t10\n").unwrap();
+
+        let progress = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let recorded = Arc::clone(&progress);
+        let mut engine = SimplifiedPinyinEngine::with_rime_dicts_in_background(
+            dir.to_str().unwrap(),
+            Some(Box::new(move |p: &LoadProgress| recorded.lock().unwrap().push(p.files_done))),
+        );
+        assert!(engine.is_loading_dicts());
+        assert_eq!(engine.dict_load_progress().unwrap().files_total, 2);
+
+        engine.wait_for_dicts();
+        assert!(!engine.is_loading_dicts());
+        assert!(!engine.poll_dict_loading());
+        assert_eq!(engine.dict_stats().unwrap().total_entries, 3);
+        assert!(engine.get_candidates("zhonghua").unwrap().iter().any(|c| c.text == "中华"));
+        assert_eq!(progress.lock().unwrap().last(), Some(&2));
+    }
+
+    #[test]
+    fn test_background_load_restarted_on_stack_change() {
+        use lingcode_dict::{DictSource, DictSpec};
+
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let dir = temp_dir.path();
+        std::fs::write(dir.join("8105.dict.yaml"), "---\n...\n种\tzhong\t1000\n").unwrap();
+        std::fs::write(dir.join("jargon.dict.yaml"), "---\n...\n钟\tzhong\t10\n").unwrap();
+        let stack = DictStack {
+            main: None,
+            extra: vec![DictSpec::new("jargon", DictSource::new("jargon", 120))],
+        };
+
+        let mut engine = SimplifiedPinyinEngine::with_rime_dicts_in_background(dir.to_str().unwrap(), None);
+        // 加载完成前更换词库组合：丢弃原任务，按新组合加载
+        engine.set_dict_stack(stack.clone());
+        assert!(engine.is_loading_dicts());
+        engine.wait_for_dicts();
+        assert_eq!(engine.dict_stack(), &stack);
+        assert!(engine.get_candidates("zhong").unwrap().iter().any(|c| c.text == "钟"));
+        assert_eq!(engine.ranking_policy().boost("jargon"), 1.2);
+    }
+
+    #[test]
+    fn test_memory_budget() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let dir = temp_dir.path();
+        std::fs::write(dir.join("8105.dict.yaml"), "---\n...\n锺\tzhong\t1\n中\tzhong\t1000\n").unwrap();
+        std::fs::write(dir.join("tencent.dict.yaml"), "---\n...\n中华\tzhonghua\t500\n钟华\tzhonghua\t2\n").unwrap();
+
+        let budget = MemoryBudget::default()
+            .with_max_bytes(1024)
+            .with_min_weight(10)
+            .with_page_dir(dir.to_path_buf());
+        let mut engine = SimplifiedPinyinEngine::new().with_memory_budget(budget);
+        engine.load_rime_dicts(dir.to_str().unwrap());
+
+        let stats = engine.dict_stats().unwrap();
+        assert!(stats.paged);
+        assert_eq!((stats.total_entries, stats.dropped_entries), (2, 2));
+        assert!(stats.memory_bytes > 0);
+        assert_eq!(engine.get_candidates("zhong").unwrap().get(0).unwrap().text, "中");
+        let texts: Vec<String> = engine.get_candidates("zhonghua").unwrap().iter().map(|c| c.text.clone()).collect();
+        assert!(texts.contains(&"中华".to_string()));
+        assert!(!texts.contains(&"钟华".to_string()));
+
+        // 改为全部常驻内存时重新加载
+        engine.set_memory_budget(MemoryBudget::default());
+        let stats = engine.dict_stats().unwrap();
+        assert!(!stats.paged);
+        assert_eq!(stats.total_entries, 4);
+    }
+
+    #[test]
+    fn test_memory_budget_total() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let dir = temp_dir.path();
+        let mut content = String::from("---\n...\n");
+        for i in 0..20000 {
+            content.push_str(&format!("词组{}\tci{}\t{}\n", i, i % 2000, i));
+        }
+        std::fs::write(dir.join("tencent.dict.yaml"), content).unwrap();
+
+        let budget = MemoryBudget::default()
+            .with_max_bytes(256 * 1024)
+            .with_page_dir(dir.to_path_buf());
+        let mut engine = SimplifiedPinyinEngine::new().with_memory_budget(budget.clone());
+        engine.load_rime_dicts(dir.to_str().unwrap());
+
+        // 读过所有编码、构建联想索引后，词条、语言模型与联想索引合计仍在预算内
+        let dict = Arc::clone(engine.shared_dict());
+        let index = SharedDict::wait_for_phrase_index(&dict);
+        for i in 0..2000 {
+            assert_eq!(dict.rime().unwrap().lookup(&format!("ci{}", i)).len(), 10);
+        }
+        let stats = engine.dict_stats().unwrap();
+        assert!(stats.paged);
+        assert!(stats.memory_bytes <= budget.max_bytes, "{} > {}", stats.memory_bytes, budget.max_bytes);
+        assert!(dict.language_model().memory_bytes() <= budget.derived_bytes().unwrap());
+        assert!(index.memory_bytes() <= budget.derived_bytes().unwrap());
+        // 只丢弃低权重的词组
+        assert!(index.continuations("词组1999").any(|(rest, _)| rest == "9"));
+        assert!(!index.continuations("词组").any(|(rest, _)| rest == "1"));
+        drop(dict);
+
+        // 全部常驻内存时远超该预算
+        engine.set_memory_budget(MemoryBudget::default());
+        SharedDict::wait_for_phrase_index(engine.shared_dict());
+        assert!(engine.dict_stats().unwrap().memory_bytes > budget.max_bytes * 4);
+    }
+
+    #[test]
+    fn test_import_rime_userdb() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let dir = temp_dir.path();
+        let snapshot = dir.join("luna_pinyin.userdb.txt");
+        std::fs::write(&snapshot, "#@/user_id\tabc\nzhong \t钟\tc=20 d=20 t=1\n").unwrap();
+
+        let mut engine = SimplifiedPinyinEngine::new();
+        let summary = engine.import_rime_userdb(snapshot.to_str().unwrap()).unwrap();
+        assert_eq!(summary.imported, 1);
+        assert_eq!(engine.get_candidates("zhong").unwrap().get(0).unwrap().text, "钟");
+
+        let exported = dir.join("export.userdb.txt");
+        assert_eq!(engine.export_rime_userdb(exported.to_str().unwrap(), "luna_pinyin").unwrap(), 1);
+        assert!(std::fs::read_to_string(&exported).unwrap().contains("zhong \t钟\tc=20"));
+    }
+
+    #[test]
+    fn test_encrypted_user_dict_store() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let dir = temp_dir.path();
+        let dir_str = dir.to_str().unwrap();
+        {
+            let mut engine = SimplifiedPinyinEngine::new();
+            engine.load_encrypted_user_dict_store(dir_str, DictCipher::from_key([1; 32])).unwrap();
+            engine.record_usage("种", "zhong");
+            engine.rotate_user_dict_key(Some(DictCipher::from_key([2; 32]))).unwrap();
+        }
+
+        let mut engine = SimplifiedPinyinEngine::new().with_user_dict_store(dir_str);
+        assert!(engine.load_encrypted_user_dict_store(dir_str, DictCipher::from_key([1; 32])).is_err());
+        engine.load_encrypted_user_dict_store(dir_str, DictCipher::from_key([2; 32])).unwrap();
+        assert_eq!(engine.user_dict().unwrap().get_count("种"), 1);
+    }
+
+    #[test]
+    fn test_encrypted_user_dict_file() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let dir = temp_dir.path();
+        let path = dir.join("user_dict.json");
+        let path_str = path.to_str().unwrap();
+        {
+            let mut engine = SimplifiedPinyinEngine::new();
+            engine.load_encrypted_user_dict(path_str, DictCipher::from_key([1; 32])).unwrap();
+            engine.record_usage("机密", "jimi");
+            engine.save_user_dict().unwrap();
+        }
+        let data = std::fs::read(&path).unwrap();
+        assert!(!String::from_utf8_lossy(&data).contains("jimi"));
+
+        // 没有密钥时加载失败，之后的保存不覆盖加密文件
+        let mut engine = SimplifiedPinyinEngine::new();
+        engine.load_user_dict(path_str);
+        engine.record_usage("中文", "zhongwen");
+        engine.save_user_dict().unwrap();
+        assert_eq!(std::fs::read(&path).unwrap(), data);
+
+        assert!(engine.load_encrypted_user_dict(path_str, DictCipher::from_key([2; 32])).is_err());
+        engine.load_encrypted_user_dict(path_str, DictCipher::from_key([1; 32])).unwrap();
+        engine.rotate_user_dict_key(Some(DictCipher::from_key([2; 32]))).unwrap();
+        engine.record_usage("机密", "jimi");
+        engine.save_user_dict().unwrap();
+
+        let mut engine = SimplifiedPinyinEngine::new();
+        assert!(engine.load_encrypted_user_dict(path_str, DictCipher::from_key([1; 32])).is_err());
+        engine.load_encrypted_user_dict(path_str, DictCipher::from_key([2; 32])).unwrap();
+        assert_eq!(engine.user_dict().unwrap().get_count("机密"), 2);
+    }
+
+    #[test]
+    fn test_delete_pin_and_add_word() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let dir = temp_dir.path();
+        let mut engine = SimplifiedPinyinEngine::new().with_user_dict_store(dir.to_str().unwrap());
+        let texts = |engine: &SimplifiedPinyinEngine, pinyin: &str| -> Vec<String> {
+            engine.get_candidates(pinyin).unwrap().iter().map(|c| c.text.clone()).collect()
+        };
+
+        let first = texts(&engine, "zhong")[0].clone();
+        assert!(!engine.delete_word(&first, "zhong"));
+        assert!(!texts(&engine, "zhong").contains(&first));
+
+        // 再次选用后恢复
+        engine.record_usage(&first, "zhong");
+        assert!(texts(&engine, "zhong").contains(&first));
+
+        let last = texts(&engine, "zhong").last().unwrap().clone();
+        engine.pin_word(&last, "zhong", Some(0));
+        assert_eq!(texts(&engine, "zhong")[0], last);
+        engine.pin_word(&last, "zhong", None);
+        assert_ne!(texts(&engine, "zhong")[0], last);
+
+        engine.add_word("灵码输入法", "lmsrf");
+        assert_eq!(texts(&engine, "lmsrf"), vec!["灵码输入法".to_string()]);
+    }
+
+    #[test]
+    fn test_user_scoring_policy_and_clock() {
+        use lingcode_core::schema_parser::{Ranking, UserFrequency};
+        use lingcode_dict::ManualClock;
+        const DAY: u64 = 86400;
+
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let dir = temp_dir.path();
+        let clock = Arc::new(ManualClock::new(DAY));
+        let mut engine = SimplifiedPinyinEngine::new();
+        engine.set_clock(clock.clone());
+        engine.load_user_dict_store(dir.to_str().unwrap()).unwrap();
+        for _ in 0..3 {
+            engine.record_usage("术语", "shuyu");
+        }
+        clock.set(61 * DAY);
+        engine.record_usage("数域", "shuyu");
+        let top = |engine: &SimplifiedPinyinEngine| engine.get_candidates("shuyu").unwrap().get(0).unwrap().text.clone();
+        assert_eq!(top(&engine), "数域");
+
+        let config = Ranking {
+            user_frequency: Some(UserFrequency::HalfLife { half_life_days: 365.0 }),
+            ..Default::default()
+        };
+        engine.set_ranking_policy(RankingPolicy::from_config(&config));
+        assert_eq!(top(&engine), "术语");
+    }
+
     #[test]
     fn test_user_dict() {
         let mut engine = SimplifiedPinyinEngine::new();
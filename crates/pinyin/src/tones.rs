@@ -0,0 +1,273 @@
+//! 带声调拼音
+//!
+//! 根据汉字读音表为拼音标注声调。读音优先取自词库中带声调的词条（见 [`ToneTable`]），
+//! 内置读音表只覆盖内置词典中的常用字
+
+use lingcode_core::syllable::{add_tone_mark, parse_tone, split_syllables, split_syllables_into};
+use std::collections::HashMap;
+
+/// 汉字 -> 带数字声调的读音（多音字列出全部读音，5 表示轻声）
+const CHAR_READINGS: &[(char, &[&str])] = &[
+    ('中', &["zhong1", "zhong4"]),
+    ('种', &["zhong3", "zhong4"]),
+    ('重', &["zhong4", "chong2"]),
+    ('众', &["zhong4"]),
+    ('钟', &["zhong1"]),
+    ('文', &["wen2"]),
+    ('问', &["wen4"]),
+    ('闻', &["wen2"]),
+    ('稳', &["wen3"]),
+    ('国', &["guo2"]),
+    ('过', &["guo4"]),
+    ('果', &["guo3"]),
+    ('人', &["ren2"]),
+    ('任', &["ren4"]),
+    ('认', &["ren4"]),
+    ('仁', &["ren2"]),
+    ('大', &["da4", "dai4"]),
+    ('打', &["da3"]),
+    ('达', &["da2"]),
+    ('小', &["xiao3"]),
+    ('笑', &["xiao4"]),
+    ('校', &["xiao4", "jiao4"]),
+    ('的', &["de5", "di2", "di4"]),
+    ('得', &["de2", "de5", "dei3"]),
+    ('地', &["di4", "de5"]),
+    ('是', &["shi4"]),
+    ('时', &["shi2"]),
+    ('事', &["shi4"]),
+    ('十', &["shi2"]),
+    ('我', &["wo3"]),
+    ('握', &["wo4"]),
+    ('你', &["ni3"]),
+    ('您', &["nin2"]),
+    ('尼', &["ni2"]),
+    ('好', &["hao3", "hao4"]),
+    ('号', &["hao4"]),
+    ('毫', &["hao2"]),
+    ('在', &["zai4"]),
+    ('再', &["zai4"]),
+    ('载', &["zai4", "zai3"]),
+    ('有', &["you3"]),
+    ('又', &["you4"]),
+    ('由', &["you2"]),
+    ('和', &["he2", "he4", "huo2"]),
+    ('合', &["he2"]),
+    ('河', &["he2"]),
+    ('了', &["le5", "liao3"]),
+    ('乐', &["le4", "yue4"]),
+    ('不', &["bu4"]),
+    ('部', &["bu4"]),
+    ('步', &["bu4"]),
+    ('一', &["yi1"]),
+    ('以', &["yi3"]),
+    ('已', &["yi3"]),
+    ('意', &["yi4"]),
+    ('个', &["ge4"]),
+    ('各', &["ge4"]),
+    ('歌', &["ge1"]),
+    ('上', &["shang4"]),
+    ('商', &["shang1"]),
+    ('伤', &["shang1"]),
+    ('下', &["xia4"]),
+    ('夏', &["xia4"]),
+    ('来', &["lai2"]),
+    ('赖', &["lai4"]),
+    ('去', &["qu4"]),
+    ('取', &["qu3"]),
+    ('趣', &["qu4"]),
+    ('到', &["dao4"]),
+    ('道', &["dao4"]),
+    ('倒', &["dao3", "dao4"]),
+    ('说', &["shuo1"]),
+    ('硕', &["shuo4"]),
+    ('要', &["yao4", "yao1"]),
+    ('药', &["yao4"]),
+    ('遥', &["yao2"]),
+    ('会', &["hui4", "kuai4"]),
+    ('回', &["hui2"]),
+    ('汇', &["hui4"]),
+    ('能', &["neng2"]),
+    ('农', &["nong2"]),
+    ('可', &["ke3"]),
+];
+
+/// 查找汉字在指定音节下的声调，内置读音表中没有时返回 None
+pub fn char_tone(ch: char, syllable: &str) -> Option<u8> {
+    let (_, readings) = CHAR_READINGS.iter().find(|(c, _)| *c == ch)?;
+    readings
+        .iter()
+        .map(|reading| parse_tone(reading))
+        .find(|(base, _)| *base == syllable)
+        .map(|(_, tone)| tone)
+}
+
+/// 带声调符号的韵母字母 -> (字母, 声调)
+const TONE_MARKS: &[(char, char, u8)] = &[
+    ('ā', 'a', 1), ('á', 'a', 2), ('ǎ', 'a', 3), ('à', 'a', 4),
+    ('ē', 'e', 1), ('é', 'e', 2), ('ě', 'e', 3), ('è', 'e', 4),
+    ('ī', 'i', 1), ('í', 'i', 2), ('ǐ', 'i', 3), ('ì', 'i', 4),
+    ('ō', 'o', 1), ('ó', 'o', 2), ('ǒ', 'o', 3), ('ò', 'o', 4),
+    ('ū', 'u', 1), ('ú', 'u', 2), ('ǔ', 'u', 3), ('ù', 'u', 4),
+    ('ǖ', 'v', 1), ('ǘ', 'v', 2), ('ǚ', 'v', 3), ('ǜ', 'v', 4), ('ü', 'v', 0),
+];
+
+/// 解析带声调的音节：数字声调（`zhong1`）或声调符号（`zhōng`），没有声调时返回 None
+fn parse_toned_syllable(s: &str) -> Option<(String, u8)> {
+    let (base, tone) = parse_tone(s);
+    if tone > 0 {
+        return Some((base.to_string(), tone));
+    }
+    let mut tone = 0;
+    let base: String = s
+        .chars()
+        .map(|c| match TONE_MARKS.iter().find(|(marked, _, _)| *marked == c) {
+            Some(&(_, plain, t)) => {
+                tone = tone.max(t);
+                plain
+            }
+            None => c,
+        })
+        .collect();
+    (tone > 0).then_some((base, tone))
+}
+
+/// 由词库词条得到的汉字读音表
+///
+/// 只收录拼音带声调的词条（如 `中文\tzhong1 wen2` 或 `中文\tzhōng wén`），
+/// 音节数与字数一致时逐字记录读音；查不到时退回内置读音表
+#[derive(Debug, Clone, Default)]
+pub struct ToneTable {
+    /// 汉字 -> (音节, 声调)
+    readings: HashMap<char, Vec<(String, u8)>>,
+}
+
+impl ToneTable {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 加入词条的读音，拼音不带声调或与文字不对应时忽略，返回是否收录
+    pub fn add_entry(&mut self, text: &str, pinyin: &str) -> bool {
+        let syllables: Option<Vec<(String, u8)>> = pinyin.split_whitespace().map(parse_toned_syllable).collect();
+        let Some(syllables) = syllables else {
+            return false;
+        };
+        if syllables.is_empty() || syllables.len() != text.chars().count() {
+            return false;
+        }
+        for (ch, reading) in text.chars().zip(syllables) {
+            let readings = self.readings.entry(ch).or_default();
+            if !readings.contains(&reading) {
+                readings.push(reading);
+            }
+        }
+        true
+    }
+
+    /// 汉字在指定音节下的声调
+    pub fn tone(&self, ch: char, syllable: &str) -> Option<u8> {
+        self.readings
+            .get(&ch)
+            .and_then(|readings| readings.iter().find(|(base, _)| base == syllable))
+            .map(|(_, tone)| *tone)
+            .or_else(|| char_tone(ch, syllable))
+    }
+
+    /// 收录的汉字数
+    pub fn len(&self) -> usize {
+        self.readings.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.readings.is_empty()
+    }
+
+    /// 将拼音转换为带声调符号的形式，见 [`tone_marked_pinyin`]
+    pub fn mark(&self, text: &str, pinyin: &str) -> String {
+        mark_syllables(text, pinyin, |ch, syllable| self.tone(ch, syllable))
+    }
+}
+
+/// 将拼音转换为带声调符号的形式，如 (`中文`, `zhongwen`) -> `zhōngwén`
+///
+/// `text` 为拼音对应的汉字，用于确定每个音节的声调，也用于在有多种切分时选取音节数与汉字数相同的切分；
+/// 汉字与音节数量不一致或读音未知时该音节不标调，以元音开头的音节前加隔音符号（如 `xī'ān`）。
+/// 拼音无法切分时原样返回。
+/// 只使用内置读音表，需要词库中的读音时用 [`ToneTable::mark`]
+pub fn tone_marked_pinyin(text: &str, pinyin: &str) -> String {
+    mark_syllables(text, pinyin, char_tone)
+}
+
+fn mark_syllables(text: &str, pinyin: &str, tone: impl Fn(char, &str) -> Option<u8>) -> String {
+    let chars: Vec<char> = text.chars().collect();
+    // 有多种切分时取音节数与汉字数相同的（如“西安”的 xian 切为 xi an）
+    let syllables = match split_syllables_into(pinyin, chars.len()).or_else(|| split_syllables(pinyin)) {
+        Some(syllables) => syllables,
+        None => return pinyin.to_string(),
+    };
+    let aligned = chars.len() == syllables.len();
+
+    let mut marked = String::new();
+    for (i, syllable) in syllables.iter().enumerate() {
+        if i > 0 && syllable.starts_with(['a', 'o', 'e']) {
+            marked.push('\'');
+        }
+        let tone = if aligned { tone(chars[i], syllable) } else { None };
+        marked.push_str(&add_tone_mark(syllable, tone.unwrap_or(0)));
+    }
+    marked
+}
+
+/// 将拼音按音节以空格分隔，如 `zhongwen` -> `zhong wen`；无法切分时原样返回
+pub fn spaced_pinyin(pinyin: &str) -> String {
+    match split_syllables(pinyin) {
+        Some(syllables) => syllables.join(" "),
+        None => pinyin.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tone_marked_pinyin() {
+        assert_eq!(tone_marked_pinyin("中文", "zhongwen"), "zhōngwén");
+        assert_eq!(tone_marked_pinyin("你好", "nihao"), "nǐhǎo");
+        // 多音字按音节选择读音
+        assert_eq!(tone_marked_pinyin("重", "chong"), "chóng");
+        // 汉字与音节不对应时不标调
+        assert_eq!(tone_marked_pinyin("中", "zhongwen"), "zhongwen");
+        assert_eq!(tone_marked_pinyin("中文", "hello"), "hello");
+    }
+
+    #[test]
+    fn test_tone_table_from_dict_entries() {
+        let mut table = ToneTable::new();
+        assert!(table.add_entry("银行", "yin2 hang2"));
+        assert!(table.add_entry("行", "xíng"));
+        // 不带声调或音节数不一致的词条不收录
+        assert!(!table.add_entry("银", "yin"));
+        assert!(!table.add_entry("银行", "yin2"));
+        assert_eq!(table.len(), 2);
+
+        assert_eq!(table.mark("银行", "yinhang"), "yínháng");
+        assert_eq!(table.mark("行", "xing"), "xíng");
+        // 词库中没有的字退回内置读音表
+        assert_eq!(table.mark("中文", "zhongwen"), "zhōngwén");
+        assert_eq!(tone_marked_pinyin("银行", "yinhang"), "yinhang");
+
+        // 按汉字数切分，以元音开头的音节前加隔音符号
+        assert!(table.add_entry("西安", "xi1 an1"));
+        assert_eq!(table.mark("西安", "xian"), "xī'ān");
+        assert_eq!(table.mark("先", "xian"), "xian");
+    }
+
+    #[test]
+    fn test_spaced_pinyin() {
+        assert_eq!(spaced_pinyin("zhongwen"), "zhong wen");
+        assert_eq!(spaced_pinyin("xi'an"), "xi an");
+        assert_eq!(spaced_pinyin("abc1"), "abc1");
+    }
+}